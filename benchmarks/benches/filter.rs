@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use geozero::mvt::{tile, Message};
+use maplibre::{
+    coords::ZoomLevel,
+    io::apc::{Context, IntoMessage, SendError},
+    style::{
+        expression::{ComparisonLiteral, LegacyFilterExpression},
+        layer::StyleLayer,
+        Style,
+    },
+    vector::{
+        process_vector_tile, DefaultVectorTransferables, ProcessVectorContext, VectorTileRequest,
+    },
+};
+
+const FEATURES: usize = 2000;
+const CLASSES: [&str; 4] = ["primary", "secondary", "tertiary", "path"];
+
+/// A filter as it is commonly found in styles for roads.
+fn road_filter() -> LegacyFilterExpression {
+    serde_json::from_str(
+        r#"[
+            "all",
+            ["==", "$type", "LineString"],
+            ["any", ["==", "class", "primary"], ["==", "class", "secondary"], ["in", "subclass", "trunk", "motorway"]],
+            [">=", "rank", 3],
+            ["!=", "brunnel", "tunnel"],
+            ["has", "name"],
+            ["none", ["==", "oneway", 1], ["<", "layer", 0]]
+        ]"#,
+    )
+    .unwrap()
+}
+
+fn feature_properties(i: usize) -> HashMap<String, ComparisonLiteral> {
+    let mut properties: HashMap<String, ComparisonLiteral> = (0..16)
+        .map(|key| (format!("unused_{key}"), ComparisonLiteral::Integer(key)))
+        .collect();
+    properties.insert(
+        "$type".to_string(),
+        ComparisonLiteral::String("LineString".to_string()),
+    );
+    properties.insert(
+        "class".to_string(),
+        ComparisonLiteral::String(CLASSES[i % CLASSES.len()].to_string()),
+    );
+    properties.insert(
+        "subclass".to_string(),
+        ComparisonLiteral::String("trunk".to_string()),
+    );
+    properties.insert(
+        "rank".to_string(),
+        ComparisonLiteral::Integer((i % 6) as isize),
+    );
+    properties.insert(
+        "brunnel".to_string(),
+        ComparisonLiteral::String("bridge".to_string()),
+    );
+    properties.insert(
+        "name".to_string(),
+        ComparisonLiteral::String(format!("Road {i}")),
+    );
+    properties.insert("layer".to_string(), ComparisonLiteral::Float(1.0));
+    properties
+}
+
+fn evaluate(c: &mut Criterion) {
+    let filter = road_filter();
+    let features: Vec<_> = (0..FEATURES).map(feature_properties).collect();
+
+    c.bench_function("filter_evaluate", |b| {
+        b.iter(|| {
+            features
+                .iter()
+                .filter(|properties| filter.evaluate(black_box(properties)))
+                .count()
+        })
+    });
+}
+
+/// Discards all results of processing a tile.
+struct NopContext;
+
+impl Context for NopContext {
+    fn send_back<T: IntoMessage>(&self, _message: T) -> Result<(), SendError> {
+        Ok(())
+    }
+}
+
+/// Encodes a tile with a single layer of lines which each have many properties.
+fn roads_tile() -> Vec<u8> {
+    let mut keys: Vec<String> = (0..16).map(|key| format!("unused_{key}")).collect();
+    keys.extend(["class", "rank", "brunnel", "name"].map(str::to_string));
+
+    let mut values: Vec<tile::Value> = (0..16)
+        .map(|value| tile::Value {
+            int_value: Some(value),
+            ..Default::default()
+        })
+        .collect();
+    let string_value = |value: &str| tile::Value {
+        string_value: Some(value.to_string()),
+        ..Default::default()
+    };
+    values.extend(CLASSES.map(string_value));
+    values.push(tile::Value {
+        int_value: Some(4),
+        ..Default::default()
+    });
+    values.push(string_value("bridge"));
+    values.push(string_value("Road"));
+
+    let features = (0..FEATURES)
+        .map(|i| {
+            let mut tags: Vec<u32> = (0..16).flat_map(|key| [key, key]).collect();
+            tags.extend([16, 16 + (i % CLASSES.len()) as u32, 17, 20, 18, 21, 19, 22]);
+
+            tile::Feature {
+                id: Some(i as u64),
+                tags,
+                r#type: Some(tile::GeomType::Linestring as i32),
+                // MoveTo(0, 0), LineTo(10, 0)
+                geometry: vec![9, 0, 0, 10, 20, 0],
+            }
+        })
+        .collect();
+
+    geozero::mvt::Tile {
+        layers: vec![tile::Layer {
+            version: 2,
+            name: "transportation".to_string(),
+            features,
+            keys,
+            values,
+            extent: Some(4096),
+        }],
+    }
+    .encode_to_vec()
+}
+
+fn process_tile(c: &mut Criterion) {
+    let data = roads_tile();
+    let style = Style {
+        layers: vec![StyleLayer {
+            id: "roads".to_string(),
+            source_layer: Some("transportation".to_string()),
+            filter: Some(road_filter()),
+            ..StyleLayer::default()
+        }],
+        ..Style::default()
+    };
+
+    c.bench_function("filter_process_tile", |b| {
+        b.iter(|| {
+            process_vector_tile(
+                black_box(&data),
+                VectorTileRequest {
+                    coords: (0, 0, ZoomLevel::default()).into(),
+                    layers: HashSet::from(["transportation".to_string()]),
+                    style: style.clone(),
+                    style_layer_ids: None,
+                    tessellation_budget: None,
+                    chunk_size: None,
+                    retain_feature_properties: false,
+                    clip: None,
+                },
+                &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(NopContext),
+            )
+            .unwrap()
+        })
+    });
+}
+
+criterion_group!(filter, evaluate, process_tile);
+criterion_main!(filter);
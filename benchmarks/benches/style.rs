@@ -0,0 +1,54 @@
+use cint::{Alpha, EncodedSrgb};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use maplibre::{
+    coords::ZoomLevel,
+    style::{color::InterpolatedColor, layer::LayerPaint, Style},
+};
+
+const ZOOM: ZoomLevel = ZoomLevel::new(12);
+
+/// The colors of all layers of the default style, as they are looked up for each tile.
+fn colors(style: &Style) -> Vec<&InterpolatedColor> {
+    style
+        .layers
+        .iter()
+        .filter_map(|layer| match layer.paint.as_ref()? {
+            LayerPaint::Background(paint) => paint.background_color.as_ref(),
+            LayerPaint::Line(paint) => paint.line_color.as_ref(),
+            LayerPaint::Fill(paint) => paint.fill_color.as_ref(),
+            LayerPaint::FillExtrusion(paint) => paint.fill_extrusion_color.as_ref(),
+            LayerPaint::Raster(_) => None,
+        })
+        .collect()
+}
+
+fn get_color(c: &mut Criterion) {
+    let style = Style::default();
+    let colors = colors(&style);
+
+    c.bench_function("style_get_color", |b| {
+        b.iter(|| {
+            colors
+                .iter()
+                .filter_map(|color| color.evaluate_srgb(black_box(ZOOM)))
+                .count()
+        })
+    });
+
+    // Converts the colors on each call, like before they were cached
+    c.bench_function("style_get_color_uncached", |b| {
+        b.iter(|| {
+            colors
+                .iter()
+                .filter_map(|color| {
+                    color
+                        .evaluate(black_box(ZOOM))
+                        .map(Into::<Alpha<EncodedSrgb<f32>>>::into)
+                })
+                .count()
+        })
+    });
+}
+
+criterion_group!(style, get_color);
+criterion_main!(style);
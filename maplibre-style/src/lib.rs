@@ -1,4 +1,7 @@
 mod expression;
+// The schema is only used by the build script. It is compiled here to be able to test it.
+#[cfg(test)]
+mod schema;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,263 @@
+//! Types describing the JSON schema of the style specification, see `style-spec-v8.json`.
+
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use serde::{Deserialize, Deserializer};
+use serde::de::{MapAccess, Visitor};
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum ArrayType {
+    Tuple(Vec<ArrayType>),
+    SimpleReference(String),
+    Reference(Box<JsonSchemaTypeReference>),
+}
+
+/// JSON of the form
+/// ```
+///     {
+///         "type": "...",
+///         ...
+///     }
+///
+/// The type field indicates a reference to a type defined elsewhere or a primitive type. This
+/// enum explicitly specifies the primitives,
+#[derive(Debug)]
+pub(crate) enum JsonSchemaTypeReference {
+    String,
+    Number,
+    Bool,
+    Array {
+        value: ArrayType,
+        length: Option<usize>,
+    },
+    Enum,
+    Reference {
+        r#type: String,
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonSchemaTypeReference {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        #[derive(Deserialize, Debug)]
+        #[serde(field_identifier, rename_all = "lowercase")]
+        enum Field {
+            Type,
+            Value,
+            Length,
+            #[serde(other)]
+            Unknown,
+        }
+
+        struct JsonSchemaTypeReferenceVisitor;
+
+        impl<'de> Visitor<'de> for JsonSchemaTypeReferenceVisitor {
+            type Value = JsonSchemaTypeReference;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("struct JsonSchemaTypeReference")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<JsonSchemaTypeReference, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut r#type = None;
+                let mut value = None;
+                let mut length = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        Field::Type => {
+                            if r#type.is_some() {
+                                return Err(serde::de::Error::duplicate_field("type"));
+                            }
+                            r#type = Some(map.next_value::<String>()?);
+                        }
+                        Field::Value => {
+                            if value.is_some() {
+                                return Err(serde::de::Error::duplicate_field("value"));
+                            }
+                            value = Some(map.next_value::<ArrayType>()?);
+                        }
+                        Field::Length => {
+                            if length.is_some() {
+                                return Err(serde::de::Error::duplicate_field("length"));
+                            }
+                            length = Some(map.next_value::<usize>()?);
+                        }
+                        Field::Unknown => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                };
+
+                let r#type = r#type.ok_or_else(|| serde::de::Error::missing_field("type"))?;
+
+                match r#type.as_str() {
+                    "string" => Ok(JsonSchemaTypeReference::String),
+                    "number" => Ok(JsonSchemaTypeReference::Number),
+                    "boolean" => Ok(JsonSchemaTypeReference::Bool),
+                    "array" => {
+                        Ok(JsonSchemaTypeReference::Array {
+                            value: value.ok_or_else(|| serde::de::Error::missing_field("value"))?,
+                            length,
+                        })
+                    }
+                    "enum" => Ok(JsonSchemaTypeReference::Enum),
+                    _ => Ok(JsonSchemaTypeReference::Reference { r#type }),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(JsonSchemaTypeReferenceVisitor)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum JsonSchemaTypedef {
+    TypeReference(JsonSchemaTypeReference),
+    UnionType(Vec<String>),
+    Object(HashMap<String, JsonSchemaTypedef>),
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct JsonSchema {
+    #[serde(rename="$version")]
+    pub(crate) version: u64,
+    #[serde(rename="$root")]
+    pub(crate) root: JsonSchemaTypedef,
+    #[serde(flatten)]
+    pub(crate) types: HashMap<String, JsonSchemaTypedef>,
+}
+
+/// Converts a schema type name like `paint_fill` to the name of the generated Rust type.
+fn rust_type_name(name: &str) -> String {
+    name.split(['_', '-'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Resolves primitive schema types to Rust types. Other names reference a generated type.
+fn resolve_type_name(name: &str) -> String {
+    match name {
+        "string" | "enum" => "String".to_string(),
+        "number" => "f64".to_string(),
+        "boolean" => "bool".to_string(),
+        "*" => "serde_json::Value".to_string(),
+        _ => rust_type_name(name),
+    }
+}
+
+impl ArrayType {
+    /// Returns the Rust type of an array of this element type. Arrays with a `length` become
+    /// fixed-size arrays. A tuple element type whose arity matches `length` describes the whole
+    /// array and becomes a Rust tuple.
+    pub(crate) fn rust_array_type(&self, length: Option<usize>) -> String {
+        match (self, length) {
+            (ArrayType::Tuple(types), length) if length.is_none_or(|length| length == types.len()) => {
+                self.rust_element_type()
+            }
+            (_, Some(length)) => format!("[{}; {length}]", self.rust_element_type()),
+            (_, None) => format!("Vec<{}>", self.rust_element_type()),
+        }
+    }
+
+    fn rust_element_type(&self) -> String {
+        match self {
+            ArrayType::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.rust_element_type()).collect();
+                format!("({})", types.join(", "))
+            }
+            ArrayType::SimpleReference(name) => resolve_type_name(name),
+            ArrayType::Reference(reference) => reference.rust_type(),
+        }
+    }
+}
+
+impl JsonSchemaTypeReference {
+    /// Returns the Rust type which holds a value of this schema type.
+    pub(crate) fn rust_type(&self) -> String {
+        match self {
+            JsonSchemaTypeReference::String | JsonSchemaTypeReference::Enum => "String".to_string(),
+            JsonSchemaTypeReference::Number => "f64".to_string(),
+            JsonSchemaTypeReference::Bool => "bool".to_string(),
+            JsonSchemaTypeReference::Array { value, length } => value.rust_array_type(*length),
+            JsonSchemaTypeReference::Reference { r#type } => resolve_type_name(r#type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonSchema, JsonSchemaTypeReference, JsonSchemaTypedef};
+
+    fn rust_type(json: &str) -> String {
+        serde_json::from_str::<JsonSchemaTypeReference>(json)
+            .unwrap()
+            .rust_type()
+    }
+
+    #[test]
+    fn test_style_spec() {
+        let schema: JsonSchema = serde_json::from_str(include_str!("../style-spec-v8.json")).unwrap();
+        assert_eq!(schema.version, 8);
+
+        let JsonSchemaTypedef::Object(root) = schema.root else {
+            panic!("expected the root to be an object");
+        };
+        let Some(JsonSchemaTypedef::TypeReference(layers)) = root.get("layers") else {
+            panic!("expected the layers to be a type reference");
+        };
+        assert_eq!(layers.rust_type(), "Vec<Layer>");
+
+        let Some(JsonSchemaTypedef::UnionType(sources)) = schema.types.get("source") else {
+            panic!("expected the source to be a union type");
+        };
+        assert!(sources.contains(&"source_vector".to_string()));
+    }
+
+    #[test]
+    fn test_fixed_length_array() {
+        assert_eq!(rust_type(r#"{"type": "array", "value": "color", "length": 2}"#), "[Color; 2]");
+        assert_eq!(rust_type(r#"{"type": "array", "value": "number", "length": 4}"#), "[f64; 4]");
+    }
+
+    #[test]
+    fn test_variable_length_array() {
+        assert_eq!(rust_type(r#"{"type": "array", "value": "string"}"#), "Vec<String>");
+        assert_eq!(rust_type(r#"{"type": "array", "value": "layer"}"#), "Vec<Layer>");
+        assert_eq!(rust_type(r#"{"type": "array", "value": "function_stop"}"#), "Vec<FunctionStop>");
+    }
+
+    #[test]
+    fn test_tuple_array() {
+        assert_eq!(
+            rust_type(r#"{"type": "array", "value": ["number", "color"], "length": 2}"#),
+            "(f64, Color)"
+        );
+        assert_eq!(
+            rust_type(r#"{"type": "array", "value": ["number", "color"], "length": 3}"#),
+            "[(f64, Color); 3]"
+        );
+    }
+
+    #[test]
+    fn test_nested_array() {
+        assert_eq!(
+            rust_type(r#"{"type": "array", "length": 4, "value": {"type": "array", "length": 2, "value": "number"}}"#),
+            "[[f64; 2]; 4]"
+        );
+    }
+}
@@ -120,6 +120,8 @@ pub fn run_headed_map<P>(
                 TokioScheduler::new(),
                 OffscreenKernelConfig {
                     cache_directory: cache_path.map(|path| path.to_str().unwrap().to_string()),
+                    fetch_policy: Default::default(),
+                    tile_cache: Default::default(),
                 },
             ))
             .with_scheduler(TokioScheduler::new())
@@ -1,7 +1,12 @@
 use crate::{
-    render::{view_state::ViewState, Renderer},
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        view_state::ViewState,
+        Renderer,
+    },
     style::Style,
     tcs::world::World,
+    vector::{retessellate_for_style_change, VectorBufferPool},
     window::PhysicalSize,
 };
 
@@ -21,4 +26,26 @@ impl MapContext {
         self.view_state.resize(size.to_logical(scale_factor));
         self.renderer.resize_surface(size)
     }
+
+    /// Replaces the style of the map. Only the layers which changed are tessellated or uploaded
+    /// again, see [`retessellate_for_style_change`].
+    pub fn set_style(&mut self, style: Style) {
+        let diff = retessellate_for_style_change(&mut self.world.tiles, &self.style, &style);
+        self.style = style;
+
+        if let Some(Initialized(buffer_pool)) = self
+            .world
+            .resources
+            .query_mut::<&mut Eventually<VectorBufferPool>>()
+        {
+            let outdated = diff
+                .retessellate
+                .iter()
+                .chain(&diff.reupload)
+                .chain(&diff.removed)
+                .cloned()
+                .collect();
+            buffer_pool.invalidate_layers(&outdated);
+        }
+    }
 }
@@ -17,7 +17,7 @@ use crate::{
         SignificantlyDifferent,
     },
 };
-use crate::render::tile_view_pattern::MAX_ZOOM_LEVEL;
+use crate::render::{settings::CameraMode, tile_view_pattern::MAX_ZOOM_LEVEL};
 
 pub const EXTENT_UINT: u32 = 4096;
 pub const EXTENT_SINT: i32 = EXTENT_UINT as i32;
@@ -44,7 +44,7 @@ const fn create_zoom_bounds<const DIM: usize>() -> [u32; DIM] {
 /// subdivision of the quadtree is concerned.
 ///
 /// TODO: We can optimize the quadkey and store the keys on 2 bits instead of 8
-#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Copy)]
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Quadkey([ZoomLevel; MAX_ZOOM]);
 
 impl Quadkey {
@@ -96,6 +96,12 @@ impl ZoomLevel {
     pub fn is_root(self) -> bool {
         self.0 == 0
     }
+
+    /// Iterates the zoom levels from `min` up to and including `max`. Empty if `min` is greater
+    /// than `max`.
+    pub fn range_inclusive(min: ZoomLevel, max: ZoomLevel) -> impl Iterator<Item = ZoomLevel> {
+        (min.0..=max.0).map(ZoomLevel)
+    }
 }
 
 impl std::ops::Add<u8> for ZoomLevel {
@@ -140,7 +146,7 @@ impl From<ZoomLevel> for f64 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct LatLon {
     pub latitude: f64,
     pub longitude: f64,
@@ -168,6 +174,15 @@ impl LatLon {
         Self::EARTH_CIRCUMFRENCE * (self.latitude * PI / 180.0).cos()
     }
 
+    /// Normalizes the longitude to the range `[-180, 180)` by wrapping it around the
+    /// anti-meridian.
+    pub fn wrap(self) -> LatLon {
+        LatLon {
+            latitude: self.latitude,
+            longitude: (self.longitude + 180.0).rem_euclid(360.0) - 180.0,
+        }
+    }
+
     fn mercator_x_from_lng(&self) -> f64 {
         (180.0 + self.longitude) / 360.0
     }
@@ -207,12 +222,18 @@ impl Zoom {
     }
 }
 
-impl Zoom {
-    pub fn from(zoom_level: ZoomLevel) -> Self {
+impl From<ZoomLevel> for Zoom {
+    fn from(zoom_level: ZoomLevel) -> Self {
         Zoom(zoom_level.0 as f64)
     }
 }
 
+impl From<Zoom> for f64 {
+    fn from(zoom: Zoom) -> Self {
+        zoom.0
+    }
+}
+
 impl Default for Zoom {
     fn default() -> Self {
         Zoom(0.0)
@@ -373,17 +394,18 @@ pub struct WorldTileCoords {
 }
 
 impl WorldTileCoords {
-    /// Returns the tile coords according to an addressing scheme. This is not possible if the
-    /// coordinates of this [`WorldTileCoords`] exceed their bounds.
+    /// Returns the tile coords according to an addressing scheme. The x coordinate is wrapped
+    /// around the anti-meridian. This is not possible if the y coordinate of this
+    /// [`WorldTileCoords`] exceeds its bounds.
     ///
     /// # Example
     ///
     /// The [`WorldTileCoords`] `WT(x=5,y=5,z=0)` exceeds its bounds because there is no tile
-    /// `x=5,y=5` at zoom level `z=0`.
+    /// `y=5` at zoom level `z=0`.
     pub fn into_tile(self, scheme: TileAddressingScheme) -> Option<TileCoords> {
         // FIXME: MAX_ZOOM is 32, which means max bound is 2^32, which wouldn't fit in u32 or i32
         let bounds = ZOOM_BOUNDS[self.z.0 as usize];
-        let x = self.x as u32;
+        let x = self.wrap().x as u32;
         let y = self.y as u32;
 
         if x >= bounds || y >= bounds {
@@ -400,6 +422,89 @@ impl WorldTileCoords {
         })
     }
 
+    /// Wraps the x coordinate around the anti-meridian such that it lies within `0..2^z`. Tiles
+    /// which lie left or right of the world show the same data as their wrapped tile.
+    pub fn wrap(self) -> WorldTileCoords {
+        let bounds = ZOOM_BOUNDS[self.z.0 as usize] as i64;
+        WorldTileCoords {
+            x: (self.x as i64).rem_euclid(bounds) as i32,
+            y: self.y,
+            z: self.z,
+        }
+    }
+
+    /// Returns whether both coordinates refer to the same tile after wrapping them around the
+    /// anti-meridian.
+    pub fn wrapped_eq(&self, other: &WorldTileCoords) -> bool {
+        self.wrap() == other.wrap()
+    }
+
+    /// Returns all tiles at zoom level `z` which cover the bounding box in degrees. The tiles are
+    /// ordered row by row. Latitudes are clamped to the bounds of Web Mercator.
+    pub fn tiles_for_bbox(
+        min_lng: f64,
+        min_lat: f64,
+        max_lng: f64,
+        max_lat: f64,
+        z: ZoomLevel,
+    ) -> Vec<WorldTileCoords> {
+        const MAX_LATITUDE: f64 = 85.0511287798066;
+
+        let bounds = ZOOM_BOUNDS[z.0 as usize] as f64;
+        let to_tile = |fraction: f64| (fraction * bounds).floor().clamp(0.0, bounds - 1.0) as i32;
+
+        let north_west = LatLon::new(max_lat.clamp(-MAX_LATITUDE, MAX_LATITUDE), min_lng);
+        let south_east = LatLon::new(min_lat.clamp(-MAX_LATITUDE, MAX_LATITUDE), max_lng);
+
+        let min_x = to_tile(north_west.mercator_x_from_lng());
+        let max_x = to_tile(south_east.mercator_x_from_lng());
+        let min_y = to_tile(north_west.mercator_y_from_lat());
+        let max_y = to_tile(south_east.mercator_y_from_lat());
+
+        (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| WorldTileCoords { x, y, z }))
+            .collect()
+    }
+
+    /// Returns all tiles from `min_zoom` up to and including `max_zoom` which cover the bounding
+    /// box `(min_lng, min_lat, max_lng, max_lat)` in degrees, e.g. to seed a region for offline
+    /// use. Each zoom level is covered like in [`WorldTileCoords::tiles_for_bbox`], lower zoom
+    /// levels come first.
+    pub fn pyramid(
+        bbox: (f64, f64, f64, f64),
+        min_zoom: ZoomLevel,
+        max_zoom: ZoomLevel,
+    ) -> impl Iterator<Item = WorldTileCoords> {
+        let (min_lng, min_lat, max_lng, max_lat) = bbox;
+        ZoomLevel::range_inclusive(min_zoom, max_zoom)
+            .flat_map(move |z| Self::tiles_for_bbox(min_lng, min_lat, max_lng, max_lat, z))
+    }
+
+    /// Returns the position of a point within this tile, which is given in tile-local coordinates
+    /// ranging from 0 to `extent`.
+    pub fn lat_lon_at(&self, x: f64, y: f64, extent: f64) -> LatLon {
+        let bounds = ZOOM_BOUNDS[self.z.0 as usize] as f64;
+        let mercator_x = (self.x as f64 + x / extent) / bounds;
+        let mercator_y = (self.y as f64 + y / extent) / bounds;
+
+        let longitude = mercator_x * 360.0 - 180.0;
+        let latitude = (PI * (1.0 - 2.0 * mercator_y)).sinh().atan().to_degrees();
+        LatLon::new(latitude, longitude)
+    }
+
+    /// Returns the geographic bounds of this tile in degrees as `(min_lng, min_lat, max_lng,
+    /// max_lat)`, which is the order [`WorldTileCoords::tiles_for_bbox`] expects.
+    pub fn lat_lng_bounds(&self) -> (f64, f64, f64, f64) {
+        let north_west = self.lat_lon_at(0.0, 0.0, 1.0);
+        let south_east = self.lat_lon_at(1.0, 1.0, 1.0);
+        (
+            north_west.longitude,
+            south_east.latitude,
+            south_east.longitude,
+            north_west.latitude,
+        )
+    }
+
     /// Adopted from
     /// [Transform::calculatePosMatrix](https://github.com/maplibre/maplibre-gl-js/blob/80e232a64716779bfff841dbc18fddc1f51535ad/src/geo/transform.ts#L719-L731)
     #[tracing::instrument(skip_all)]
@@ -436,10 +541,14 @@ impl WorldTileCoords {
     }
 
     /// Adopted from [tilebelt](https://github.com/mapbox/tilebelt)
+    ///
+    /// The x coordinate is wrapped around the anti-meridian, so tiles which are wrapped to the same
+    /// tile share their key.
     pub fn build_quad_key(&self) -> Option<Quadkey> {
         let bounds = ZOOM_BOUNDS[self.z.0 as usize];
-        let x = self.x as u32;
-        let y = self.y as u32;
+        let wrapped = self.wrap();
+        let x = wrapped.x as u32;
+        let y = wrapped.y as u32;
 
         if x >= bounds || y >= bounds {
             return None;
@@ -452,10 +561,10 @@ impl WorldTileCoords {
         for z in 1..self.z.0 + 1 {
             let mut b = 0;
             let mask: i32 = 1 << (z - 1);
-            if (self.x & mask) != 0 {
+            if (wrapped.x & mask) != 0 {
                 b += 1u8;
             }
-            if (self.y & mask) != 0 {
+            if (wrapped.y & mask) != 0 {
                 b += 2u8;
             }
             key[z as usize] = ZoomLevel::from(b);
@@ -515,6 +624,26 @@ impl WorldTileCoords {
             (false, false) => 3 + z * CASES,
         }
     }
+
+    /// Returns unique stencil reference values for WorldTileCoords which are viewed top-down.
+    /// Neighboring tiles within a 8x8 window are distinct, as long as they are at most 4 zoom
+    /// levels apart.
+    pub fn stencil_reference_value_2d(&self) -> u8 {
+        const WINDOW: i32 = 8;
+        const ZOOM_LEVELS: u8 = 4;
+        let x = self.x.rem_euclid(WINDOW) as u8;
+        let y = self.y.rem_euclid(WINDOW) as u8;
+        let z = u8::from(self.z) % ZOOM_LEVELS;
+        x + y * WINDOW as u8 + z * (WINDOW * WINDOW) as u8
+    }
+
+    /// Returns the stencil reference value of the scheme which fits the `camera_mode`.
+    pub fn stencil_reference_value(&self, camera_mode: CameraMode) -> u8 {
+        match camera_mode {
+            CameraMode::Perspective => self.stencil_reference_value_3d(),
+            CameraMode::TopDown => self.stencil_reference_value_2d(),
+        }
+    }
 }
 
 impl From<(i32, i32, ZoomLevel)> for WorldTileCoords {
@@ -582,6 +711,7 @@ pub struct WorldCoords {
 
 impl WorldCoords {
     pub fn from_lat_lon(lat_lon: LatLon, zoom: Zoom) -> WorldCoords {
+        let lat_lon = lat_lon.wrap();
         let tile_size = TILE_SIZE * 2.0_f64.powf(zoom.0);
         // Get x value
         let x = (lat_lon.longitude + 180.0) * (tile_size / 360.0);
@@ -687,6 +817,39 @@ impl ViewRegion {
             && world_coords.z == self.zoom_level
     }
 
+    /// The distance of the tile at `world_coords` to the center of the region, in tiles.
+    pub fn distance_to_center(&self, world_coords: &WorldTileCoords) -> f64 {
+        let center_x = (self.min_tile.x + self.max_tile.x) as f64 / 2.0;
+        let center_y = (self.min_tile.y + self.max_tile.y) as f64 / 2.0;
+        (world_coords.x as f64 - center_x).hypot(world_coords.y as f64 - center_y)
+    }
+
+    /// The priority with which the tile at `world_coords` is processed, lower values first, see
+    /// [`WorkerPool`](crate::io::worker_pool::WorkerPool). Tiles at the zoom level of the region
+    /// are ordered by their distance to the center. Tiles at other zoom levels rank behind all
+    /// tiles of the region, the further behind the more their zoom level is off. Their distance is
+    /// measured from their center in the tile grid of the region.
+    pub fn priority(&self, world_coords: &WorldTileCoords) -> f64 {
+        let zoom_offset =
+            i32::from(u8::from(self.zoom_level)) - i32::from(u8::from(world_coords.z));
+        if zoom_offset == 0 {
+            return self.distance_to_center(world_coords);
+        }
+
+        let scale = 2f64.powi(zoom_offset);
+        let to_region = |coordinate: i32| (coordinate as f64 + 0.5) * scale - 0.5;
+        let center_x = (self.min_tile.x + self.max_tile.x) as f64 / 2.0;
+        let center_y = (self.min_tile.y + self.max_tile.y) as f64 / 2.0;
+        let distance =
+            (to_region(world_coords.x) - center_x).hypot(to_region(world_coords.y) - center_y);
+
+        // Larger than the distance of any tile of the region to its center
+        let span = 2 * self.padding + 1;
+        let region_diagonal = ((self.max_tile.x - self.min_tile.x + span) as f64)
+            .hypot((self.max_tile.y - self.min_tile.y + span) as f64);
+        zoom_offset.unsigned_abs() as f64 * region_diagonal + distance
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = WorldTileCoords> + '_ {
         (self.min_tile.x - self.padding..self.max_tile.x + 1 + self.padding)
             .flat_map(move |x| {
@@ -730,13 +893,17 @@ impl Display for WorldCoords {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use cgmath::{Point2, Vector4};
 
     use crate::{
         coords::{
-            Quadkey, TileCoords, ViewRegion, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EXTENT,
+            LatLon, Quadkey, TileCoords, ViewRegion, WorldCoords, WorldTileCoords, Zoom, ZoomLevel,
+            EXTENT,
         },
-        render::tile_view_pattern::DEFAULT_TILE_SIZE,
+        io::worker_pool::WorkerPool,
+        render::{settings::CameraMode, tile_view_pattern::DEFAULT_TILE_SIZE},
         style::source::TileAddressingScheme,
         util::math::Aabb2,
     };
@@ -812,6 +979,210 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_wrap_anti_meridian() {
+        let z = ZoomLevel::new(2);
+        let past_east = WorldTileCoords::from((4, 1, z));
+        let past_west = WorldTileCoords::from((-1, 1, z));
+
+        assert_eq!(past_east.wrap(), WorldTileCoords::from((0, 1, z)));
+        assert_eq!(past_west.wrap(), WorldTileCoords::from((3, 1, z)));
+        assert!(past_east.wrapped_eq(&(8, 1, z).into()));
+        assert!(!past_east.wrapped_eq(&(1, 1, z).into()));
+
+        assert_eq!(
+            past_east.into_tile(TileAddressingScheme::XYZ),
+            Some(TileCoords { x: 0, y: 1, z })
+        );
+        assert_eq!(
+            past_west.into_tile(TileAddressingScheme::XYZ),
+            Some(TileCoords { x: 3, y: 1, z })
+        );
+        assert_eq!(
+            past_east.build_quad_key(),
+            WorldTileCoords::from((0, 1, z)).build_quad_key()
+        );
+
+        // The y coordinate does not wrap
+        assert_eq!(
+            WorldTileCoords::from((0, 4, z)).into_tile(TileAddressingScheme::XYZ),
+            None
+        );
+        assert_eq!(WorldTileCoords::from((0, -1, z)).build_quad_key(), None);
+    }
+
+    #[test]
+    fn test_wrap_longitude() {
+        assert_eq!(LatLon::new(10.0, 190.0).wrap(), LatLon::new(10.0, -170.0));
+        assert_eq!(LatLon::new(10.0, -190.0).wrap(), LatLon::new(10.0, 170.0));
+        assert_eq!(LatLon::new(10.0, 180.0).wrap(), LatLon::new(10.0, -180.0));
+        assert_eq!(LatLon::new(10.0, 45.0).wrap(), LatLon::new(10.0, 45.0));
+
+        assert_eq!(
+            WorldCoords::from_lat_lon(LatLon::new(0.0, 540.0), Zoom::new(0.0)),
+            WorldCoords::from_lat_lon(LatLon::new(0.0, -180.0), Zoom::new(0.0))
+        );
+    }
+
+    #[test]
+    fn test_tiles_for_bbox() {
+        let z = ZoomLevel::new(10);
+        let tiles = WorldTileCoords::tiles_for_bbox(13.3, 52.45, 13.5, 52.55, z);
+
+        assert_eq!(
+            tiles,
+            vec![
+                WorldTileCoords::from((549, 335, z)),
+                WorldTileCoords::from((550, 335, z)),
+                WorldTileCoords::from((549, 336, z)),
+                WorldTileCoords::from((550, 336, z)),
+            ]
+        );
+
+        // A bbox within a single tile
+        assert_eq!(
+            WorldTileCoords::tiles_for_bbox(13.40, 52.50, 13.41, 52.51, z),
+            vec![WorldTileCoords::from((550, 335, z))]
+        );
+
+        // The whole world
+        assert_eq!(
+            WorldTileCoords::tiles_for_bbox(-180.0, -90.0, 180.0, 90.0, ZoomLevel::new(2)).len(),
+            16
+        );
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let world = (-180.0, -90.0, 180.0, 90.0);
+        let tiles: Vec<_> =
+            WorldTileCoords::pyramid(world, ZoomLevel::new(0), ZoomLevel::new(2)).collect();
+        assert_eq!(tiles.len(), 1 + 4 + 16);
+        for z in 0..=2 {
+            let count = tiles.iter().filter(|tile| tile.z == ZoomLevel::new(z)).count();
+            assert_eq!(count, 4usize.pow(z as u32));
+        }
+        assert_eq!(tiles[0], WorldTileCoords::from((0, 0, ZoomLevel::new(0))));
+        assert!(tiles.windows(2).all(|pair| pair[0].z <= pair[1].z));
+
+        // Each zoom level is covered by the tiles of the bbox of a single tile
+        let bounds = WorldTileCoords::from((550, 335, ZoomLevel::new(10))).lat_lng_bounds();
+        let (min_lng, min_lat, max_lng, max_lat) = bounds;
+        let shrunk = (min_lng + 1e-6, min_lat + 1e-6, max_lng - 1e-6, max_lat - 1e-6);
+        let tiles: Vec<_> =
+            WorldTileCoords::pyramid(shrunk, ZoomLevel::new(10), ZoomLevel::new(11)).collect();
+        assert_eq!(tiles.len(), 1 + 4);
+
+        assert_eq!(
+            WorldTileCoords::pyramid(world, ZoomLevel::new(2), ZoomLevel::new(1)).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_lat_lon_at() {
+        let tile = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+
+        let center = tile.lat_lon_at(2048.0, 2048.0, 4096.0);
+        assert!(center.latitude.abs() < 1e-9 && center.longitude.abs() < 1e-9);
+
+        let north_west = tile.lat_lon_at(0.0, 0.0, 4096.0);
+        assert!((north_west.longitude + 180.0).abs() < 1e-9);
+        assert!((north_west.latitude - 85.0511287798066).abs() < 1e-9);
+
+        // The south-east corner of the north-west tile at zoom level 1 is the center
+        let tile = WorldTileCoords::from((0, 0, ZoomLevel::new(1)));
+        let center = tile.lat_lon_at(4096.0, 4096.0, 4096.0);
+        assert!(center.latitude.abs() < 1e-9 && center.longitude.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lat_lng_bounds() {
+        let assert_bounds = |actual: (f64, f64, f64, f64), expected: (f64, f64, f64, f64)| {
+            assert!((actual.0 - expected.0).abs() < 1e-9, "{actual:?} != {expected:?}");
+            assert!((actual.1 - expected.1).abs() < 1e-9, "{actual:?} != {expected:?}");
+            assert!((actual.2 - expected.2).abs() < 1e-9, "{actual:?} != {expected:?}");
+            assert!((actual.3 - expected.3).abs() < 1e-9, "{actual:?} != {expected:?}");
+        };
+
+        // The whole world
+        assert_bounds(
+            WorldTileCoords::from((0, 0, ZoomLevel::new(0))).lat_lng_bounds(),
+            (-180.0, -85.0511287798066, 180.0, 85.0511287798066),
+        );
+
+        // Berlin at zoom level 10
+        let tile = WorldTileCoords::from((550, 335, ZoomLevel::new(10)));
+        let bounds = tile.lat_lng_bounds();
+        assert_bounds(bounds, (13.359375, 52.482780222078, 13.7109375, 52.696361078274));
+
+        // The tile covers its own bounds, shrunk to exclude the neighbouring tiles
+        let (min_lng, min_lat, max_lng, max_lat) = bounds;
+        assert_eq!(
+            WorldTileCoords::tiles_for_bbox(
+                min_lng + 1e-6,
+                min_lat + 1e-6,
+                max_lng - 1e-6,
+                max_lat - 1e-6,
+                ZoomLevel::new(10)
+            ),
+            vec![tile]
+        );
+    }
+
+    /// The stencil reference values of a `size` x `size` window of tiles at `z`.
+    fn stencil_references(
+        size: i32,
+        z: ZoomLevel,
+        stencil_reference: impl Fn(&WorldTileCoords) -> u8,
+    ) -> Vec<u8> {
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| WorldTileCoords::from((x + 5, y + 5, z))))
+            .map(|coords| stencil_reference(&coords))
+            .collect()
+    }
+
+    fn assert_distinct(references: &[u8]) {
+        let distinct: HashSet<_> = references.iter().collect();
+        assert_eq!(distinct.len(), references.len(), "{references:?}");
+    }
+
+    #[test]
+    fn test_stencil_reference_values() {
+        let z = ZoomLevel::new(10);
+
+        // Direct neighbors are distinct in both schemes, including their parents
+        for camera_mode in [CameraMode::Perspective, CameraMode::TopDown] {
+            let reference = |coords: &WorldTileCoords| coords.stencil_reference_value(camera_mode);
+            let mut references = stencil_references(2, z, reference);
+            references.extend(stencil_references(2, ZoomLevel::new(9), reference));
+            assert_distinct(&references);
+        }
+
+        // The 2D scheme distinguishes all tiles of an 8x8 window at 4 zoom levels
+        let references: Vec<u8> = (8..12)
+            .flat_map(|z| {
+                stencil_references(8, ZoomLevel::new(z), WorldTileCoords::stencil_reference_value_2d)
+            })
+            .collect();
+        assert_eq!(references.len(), 256);
+        assert_distinct(&references);
+
+        // The 3D scheme repeats within a 3x3 window, but distinguishes all zoom levels
+        let references = stencil_references(3, z, WorldTileCoords::stencil_reference_value_3d);
+        assert_eq!(references.iter().collect::<HashSet<_>>().len(), 4);
+        let references: Vec<u8> = (0..20)
+            .flat_map(|z| {
+                stencil_references(2, ZoomLevel::new(z), WorldTileCoords::stencil_reference_value_3d)
+            })
+            .collect();
+        assert_distinct(&references);
+
+        // Negative coordinates wrap around
+        let coords = WorldTileCoords::from((-1, -8, z));
+        assert_eq!(coords.stencil_reference_value_2d(), 7 + 2 * 64);
+    }
+
     #[test]
     fn test_view_region() {
         for tile_coords in ViewRegion::new(
@@ -826,4 +1197,49 @@ mod tests {
             println!("{tile_coords}");
         }
     }
+
+    #[test]
+    fn test_view_region_priority() {
+        // The tiles (0, 0) to (3, 3) at zoom level 3 with the center between (1, 1) and (2, 2)
+        let view_region = ViewRegion::new(
+            Aabb2::new(Point2::new(0.0, 0.0), Point2::new(2000.0, 2000.0)),
+            0,
+            32,
+            Zoom::new(3.0),
+            ZoomLevel::new(3),
+        );
+        let coords = |x, y, z| WorldTileCoords::from((x, y, ZoomLevel::new(z)));
+
+        let mut pool = WorkerPool::new(1);
+        for coords in [
+            coords(0, 0, 2),
+            coords(3, 3, 3),
+            coords(0, 0, 1),
+            coords(1, 1, 3),
+            coords(0, 2, 3),
+            coords(2, 2, 3),
+            coords(0, 0, 4),
+        ] {
+            pool.push(view_region.priority(&coords), coords);
+        }
+
+        let mut dequeued = Vec::new();
+        while let Some((_, coords)) = pool.pop() {
+            dequeued.push(coords);
+        }
+        assert_eq!(
+            dequeued,
+            vec![
+                // The center-most tiles go first
+                coords(1, 1, 3),
+                coords(2, 2, 3),
+                coords(0, 2, 3),
+                coords(3, 3, 3),
+                // Followed by the tiles whose zoom level is off by one, nearest first
+                coords(0, 0, 2),
+                coords(0, 0, 4),
+                coords(0, 0, 1),
+            ]
+        );
+    }
 }
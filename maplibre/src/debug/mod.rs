@@ -63,8 +63,35 @@ impl PhaseItem for TileDebugItem {
     }
 }
 
+/// Controls the debug grid which outlines the visible tiles. Can be changed at runtime through the
+/// resources of the world.
+#[derive(Clone, Copy, Debug)]
+pub struct TileGridSettings {
+    pub enabled: bool,
+    /// Whether each tile is labeled with its `z/x/y` coordinates.
+    pub labels: bool,
+}
+
+impl Default for TileGridSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            labels: false,
+        }
+    }
+}
+
 #[derive(Default)]
-pub struct DebugPlugin;
+pub struct DebugPlugin {
+    pub tile_grid: TileGridSettings,
+}
+
+impl DebugPlugin {
+    pub fn with_tile_grid(mut self, tile_grid: TileGridSettings) -> Self {
+        self.tile_grid = tile_grid;
+        self
+    }
+}
 
 impl<E: Environment> Plugin<E> for DebugPlugin {
     fn build(
@@ -85,6 +112,7 @@ impl<E: Environment> Plugin<E> for DebugPlugin {
 
         resources.init::<RenderPhase<TileDebugItem>>();
         resources.insert(Eventually::<DebugPipeline>::Uninitialized);
+        resources.insert(self.tile_grid);
 
         schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
         schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
@@ -1,7 +1,7 @@
 //! Queues [PhaseItems](crate::render::render_phase::PhaseItem) for rendering.
 use crate::{
     context::MapContext,
-    debug::{render_commands::DrawDebugOutlines, TileDebugItem},
+    debug::{render_commands::DrawTileGrids, TileDebugItem, TileGridSettings},
     render::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{DrawState, RenderPhase},
@@ -10,6 +10,14 @@ use crate::{
 };
 
 pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
+    if !world
+        .resources
+        .get::<TileGridSettings>()
+        .is_some_and(|settings| settings.enabled)
+    {
+        return;
+    }
+
     let Some((Initialized(tile_view_pattern), tile_debug_phase)) = world.resources.query_mut::<(
         &mut Eventually<WgpuTileViewPattern>,
         &mut RenderPhase<TileDebugItem>,
@@ -25,7 +33,7 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
         view_tile.render(|source_shape| {
             // Draw masks for all source_shapes
             tile_debug_phase.add(TileDebugItem {
-                draw_function: Box::new(DrawState::<TileDebugItem, DrawDebugOutlines>::new()),
+                draw_function: Box::new(DrawState::<TileDebugItem, DrawTileGrids>::new()),
                 source_shape: source_shape.clone(),
             });
         });
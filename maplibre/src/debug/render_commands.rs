@@ -1,7 +1,10 @@
 //! Specifies the instructions which are going to be sent to the GPU. Render commands can be concatenated
 //! into a new render command which executes multiple instruction sets.
+use std::ops::Range;
+
 use crate::{
-    debug::{DebugPipeline, TileDebugItem},
+    coords::WorldTileCoords,
+    debug::{DebugPipeline, TileDebugItem, TileGridSettings},
     render::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{PhaseItem, RenderCommand, RenderCommandResult},
@@ -27,8 +30,48 @@ impl<P: PhaseItem> RenderCommand<P> for SetDebugPipeline {
     }
 }
 
-pub struct DrawDebugOutline;
-impl RenderCommand<TileDebugItem> for DrawDebugOutline {
+/// Number of vertices of the outline of a tile, see `tile_debug.vertex.wgsl`.
+const OUTLINE_VERTICES: u32 = 24;
+/// Number of vertices of a glyph of a label. Each glyph has 8 segments made of 6 vertices.
+const GLYPH_VERTICES: u32 = 48;
+/// Number of glyphs the shader can draw, the digits 0-9 and '/'.
+const GLYPH_COUNT: u32 = 11;
+
+/// Returns the glyph of `character` as it is indexed by the shader.
+fn glyph(character: char) -> Option<u32> {
+    match character {
+        '/' => Some(10),
+        character => character.to_digit(10),
+    }
+}
+
+/// Returns the vertex ranges which draw the `z/x/y` label of the tile at `coords`. The shader
+/// decodes the position and the glyph of each character from the vertex index.
+fn label_vertices(coords: WorldTileCoords) -> Vec<Range<u32>> {
+    format!("{}/{}/{}", coords.z, coords.x, coords.y)
+        .chars()
+        .enumerate()
+        .filter_map(|(position, character)| {
+            let slot = position as u32 * GLYPH_COUNT + glyph(character)?;
+            let start = OUTLINE_VERTICES + slot * GLYPH_VERTICES;
+            Some(start..start + GLYPH_VERTICES)
+        })
+        .collect()
+}
+
+/// Returns the vertex ranges which draw the grid cell of the tile at `coords`.
+fn tile_grid_vertices(coords: WorldTileCoords, labels: bool) -> Vec<Range<u32>> {
+    let mut vertices = Vec::with_capacity(1);
+    vertices.push(0..OUTLINE_VERTICES);
+    if labels {
+        vertices.extend(label_vertices(coords));
+    }
+    vertices
+}
+
+/// Outlines a tile and labels it with its coordinates if [`TileGridSettings::labels`] is set.
+pub struct DrawTileGrid;
+impl RenderCommand<TileDebugItem> for DrawTileGrid {
     fn render<'w>(
         world: &'w World,
         item: &TileDebugItem,
@@ -42,19 +85,74 @@ impl RenderCommand<TileDebugItem> for DrawDebugOutline {
 
         let source_shape = &item.source_shape;
 
-        let tile_view_pattern_buffer = source_shape
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
-        pass.set_vertex_buffer(
-            0,
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
+        let tile_view_pattern_buffer = match tile_view_pattern.shape_slice(source_shape) {
+            Ok(slice) => slice,
+            Err(error) => {
+                log::error!("Drawing the tile grid failed: {error}");
+                return RenderCommandResult::Failure;
+            }
+        };
+        pass.set_vertex_buffer(0, tile_view_pattern_buffer);
 
-        const TILE_MASK_SHADER_VERTICES: u32 = 24;
-        pass.draw(0..TILE_MASK_SHADER_VERTICES, 0..1);
+        let labels = world
+            .resources
+            .get::<TileGridSettings>()
+            .is_some_and(|settings| settings.labels);
+        for vertices in tile_grid_vertices(source_shape.coords(), labels) {
+            pass.draw(vertices, 0..1);
+        }
 
         RenderCommandResult::Success
     }
 }
 
-pub type DrawDebugOutlines = (SetDebugPipeline, DrawDebugOutline);
+pub type DrawTileGrids = (SetDebugPipeline, DrawTileGrid);
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point2;
+
+    use super::{label_vertices, tile_grid_vertices, GLYPH_VERTICES, OUTLINE_VERTICES};
+    use crate::{
+        coords::{ViewRegion, WorldTileCoords, Zoom, ZoomLevel},
+        util::math::Aabb2,
+    };
+
+    #[test]
+    fn test_draws_for_each_visible_tile() {
+        let view_region = ViewRegion::new(
+            Aabb2::new(Point2::new(0.0, 0.0), Point2::new(1000.0, 1000.0)),
+            0,
+            32,
+            Zoom::new(2.0),
+            ZoomLevel::new(2),
+        );
+
+        let mut tiles = 0;
+        for coords in view_region.iter() {
+            let outline = tile_grid_vertices(coords, false);
+            assert_eq!(outline.len(), 1);
+            assert_eq!(outline[0], 0..OUTLINE_VERTICES);
+
+            // The outline and one draw per character of the label
+            let label = format!("{}/{}/{}", coords.z, coords.x, coords.y);
+            let grid = tile_grid_vertices(coords, true);
+            assert_eq!(grid.len(), 1 + label.len());
+            assert_eq!(grid[0], 0..OUTLINE_VERTICES);
+            tiles += 1;
+        }
+        assert!(tiles > 0);
+    }
+
+    #[test]
+    fn test_label_vertices() {
+        let vertices = label_vertices(WorldTileCoords::from((12, 3, ZoomLevel::new(4))));
+        assert_eq!(vertices.len(), "4/12/3".len());
+
+        // Glyph 4 at position 0, glyph '/' at position 1 and glyph 2 at position 3
+        let glyph_start = |position: u32, glyph: u32| OUTLINE_VERTICES + (position * 11 + glyph) * GLYPH_VERTICES;
+        assert_eq!(vertices[0].start, glyph_start(0, 4));
+        assert_eq!(vertices[1].start, glyph_start(1, 10));
+        assert_eq!(vertices[3], glyph_start(3, 2)..glyph_start(3, 2) + GLYPH_VERTICES);
+    }
+}
@@ -3,8 +3,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     io::{
         apc::AsyncProcedureCall,
+        fetch_policy::FetchPolicy,
         scheduler::Scheduler,
         source_client::{HttpClient, SourceClient},
+        tile_cache::TileCache,
     },
     window::MapWindowConfig,
 };
@@ -32,6 +34,13 @@ pub trait Environment: 'static {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct OffscreenKernelConfig {
     pub cache_directory: Option<String>,
+    /// Timeout and retry policy for fetching tiles within the offscreen kernel.
+    #[serde(default)]
+    pub fetch_policy: FetchPolicy,
+    /// Tiles which were fetched within the offscreen kernel. The cache is shared by clones of the
+    /// config but not transferred to other threads by serialization.
+    #[serde(skip)]
+    pub tile_cache: TileCache,
 }
 
 pub trait OffscreenKernel: Send + Sync + 'static {
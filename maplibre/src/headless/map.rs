@@ -2,7 +2,7 @@ use std::{cell::RefCell, ops::Deref, rc::Rc};
 
 use crate::{
     context::MapContext,
-    coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE},
+    coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EXTENT_UINT, TILE_SIZE},
     headless::environment::HeadlessEnvironment,
     io::{
         apc::{Context, IntoMessage, Message, SendError},
@@ -83,6 +83,7 @@ impl HeadlessMap {
             .expect("unable to spawn tile")
             .insert(VectorLayersDataComponent {
                 done: true,
+                empty: false,
                 layers: layers
                     .into_iter()
                     .map(|layer| {
@@ -90,11 +91,17 @@ impl HeadlessMap {
                             coords: layer.coords,
                             buffer: layer.buffer,
                             feature_indices: layer.feature_indices,
+                            feature_line_width_values: layer.feature_line_width_values,
+                            feature_opacity_values: layer.feature_opacity_values,
+                            feature_properties: layer.feature_properties,
+                            extent: layer.layer_data.extent.unwrap_or(EXTENT_UINT),
                             // TODO(aidangoettsch): this is probably bad
                             style_layer_id: layer.layer_data.name,
                         })
                     })
                     .collect::<Vec<_>>(),
+                pending_chunks: vec![],
+                retessellate: Default::default(),
             });
 
         self.schedule.run(context);
@@ -143,6 +150,11 @@ impl HeadlessMap {
                     .map(|layer| layer.to_string())
                     .collect(),
                 style: self.map_context.style.clone(),
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
             },
             &mut processor,
         )
@@ -43,6 +43,8 @@ pub async fn create_headless_renderer(
             TokioScheduler::new(),
             OffscreenKernelConfig {
                 cache_directory: None,
+                fetch_policy: Default::default(),
+                tile_cache: Default::default(),
             },
         ))
         .with_scheduler(TokioScheduler::new())
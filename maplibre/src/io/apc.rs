@@ -1,13 +1,16 @@
 use std::{
     any::Any,
     cell::RefCell,
+    collections::HashSet,
     fmt::Debug,
     future::Future,
     marker::PhantomData,
     pin::Pin,
     sync::{
+        atomic::{AtomicBool, Ordering},
         mpsc,
         mpsc::{Receiver, Sender},
+        Arc,
     },
     vec::IntoIter,
 };
@@ -75,10 +78,40 @@ pub enum Input {
     TileRequest {
         coords: WorldTileCoords,
         style: Style, // TODO
+        /// Only the style layers with these ids are processed, see
+        /// [`VectorTileRequest::style_layer_ids`](crate::vector::VectorTileRequest::style_layer_ids).
+        style_layer_ids: Option<HashSet<String>>,
+        /// The token is shared with the requesting thread. It is not serialized, so procedures
+        /// which run on the other side of a serialization boundary, like web workers, can not be
+        /// cancelled. They run to completion and their results for despawned tiles are dropped
+        /// by the caller.
+        #[serde(skip)]
+        cancellation: CancellationToken,
     },
     NotYetImplemented, // TODO: Placeholder, should be removed when second input is added
 }
 
+/// Signals an [`AsyncProcedure`] that its result is no longer needed, e.g. because the requested
+/// tile was despawned. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum SendError {
     #[error("could not transmit data")]
@@ -89,6 +122,32 @@ pub enum SendError {
 pub trait Context: 'static {
     /// Send a message back to the caller.
     fn send_back<T: IntoMessage>(&self, message: T) -> Result<(), SendError>;
+
+    /// Called after each processed layer of the tile at `coords`. Can be used to report the
+    /// loading progress of a tile.
+    fn layer_progress(&self, _coords: &WorldTileCoords, _layers_done: usize, _layers_total: usize) {
+    }
+}
+
+/// A [`Context`] which buffers all messages instead of sending them anywhere. This allows running
+/// a procedure synchronously and inspecting its output afterwards.
+#[derive(Default)]
+pub struct BufferingContext {
+    messages: RefCell<Vec<Message>>,
+}
+
+impl BufferingContext {
+    /// The buffered messages in the order in which they were sent.
+    pub fn into_messages(self) -> Vec<Message> {
+        self.messages.into_inner()
+    }
+}
+
+impl Context for BufferingContext {
+    fn send_back<T: IntoMessage>(&self, message: T) -> Result<(), SendError> {
+        self.messages.borrow_mut().push(message.into());
+        Ok(())
+    }
 }
 
 #[derive(Error, Debug)]
@@ -0,0 +1,227 @@
+//! Timeout and retry policy for fetching tiles.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::io::source_client::SourceFetchError;
+
+/// Defines how often and how patiently a tile is fetched from a source.
+///
+/// A failed fetch is retried up to `max_retries` times. Between two attempts the client waits for
+/// an exponentially growing backoff of `base_backoff * 2^attempt` plus a random jitter of up to
+/// half of that delay. Each attempt is aborted after `timeout`, if the platform supports it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FetchPolicy {
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+    pub timeout: Option<Duration>,
+}
+
+impl FetchPolicy {
+    /// A policy which tries exactly once and waits as long as the HTTP client does.
+    pub fn no_retry() -> Self {
+        Self {
+            max_retries: 0,
+            base_backoff: Duration::ZERO,
+            timeout: None,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_base_backoff(mut self, base_backoff: Duration) -> Self {
+        self.base_backoff = base_backoff;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the time to wait before the retry which follows the failed `attempt`. The first
+    /// attempt is `0`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_backoff
+            .saturating_mul(2u32.saturating_pow(attempt));
+
+        let max_jitter = exponential.as_millis() as u64 / 2;
+        if max_jitter == 0 {
+            return exponential;
+        }
+
+        // A randomly seeded hasher is sufficient to spread out retries of concurrent fetches.
+        let random = RandomState::new().build_hasher().finish();
+        exponential.saturating_add(Duration::from_millis(random % (max_jitter + 1)))
+    }
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_backoff: Duration::from_millis(200),
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// Returned if a fetch failed even after retrying it according to the [`FetchPolicy`].
+#[derive(Error, Debug)]
+#[error("fetching {url} failed after {attempts} attempts")]
+pub struct RetriesExhaustedError {
+    pub url: String,
+    pub attempts: u32,
+    #[source]
+    pub last_error: SourceFetchError,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use crate::{
+        coords::ZoomLevel,
+        io::{
+            fetch_policy::{FetchPolicy, RetriesExhaustedError},
+            source_client::{FetchErrorKind, HttpClient, HttpSourceClient, SourceFetchError},
+            source_type::{SourceType, TessellateSource},
+        },
+    };
+
+    /// Fails the first `failures` fetches with an error of `kind` and succeeds afterwards.
+    #[derive(Clone)]
+    struct MockHttpClient {
+        failures: u32,
+        kind: FetchErrorKind,
+        calls: Arc<AtomicU32>,
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("mock failure")]
+    struct MockError;
+
+    #[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+    #[cfg_attr(feature = "thread-safe-futures", async_trait)]
+    impl HttpClient for MockHttpClient {
+        async fn fetch(&self, _url: &str) -> Result<Vec<u8>, SourceFetchError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                Err(SourceFetchError::new(self.kind, MockError))
+            } else {
+                Ok(vec![1, 2, 3])
+            }
+        }
+    }
+
+    fn source() -> SourceType {
+        SourceType::Tessellate(TessellateSource::new(
+            "http://localhost",
+            "pbf",
+            ZoomLevel::new(14),
+        ))
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = FetchPolicy::default().with_base_backoff(Duration::from_millis(100));
+
+        for attempt in 0..4 {
+            let exponential = Duration::from_millis(100 * 2u64.pow(attempt));
+            let backoff = policy.backoff(attempt);
+            assert!(backoff >= exponential);
+            assert!(backoff <= exponential + exponential / 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failure_is_retried() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let client = HttpSourceClient::new(MockHttpClient {
+            failures: 2,
+            kind: FetchErrorKind::Connection,
+            calls: calls.clone(),
+        })
+        .with_fetch_policy(FetchPolicy::no_retry().with_max_retries(3));
+
+        let data = client
+            .fetch(&(0, 0, ZoomLevel::new(0)).into(), &source())
+            .await
+            .unwrap();
+
+        assert_eq!(data, vec![1, 2, 3]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failure_gives_up() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let client = HttpSourceClient::new(MockHttpClient {
+            failures: u32::MAX,
+            kind: FetchErrorKind::Status(503),
+            calls: calls.clone(),
+        })
+        .with_fetch_policy(FetchPolicy::no_retry().with_max_retries(2));
+
+        let error = client
+            .fetch(&(0, 0, ZoomLevel::new(0)).into(), &source())
+            .await
+            .unwrap_err();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let exhausted = error
+            .error
+            .downcast_ref::<RetriesExhaustedError>()
+            .expect("expected RetriesExhaustedError");
+        assert_eq!(exhausted.attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_is_not_retried() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let client = HttpSourceClient::new(MockHttpClient {
+            failures: u32::MAX,
+            kind: FetchErrorKind::Status(404),
+            calls: calls.clone(),
+        });
+
+        let error = client
+            .fetch(&(0, 0, ZoomLevel::new(0)).into(), &source())
+            .await
+            .unwrap_err();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(error.kind, FetchErrorKind::Status(404));
+        // The error of the client is returned as it is
+        assert!(error.error.downcast_ref::<MockError>().is_some());
+    }
+
+    #[test]
+    fn test_retryable_kinds() {
+        assert!(FetchErrorKind::Timeout.is_retryable());
+        assert!(FetchErrorKind::Connection.is_retryable());
+        assert!(FetchErrorKind::Status(429).is_retryable());
+        assert!(FetchErrorKind::Status(503).is_retryable());
+        assert!(!FetchErrorKind::Status(404).is_retryable());
+        assert!(!FetchErrorKind::Status(403).is_retryable());
+        assert!(!FetchErrorKind::Other.is_retryable());
+    }
+}
@@ -0,0 +1,255 @@
+//! Loads styles and tiles from the local filesystem, e.g. for offline use.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use thiserror::Error;
+
+use crate::style::{
+    source::{Source, TileJson},
+    Style,
+};
+
+/// URLs with this scheme refer to local files, e.g. `file:///data/tiles/{z}/{x}/{y}.pbf`.
+pub const FILE_SCHEME: &str = "file://";
+
+/// The magic bytes at the beginning of gzip compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Error, Debug)]
+pub enum FileSourceError {
+    /// The URL does not use the [`FILE_SCHEME`]
+    #[error("{0} is not a file URL")]
+    NotAFileUrl(String),
+    /// Reading or decompressing the file failed
+    #[error("reading the file failed")]
+    Io(#[from] io::Error),
+    /// The file does not contain a valid style
+    #[error("parsing the style failed")]
+    Style(#[from] serde_json::Error),
+    /// The TileJSON of the source with this id is not valid
+    #[error("parsing the TileJSON of source {0} failed")]
+    TileJson(String, #[source] serde_json::Error),
+}
+
+/// Returns the path of a `file://` URL.
+pub fn path_from_url(url: &str) -> Option<PathBuf> {
+    url.strip_prefix(FILE_SCHEME).map(PathBuf::from)
+}
+
+/// Reads the file at `path`. Gzip compressed files, like `style.json.gz` or tiles which are
+/// stored compressed, are decompressed.
+pub fn read_file(path: &Path) -> Result<Vec<u8>, FileSourceError> {
+    let data = fs::read(path)?;
+
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(data);
+    }
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Reads the file which is referenced by the `file://` URL `url`, see [`read_file`].
+pub fn read_url(url: &str) -> Result<Vec<u8>, FileSourceError> {
+    let path = path_from_url(url).ok_or_else(|| FileSourceError::NotAFileUrl(url.to_string()))?;
+    read_file(&path)
+}
+
+/// Loads the style from the `file://` URL `url`. The style may be gzip compressed. Relative URLs
+/// within the style are resolved against `url`, see [`Style::resolve_urls`]. The type of sources
+/// without a `type` is determined from their local TileJSON, see [`Source::probe`].
+pub fn load_style(url: &str) -> Result<Style, FileSourceError> {
+    let mut style: Style = serde_json::from_slice(&read_url(url)?)?;
+    style.resolve_urls(url);
+
+    for (source_id, source) in &mut style.sources {
+        let Source::Untyped(untyped) = source else {
+            continue;
+        };
+        // Remote TileJSONs are probed by `HttpSourceClient::probe_sources`
+        let Some(tilejson_url) = untyped
+            .url
+            .as_deref()
+            .filter(|url| path_from_url(url).is_some())
+        else {
+            continue;
+        };
+
+        let tilejson: TileJson = serde_json::from_slice(&read_url(tilejson_url)?)
+            .map_err(|error| FileSourceError::TileJson(source_id.clone(), error))?;
+        *source = source.clone().probe(&tilejson);
+    }
+
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, path::PathBuf};
+
+    use async_trait::async_trait;
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{load_style, path_from_url, read_url, FileSourceError};
+    use crate::{
+        coords::ZoomLevel,
+        io::{
+            source_client::{HttpClient, HttpSourceClient, SourceFetchError},
+            source_type::{SourceType, TessellateSource},
+        },
+        style::{source::Source, ResolveSourceError, Style},
+    };
+
+    /// Fails the test if tiles are fetched over HTTP.
+    #[derive(Clone)]
+    struct UnreachableHttpClient;
+
+    #[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+    #[cfg_attr(feature = "thread-safe-futures", async_trait)]
+    impl HttpClient for UnreachableHttpClient {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+            unreachable!("{url} is fetched over HTTP")
+        }
+    }
+
+    /// Creates an empty directory for a test within the temporary directory.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("maplibre-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_load_gzipped_style() {
+        let dir = test_dir("gzipped-style");
+        let path = dir.join("style.json.gz");
+        let style = serde_json::to_vec(&Style::default()).unwrap();
+        fs::write(&path, gzip(&style)).unwrap();
+
+        let loaded = load_style(&format!("file://{}", path.display())).unwrap();
+        assert_eq!(loaded.layers.len(), Style::default().layers.len());
+
+        assert!(matches!(
+            load_style("https://example.com/style.json"),
+            Err(FileSourceError::NotAFileUrl(_))
+        ));
+        assert!(matches!(
+            load_style(&format!("file://{}", dir.join("missing.json").display())),
+            Err(FileSourceError::Io(_))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_style_resolves_relative_urls() {
+        let dir = test_dir("relative-urls");
+        let path = dir.join("style.json");
+        fs::write(&path, r#"{"version": 8, "name": "", "metadata": {}, "sources": {}, "layers": [], "glyphs": "fonts/{fontstack}/{range}.pbf"}"#).unwrap();
+
+        let loaded = load_style(&format!("file://{}", path.display())).unwrap();
+        assert_eq!(
+            loaded.glyphs,
+            Some(format!("file://{}/fonts/{{fontstack}}/{{range}}.pbf", dir.display()))
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_style_probes_untyped_sources() {
+        let dir = test_dir("probe-sources");
+        fs::write(
+            dir.join("style.json"),
+            r#"{"version": 8, "name": "", "metadata": {}, "layers": [], "sources": {
+                "openmaptiles": {"url": "openmaptiles.json"},
+                "satellite": {"url": "satellite.json"},
+                "remote": {"url": "https://example.com/tiles.json"}
+            }}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("openmaptiles.json"),
+            r#"{"tiles": ["tiles/{z}/{x}/{y}.pbf"], "vector_layers": [{"id": "water"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("satellite.json"),
+            r#"{"tiles": ["satellite/{z}/{x}/{y}.png"], "format": "png"}"#,
+        )
+        .unwrap();
+
+        let loaded = load_style(&format!("file://{}", dir.join("style.json").display())).unwrap();
+        let Source::Vector(openmaptiles) = &loaded.sources["openmaptiles"] else {
+            panic!("expected a vector source");
+        };
+        assert_eq!(
+            openmaptiles.tiles,
+            Some(format!("file://{}/tiles/{{z}}/{{x}}/{{y}}.pbf", dir.display()))
+        );
+        assert!(matches!(loaded.sources["satellite"], Source::Raster(_)));
+        assert!(matches!(loaded.sources["remote"], Source::Untyped(_)));
+        assert!(matches!(
+            loaded.resolve_sources(),
+            Err(ResolveSourceError::UnknownSourceType { source_id }) if source_id == "remote"
+        ));
+
+        fs::write(dir.join("satellite.json"), "{").unwrap();
+        assert!(matches!(
+            load_style(&format!("file://{}", dir.join("style.json").display())),
+            Err(FileSourceError::TileJson(source_id, _)) if source_id == "satellite"
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_tile_directory() {
+        let dir = test_dir("tile-directory");
+        fs::create_dir_all(dir.join("1/0")).unwrap();
+        fs::write(dir.join("1/0/1.pbf"), b"plain").unwrap();
+        fs::write(dir.join("1/0/0.pbf"), gzip(b"compressed")).unwrap();
+
+        let url = format!("file://{}", dir.display());
+        assert_eq!(path_from_url(&url), Some(dir.clone()));
+        assert_eq!(read_url(&format!("{url}/1/0/1.pbf")).unwrap(), b"plain");
+        assert_eq!(read_url(&format!("{url}/1/0/0.pbf")).unwrap(), b"compressed");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_file_source() {
+        let dir = test_dir("file-source");
+        fs::create_dir_all(dir.join("1/1")).unwrap();
+        fs::write(dir.join("1/1/0.pbf"), gzip(b"tile")).unwrap();
+
+        let client = HttpSourceClient::new(UnreachableHttpClient);
+        let source = SourceType::Tessellate(TessellateSource::new(
+            &format!("file://{}", dir.display()),
+            "pbf",
+            ZoomLevel::new(14),
+        ));
+        let coords = (1, 0, ZoomLevel::new(1)).into();
+        assert_eq!(client.fetch(&coords, &source).await.unwrap(), b"tile");
+        assert!(client
+            .fetch(&(0, 0, ZoomLevel::new(1)).into(), &source)
+            .await
+            .is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,15 +1,22 @@
 //! Geometry index.
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
 
 use cgmath::{num_traits::Signed, Bounded};
 use geo::prelude::*;
 use geo_types::{Coord, CoordFloat, Geometry, LineString, Point, Polygon};
 use geozero::{
     error::GeozeroError, geo_types::GeoWriter, ColumnValue, FeatureProcessor, GeomProcessor,
-    PropertyProcessor,
+    GeozeroGeometry, PropertyProcessor,
 };
 use rstar::{Envelope, PointDistance, RTree, RTreeObject, AABB};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
     coords::{
@@ -18,7 +25,17 @@ use crate::{
     util::math::bounds_from_points,
 };
 
+/// Errors which occur while saving or loading a [`GeometryIndex`].
+#[derive(Error, Debug)]
+pub enum GeometryIndexError {
+    #[error("reading or writing the index failed")]
+    Io(#[from] std::io::Error),
+    #[error("encoding or decoding the index failed")]
+    Encoding(#[from] bincode::Error),
+}
+
 /// A quad tree storing the currently loaded tiles.
+#[derive(Serialize, Deserialize)]
 pub struct GeometryIndex {
     index: BTreeMap<Quadkey, TileIndex>,
 }
@@ -60,6 +77,29 @@ impl GeometryIndex {
             None
         }
     }
+
+    /// Writes the index in a binary format, so that it can be precomputed for static datasets.
+    pub fn write_to(&self, writer: impl Write) -> Result<(), GeometryIndexError> {
+        Ok(bincode::serialize_into(writer, self)?)
+    }
+
+    /// Reads an index which was written by [`GeometryIndex::write_to`].
+    pub fn read_from(reader: impl Read) -> Result<Self, GeometryIndexError> {
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    /// Writes the index to the file at `path`, see [`GeometryIndex::write_to`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GeometryIndexError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads an index from the file at `path`, see [`GeometryIndex::read_from`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GeometryIndexError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
 }
 
 impl Default for GeometryIndex {
@@ -73,6 +113,7 @@ impl Default for GeometryIndex {
 /// Linear tiles are simply stored in a vector.
 ///
 /// A spatial tile index can theoretically improve query performance on tiles. Practically it could be slower though. The `Spatial` index is experimental and currently unused.
+#[derive(Serialize, Deserialize)]
 pub enum TileIndex {
     Spatial { tree: RTree<IndexedGeometry<f64>> },
     Linear { list: Vec<IndexedGeometry<f64>> },
@@ -105,7 +146,7 @@ impl TileIndex {
 
 /// An indexed geometry contains an exact vector geometry, computed bounds which
 /// can be helpful when interacting with the geometry and a hashmap of properties.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedGeometry<T>
 where
     T: CoordFloat + Bounded + Signed,
@@ -116,7 +157,7 @@ where
 }
 
 /// Contains either a polygon or line vector.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExactGeometry<T>
 where
     T: CoordFloat + Bounded + Signed,
@@ -180,6 +221,11 @@ where
 }
 
 /// A processor able to create geometries using `[geozero::geo_types::GeoWriter]`.
+///
+/// Besides processing vector tiles, it can be used to index geometries which are already in
+/// memory, e.g. from `geo` or GeoJSON. Features of a [`geozero::GeozeroDatasource`] are indexed
+/// by processing it, single geometries are added with [`IndexProcessor::add_geometry`]. The
+/// coordinates are expected to be within the tile, i.e. between 0 and [`EXTENT`].
 pub struct IndexProcessor {
     geo_writer: GeoWriter,
     geometries: Vec<IndexedGeometry<f64>>,
@@ -202,6 +248,27 @@ impl IndexProcessor {
     pub fn get_geometries(self) -> Vec<IndexedGeometry<f64>> {
         self.geometries
     }
+
+    /// Returns an index of the processed geometries which can be added to a [`GeometryIndex`]
+    /// with [`GeometryIndex::index_tile`].
+    pub fn into_tile_index(self) -> TileIndex {
+        TileIndex::Linear {
+            list: self.geometries,
+        }
+    }
+
+    /// Indexes a single geometry with `properties`. Only polygons and line strings are
+    /// indexed, other geometries are skipped.
+    pub fn add_geometry(
+        &mut self,
+        geometry: &impl GeozeroGeometry,
+        properties: HashMap<String, String>,
+    ) -> Result<(), GeozeroError> {
+        self.properties = Some(properties);
+        self.geometry_begin()?;
+        geometry.process_geom(self)?;
+        self.geometry_end()
+    }
 }
 
 impl Default for IndexProcessor {
@@ -305,15 +372,16 @@ impl FeatureProcessor for IndexProcessor {
     /// End of feature geometry processing.
     fn geometry_end(&mut self) -> Result<(), GeozeroError> {
         let geometry = self.geo_writer.take_geometry();
+        // Features without properties do not begin processing them
+        let properties = self.properties.take().unwrap_or_default();
 
         match geometry {
-            Some(Geometry::Polygon(polygon)) => self.geometries.push(
-                IndexedGeometry::from_polygon(polygon, self.properties.take().unwrap()).unwrap(),
-            ),
-            Some(Geometry::LineString(linestring)) => self.geometries.push(
-                IndexedGeometry::from_linestring(linestring, self.properties.take().unwrap())
-                    .unwrap(),
-            ),
+            Some(Geometry::Polygon(polygon)) => self
+                .geometries
+                .extend(IndexedGeometry::from_polygon(polygon, properties)),
+            Some(Geometry::LineString(linestring)) => self
+                .geometries
+                .extend(IndexedGeometry::from_linestring(linestring, properties)),
             Some(Geometry::Point(_))
             | Some(Geometry::Line(_))
             | Some(Geometry::MultiPoint(_))
@@ -332,3 +400,150 @@ impl FeatureProcessor for IndexProcessor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use geo_types::{line_string, polygon};
+    use geozero::{geojson::GeoJson, GeozeroDatasource};
+    use rstar::RTree;
+
+    use super::{GeometryIndex, IndexProcessor, IndexedGeometry, TileIndex};
+    use crate::coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel};
+
+    fn geometries() -> Vec<IndexedGeometry<f64>> {
+        let square = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 1024.0, y: 0.0),
+            (x: 1024.0, y: 1024.0),
+            (x: 0.0, y: 1024.0),
+        ];
+        let line = line_string![(x: 2048.0, y: 0.0), (x: 2048.0, y: 4096.0)];
+
+        vec![
+            IndexedGeometry::from_polygon(
+                square,
+                HashMap::from([("class".to_string(), "water".to_string())]),
+            )
+            .unwrap(),
+            IndexedGeometry::from_linestring(
+                line,
+                HashMap::from([("class".to_string(), "road".to_string())]),
+            )
+            .unwrap(),
+        ]
+    }
+
+    /// The classes of the geometries at the world coordinates `x`, `y` of the tile 0/0/0.
+    fn classes_at(index: &GeometryIndex, x: f64, y: f64) -> Vec<String> {
+        index
+            .query_point(
+                &WorldCoords::from((x, y)),
+                ZoomLevel::default(),
+                Zoom::new(0.0),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|geometry| geometry.properties["class"].clone())
+            .collect()
+    }
+
+    fn assert_same_queries(index: &GeometryIndex, loaded: &GeometryIndex) {
+        // Within the square, on the line and outside of both
+        for (x, y) in [(64.0, 64.0), (256.0, 400.0), (400.0, 400.0)] {
+            assert_eq!(classes_at(index, x, y), classes_at(loaded, x, y));
+        }
+        assert_eq!(classes_at(loaded, 64.0, 64.0), vec!["water"]);
+        assert_eq!(classes_at(loaded, 256.0, 400.0), vec!["road"]);
+        assert!(classes_at(loaded, 400.0, 400.0).is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut index = GeometryIndex::new();
+        index.index_tile(
+            &WorldTileCoords::default(),
+            TileIndex::Linear { list: geometries() },
+        );
+
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        let loaded = GeometryIndex::read_from(bytes.as_slice()).unwrap();
+        assert_same_queries(&index, &loaded);
+
+        // Tiles which are not indexed stay unknown
+        let coords = WorldCoords::from((600.0, 600.0));
+        assert!(loaded
+            .query_point(&coords, ZoomLevel::default(), Zoom::new(0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_spatial() {
+        let mut index = GeometryIndex::new();
+        index.index_tile(
+            &WorldTileCoords::default(),
+            TileIndex::Spatial {
+                tree: RTree::bulk_load(geometries()),
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "maplibre-geometry-index-{}.bin",
+            std::process::id()
+        ));
+        index.save(&path).unwrap();
+        let loaded = GeometryIndex::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_same_queries(&index, &loaded);
+    }
+
+    #[test]
+    fn test_index_geojson() {
+        // language=JSON
+        let features = r#"{
+          "type": "FeatureCollection",
+          "features": [
+            {
+              "type": "Feature",
+              "properties": {"class": "water"},
+              "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[0, 0], [1024, 0], [1024, 1024], [0, 1024], [0, 0]]]
+              }
+            },
+            {
+              "type": "Feature",
+              "geometry": {"type": "Point", "coordinates": [3000, 3000]}
+            }
+          ]
+        }"#;
+        // language=JSON
+        let road = r#"{"type": "LineString", "coordinates": [[2048, 0], [2048, 4096]]}"#;
+
+        let mut processor = IndexProcessor::new();
+        GeoJson(features).process(&mut processor).unwrap();
+        processor
+            .add_geometry(
+                &GeoJson(road),
+                HashMap::from([("class".to_string(), "road".to_string())]),
+            )
+            .unwrap();
+
+        let mut index = GeometryIndex::new();
+        index.index_tile(&WorldTileCoords::default(), processor.into_tile_index());
+
+        assert_eq!(classes_at(&index, 64.0, 64.0), vec!["water"]);
+        assert_eq!(classes_at(&index, 256.0, 400.0), vec!["road"]);
+        // The point is not indexed
+        assert!(classes_at(&index, 375.0, 375.0).is_empty());
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        assert!(GeometryIndex::read_from(&[1, 2, 3][..]).is_err());
+        assert!(GeometryIndex::load("/nonexistent/geometry-index.bin").is_err());
+    }
+}
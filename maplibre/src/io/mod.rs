@@ -3,9 +3,13 @@
 pub use geozero::mvt::tile::Layer as RawLayer;
 
 pub mod apc;
+pub mod fetch_policy;
+pub mod file_source;
 pub mod geometry_index;
 pub mod scheduler;
 pub mod source_client;
 pub mod source_type;
 #[cfg(feature = "embed-static-tiles")]
 pub mod static_tile_fetcher;
+pub mod tile_cache;
+pub mod worker_pool;
@@ -1,9 +1,24 @@
 //! HTTP client.
 
+use std::time::Duration;
+
 use async_trait::async_trait;
+use instant::SystemTime;
 use thiserror::Error;
 
-use crate::{coords::WorldTileCoords, io::source_type::SourceType};
+use crate::{
+    coords::WorldTileCoords,
+    io::{
+        fetch_policy::{FetchPolicy, RetriesExhaustedError},
+        file_source::{self, FileSourceError, FILE_SCHEME},
+        source_type::SourceType,
+        tile_cache::{CachedTile, TileCache},
+    },
+    style::{
+        source::{Source, TileJson},
+        Style,
+    },
+};
 
 /// A closure that returns a HTTP client.
 pub type HTTPClientFactory<HC> = dyn Fn() -> HC;
@@ -18,6 +33,32 @@ pub type HTTPClientFactory<HC> = dyn Fn() -> HC;
 #[cfg_attr(feature = "thread-safe-futures", async_trait)]
 pub trait HttpClient: Clone + Sync + Send + 'static {
     async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError>;
+
+    /// Fetches `url` and aborts after `timeout`. The default implementation ignores the timeout,
+    /// HTTP clients which support timeouts should override it.
+    async fn fetch_with_timeout(
+        &self,
+        url: &str,
+        _timeout: Option<Duration>,
+    ) -> Result<Vec<u8>, SourceFetchError> {
+        self.fetch(url).await
+    }
+
+    /// Fetches `url` like [`Self::fetch_with_timeout`] and determines when the data expires. The
+    /// default implementation does not know about expiry, HTTP clients which can read the
+    /// response headers should override it.
+    async fn fetch_cacheable(
+        &self,
+        url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CachedTile, SourceFetchError> {
+        Ok(CachedTile::new(self.fetch_with_timeout(url, timeout).await?, None))
+    }
+
+    /// Waits for `duration` before a failed fetch is retried. The default implementation returns
+    /// immediately, i.e. clients which do not override it retry without any backoff. Platforms
+    /// which provide a timer should override it.
+    async fn sleep(&self, _duration: Duration) {}
 }
 
 /// Gives access to the HTTP client which can be of multiple types,
@@ -28,11 +69,57 @@ where
     HC: HttpClient,
 {
     inner_client: HC,
+    fetch_policy: FetchPolicy,
+    cache: Option<TileCache>,
 }
 
 #[derive(Error, Debug)]
 #[error("failed to fetch from source")]
-pub struct SourceFetchError(#[source] pub Box<dyn std::error::Error>);
+pub struct SourceFetchError {
+    pub kind: FetchErrorKind,
+    #[source]
+    pub error: Box<dyn std::error::Error>,
+}
+
+impl SourceFetchError {
+    pub fn new(kind: FetchErrorKind, error: impl std::error::Error + 'static) -> Self {
+        Self {
+            kind,
+            error: Box::new(error),
+        }
+    }
+
+    /// Whether fetching again might succeed, see [`FetchErrorKind::is_retryable`].
+    pub fn is_retryable(&self) -> bool {
+        self.kind.is_retryable()
+    }
+}
+
+/// Why fetching from a source failed. HTTP clients classify their errors, such that only
+/// transient failures are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchErrorKind {
+    /// The request was aborted after the timeout
+    Timeout,
+    /// Connecting to the server failed or the connection was interrupted
+    Connection,
+    /// The server responded with this unsuccessful HTTP status
+    Status(u16),
+    /// Any other failure, e.g. an invalid URL or a file which could not be read
+    Other,
+}
+
+impl FetchErrorKind {
+    /// Timeouts, connection errors, server errors and `429 Too Many Requests` are transient.
+    /// Other errors, e.g. `404 Not Found`, fail again when they are retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchErrorKind::Timeout | FetchErrorKind::Connection => true,
+            FetchErrorKind::Status(status) => *status == 429 || (500..600).contains(status),
+            FetchErrorKind::Other => false,
+        }
+    }
+}
 
 /// Defines the different types of HTTP clients such as basic HTTP and Mbtiles.
 /// More types might be coming such as S3 and other cloud http clients.
@@ -68,16 +155,183 @@ where
     pub fn new(http_client: HC) -> Self {
         Self {
             inner_client: http_client,
+            fetch_policy: FetchPolicy::default(),
+            cache: None,
+        }
+    }
+
+    pub fn with_fetch_policy(mut self, fetch_policy: FetchPolicy) -> Self {
+        self.fetch_policy = fetch_policy;
+        self
+    }
+
+    pub fn fetch_policy(&self) -> &FetchPolicy {
+        &self.fetch_policy
+    }
+
+    /// Serves tiles from `cache` until they expire. Fetched tiles are added to it, unless their
+    /// source is volatile, see [`SourceType::is_volatile`].
+    pub fn with_cache(mut self, cache: TileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Determines the type of the sources of `style` which have no `type` from their TileJSON,
+    /// see [`Source::probe`]. TileJSONs with a `file://` URL are read from the filesystem, others
+    /// are fetched with the HTTP client.
+    pub async fn probe_sources(&self, style: &mut Style) -> Result<(), SourceFetchError> {
+        let untyped = style
+            .sources
+            .iter()
+            .filter_map(|(source_id, source)| match source {
+                Source::Untyped(untyped) => Some((source_id.clone(), untyped.url.clone()?)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (source_id, url) in untyped {
+            let data = if url.starts_with(FILE_SCHEME) {
+                file_source::read_url(&url)
+                    .map_err(|e| SourceFetchError::new(FetchErrorKind::Other, e))?
+            } else {
+                self.inner_client
+                    .fetch_with_timeout(&url, self.fetch_policy.timeout)
+                    .await?
+            };
+
+            let tilejson: TileJson = serde_json::from_slice(&data).map_err(|e| {
+                SourceFetchError::new(
+                    FetchErrorKind::Other,
+                    FileSourceError::TileJson(source_id.clone(), e),
+                )
+            })?;
+            if let Some(source) = style.sources.get_mut(&source_id) {
+                *source = source.clone().probe(&tilejson);
+            }
         }
+
+        Ok(())
     }
 
+    /// Fetches the tile at `coords` and retries according to the [`FetchPolicy`]. Only errors
+    /// which are retryable are retried, see [`SourceFetchError::is_retryable`], others are
+    /// returned unchanged. If all attempts fail, the returned error contains a
+    /// [`RetriesExhaustedError`]. Tiles of sources with a `file://` URL are read from the
+    /// filesystem instead.
     pub async fn fetch(
         &self,
         coords: &WorldTileCoords,
         source_type: &SourceType,
     ) -> Result<Vec<u8>, SourceFetchError> {
-        self.inner_client
-            .fetch(source_type.format(coords).as_str())
-            .await
+        let url = source_type.format(coords);
+        let policy = &self.fetch_policy;
+
+        if url.starts_with(FILE_SCHEME) {
+            return file_source::read_url(&url)
+                .map_err(|e| SourceFetchError::new(FetchErrorKind::Other, e));
+        }
+
+        let cache = self.cache.as_ref().filter(|_| !source_type.is_volatile());
+
+        if let Some(data) = cache.and_then(|cache| cache.get(&url, SystemTime::now())) {
+            return Ok(data);
+        }
+
+        let mut attempt = 0;
+        loop {
+            // The error is not Send and therefore must be dropped before waiting for the backoff
+            {
+                let error = match self
+                    .inner_client
+                    .fetch_cacheable(url.as_str(), policy.timeout)
+                    .await
+                {
+                    Ok(tile) => {
+                        if let Some(cache) = cache {
+                            cache.insert(&url, tile.clone(), SystemTime::now());
+                        }
+                        return Ok(tile.data);
+                    }
+                    Err(e) => e,
+                };
+
+                if !error.is_retryable() {
+                    return Err(error);
+                }
+                if attempt >= policy.max_retries {
+                    return Err(SourceFetchError::new(
+                        error.kind,
+                        RetriesExhaustedError {
+                            url,
+                            attempts: attempt + 1,
+                            last_error: error,
+                        },
+                    ));
+                }
+
+                log::warn!("fetching {url} failed (attempt {attempt}), retrying: {error:?}");
+            }
+
+            self.inner_client.sleep(policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::{FetchErrorKind, HttpClient, HttpSourceClient, SourceFetchError};
+    use crate::style::{source::Source, Style};
+
+    /// Serves a vector TileJSON at `https://example.com/tiles.json`.
+    #[derive(Clone)]
+    struct TileJsonHttpClient;
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("not found")]
+    struct NotFound;
+
+    #[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+    #[cfg_attr(feature = "thread-safe-futures", async_trait)]
+    impl HttpClient for TileJsonHttpClient {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+            match url {
+                "https://example.com/tiles.json" => {
+                    Ok(br#"{"tiles": ["{z}/{x}/{y}.pbf"], "vector_layers": []}"#.to_vec())
+                }
+                "https://example.com/invalid.json" => Ok(b"{".to_vec()),
+                _ => Err(SourceFetchError::new(FetchErrorKind::Status(404), NotFound)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_remote_sources() {
+        let mut style: Style = serde_json::from_str(
+            r#"{"version": 8, "name": "", "metadata": {}, "layers": [], "sources": {
+                "remote": {"url": "https://example.com/tiles.json"}
+            }}"#,
+        )
+        .unwrap();
+        let client = HttpSourceClient::new(TileJsonHttpClient);
+        client.probe_sources(&mut style).await.unwrap();
+
+        let Source::Vector(remote) = &style.sources["remote"] else {
+            panic!("expected a vector source");
+        };
+        assert_eq!(remote.tiles.as_deref(), Some("https://example.com/{z}/{x}/{y}.pbf"));
+        assert!(style.resolve_sources().is_ok());
+
+        for url in ["https://example.com/invalid.json", "https://example.com/missing.json"] {
+            let mut style: Style = serde_json::from_value(serde_json::json!({
+                "version": 8, "name": "", "metadata": {}, "layers": [],
+                "sources": {"remote": {"url": url}}
+            }))
+            .unwrap();
+            assert!(client.probe_sources(&mut style).await.is_err());
+            assert!(matches!(style.sources["remote"], Source::Untyped(_)));
+        }
     }
 }
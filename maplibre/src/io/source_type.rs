@@ -1,12 +1,42 @@
+use std::sync::OnceLock;
+
 use crate::{coords::WorldTileCoords, style::source::TileAddressingScheme};
 use crate::coords::ZoomLevel;
 
+/// Environment variable from which the API key of the default sources is read.
+pub const MAPTILER_KEY_ENV: &str = "MAPTILER_API_KEY";
+
+/// Reads a non-empty API key from the environment variable `name`.
+fn key_from_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|key| !key.is_empty())
+}
+
+/// The API key of the default sources. It is read from [`MAPTILER_KEY_ENV`] only once, such that
+/// a missing key is reported once instead of for every requested tile.
+fn default_key() -> Option<String> {
+    static DEFAULT_KEY: OnceLock<Option<String>> = OnceLock::new();
+
+    DEFAULT_KEY
+        .get_or_init(|| {
+            let key = key_from_env(MAPTILER_KEY_ENV);
+            if key.is_none() {
+                log::warn!("the default tile sources require an API key, set {MAPTILER_KEY_ENV}");
+            }
+            key
+        })
+        .clone()
+}
+
 /// Represents a source from which the vector tile are fetched.
 #[derive(Clone)]
 pub struct TessellateSource {
     pub url: String,
     pub filetype: String,
-    pub max_zoom: ZoomLevel
+    pub max_zoom: ZoomLevel,
+    /// API key which is appended to the tile URLs.
+    pub key: Option<String>,
+    /// Whether fetched tiles bypass the tile cache, see [`SourceType::is_volatile`].
+    pub volatile: bool,
 }
 
 impl TessellateSource {
@@ -15,25 +45,40 @@ impl TessellateSource {
             url: url.to_string(),
             filetype: filetype.to_string(),
             max_zoom,
+            key: None,
+            volatile: false,
         }
     }
 
+    pub fn with_key(mut self, key: Option<String>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_volatile(mut self, volatile: bool) -> Self {
+        self.volatile = volatile;
+        self
+    }
+
     pub fn format(&self, coords: &WorldTileCoords) -> String {
         let tile_coords = coords.into_tile(TileAddressingScheme::XYZ).unwrap();
-        format!(
+        let url = format!(
             "{url}/{z}/{x}/{y}.{filetype}",
             url = self.url,
             z = tile_coords.z,
             x = tile_coords.x,
             y = tile_coords.y,
             filetype = self.filetype,
-        )
+        );
+        with_key_query(url, &self.key)
     }
 }
 
 impl Default for TessellateSource {
+    /// The OpenMapTiles vector tiles from MapTiler. The API key is read from [`MAPTILER_KEY_ENV`].
     fn default() -> Self {
-        Self::new("https://api.maptiler.com/tiles/v3-openmaptiles", "pbf?key=rzq14TmV1096yjvD3Uyw", ZoomLevel::new(14))
+        Self::new("https://api.maptiler.com/tiles/v3-openmaptiles", "pbf", ZoomLevel::new(14))
+            .with_key(default_key())
     }
 }
 
@@ -42,39 +87,58 @@ impl Default for TessellateSource {
 pub struct RasterSource {
     pub url: String,
     pub filetype: String,
-    pub key: String,
+    /// API key which is appended to the tile URLs.
+    pub key: Option<String>,
+    /// Whether fetched tiles bypass the tile cache, see [`SourceType::is_volatile`].
+    pub volatile: bool,
 }
 
 impl RasterSource {
-    pub fn new(url: &str, filetype: &str, key: &str) -> Self {
+    pub fn new(url: &str, filetype: &str) -> Self {
         Self {
             url: url.to_string(),
             filetype: filetype.to_string(),
-            key: key.to_string(),
+            key: None,
+            volatile: false,
         }
     }
 
+    pub fn with_key(mut self, key: Option<String>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_volatile(mut self, volatile: bool) -> Self {
+        self.volatile = volatile;
+        self
+    }
+
     pub fn format(&self, coords: &WorldTileCoords) -> String {
         let tile_coords = coords.into_tile(TileAddressingScheme::XYZ).unwrap();
-        format!(
-            "{url}/{z}/{x}/{y}.{filetype}?key={key}",
+        let url = format!(
+            "{url}/{z}/{x}/{y}.{filetype}",
             url = self.url,
             z = tile_coords.z,
             x = tile_coords.x,
             y = tile_coords.y,
             filetype = self.filetype,
-            key = self.key,
-        )
+        );
+        with_key_query(url, &self.key)
     }
 }
 
 impl Default for RasterSource {
+    /// The satellite raster tiles from MapTiler. The API key is read from [`MAPTILER_KEY_ENV`].
     fn default() -> Self {
-        Self::new(
-            "https://api.maptiler.com/tiles/satellite-v2",
-            "jpg",
-            "qnePkfbGpMsLCi3KFBs3",
-        )
+        Self::new("https://api.maptiler.com/tiles/satellite-v2", "jpg")
+            .with_key(default_key())
+    }
+}
+
+fn with_key_query(url: String, key: &Option<String>) -> String {
+    match key {
+        Some(key) => format!("{url}?key={key}"),
+        None => url,
     }
 }
 
@@ -92,4 +156,84 @@ impl SourceType {
             SourceType::Tessellate(tessellate_source) => tessellate_source.format(coords),
         }
     }
+
+    /// Whether the tiles of the source change frequently. They are neither served from nor added
+    /// to the tile cache and are therefore refetched every time.
+    pub fn is_volatile(&self) -> bool {
+        match self {
+            SourceType::Raster(raster_source) => raster_source.volatile,
+            SourceType::Tessellate(tessellate_source) => tessellate_source.volatile,
+        }
+    }
+
+    /// Whether the source is served by MapTiler but no API key is configured. Requests to such a
+    /// source will be rejected.
+    pub fn requires_key(&self) -> bool {
+        let (url, key) = match self {
+            SourceType::Raster(raster_source) => (&raster_source.url, &raster_source.key),
+            SourceType::Tessellate(tessellate_source) => {
+                (&tessellate_source.url, &tessellate_source.key)
+            }
+        };
+        key.is_none() && url.contains("api.maptiler.com")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::source_type::{
+            key_from_env, RasterSource, SourceType, TessellateSource, MAPTILER_KEY_ENV,
+        },
+    };
+
+    #[test]
+    fn test_default_has_no_hardcoded_key() {
+        let coords = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let expected = key_from_env(MAPTILER_KEY_ENV);
+
+        let tessellate = TessellateSource::default();
+        assert_eq!(tessellate.key, expected);
+        assert_eq!(tessellate.filetype, "pbf");
+
+        let raster = RasterSource::default();
+        assert_eq!(raster.key, expected);
+
+        if expected.is_none() {
+            assert!(!tessellate.format(&coords).contains("key="));
+            assert!(!raster.format(&coords).contains("key="));
+            assert!(SourceType::Tessellate(tessellate).requires_key());
+            assert!(SourceType::Raster(raster).requires_key());
+        }
+    }
+
+    #[test]
+    fn test_key_from_env() {
+        let name = "MAPLIBRE_TEST_SOURCE_KEY";
+        assert_eq!(key_from_env(name), None);
+
+        std::env::set_var(name, "");
+        assert_eq!(key_from_env(name), None);
+
+        std::env::set_var(name, "secret");
+        assert_eq!(key_from_env(name), Some("secret".to_string()));
+
+        let coords = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let source = TessellateSource::new("https://api.maptiler.com/tiles", "pbf", ZoomLevel::new(14))
+            .with_key(key_from_env(name));
+        assert_eq!(source.format(&coords), "https://api.maptiler.com/tiles/0/0/0.pbf?key=secret");
+        assert!(!SourceType::Tessellate(source).requires_key());
+
+        std::env::remove_var(name);
+    }
+
+    #[test]
+    fn test_requires_key() {
+        let source = SourceType::Raster(RasterSource::new("http://localhost", "png"));
+        assert!(!source.requires_key());
+
+        let source = SourceType::Raster(RasterSource::new("https://api.maptiler.com/tiles", "png"));
+        assert!(source.requires_key());
+    }
 }
@@ -0,0 +1,329 @@
+//! In-memory cache for fetched tiles which respects the expiry announced by the server.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use instant::SystemTime;
+
+/// Fetched tile data together with the time at which it expires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedTile {
+    pub data: Vec<u8>,
+    /// Tiles without an expiry never expire, but are still evicted from a full [`TileCache`].
+    pub expires: Option<SystemTime>,
+}
+
+impl CachedTile {
+    pub fn new(data: Vec<u8>, expires: Option<SystemTime>) -> Self {
+        Self { data, expires }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// Determines when a response expires from its `Cache-Control` and `Expires` headers. Like in
+/// HTTP, `max-age` takes precedence over `Expires`. Responses which must not be cached expire
+/// immediately.
+pub fn parse_expiry(
+    cache_control: Option<&str>,
+    expires: Option<&str>,
+    now: SystemTime,
+) -> Option<SystemTime> {
+    if let Some(cache_control) = cache_control {
+        for directive in cache_control.split(',').map(str::trim) {
+            let directive = directive.to_ascii_lowercase();
+            if directive == "no-store" || directive == "no-cache" {
+                return Some(now);
+            }
+            if let Some(max_age) = directive.strip_prefix("max-age=") {
+                if let Ok(max_age) = max_age.trim_matches('"').parse::<u64>() {
+                    return Some(now + Duration::from_secs(max_age));
+                }
+            }
+        }
+    }
+
+    // Like in HTTP, invalid dates such as `0` mean that the response is already expired
+    let Ok(expires) = httpdate::parse_http_date(expires?.trim()) else {
+        return Some(now);
+    };
+    // Convert into the clock of `now`, which differs from the std clock on the web
+    let ttl = expires
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .ok()?
+        .saturating_sub(now.duration_since(SystemTime::UNIX_EPOCH).ok()?);
+    Some(now + ttl)
+}
+
+/// The number of tiles which a [`TileCache`] holds by default.
+pub const DEFAULT_TILE_CACHE_CAPACITY: usize = 512;
+
+/// Caches fetched tiles by their URL. Clones share the same entries.
+///
+/// The cache holds at most `capacity` tiles. Once it is full, the least recently used tile is
+/// evicted for a new one. This also bounds tiles which never expire.
+#[derive(Clone, Debug)]
+pub struct TileCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    /// The tiles together with the time at which they were used last.
+    tiles: HashMap<String, (CachedTile, u64)>,
+    /// Increases with every use of a tile.
+    clock: u64,
+}
+
+impl Entries {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl Default for TileCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_TILE_CACHE_CAPACITY)
+    }
+}
+
+impl TileCache {
+    /// A cache which holds at most `capacity` tiles.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                capacity,
+                tiles: HashMap::new(),
+                clock: 0,
+            })),
+        }
+    }
+
+    /// Returns the data cached for `url`. Expired entries are treated as misses and removed.
+    pub fn get(&self, url: &str, now: SystemTime) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        let used = entries.tick();
+        match entries.tiles.get_mut(url) {
+            Some((tile, _)) if tile.is_expired(now) => {
+                entries.tiles.remove(url);
+                None
+            }
+            Some((tile, last_used)) => {
+                *last_used = used;
+                Some(tile.data.clone())
+            }
+            None => None,
+        }
+    }
+
+    /// Adds `tile` to the cache and evicts the least recently used tile if the cache is full.
+    /// Tiles which are already expired at `now`, e.g. `no-store` responses, are not added.
+    pub fn insert(&self, url: &str, tile: CachedTile, now: SystemTime) {
+        if tile.is_expired(now) {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.capacity == 0 {
+            return;
+        }
+        if !entries.tiles.contains_key(url) && entries.tiles.len() >= entries.capacity {
+            let least_recently_used = entries
+                .tiles
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(url, _)| url.clone());
+            if let Some(url) = least_recently_used {
+                entries.tiles.remove(&url);
+            }
+        }
+        let used = entries.tick();
+        entries.tiles.insert(url.to_string(), (tile, used));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use instant::SystemTime;
+
+    use crate::{
+        coords::ZoomLevel,
+        io::{
+            source_client::{HttpClient, HttpSourceClient, SourceFetchError},
+            source_type::{SourceType, TessellateSource},
+            tile_cache::{parse_expiry, CachedTile, TileCache},
+        },
+    };
+
+    /// Serves tiles which expire after `ttl`.
+    #[derive(Clone)]
+    struct MockHttpClient {
+        ttl: Duration,
+        calls: Arc<AtomicU32>,
+    }
+
+    #[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+    #[cfg_attr(feature = "thread-safe-futures", async_trait)]
+    impl HttpClient for MockHttpClient {
+        async fn fetch(&self, _url: &str) -> Result<Vec<u8>, SourceFetchError> {
+            unreachable!("tiles are fetched with fetch_cacheable")
+        }
+
+        async fn fetch_cacheable(
+            &self,
+            _url: &str,
+            _timeout: Option<Duration>,
+        ) -> Result<CachedTile, SourceFetchError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CachedTile::new(vec![call as u8], Some(SystemTime::now() + self.ttl)))
+        }
+    }
+
+    /// Fetches the same tile twice and returns the data of both fetches.
+    async fn fetch_twice(ttl: Duration) -> (Vec<u8>, Vec<u8>) {
+        fetch_twice_from(ttl, false).await
+    }
+
+    /// Like [`fetch_twice`], but from a source which is `volatile` or not.
+    async fn fetch_twice_from(ttl: Duration, volatile: bool) -> (Vec<u8>, Vec<u8>) {
+        let client = HttpSourceClient::new(MockHttpClient {
+            ttl,
+            calls: Arc::new(AtomicU32::new(0)),
+        })
+        .with_cache(TileCache::default());
+
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let source = SourceType::Tessellate(TessellateSource::new(
+            "http://localhost",
+            "pbf",
+            ZoomLevel::new(14),
+        )
+        .with_volatile(volatile));
+        let first = client.fetch(&coords, &source).await.unwrap();
+        let second = client.fetch(&coords, &source).await.unwrap();
+        (first, second)
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        let now = time(1_000_000_000);
+
+        assert_eq!(
+            parse_expiry(Some("public, max-age=3600"), None, now),
+            Some(time(1_000_003_600))
+        );
+        assert_eq!(parse_expiry(Some("no-cache"), None, now), Some(now));
+        assert_eq!(parse_expiry(None, None, now), None);
+
+        // Sun, 09 Sep 2001 01:46:40 GMT is 1_000_000_000 seconds after the epoch
+        assert_eq!(
+            parse_expiry(None, Some("Sun, 09 Sep 2001 02:46:40 GMT"), now),
+            Some(time(1_000_003_600))
+        );
+        assert_eq!(
+            parse_expiry(Some("max-age=60"), Some("Sun, 09 Sep 2001 02:46:40 GMT"), now),
+            Some(time(1_000_000_060))
+        );
+        // Dates in the past and invalid dates
+        assert_eq!(parse_expiry(None, Some("Thu, 01 Jan 1970 00:00:00 GMT"), now), Some(now));
+        assert_eq!(parse_expiry(None, Some("0"), now), Some(now));
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses() {
+        let cache = TileCache::default();
+        cache.insert("fresh", CachedTile::new(vec![1], Some(time(100))), time(0));
+        cache.insert("forever", CachedTile::new(vec![2], None), time(0));
+
+        assert_eq!(cache.get("fresh", time(99)), Some(vec![1]));
+        assert_eq!(cache.get("forever", time(1000)), Some(vec![2]));
+
+        assert_eq!(cache.get("fresh", time(100)), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_expired_tiles_are_not_inserted() {
+        let cache = TileCache::default();
+        let no_store = parse_expiry(Some("no-store"), None, time(100));
+        cache.insert("no-store", CachedTile::new(vec![1], no_store), time(100));
+        cache.insert("past", CachedTile::new(vec![2], Some(time(50))), time(100));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_tile_is_evicted() {
+        let cache = TileCache::with_capacity(2);
+        cache.insert("a", CachedTile::new(vec![1], None), time(0));
+        cache.insert("b", CachedTile::new(vec![2], None), time(0));
+
+        // Using `a` makes `b` the least recently used tile
+        assert_eq!(cache.get("a", time(0)), Some(vec![1]));
+        cache.insert("c", CachedTile::new(vec![3], None), time(0));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b", time(0)), None);
+        assert_eq!(cache.get("a", time(0)), Some(vec![1]));
+        assert_eq!(cache.get("c", time(0)), Some(vec![3]));
+
+        // Replacing a tile does not evict another one
+        cache.insert("c", CachedTile::new(vec![4], None), time(0));
+        assert_eq!(cache.get("a", time(0)), Some(vec![1]));
+        assert_eq!(cache.get("c", time(0)), Some(vec![4]));
+
+        let disabled = TileCache::with_capacity(0);
+        disabled.insert("a", CachedTile::new(vec![1], None), time(0));
+        assert!(disabled.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fresh_tile_is_served_from_cache() {
+        let (first, second) = fetch_twice(Duration::from_secs(3600)).await;
+        assert_eq!(first, vec![0]);
+        assert_eq!(second, vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_tile_is_refetched() {
+        let (first, second) = fetch_twice(Duration::ZERO).await;
+        assert_eq!(first, vec![0]);
+        assert_eq!(second, vec![1]);
+    }
+    #[tokio::test]
+    async fn test_volatile_source_bypasses_cache() {
+        let (first, second) = fetch_twice_from(Duration::from_secs(3600), true).await;
+        assert_eq!(first, vec![0]);
+        assert_eq!(second, vec![1]);
+
+        let (first, second) = fetch_twice_from(Duration::from_secs(3600), false).await;
+        assert_eq!(first, vec![0]);
+        assert_eq!(second, vec![0]);
+    }
+}
@@ -0,0 +1,275 @@
+//! Limits how much work is processed concurrently.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::io::apc::CancellationToken;
+
+/// An occupied slot of a [`WorkerPool`]. The slot is freed once the permit and all of its clones
+/// are dropped, e.g. when the result of the work arrived, see [`RunningPermits`].
+#[derive(Clone, Debug)]
+pub struct WorkerPermit {
+    _guard: Arc<PermitGuard>,
+}
+
+#[derive(Debug)]
+struct PermitGuard(Arc<AtomicUsize>);
+
+impl Drop for PermitGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Queues work and starts at most `max_workers` items at once. Queued work with the lowest
+/// priority starts first, e.g. the tiles nearest to the center of the view.
+pub struct WorkerPool<T> {
+    max_workers: usize,
+    running: Arc<AtomicUsize>,
+    queue: Vec<(f64, T)>,
+}
+
+impl<T> WorkerPool<T> {
+    /// Creates a pool which runs at most `max_workers` items at once. At least one item runs.
+    pub fn new(max_workers: usize) -> Self {
+        Self {
+            max_workers: max_workers.max(1),
+            running: Default::default(),
+            queue: Vec::new(),
+        }
+    }
+
+    pub fn max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    /// The number of started items whose permits are still held.
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// The number of items which wait for a free slot.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Queues `work`. Items with a lower `priority` are started first.
+    pub fn push(&mut self, priority: f64, work: T) {
+        self.queue.push((priority, work));
+    }
+
+    /// Removes and returns the queued item with the lowest priority, together with its priority.
+    /// Items with equal priorities are returned in the order in which they were queued.
+    pub fn pop(&mut self) -> Option<(f64, T)> {
+        let (index, _) = self
+            .queue
+            .iter()
+            .enumerate()
+            .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b))?;
+        Some(self.queue.remove(index))
+    }
+
+    /// Keeps only the queued items for which `keep` returns true, e.g. to drop cancelled work.
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        self.queue.retain(|(_, work)| keep(work));
+    }
+
+    /// Starts queued items by passing them to `start` until all slots are occupied. Each item
+    /// occupies its slot as long as the passed [`WorkerPermit`] is held. Items with equal
+    /// priorities are started in the order in which they were queued.
+    pub fn dispatch(&mut self, mut start: impl FnMut(T, WorkerPermit)) {
+        self.queue.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let available = self.max_workers.saturating_sub(self.running());
+        let started = available.min(self.queue.len());
+        for (_, work) in self.queue.drain(..started) {
+            self.running.fetch_add(1, Ordering::SeqCst);
+            let guard = PermitGuard(self.running.clone());
+            start(work, WorkerPermit { _guard: Arc::new(guard) });
+        }
+    }
+}
+
+/// Holds the permits of started work on the side which started it, until the result of the work
+/// arrives. Procedures on the other side of a serialization boundary can not hold a permit, so it
+/// stays with the caller. Work which is cancelled sends no result and frees its slot once the
+/// cancellation is noticed, see [`RunningPermits::release_cancelled`].
+pub struct RunningPermits<K> {
+    permits: HashMap<K, Vec<(CancellationToken, WorkerPermit)>>,
+}
+
+impl<K> Default for RunningPermits<K> {
+    fn default() -> Self {
+        Self {
+            permits: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash> RunningPermits<K> {
+    /// Holds the `permit` of the work for `key` until its result arrives or it is cancelled.
+    pub fn hold(&mut self, key: K, cancellation: CancellationToken, permit: WorkerPermit) {
+        self.permits
+            .entry(key)
+            .or_default()
+            .push((cancellation, permit));
+    }
+
+    /// Frees the slot of the earliest started work for `key`, because its result arrived.
+    pub fn release(&mut self, key: &K) {
+        let Some(permits) = self.permits.get_mut(key) else {
+            return;
+        };
+        permits.remove(0);
+        if permits.is_empty() {
+            self.permits.remove(key);
+        }
+    }
+
+    /// Frees the slots of cancelled work, which never sends a result.
+    pub fn release_cancelled(&mut self) {
+        self.permits.retain(|_, permits| {
+            permits.retain(|(cancellation, _)| !cancellation.is_cancelled());
+            !permits.is_empty()
+        });
+    }
+
+    /// The number of held permits.
+    pub fn len(&self) -> usize {
+        self.permits.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.permits.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunningPermits, WorkerPermit, WorkerPool};
+    use crate::io::apc::CancellationToken;
+
+    /// Records the started work and keeps it running until it is finished explicitly.
+    #[derive(Default)]
+    struct MockScheduler {
+        started: Vec<&'static str>,
+        running: Vec<(&'static str, WorkerPermit)>,
+        max_running: usize,
+    }
+
+    impl MockScheduler {
+        fn start(&mut self, work: &'static str, permit: WorkerPermit) {
+            self.started.push(work);
+            self.running.push((work, permit));
+            self.max_running = self.max_running.max(self.running.len());
+        }
+
+        fn finish(&mut self, work: &str) {
+            self.running.retain(|(running, _)| *running != work);
+        }
+    }
+
+    #[test]
+    fn test_limits_concurrent_work() {
+        let mut pool = WorkerPool::new(2);
+        let mut scheduler = MockScheduler::default();
+
+        pool.push(9.0, "corner");
+        pool.push(0.0, "center");
+        pool.push(4.0, "edge");
+        pool.push(1.0, "near");
+        pool.push(4.0, "other edge");
+
+        pool.dispatch(|work, permit| scheduler.start(work, permit));
+        // The tiles nearest to the center go first
+        assert_eq!(scheduler.started, vec!["center", "near"]);
+        assert_eq!(pool.running(), 2);
+        assert_eq!(pool.queued(), 3);
+
+        // All workers are busy
+        pool.dispatch(|work, permit| scheduler.start(work, permit));
+        assert_eq!(scheduler.started.len(), 2);
+
+        scheduler.finish("near");
+        assert_eq!(pool.running(), 1);
+        pool.dispatch(|work, permit| scheduler.start(work, permit));
+        assert_eq!(scheduler.started, vec!["center", "near", "edge"]);
+
+        while pool.queued() > 0 {
+            let finished = scheduler.running[0].0;
+            scheduler.finish(finished);
+            pool.dispatch(|work, permit| scheduler.start(work, permit));
+        }
+        assert_eq!(
+            scheduler.started,
+            vec!["center", "near", "edge", "other edge", "corner"]
+        );
+        assert_eq!(scheduler.max_running, 2);
+    }
+
+    #[test]
+    fn test_permit_clones_hold_the_slot() {
+        let mut pool = WorkerPool::new(1);
+        pool.push(0.0, ());
+
+        let mut permits = Vec::new();
+        pool.dispatch(|_, permit| permits.push(permit));
+        let clone = permits[0].clone();
+        drop(permits);
+        assert_eq!(pool.running(), 1);
+
+        drop(clone);
+        assert_eq!(pool.running(), 0);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut pool = WorkerPool::new(0);
+        assert_eq!(pool.max_workers(), 1);
+        pool.push(0.0, 1);
+        pool.push(1.0, 2);
+        pool.retain(|work| *work != 1);
+
+        let mut started = Vec::new();
+        pool.dispatch(|work, _| started.push(work));
+        assert_eq!(started, vec![2]);
+    }
+
+    #[test]
+    fn test_running_permits_are_held_until_the_result_arrives() {
+        let mut pool = WorkerPool::new(2);
+        let mut running = RunningPermits::default();
+        let cancelled = CancellationToken::new();
+        pool.push(0.0, ("tile", CancellationToken::new()));
+        pool.push(1.0, ("tile", CancellationToken::new()));
+        pool.push(2.0, ("other tile", cancelled.clone()));
+
+        pool.dispatch(|(key, cancellation), permit| running.hold(key, cancellation, permit));
+        assert_eq!(pool.running(), 2);
+        assert_eq!(running.len(), 2);
+
+        // Each result frees one slot of the work for its key
+        running.release(&"tile");
+        assert_eq!(pool.running(), 1);
+        pool.dispatch(|(key, cancellation), permit| running.hold(key, cancellation, permit));
+        assert_eq!(pool.running(), 2);
+
+        running.release(&"unknown");
+        assert_eq!(pool.running(), 2);
+
+        // Cancelled work sends no result
+        cancelled.cancel();
+        running.release_cancelled();
+        assert_eq!(pool.running(), 1);
+
+        running.release(&"tile");
+        assert!(running.is_empty());
+        assert_eq!(pool.running(), 0);
+    }
+}
@@ -1,11 +1,14 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use async_trait::async_trait;
 use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
-use reqwest::{Client, StatusCode};
+use reqwest::{header, Client, StatusCode};
 use reqwest_middleware::ClientWithMiddleware;
 
-use crate::io::source_client::{HttpClient, SourceFetchError};
+use crate::io::{
+    source_client::{FetchErrorKind, HttpClient, SourceFetchError},
+    tile_cache::{parse_expiry, CachedTile},
+};
 
 #[derive(Clone)]
 pub struct ReqwestHttpClient {
@@ -14,13 +17,29 @@ pub struct ReqwestHttpClient {
 
 impl From<reqwest::Error> for SourceFetchError {
     fn from(err: reqwest::Error) -> Self {
-        SourceFetchError(Box::new(err))
+        SourceFetchError::new(fetch_error_kind(&err), err)
     }
 }
 
 impl From<reqwest_middleware::Error> for SourceFetchError {
     fn from(err: reqwest_middleware::Error) -> Self {
-        SourceFetchError(Box::new(err))
+        let kind = match &err {
+            reqwest_middleware::Error::Reqwest(err) => fetch_error_kind(err),
+            reqwest_middleware::Error::Middleware(_) => FetchErrorKind::Other,
+        };
+        SourceFetchError::new(kind, err)
+    }
+}
+
+fn fetch_error_kind(err: &reqwest::Error) -> FetchErrorKind {
+    if err.is_timeout() {
+        FetchErrorKind::Timeout
+    } else if let Some(status) = err.status() {
+        FetchErrorKind::Status(status.as_u16())
+    } else if err.is_connect() || err.is_request() {
+        FetchErrorKind::Connection
+    } else {
+        FetchErrorKind::Other
     }
 }
 
@@ -51,18 +70,55 @@ impl ReqwestHttpClient {
 #[cfg_attr(feature = "thread-safe-futures", async_trait)]
 impl HttpClient for ReqwestHttpClient {
     async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
-        let response = self.client.get(url).send().await?;
+        self.fetch_with_timeout(url, None).await
+    }
+
+    async fn fetch_with_timeout(
+        &self,
+        url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u8>, SourceFetchError> {
+        Ok(self.fetch_cacheable(url, timeout).await?.data)
+    }
+
+    async fn fetch_cacheable(
+        &self,
+        url: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CachedTile, SourceFetchError> {
+        let mut request = self.client.get(url);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+
+        let response = request.send().await?;
         match response.error_for_status() {
             Ok(response) => {
                 if response.status() == StatusCode::NOT_MODIFIED {
                     log::info!("Using data from cache");
                 }
 
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                };
+                let expires = parse_expiry(
+                    header(header::CACHE_CONTROL),
+                    header(header::EXPIRES),
+                    instant::SystemTime::now(),
+                );
+
                 let body = response.bytes().await?;
 
-                Ok(Vec::from(body.as_ref()))
+                Ok(CachedTile::new(Vec::from(body.as_ref()), expires))
             }
-            Err(e) => Err(SourceFetchError(Box::new(e))),
+            Err(e) => Err(e.into()),
         }
     }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
 }
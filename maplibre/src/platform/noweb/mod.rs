@@ -45,8 +45,12 @@ impl OffscreenKernel for ReqwestOffscreenKernelEnvironment {
     }
 
     fn source_client(&self) -> SourceClient<Self::HttpClient> {
-        SourceClient::new(HttpSourceClient::new(ReqwestHttpClient::new::<String>(
-            self.0.cache_directory.clone(),
-        )))
+        SourceClient::new(
+            HttpSourceClient::new(ReqwestHttpClient::new::<String>(
+                self.0.cache_directory.clone(),
+            ))
+            .with_fetch_policy(self.0.fetch_policy.clone())
+            .with_cache(self.0.tile_cache.clone()),
+        )
     }
 }
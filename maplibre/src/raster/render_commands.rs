@@ -4,6 +4,7 @@ use crate::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
         resource::TrackedRenderPass,
+        settings::CameraMode,
         tile_view_pattern::WgpuTileViewPattern,
     },
     tcs::world::World,
@@ -23,6 +24,14 @@ impl<P: PhaseItem> RenderCommand<P> for SetRasterTilePipeline {
         };
 
         pass.set_render_pipeline(raster_resources.pipeline());
+
+        let opacity = raster_resources.opacity() as f64;
+        pass.set_blend_constant(wgpu::Color {
+            r: opacity,
+            g: opacity,
+            b: opacity,
+            a: opacity,
+        });
         RenderCommandResult::Success
     }
 }
@@ -63,28 +72,23 @@ impl RenderCommand<LayerItem> for DrawRasterTile {
         };
 
         let source_shape = &item.source_shape;
+        let tile_view_pattern_buffer = match tile_view_pattern.shape_slice(source_shape) {
+            Ok(slice) => slice,
+            Err(error) => {
+                log::error!("Rendering {} failed: {error}", item.style_layer);
+                return RenderCommandResult::Failure;
+            }
+        };
 
-        let reference = source_shape.coords().stencil_reference_value_3d() as u32;
+        let camera_mode = world.resources.get::<CameraMode>().copied().unwrap_or_default();
+        let reference = source_shape.coords().stencil_reference_value(camera_mode) as u32;
 
         pass.set_stencil_reference(reference);
 
-        let tile_view_pattern_buffer = source_shape
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
-        pass.set_vertex_buffer(
-            0,
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
-
-        let tile_view_pattern_buffer = source_shape
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
+        pass.set_vertex_buffer(0, tile_view_pattern_buffer);
 
         // FIXME tcs: I passin random data here right now, but instead we need the correct metadata here
-        pass.set_vertex_buffer(
-            1,
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
+        pass.set_vertex_buffer(1, tile_view_pattern_buffer);
 
         const TILE_MASK_SHADER_VERTICES: u32 = 6;
         pass.draw(0..TILE_MASK_SHADER_VERTICES, 0..1);
@@ -53,6 +53,12 @@ impl<E: Environment, T: RasterTransferables> System for RequestSystem<E, T> {
 
         if view_state.did_camera_change() || view_state.did_zoom_change() {
             if let Some(view_region) = &view_region {
+                let source_id = style
+                    .layers
+                    .iter()
+                    .filter(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))
+                    .find_map(|layer| layer.source.clone());
+
                 // TODO: We also need to request tiles from layers above if we are over the maximum zoom level
 
                 for coords in view_region.iter() {
@@ -69,11 +75,13 @@ impl<E: Environment, T: RasterTransferables> System for RequestSystem<E, T> {
                         continue;
                     }
 
-                    world
-                        .tiles
-                        .spawn_mut(coords)
-                        .unwrap()
-                        .insert(RasterLayersDataComponent::default());
+                    let component = RasterLayersDataComponent::default();
+                    let mut tile = world.tiles.spawn_mut(coords).unwrap();
+                    match &source_id {
+                        Some(source_id) => tile.insert_from_source(source_id, component),
+                        None => tile.insert(component),
+                    };
+                    let cancellation = world.tiles.cancellation_token(coords).unwrap();
 
                     tracing::event!(tracing::Level::ERROR, %coords, "tile request started: {coords}");
                     log::info!("tile request started: {coords}");
@@ -84,6 +92,8 @@ impl<E: Environment, T: RasterTransferables> System for RequestSystem<E, T> {
                             Input::TileRequest {
                                 coords,
                                 style: style.clone(), // TODO: Avoid cloning whole style
+                                style_layer_ids: None,
+                                cancellation,
                             },
                             fetch_raster_apc::<
                                 E::OffscreenKernelEnvironment,
@@ -107,7 +117,13 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
     kernel: K,
 ) -> AsyncProcedureFuture {
     Box::pin(async move {
-        let Input::TileRequest { coords, style } = input else {
+        let Input::TileRequest {
+            coords,
+            style,
+            cancellation,
+            ..
+        } = input
+        else {
             return Err(ProcedureError::IncompatibleInput);
         };
 
@@ -131,6 +147,11 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
 
             match client.fetch(&coords, &source).await {
                 Ok(data) => {
+                    if cancellation.is_cancelled() {
+                        log::info!("tile request cancelled: {coords}");
+                        return Ok(());
+                    }
+
                     let data = data.into_boxed_slice();
 
                     let mut process_context = ProcessRasterContext::<T, C>::new(context);
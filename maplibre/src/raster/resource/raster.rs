@@ -12,28 +12,34 @@ use crate::{
 /// * pipeline
 /// * bindgroups
 pub struct RasterResources {
-    sampler: wgpu::Sampler,
+    linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
     msaa: Msaa,
     pipeline: wgpu::RenderPipeline,
     bound_textures: HashMap<WorldTileCoords, wgpu::BindGroup>,
+    opacity: f32,
 }
 
 impl RasterResources {
     pub fn new(msaa: Msaa, device: &wgpu::Device, pipeline: wgpu::RenderPipeline) -> Self {
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+        let create_sampler = |filter: wgpu::FilterMode| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        };
         Self {
-            sampler,
+            linear_sampler: create_sampler(wgpu::FilterMode::Linear),
+            nearest_sampler: create_sampler(wgpu::FilterMode::Nearest),
             msaa,
             pipeline,
             bound_textures: Default::default(),
+            opacity: 1.0,
         }
     }
 
@@ -53,13 +59,20 @@ impl RasterResources {
         self.bound_textures.get(coords)
     }
 
-    /// Creates a bind group for each fetched raster tile and store it inside a hashmap.
+    /// Creates a bind group for each fetched raster tile and store it inside a hashmap. The texture
+    /// is sampled using `filter`.
     pub fn bind_texture(
         &mut self,
         device: &wgpu::Device,
         coords: &WorldTileCoords,
         texture: Texture,
+        filter: wgpu::FilterMode,
     ) {
+        let sampler = match filter {
+            wgpu::FilterMode::Linear => &self.linear_sampler,
+            wgpu::FilterMode::Nearest => &self.nearest_sampler,
+        };
+
         self.bound_textures.insert(
             *coords,
             device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -71,7 +84,7 @@ impl RasterResources {
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        resource: wgpu::BindingResource::Sampler(sampler),
                     },
                 ],
                 label: None,
@@ -82,6 +95,15 @@ impl RasterResources {
     pub fn pipeline(&self) -> &wgpu::RenderPipeline {
         &self.pipeline
     }
+
+    /// The opacity with which the raster tiles are composited, see `raster-opacity`.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
 }
 
 impl HasTile for RasterResources {
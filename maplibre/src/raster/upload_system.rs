@@ -1,7 +1,7 @@
 //! Uploads data to the GPU which is needed for rendering.
 use crate::{
     context::MapContext,
-    coords::ViewRegion,
+    coords::{ViewRegion, Zoom},
     raster::{
         resource::RasterResources, AvailableRasterLayerData, RasterLayerData,
         RasterLayersDataComponent,
@@ -11,7 +11,11 @@ use crate::{
         tile_view_pattern::DEFAULT_TILE_SIZE,
         Renderer,
     },
-    style::Style,
+    style::{
+        layer::{LayerPaint, StyleLayer},
+        raster::RasterResampling,
+        Style,
+    },
     tcs::tiles::Tiles,
 };
 
@@ -30,6 +34,8 @@ pub fn upload_system(
     else {
         return;
     };
+    raster_resources.set_opacity(raster_opacity(style, view_state.zoom()));
+
     let view_region =
         view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
@@ -105,7 +111,98 @@ fn upload_raster_layer(
                 texture.size,
             );
 
-            raster_resources.bind_texture(device, coords, texture);
+            raster_resources.bind_texture(device, coords, texture, filter_mode(style_layer));
+        }
+    }
+}
+
+/// Returns the `raster-opacity` of the first raster layer of `style` at `zoom`. Defaults to fully
+/// opaque.
+fn raster_opacity(style: &Style, zoom: Zoom) -> f32 {
+    style
+        .layers
+        .iter()
+        .find(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))
+        .and_then(|layer| layer.paint.as_ref())
+        .map_or(1.0, |paint| paint.get_opacity(zoom))
+}
+
+/// Returns the filter which is used to sample the textures of a raster layer according to its
+/// `raster-resampling`. Defaults to linear filtering.
+fn filter_mode(style_layer: &StyleLayer) -> wgpu::FilterMode {
+    match &style_layer.paint {
+        Some(LayerPaint::Raster(raster)) => match raster.raster_resampling {
+            Some(RasterResampling::Nearest) => wgpu::FilterMode::Nearest,
+            Some(RasterResampling::Linear) | None => wgpu::FilterMode::Linear,
+        },
+        _ => wgpu::FilterMode::Linear,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_mode, raster_opacity};
+    use crate::{
+        coords::Zoom,
+        style::{
+            layer::{LayerPaint, StyleLayer},
+            raster::{RasterLayer, RasterResampling},
+            Style,
+        },
+    };
+
+    fn raster_layer(raster_resampling: Option<RasterResampling>) -> StyleLayer {
+        StyleLayer {
+            paint: Some(LayerPaint::Raster(RasterLayer {
+                raster_resampling,
+                ..RasterLayer::default()
+            })),
+            ..StyleLayer::default()
         }
     }
+
+    #[test]
+    fn test_filter_mode() {
+        assert_eq!(
+            filter_mode(&raster_layer(Some(RasterResampling::Nearest))),
+            wgpu::FilterMode::Nearest
+        );
+        assert_eq!(
+            filter_mode(&raster_layer(Some(RasterResampling::Linear))),
+            wgpu::FilterMode::Linear
+        );
+        assert_eq!(filter_mode(&raster_layer(None)), wgpu::FilterMode::Linear);
+    }
+
+    #[test]
+    fn test_filter_mode_from_style() {
+        let layer: StyleLayer = serde_json::from_str(
+            r#"{"id": "dem", "type": "raster", "paint": {"raster-resampling": "nearest"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(filter_mode(&layer), wgpu::FilterMode::Nearest);
+    }
+
+    #[test]
+    fn test_raster_opacity() {
+        let layer: StyleLayer = serde_json::from_str(
+            r#"{"id": "satellite", "type": "raster", "paint": {"raster-opacity": {"base": 1.0, "stops": [[0, 0.0], [10, 1.0]]}}}"#,
+        )
+        .unwrap();
+        let style = Style {
+            layers: vec![layer],
+            ..Style::default()
+        };
+
+        assert!(raster_opacity(&style, Zoom::new(0.0)).abs() < 1e-6);
+        assert!((raster_opacity(&style, Zoom::new(5.0)) - 0.5).abs() < 1e-6);
+        assert!((raster_opacity(&style, Zoom::new(10.0)) - 1.0).abs() < 1e-6);
+
+        let style = Style {
+            layers: vec![raster_layer(None)],
+            ..Style::default()
+        };
+        assert_eq!(raster_opacity(&style, Zoom::new(5.0)), 1.0);
+    }
 }
@@ -30,7 +30,7 @@ use crate::{
         graph::{EmptyNode, RenderGraph},
         main_pass::{MainPassDriverNode, MainPassNode},
         resource::{Head, Surface, Texture, TextureView},
-        settings::{RendererSettings, WgpuSettings},
+        settings::{CameraMode, RendererSettings, WgpuSettings},
         systems::{
             cleanup_system::cleanup_system, resource_system::ResourceSystem,
             sort_phase_system::sort_phase_system,
@@ -76,8 +76,6 @@ use crate::{
     window::PhysicalSize,
 };
 
-pub(crate) const INDEX_FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32; // Must match IndexDataType
-
 /// The labels of the default App rendering stages.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum RenderStageLabel {
@@ -598,6 +596,7 @@ impl<E: Environment> Plugin<E> for RenderPlugin {
         resources.init::<ViewTileSources>();
         // masks
         resources.insert(Eventually::<MaskPipeline>::Uninitialized);
+        resources.init::<CameraMode>();
 
         schedule.add_stage(RenderStageLabel::Extract, SystemStage::default());
         schedule.add_stage(
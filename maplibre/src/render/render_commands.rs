@@ -5,6 +5,7 @@ use crate::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TileMaskItem},
         resource::TrackedRenderPass,
+        settings::CameraMode,
         tile_view_pattern::WgpuTileViewPattern,
         MaskPipeline,
     },
@@ -40,20 +41,22 @@ impl RenderCommand<TileMaskItem> for DrawMask {
         };
 
         let tile_mask = &item.source_shape;
+        let tile_view_pattern_buffer = match tile_view_pattern.shape_slice(tile_mask) {
+            Ok(slice) => slice,
+            Err(error) => {
+                log::error!("Drawing the mask failed: {error}");
+                return RenderCommandResult::Failure;
+            }
+        };
 
         // Draw mask with stencil value of e.g. parent
-        let reference = tile_mask.coords().stencil_reference_value_3d() as u32;
+        let camera_mode = world.resources.get::<CameraMode>().copied().unwrap_or_default();
+        let reference = tile_mask.coords().stencil_reference_value(camera_mode) as u32;
 
         pass.set_stencil_reference(reference);
 
-        let tile_view_pattern_buffer = tile_mask
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
-        pass.set_vertex_buffer(
-            0,
-            // Mask is of the requested shape
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
+        // Mask is of the requested shape
+        pass.set_vertex_buffer(0, tile_view_pattern_buffer);
         const TILE_MASK_SHADER_VERTICES: u32 = 6;
         pass.draw(0..TILE_MASK_SHADER_VERTICES, 0..1);
 
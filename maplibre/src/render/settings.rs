@@ -101,6 +101,19 @@ impl Default for Msaa {
     }
 }
 
+/// How the map is viewed, which determines how the stencil reference values of tiles are
+/// computed. Insert it as a resource into the world to change it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// The camera can be pitched, so tiles from arbitrary zoom levels can be visible next to each
+    /// other.
+    #[default]
+    Perspective,
+    /// The map is only viewed from above, so visible tiles mostly share a zoom level and more of
+    /// them can be drawn next to each other.
+    TopDown,
+}
+
 #[derive(Clone, Copy)]
 pub struct RendererSettings {
     pub msaa: Msaa,
@@ -109,6 +122,10 @@ pub struct RendererSettings {
     pub depth_texture_format: TextureFormat,
     /// Present mode for surfaces if a surface is used.
     pub present_mode: PresentMode,
+    /// Lines are never rendered thinner than this width in pixels. Without a minimum, thin lines
+    /// which interpolate to sub-pixel widths at low zoom levels disappear.
+    /// Defaults to `None`, i.e. lines are as thin as their style.
+    pub min_line_width: Option<f32>,
 }
 
 impl Default for RendererSettings {
@@ -119,6 +136,7 @@ impl Default for RendererSettings {
 
             depth_texture_format: TextureFormat::Depth24PlusStencil8,
             present_mode: PresentMode::AutoVsync,
+            min_line_width: None,
         }
     }
 }
@@ -7,8 +7,17 @@ fn main(
     @location(0) v_color: vec4<f32>,
     @location(1) @interpolate(linear, center) v_normal: vec2<f32>,
     @location(2) line_width: f32,
+    @location(3) v_edge: f32,
+    @location(4) v_blur: f32,
     @builtin(position) position: vec4<f32>,
 ) -> Output {
+    // Fade out the edges of blurred lines, based on the distance from the centerline
+    if v_blur > 0.0 {
+        let distance = abs(v_edge) * line_width;
+        let fade = clamp((line_width - distance) / v_blur, 0.0, 1.0);
+        return Output(vec4<f32>(v_color.rgb, v_color.a * fade));
+    }
+
 //    let mag = length(v_normal);
 //    if mag == 0 {
     return Output(v_color);
@@ -119,6 +119,18 @@ impl Shader for VectorTileShader {
                             format: wgpu::VertexFormat::Float32x2,
                             shader_location: 1,
                         },
+                        // edge and blur
+                        wgpu::VertexAttribute {
+                            offset: 2 * wgpu::VertexFormat::Float32x2.size(),
+                            format: wgpu::VertexFormat::Float32x2,
+                            shader_location: 2,
+                        },
+                        // offset
+                        wgpu::VertexAttribute {
+                            offset: 3 * wgpu::VertexFormat::Float32x2.size(),
+                            format: wgpu::VertexFormat::Float32x2,
+                            shader_location: 3,
+                        },
                     ],
                 },
                 // tile metadata
@@ -153,6 +165,13 @@ impl Shader for VectorTileShader {
                             format: wgpu::VertexFormat::Float32,
                             shader_location: 9,
                         },
+                        // tile_opacity
+                        wgpu::VertexAttribute {
+                            offset: 4 * wgpu::VertexFormat::Float32x4.size()
+                                + wgpu::VertexFormat::Float32.size(),
+                            format: wgpu::VertexFormat::Float32,
+                            shader_location: 15,
+                        },
                     ],
                 },
                 // layer metadata
@@ -166,6 +185,12 @@ impl Shader for VectorTileShader {
                             format: wgpu::VertexFormat::Float32,
                             shader_location: 10,
                         },
+                        // translate
+                        wgpu::VertexAttribute {
+                            offset: wgpu::VertexFormat::Float32.size(),
+                            format: wgpu::VertexFormat::Float32x2,
+                            shader_location: 12,
+                        },
                     ],
                 },
                 // features
@@ -179,11 +204,27 @@ impl Shader for VectorTileShader {
                             format: wgpu::VertexFormat::Float32x4,
                             shader_location: 8,
                         },
+                        // width and gap_width
                         wgpu::VertexAttribute {
                             offset: wgpu::VertexFormat::Float32x4.size(),
-                            format: wgpu::VertexFormat::Float32,
+                            format: wgpu::VertexFormat::Float32x2,
                             shader_location: 11,
                         },
+                        // opacity
+                        wgpu::VertexAttribute {
+                            offset: wgpu::VertexFormat::Float32x4.size()
+                                + wgpu::VertexFormat::Float32x2.size(),
+                            format: wgpu::VertexFormat::Float32,
+                            shader_location: 13,
+                        },
+                        // feature_order
+                        wgpu::VertexAttribute {
+                            offset: wgpu::VertexFormat::Float32x4.size()
+                                + wgpu::VertexFormat::Float32x2.size()
+                                + wgpu::VertexFormat::Float32.size(),
+                            format: wgpu::VertexFormat::Float32,
+                            shader_location: 14,
+                        },
                     ],
                 },
             ],
@@ -247,11 +288,38 @@ impl ShaderGlobals {
 pub struct ShaderVertex {
     pub position: Vec2f32,
     pub normal: Vec2f32,
+    /// The side of the centerline on which a stroke vertex lies, `-1` or `1`. Interpolated
+    /// between the sides, its magnitude is the distance from the centerline relative to the
+    /// line width. Zero for fills.
+    pub edge: f32,
+    /// The width of the faded edges of a stroke in pixels, see `line-blur`.
+    pub blur: f32,
+    /// The direction in which a casing of a line with a `line-gap-width` is moved away from the
+    /// centerline. The shader scales it by `(gap width + line width) / 2` and the zoom factor of
+    /// the tile, like the width. Zero for other vertices.
+    pub offset: Vec2f32,
 }
 
 impl ShaderVertex {
     pub fn new(position: Vec2f32, normal: Vec2f32) -> Self {
-        Self { position, normal }
+        Self {
+            position,
+            normal,
+            edge: 0.0,
+            blur: 0.0,
+            offset: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_edge(mut self, edge: f32, blur: f32) -> Self {
+        self.edge = edge;
+        self.blur = blur;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2f32) -> Self {
+        self.offset = offset;
+        self
     }
 }
 
@@ -266,17 +334,35 @@ impl Default for ShaderVertex {
 pub struct ShaderFeatureStyle {
     pub color: Vec4f32,
     pub width: f32,
+    /// The gap between the casings of lines with a `line-gap-width`, see [`ShaderVertex::offset`]
+    pub gap_width: f32,
+    /// Multiplied with the alpha of `color` in the shader
+    pub opacity: f32,
+    /// The position of the feature within its layer, normalized to `0..1`. It is added to the
+    /// z-index of the layer, such that overlapping features of a layer are always layered by
+    /// their order instead of whichever passes the depth test first.
+    pub feature_order: f32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub struct ShaderLayerMetadata {
     pub z_index: f32,
+    /// Offset of the layer in pixels along the axes of the map
+    pub translate: Vec2f32,
 }
 
 impl ShaderLayerMetadata {
     pub fn new(z_index: f32) -> Self {
-        Self { z_index }
+        Self {
+            z_index,
+            translate: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_translate(mut self, translate: Vec2f32) -> Self {
+        self.translate = translate;
+        self
     }
 }
 
@@ -285,6 +371,8 @@ impl ShaderLayerMetadata {
 pub struct ShaderTileMetadata {
     pub transform: Mat4x4f32,
     pub zoom_factor: f32,
+    /// Multiplies the alpha of all features of the tile
+    pub opacity: f32,
 }
 
 impl ShaderTileMetadata {
@@ -292,6 +380,7 @@ impl ShaderTileMetadata {
         Self {
             transform,
             zoom_factor,
+            opacity: 1.0,
         }
     }
 }
@@ -385,8 +474,13 @@ impl Shader for RasterTileShader {
             entry_point: "main",
             targets: vec![Some(wgpu::ColorTargetState {
                 format: self.format,
+                // The blend constant holds the opacity of the raster layer
                 blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Constant,
+                        dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                        operation: wgpu::BlendOperation::Add,
+                    },
                     alpha: wgpu::BlendComponent::REPLACE,
                 }),
                 write_mask: wgpu::ColorWrites::ALL,
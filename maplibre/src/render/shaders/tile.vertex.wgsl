@@ -13,6 +13,8 @@ struct VertexOutput {
     @location(0) v_color: vec4<f32>,
     @location(1) @interpolate(linear, center) v_normal: vec2<f32>,
     @location(2) line_width: f32,
+    @location(3) v_edge: f32,
+    @location(4) v_blur: f32,
     @builtin(position) position: vec4<f32>,
 };
 
@@ -20,6 +22,8 @@ struct VertexOutput {
 fn main(
     @location(0) position: vec2<f32>,
     @location(1) normal: vec2<f32>,
+    @location(2) edge_blur: vec2<f32>,
+    @location(3) offset: vec2<f32>,
     @location(4) translate1: vec4<f32>,
     @location(5) translate2: vec4<f32>,
     @location(6) translate3: vec4<f32>,
@@ -27,15 +31,25 @@ fn main(
     @location(8) color: vec4<f32>,
     @location(9) zoom_factor: f32,
     @location(10) z_index: f32,
-    @location(11) width_in: f32,
+    @location(11) width_gap: vec2<f32>,
+    @location(12) translate: vec2<f32>,
+    @location(13) opacity: f32,
+    @location(14) feature_order: f32,
+    @location(15) tile_opacity: f32,
     @builtin(instance_index) instance_idx: u32 // instance_index is used when we have multiple instances of the same "object"
 ) -> VertexOutput {
-    let z = -z_index;
-    let width = width_in * zoom_factor;
+    // Features later in the layer are in front of earlier ones, but behind the next layer
+    let z = -(z_index + feature_order);
+    let edge = edge_blur.x;
+    let blur = edge_blur.y;
+    let width = width_gap.x * zoom_factor;
+    // Casings of lines with a gap are moved apart by half of the gap and the line width
+    let casing_offset = offset * (width_gap.y + width_gap.x) / 2.0;
+    let translated_position = position + (translate + casing_offset) * zoom_factor;
 
-    var screen_space_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(position + normal * width, z, 1.0);
+    var screen_space_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(translated_position + normal * width, z, 1.0);
     var screen_space_normal = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(normal, 0.0, 0.0);
     var final_position = screen_space_position + screen_space_normal * width;
 
-    return VertexOutput(color, normal, width, final_position);
+    return VertexOutput(vec4<f32>(color.rgb, color.a * opacity * tile_opacity), normal, width, edge, blur * zoom_factor, final_position);
 }
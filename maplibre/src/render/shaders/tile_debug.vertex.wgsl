@@ -6,6 +6,60 @@ struct VertexOutput {
 var<private> EXTENT: f32 = 4096.0;
 var<private> DEBUG_COLOR: vec4<f32> = vec4<f32>(1.0, 0.0, 0.0, 1.0);
 
+// Labels are drawn with glyphs of a seven-segment display and an additional diagonal for '/'.
+// Each glyph cell is 1 wide and 2 high, y points down.
+var<private> SEGMENTS: array<vec4<f32>, 8> = array<vec4<f32>, 8>(
+    vec4<f32>(0.0, 0.0, 1.0, 0.0), // top
+    vec4<f32>(1.0, 0.0, 1.0, 1.0), // top right
+    vec4<f32>(1.0, 1.0, 1.0, 2.0), // bottom right
+    vec4<f32>(0.0, 2.0, 1.0, 2.0), // bottom
+    vec4<f32>(0.0, 1.0, 0.0, 2.0), // bottom left
+    vec4<f32>(0.0, 0.0, 0.0, 1.0), // top left
+    vec4<f32>(0.0, 1.0, 1.0, 1.0), // middle
+    vec4<f32>(0.0, 2.0, 1.0, 0.0), // diagonal
+);
+// Enabled segments of the glyphs 0-9 and '/', must match `render_commands::glyph`
+var<private> GLYPHS: array<u32, 11> = array<u32, 11>(63u, 6u, 91u, 79u, 102u, 109u, 125u, 7u, 127u, 111u, 128u);
+var<private> OUTLINE_VERTICES: u32 = 24u;
+var<private> GLYPH_VERTICES: u32 = 48u; // 8 segments with 6 vertices each
+var<private> GLYPH_COUNT: u32 = 11u;
+
+// Returns the vertex of a label glyph. The index encodes the position of the glyph within the
+// label, the glyph itself and the vertex within the glyph, see `render_commands::label_vertices`.
+fn label_vertex(idx: u32, width: f32) -> vec3<f32> {
+    let slot = idx / GLYPH_VERTICES;
+    let position = slot / GLYPH_COUNT;
+    let glyph = GLYPHS[slot % GLYPH_COUNT];
+    let segment_idx = (idx % GLYPH_VERTICES) / 6u;
+    let corner = idx % 6u;
+
+    let size = 6.0 * width;
+    let origin = vec2<f32>(4.0 * width + f32(position) * 8.0 * width, 4.0 * width);
+    let segment = SEGMENTS[segment_idx];
+    let a = origin + segment.xy * size;
+
+    // Disabled segments collapse into a degenerate triangle
+    if ((glyph >> segment_idx) & 1u) == 0u {
+        return vec3<f32>(a, 0.0);
+    }
+
+    let b = origin + segment.zw * size;
+    let direction = normalize(b - a) * width / 2.0;
+    let normal = vec2<f32>(-direction.y, direction.x);
+    let start = a - direction;
+    let end = b + direction;
+
+    var CORNERS: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+        start - normal,
+        start + normal,
+        end + normal,
+        start - normal,
+        end + normal,
+        end - normal,
+    );
+    return vec3<f32>(CORNERS[corner], 0.0);
+}
+
 @vertex
 fn main(
     @location(4) translate1: vec4<f32>,
@@ -62,7 +116,12 @@ fn main(
         vec3<f32>(EXTENT, EXTENT - WIDTH, z)
     );
 
-    let vertex = VERTICES[vertex_idx];
+    var vertex: vec3<f32>;
+    if vertex_idx < OUTLINE_VERTICES {
+        vertex = VERTICES[vertex_idx];
+    } else {
+        vertex = label_vertex(vertex_idx - OUTLINE_VERTICES, WIDTH);
+    }
 
     let scaling: mat3x3<f32> = mat3x3<f32>(
             vec3<f32>(target_width,   0.0,            0.0),
@@ -1,11 +1,14 @@
 //! Utility for generating a tile pattern which can be used for masking.
 
+mod opacity;
 mod pattern;
 
 use std::{marker::PhantomData, mem::size_of, ops::Range};
 
 use cgmath::Matrix4;
+pub use opacity::TileOpacity;
 pub use pattern::{TileViewPattern, DEFAULT_TILE_VIEW_PATTERN_SIZE};
+use thiserror::Error;
 
 use crate::{
     coords::{WorldTileCoords, Zoom},
@@ -71,6 +74,12 @@ impl ViewTile {
     }
 }
 
+/// The metadata of a [`TileShape`] was looked up before the [`TileViewPattern`] which contains the
+/// shape was uploaded, see [`TileViewPattern::shape_slice`].
+#[derive(Error, Debug, Clone, Copy)]
+#[error("the tile view pattern of {0} needs to be uploaded first")]
+pub struct NotUploadedError(pub WorldTileCoords);
+
 /// Defines the exact location where a specific tile on the map is rendered. It defines the shape
 /// of the tile with its location for the current zoom factor.
 #[derive(Debug, Clone)]
@@ -80,16 +89,19 @@ pub struct TileShape {
     // TODO: optimization, `zoom_factor` and `transform` are no longer required if `buffer_range` is Some()
     zoom_factor: f64,
     transform: Matrix4<f64>,
+    /// Multiplies the alpha of all features of the tile, see [`TileOpacity`].
+    opacity: f32,
 
     buffer_range: Option<Range<wgpu::BufferAddress>>,
 }
 
 impl TileShape {
-    fn new(coords: WorldTileCoords, zoom: Zoom) -> Self {
+    fn new(coords: WorldTileCoords, zoom: Zoom, opacity: f32) -> Self {
         Self {
             coords,
             zoom_factor: zoom.scale_to_tile(&coords),
             transform: coords.transform_for_zoom(zoom),
+            opacity,
             buffer_range: None,
         }
     }
@@ -106,6 +118,10 @@ impl TileShape {
     pub fn coords(&self) -> WorldTileCoords {
         self.coords
     }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
 }
 
 pub trait HasTile {
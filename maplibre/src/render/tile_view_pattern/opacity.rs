@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::tcs::tiles::TileComponent;
+
+/// The opacity with which all features of a tile are rendered. It multiplies the alpha of the
+/// colors of the features, e.g. to fade in a tile once its data is available.
+///
+/// Tiles without this component are rendered fully opaque.
+#[derive(Debug, Clone, Copy)]
+pub struct TileOpacity {
+    opacity: f32,
+    /// When the fade started and how long it takes to reach `opacity`.
+    fade: Option<(Instant, Duration)>,
+}
+
+impl TileOpacity {
+    /// A constant opacity, which is clamped to `0..=1`.
+    pub fn new(opacity: f32) -> Self {
+        Self {
+            opacity: opacity.clamp(0.0, 1.0),
+            fade: None,
+        }
+    }
+
+    /// Fades the tile in linearly from transparent to opaque over `duration`, starting now.
+    pub fn fade_in(duration: Duration) -> Self {
+        Self::fade_in_at(Instant::now(), duration)
+    }
+
+    /// Fades the tile in linearly from transparent to opaque over `duration`, starting at `start`.
+    pub fn fade_in_at(start: Instant, duration: Duration) -> Self {
+        Self {
+            opacity: 1.0,
+            fade: Some((start, duration)),
+        }
+    }
+
+    /// The opacity at `now`.
+    pub fn opacity_at(&self, now: Instant) -> f32 {
+        let Some((start, duration)) = self.fade else {
+            return self.opacity;
+        };
+
+        if duration.is_zero() {
+            return self.opacity;
+        }
+
+        let elapsed = now.saturating_duration_since(start);
+        let progress = (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0);
+        self.opacity * progress
+    }
+
+    /// Whether the opacity still changes after `now`, i.e. further frames need to be rendered.
+    pub fn is_fading_at(&self, now: Instant) -> bool {
+        self.fade
+            .is_some_and(|(start, duration)| now.saturating_duration_since(start) < duration)
+    }
+}
+
+impl Default for TileOpacity {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl TileComponent for TileOpacity {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use instant::Instant;
+
+    use super::TileOpacity;
+
+    #[test]
+    fn test_constant_opacity() {
+        let now = Instant::now();
+        assert_eq!(TileOpacity::new(0.3).opacity_at(now), 0.3);
+        assert_eq!(TileOpacity::new(2.0).opacity_at(now), 1.0);
+        assert_eq!(TileOpacity::default().opacity_at(now), 1.0);
+        assert!(!TileOpacity::new(0.3).is_fading_at(now));
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let start = Instant::now();
+        let opacity = TileOpacity::fade_in_at(start, Duration::from_millis(200));
+
+        assert_eq!(opacity.opacity_at(start), 0.0);
+        assert!((opacity.opacity_at(start + Duration::from_millis(60)) - 0.3).abs() < 1e-6);
+        assert!(opacity.is_fading_at(start + Duration::from_millis(60)));
+        assert_eq!(opacity.opacity_at(start + Duration::from_millis(400)), 1.0);
+        assert!(!opacity.is_fading_at(start + Duration::from_millis(400)));
+    }
+}
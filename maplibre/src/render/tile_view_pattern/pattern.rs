@@ -1,12 +1,16 @@
 use std::{collections::HashSet, marker::PhantomData};
 
+use instant::Instant;
+
 use crate::{
     coords::{ViewRegion, Zoom},
     render::{
         camera::ViewProjection,
         resource::{BackingBufferDescriptor, Queue},
         shaders::ShaderTileMetadata,
-        tile_view_pattern::{HasTile, SourceShapes, TileShape, ViewTile},
+        tile_view_pattern::{
+            HasTile, NotUploadedError, SourceShapes, TileOpacity, TileShape, ViewTile,
+        },
     },
     tcs::world::World,
 };
@@ -62,6 +66,14 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
     ) -> Vec<ViewTile> {
         let mut view_tiles = Vec::with_capacity(self.view_tiles.len());
         let mut source_tiles = HashSet::new(); // TODO: Optimization potential: Replace wit a bitmap, that allows false-negative matches
+        let now = Instant::now();
+        let shape = |coords| {
+            let opacity = world
+                .tiles
+                .query::<&TileOpacity>(coords)
+                .map_or(1.0, |opacity| opacity.opacity_at(now));
+            TileShape::new(coords, zoom, opacity)
+        };
 
         for coords in view_region.iter() {
             if coords.build_quad_key().is_none() {
@@ -70,7 +82,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
 
             let source_shapes = {
                 if container.has_tile(coords, world) {
-                    SourceShapes::SourceEqTarget(TileShape::new(coords, zoom))
+                    SourceShapes::SourceEqTarget(shape(coords))
                 } else if let Some(parent_coords) = container.get_available_parent(coords, world) {
                     log::debug!("Could not find data at {coords}. Falling back to {parent_coords}");
 
@@ -84,7 +96,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
 
                     source_tiles.insert(parent_coords);
 
-                    SourceShapes::Parent(TileShape::new(parent_coords, zoom))
+                    SourceShapes::Parent(shape(parent_coords))
                 } else if let Some(children_coords) =
                     container.get_available_children(coords, world, CHILDREN_SEARCH_DEPTH)
                 {
@@ -95,7 +107,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
                     SourceShapes::Children(
                         children_coords
                             .iter()
-                            .map(|child_coord| TileShape::new(*child_coord, zoom))
+                            .map(|child_coord| shape(*child_coord))
                             .collect(),
                     )
                 } else {
@@ -140,6 +152,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
                     .downcast()
                     .into(), // TODO: move this calculation to update() fn above
                 zoom_factor: shape.zoom_factor as f32,
+                opacity: shape.opacity,
             });
         };
 
@@ -167,3 +180,96 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
         queue.write_buffer(&self.view_tiles_buffer.inner, 0, raw_buffer);
     }
 }
+
+impl<Q> TileViewPattern<Q, wgpu::Buffer> {
+    /// The uploaded metadata of `shape`, see [`TileViewPattern::upload_pattern`]. Fails if the
+    /// pattern was not uploaded since `shape` was generated.
+    pub fn shape_slice(
+        &self,
+        shape: &TileShape,
+    ) -> Result<wgpu::BufferSlice<'_>, NotUploadedError> {
+        let range = shape
+            .buffer_range()
+            .ok_or(NotUploadedError(shape.coords()))?;
+        Ok(self.view_tiles_buffer.inner.slice(range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use cgmath::{Matrix4, SquareMatrix};
+
+    use super::TileViewPattern;
+    use crate::{
+        coords::{ViewRegion, WorldTileCoords, Zoom, ZoomLevel},
+        render::{
+            camera::ViewProjection,
+            resource::{BackingBufferDescriptor, Queue},
+            shaders::ShaderTileMetadata,
+            tile_view_pattern::{HasTile, TileOpacity},
+        },
+        tcs::world::World,
+        util::math::Aabb2,
+    };
+
+    struct TestBuffer;
+
+    /// Keeps the data of the last write.
+    #[derive(Default)]
+    struct RecordingQueue {
+        data: RefCell<Vec<u8>>,
+    }
+
+    impl Queue<TestBuffer> for RecordingQueue {
+        fn write_buffer(&self, _buffer: &TestBuffer, _offset: wgpu::BufferAddress, data: &[u8]) {
+            *self.data.borrow_mut() = data.to_vec();
+        }
+    }
+
+    struct AllTiles;
+
+    impl HasTile for AllTiles {
+        fn has_tile(&self, _coords: WorldTileCoords, _world: &World) -> bool {
+            true
+        }
+    }
+
+    /// Uploads the pattern of the tile `(0, 0, 0)` and returns the opacity of its metadata.
+    fn uploaded_opacity(world: &World) -> f32 {
+        let zoom = Zoom::new(0.0);
+        let view_region = ViewRegion::new(
+            Aabb2::new(cgmath::Point2::new(0.0, 0.0), cgmath::Point2::new(1.0, 1.0)),
+            0,
+            1,
+            zoom,
+            ZoomLevel::default(),
+        );
+        let mut pattern = TileViewPattern::<RecordingQueue, TestBuffer>::new(
+            BackingBufferDescriptor::new(TestBuffer, 1024),
+        );
+        let view_tiles = pattern.generate_pattern(&view_region, &AllTiles, zoom, world);
+        assert_eq!(view_tiles.len(), 1);
+        pattern.update_pattern(view_tiles);
+
+        let queue = RecordingQueue::default();
+        pattern.upload_pattern(&queue, &ViewProjection(Matrix4::identity()));
+        let data = queue.data.borrow();
+        let metadata: &[ShaderTileMetadata] = bytemuck::cast_slice(&data);
+        metadata[0].opacity
+    }
+
+    #[test]
+    fn test_tile_opacity() {
+        let mut world = World::default();
+        assert_eq!(uploaded_opacity(&world), 1.0);
+
+        world
+            .tiles
+            .spawn_mut((0, 0, ZoomLevel::default()).into())
+            .unwrap()
+            .insert(TileOpacity::new(0.3));
+        assert_eq!(uploaded_opacity(&world), 0.3);
+    }
+}
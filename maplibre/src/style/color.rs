@@ -0,0 +1,425 @@
+//! Colors which can be interpolated over the zoom level in different color spaces.
+
+use cint::{Alpha, EncodedSrgb};
+use csscolorparser::{Color, ParseColorError};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::coords::Zoom;
+use crate::style::util::interpolate_stops;
+
+/// The color space in which colors are interpolated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    #[default]
+    #[serde(rename = "rgb")]
+    Rgb,
+    /// Cylindrical representation of CIELAB, which interpolates along the hue.
+    #[serde(rename = "hcl")]
+    Hcl,
+    /// Perceptually uniform CIELAB color space.
+    #[serde(rename = "lab")]
+    Lab,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum InterpolatedColor {
+    Fixed(FixedColor),
+    Interpolated {
+        #[serde(default = "default_base")]
+        base: f32,
+        stops: Vec<(f64, Color)>,
+        #[serde(rename = "colorSpace")]
+        #[serde(default)]
+        color_space: ColorSpace,
+    },
+}
+
+fn default_base() -> f32 {
+    1.0
+}
+
+impl From<Color> for InterpolatedColor {
+    fn from(color: Color) -> Self {
+        InterpolatedColor::Fixed(color.into())
+    }
+}
+
+/// A color which does not change with the zoom level. It is converted for rendering once when it
+/// is parsed, instead of each time it is drawn.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "Color", into = "Color")]
+pub struct FixedColor {
+    color: Color,
+    srgb: Alpha<EncodedSrgb<f32>>,
+}
+
+impl FixedColor {
+    pub fn color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn srgb(&self) -> Alpha<EncodedSrgb<f32>> {
+        self.srgb
+    }
+}
+
+impl From<Color> for FixedColor {
+    fn from(color: Color) -> Self {
+        let srgb = color.clone().into();
+        Self { color, srgb }
+    }
+}
+
+impl From<FixedColor> for Color {
+    fn from(fixed: FixedColor) -> Self {
+        fixed.color
+    }
+}
+
+/// Deserializes an optional color of the paint property `property`. In contrast to the derived
+/// implementation, errors name the property and the color string which could not be parsed.
+pub(crate) fn deserialize_color<'de, D>(
+    deserializer: D,
+    property: &str,
+) -> Result<Option<InterpolatedColor>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    if let Some((color, e)) = find_invalid_color(&value) {
+        return Err(de::Error::custom(format!(
+            "invalid color \"{color}\" for {property}: {e}"
+        )));
+    }
+
+    match &value {
+        serde_json::Value::Null => Ok(None),
+        _ => InterpolatedColor::deserialize(value)
+            .map(Some)
+            .map_err(|e| de::Error::custom(format!("invalid {property}: {e}"))),
+    }
+}
+
+/// Returns the first color string which can not be parsed, either of a fixed color or of the
+/// stops of a function.
+pub(crate) fn find_invalid_color(value: &serde_json::Value) -> Option<(&str, ParseColorError)> {
+    fn parse(color: &serde_json::Value) -> Option<(&str, ParseColorError)> {
+        let color = color.as_str()?;
+        color.parse::<Color>().err().map(|e| (color, e))
+    }
+
+    match value {
+        serde_json::Value::String(_) => parse(value),
+        _ => value
+            .get("stops")
+            .and_then(|stops| stops.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|stop| stop.get(1))
+            .find_map(parse),
+    }
+}
+
+impl InterpolatedColor {
+    pub fn evaluate(&self, zoom: impl Into<Zoom>) -> Option<Color> {
+        match self {
+            InterpolatedColor::Fixed(fixed) => Some(fixed.color.clone()),
+            InterpolatedColor::Interpolated {
+                base,
+                stops,
+                color_space,
+            } => interpolate_stops(stops, *base, zoom, |a, b, t| {
+                color_space.interpolate(a, b, t as f64)
+            }),
+        }
+    }
+
+    /// Evaluates the color at `zoom` for rendering. Fixed colors reuse their conversion from
+    /// when they were parsed.
+    pub fn evaluate_srgb(&self, zoom: impl Into<Zoom>) -> Option<Alpha<EncodedSrgb<f32>>> {
+        match self {
+            InterpolatedColor::Fixed(fixed) => Some(fixed.srgb),
+            InterpolatedColor::Interpolated { .. } => self.evaluate(zoom).map(Into::into),
+        }
+    }
+}
+
+impl ColorSpace {
+    /// Blends `from` and `to` by factor `t` in this color space. The alpha channel is always
+    /// interpolated linearly.
+    pub fn interpolate(&self, from: &Color, to: &Color, t: f64) -> Color {
+        let alpha = lerp(from.a, to.a, t);
+
+        match self {
+            ColorSpace::Rgb => Color::new(
+                lerp(from.r, to.r, t),
+                lerp(from.g, to.g, t),
+                lerp(from.b, to.b, t),
+                alpha,
+            ),
+            ColorSpace::Lab => {
+                let [l1, a1, b1] = rgb_to_lab(from);
+                let [l2, a2, b2] = rgb_to_lab(to);
+                lab_to_rgb([lerp(l1, l2, t), lerp(a1, a2, t), lerp(b1, b2, t)], alpha)
+            }
+            ColorSpace::Hcl => {
+                let [h1, c1, l1] = lab_to_hcl(rgb_to_lab(from));
+                let [h2, c2, l2] = lab_to_hcl(rgb_to_lab(to));
+
+                // Achromatic colors have no meaningful hue, so the hue of the other color is used
+                let (h1, h2) = match (h1.is_nan(), h2.is_nan()) {
+                    (true, true) => (0.0, 0.0),
+                    (true, false) => (h2, h2),
+                    (false, true) => (h1, h1),
+                    (false, false) => (h1, h2),
+                };
+
+                // Interpolate along the shorter arc of the hue circle
+                let mut delta = h2 - h1;
+                if delta > 180.0 {
+                    delta -= 360.0;
+                } else if delta < -180.0 {
+                    delta += 360.0;
+                }
+
+                hcl_to_rgb([h1 + delta * t, lerp(c1, c2, t), lerp(l1, l2, t)], alpha)
+            }
+        }
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+// D65 reference white
+const XN: f64 = 0.950470;
+const YN: f64 = 1.0;
+const ZN: f64 = 1.088830;
+
+const DELTA: f64 = 6.0 / 29.0;
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    c.clamp(0.0, 1.0)
+}
+
+fn xyz_to_lab_f(t: f64) -> f64 {
+    if t > DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_to_xyz_f(t: f64) -> f64 {
+    if t > DELTA {
+        t.powi(3)
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+fn rgb_to_lab(color: &Color) -> [f64; 3] {
+    let r = srgb_to_linear(color.r);
+    let g = srgb_to_linear(color.g);
+    let b = srgb_to_linear(color.b);
+
+    let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+    let fx = xyz_to_lab_f(x / XN);
+    let fy = xyz_to_lab_f(y / YN);
+    let fz = xyz_to_lab_f(z / ZN);
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_to_rgb([l, a, b]: [f64; 3], alpha: f64) -> Color {
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+
+    let x = XN * lab_to_xyz_f(fx);
+    let y = YN * lab_to_xyz_f(fy);
+    let z = ZN * lab_to_xyz_f(fz);
+
+    let r = 3.2404542 * x - 1.5371385 * y - 0.4985314 * z;
+    let g = -0.9692660 * x + 1.8760108 * y + 0.0415560 * z;
+    let b = 0.0556434 * x - 0.2040259 * y + 1.0572252 * z;
+
+    Color::new(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), alpha)
+}
+
+/// Returns hue in degrees, chroma and luminance. The hue is NaN for achromatic colors.
+fn lab_to_hcl([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let c = (a * a + b * b).sqrt();
+    let h = if c < 1e-4 {
+        f64::NAN
+    } else {
+        (b.atan2(a).to_degrees() + 360.0) % 360.0
+    };
+    [h, c, l]
+}
+
+fn hcl_to_rgb([h, c, l]: [f64; 3], alpha: f64) -> Color {
+    let h = h.to_radians();
+    lab_to_rgb([l, h.cos() * c, h.sin() * c], alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use cint::{Alpha, EncodedSrgb};
+    use csscolorparser::Color;
+
+    use crate::{
+        coords::ZoomLevel,
+        style::{
+            color::{ColorSpace, InterpolatedColor},
+            layer::{FillPaint, LayerPaint, StyleLayer},
+        },
+    };
+
+    fn assert_color_eq(a: &Color, b: &Color) {
+        let epsilon = 1e-3;
+        assert!(
+            (a.r - b.r).abs() < epsilon
+                && (a.g - b.g).abs() < epsilon
+                && (a.b - b.b).abs() < epsilon
+                && (a.a - b.a).abs() < epsilon,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_interpolation_endpoints() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let green = Color::new(0.0, 1.0, 0.0, 0.5);
+
+        for space in [ColorSpace::Rgb, ColorSpace::Lab, ColorSpace::Hcl] {
+            assert_color_eq(&space.interpolate(&red, &green, 0.0), &red);
+            assert_color_eq(&space.interpolate(&red, &green, 1.0), &green);
+        }
+    }
+
+    #[test]
+    fn test_midpoint_differs_between_color_spaces() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let green = Color::new(0.0, 1.0, 0.0, 1.0);
+
+        let rgb = ColorSpace::Rgb.interpolate(&red, &green, 0.5);
+        let hcl = ColorSpace::Hcl.interpolate(&red, &green, 0.5);
+        let lab = ColorSpace::Lab.interpolate(&red, &green, 0.5);
+
+        assert_color_eq(&rgb, &Color::new(0.5, 0.5, 0.0, 1.0));
+
+        // HCL passes through orange/yellow hues and is brighter than the muddy RGB midpoint
+        assert!(hcl.r > rgb.r && hcl.g > rgb.g);
+        assert!((hcl.r - rgb.r).abs() > 0.1);
+        assert!((lab.r - rgb.r).abs() > 0.1);
+        assert!((lab.r - hcl.r).abs() > 0.01 || (lab.g - hcl.g).abs() > 0.01);
+    }
+
+    #[test]
+    fn test_deserialize_color_stops() {
+        let color: InterpolatedColor = serde_json::from_str(
+            r##"{"stops": [[0, "#ff0000"], [10, "#00ff00"]], "colorSpace": "hcl"}"##,
+        )
+        .unwrap();
+
+        let InterpolatedColor::Interpolated { color_space, .. } = &color else {
+            panic!("expected interpolated color")
+        };
+        assert_eq!(*color_space, ColorSpace::Hcl);
+
+        assert_color_eq(
+            &color.evaluate(ZoomLevel::new(0)).unwrap(),
+            &Color::new(1.0, 0.0, 0.0, 1.0),
+        );
+        assert_color_eq(
+            &color.evaluate(ZoomLevel::new(5)).unwrap(),
+            &ColorSpace::Hcl.interpolate(
+                &Color::new(1.0, 0.0, 0.0, 1.0),
+                &Color::new(0.0, 1.0, 0.0, 1.0),
+                0.5,
+            ),
+        );
+
+        let fixed: InterpolatedColor = serde_json::from_str(r#""red""#).unwrap();
+        assert!(matches!(fixed, InterpolatedColor::Fixed(_)));
+    }
+
+    #[test]
+    fn test_cached_srgb() {
+        for json in [r##""#336699cc""##, r##"{"stops": [[0, "#ff0000"], [10, "#00ff00"]]}"##] {
+            let color: InterpolatedColor = serde_json::from_str(json).unwrap();
+            for zoom in [0, 5, 10] {
+                let expected: Alpha<EncodedSrgb<f32>> =
+                    color.evaluate(ZoomLevel::new(zoom)).unwrap().into();
+                assert_eq!(color.evaluate_srgb(ZoomLevel::new(zoom)), Some(expected));
+            }
+        }
+
+        // Fixed colors are written back as they were read
+        let color: InterpolatedColor = serde_json::from_str(r#""red""#).unwrap();
+        assert_eq!(serde_json::to_string(&color).unwrap(), r##""#ff0000""##);
+    }
+
+    #[test]
+    fn test_invalid_color_names_property_and_value() {
+        let error = serde_json::from_str::<FillPaint>(r#"{"fill-color": "notacolor"}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("\"notacolor\""), "{error}");
+        assert!(error.contains("fill-color"), "{error}");
+
+        let error = serde_json::from_str::<LayerPaint>(
+            r##"{"type": "line", "paint": {"line-color": {"stops": [[0, "#fff"], [10, "nocolor"]]}}}"##,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("\"nocolor\""), "{error}");
+        assert!(error.contains("line-color"), "{error}");
+    }
+
+    #[test]
+    fn test_valid_and_missing_colors() {
+        let paint: FillPaint = serde_json::from_str(r#"{"fill-color": "red"}"#).unwrap();
+        assert!(matches!(paint.fill_color, Some(InterpolatedColor::Fixed(_))));
+
+        let paint: FillPaint = serde_json::from_str(r#"{"fill-opacity": 0.5}"#).unwrap();
+        assert!(paint.fill_color.is_none());
+    }
+
+    #[test]
+    fn test_invalid_color_fails_layer() {
+        let error = serde_json::from_str::<StyleLayer>(
+            r#"{"id": "park", "type": "fill", "paint": {"fill-color": "notacolor"}}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("\"notacolor\""), "{error}");
+
+        let layer: StyleLayer = serde_json::from_str(
+            r#"{"id": "park", "type": "fill", "paint": {"fill-color": "green"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(layer.paint, Some(LayerPaint::Fill(_))));
+        assert!(layer.unsupported.is_empty());
+    }
+}
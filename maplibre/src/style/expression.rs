@@ -1,8 +1,17 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use geozero::ColumnValue;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde::de::{SeqAccess, Visitor};
+use thiserror::Error;
+
+use crate::coords::LatLon;
+
+/// The default tolerance within which numbers are considered equal by `==` and `!=`, see
+/// [`ExpressionComparisonOp::compare_with_tolerance`]. Property values often differ from the
+/// literals of a style by rounding, e.g. `3.0000001` from a float column compared to `3`.
+pub const DEFAULT_FLOAT_TOLERANCE: f64 = 1e-6;
 
 #[derive(Serialize, Debug, Clone)]
 pub enum ExpressionComparisonOp {
@@ -15,15 +24,28 @@ pub enum ExpressionComparisonOp {
 }
 
 impl ExpressionComparisonOp {
-    fn compare(&self, a: ComparisonLiteral, b: ComparisonLiteral) -> bool {
+    /// Compares `a` to `b`, considering numbers equal within [`DEFAULT_FLOAT_TOLERANCE`].
+    pub fn compare(&self, a: &ComparisonLiteral, b: &ComparisonLiteral) -> bool {
+        self.compare_with_tolerance(a, b, DEFAULT_FLOAT_TOLERANCE)
+    }
+
+    /// Compares `a` to `b`. Numbers are equal if they differ by at most `tolerance` relative to
+    /// the larger magnitude, or absolutely for magnitudes below 1. A `tolerance` of zero compares
+    /// exactly.
+    pub fn compare_with_tolerance(
+        &self,
+        a: &ComparisonLiteral,
+        b: &ComparisonLiteral,
+        tolerance: f64,
+    ) -> bool {
         match self {
-            ExpressionComparisonOp::Eq => a == b,
-            ExpressionComparisonOp::Neq => a != b,
+            ExpressionComparisonOp::Eq => Self::equals(a, b, tolerance),
+            ExpressionComparisonOp::Neq => !Self::equals(a, b, tolerance),
             ExpressionComparisonOp::Gt => {
                 match (a, b) {
                     (ComparisonLiteral::Integer(a), ComparisonLiteral::Integer(b)) => a > b,
-                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (a as f64) > b,
-                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => a > (b as f64),
+                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (*a as f64) > *b,
+                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => *a > (*b as f64),
                     (ComparisonLiteral::Float(a), ComparisonLiteral::Float(b)) => a > b,
                     (ComparisonLiteral::String(a), ComparisonLiteral::String(b)) => a > b,
                     _ => false,
@@ -32,8 +54,8 @@ impl ExpressionComparisonOp {
             ExpressionComparisonOp::Geq => {
                 match (a, b) {
                     (ComparisonLiteral::Integer(a), ComparisonLiteral::Integer(b)) => a >= b,
-                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (a as f64) >= b,
-                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => a >= (b as f64),
+                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (*a as f64) >= *b,
+                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => *a >= (*b as f64),
                     (ComparisonLiteral::Float(a), ComparisonLiteral::Float(b)) => a >= b,
                     (ComparisonLiteral::String(a), ComparisonLiteral::String(b)) => a >= b,
                     _ => false,
@@ -42,8 +64,8 @@ impl ExpressionComparisonOp {
             ExpressionComparisonOp::Lt => {
                 match (a, b) {
                     (ComparisonLiteral::Integer(a), ComparisonLiteral::Integer(b)) => a < b,
-                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (a as f64) < b,
-                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => a < (b as f64),
+                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (*a as f64) < *b,
+                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => *a < (*b as f64),
                     (ComparisonLiteral::Float(a), ComparisonLiteral::Float(b)) => a < b,
                     (ComparisonLiteral::String(a), ComparisonLiteral::String(b)) => a < b,
                     _ => false,
@@ -52,8 +74,8 @@ impl ExpressionComparisonOp {
             ExpressionComparisonOp::Leq => {
                 match (a, b) {
                     (ComparisonLiteral::Integer(a), ComparisonLiteral::Integer(b)) => a <= b,
-                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (a as f64) <= b,
-                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => a <= (b as f64),
+                    (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => (*a as f64) <= *b,
+                    (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => *a <= (*b as f64),
                     (ComparisonLiteral::Float(a), ComparisonLiteral::Float(b)) => a <= b,
                     (ComparisonLiteral::String(a), ComparisonLiteral::String(b)) => a <= b,
                     _ => false,
@@ -63,6 +85,193 @@ impl ExpressionComparisonOp {
     }
 }
 
+impl ExpressionComparisonOp {
+    /// The keyword of the operator in filters, e.g. `==`.
+    fn keyword(&self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Neq => "!=",
+            Self::Gt => ">",
+            Self::Geq => ">=",
+            Self::Lt => "<",
+            Self::Leq => "<=",
+        }
+    }
+
+    fn compare_with_mode(
+        &self,
+        a: &ComparisonLiteral,
+        b: &ComparisonLiteral,
+        mode: ComparisonMode,
+        tolerance: f64,
+    ) -> bool {
+        if mode != ComparisonMode::Strict {
+            match (a, b) {
+                (ComparisonLiteral::String(a), ComparisonLiteral::String(b)) if mode == ComparisonMode::Numeric => {
+                    if let (Some(a), Some(b)) = (Self::parse_number(a), Self::parse_number(b)) {
+                        return self.compare_with_tolerance(&a, &b, tolerance);
+                    }
+                }
+                (ComparisonLiteral::String(a), ComparisonLiteral::Integer(_) | ComparisonLiteral::Float(_)) => {
+                    if let Some(a) = Self::parse_number(a) {
+                        return self.compare_with_tolerance(&a, b, tolerance);
+                    }
+                }
+                (ComparisonLiteral::Integer(_) | ComparisonLiteral::Float(_), ComparisonLiteral::String(b)) => {
+                    if let Some(b) = Self::parse_number(b) {
+                        return self.compare_with_tolerance(a, &b, tolerance);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.compare_with_tolerance(a, b, tolerance)
+    }
+
+    fn parse_number(value: &str) -> Option<ComparisonLiteral> {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .map(ComparisonLiteral::Float)
+    }
+
+    /// Numeric literals are equal if their values are equal within `tolerance`, independent of
+    /// whether they were decoded as integer or float. Integers are compared to booleans like flags,
+    /// where 0 is false and any other value true. [`ComparisonLiteral::Null`] only equals itself.
+    /// Arrays are equal if they have the same length and their elements are pairwise equal.
+    fn equals(a: &ComparisonLiteral, b: &ComparisonLiteral, tolerance: f64) -> bool {
+        match (a, b) {
+            (ComparisonLiteral::Array(a), ComparisonLiteral::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| Self::equals(a, b, tolerance))
+            }
+            (ComparisonLiteral::Integer(a), ComparisonLiteral::Float(b)) => Self::floats_equal(*a as f64, *b, tolerance),
+            (ComparisonLiteral::Float(a), ComparisonLiteral::Integer(b)) => Self::floats_equal(*a, *b as f64, tolerance),
+            (ComparisonLiteral::Float(a), ComparisonLiteral::Float(b)) => Self::floats_equal(*a, *b, tolerance),
+            (ComparisonLiteral::Integer(a), ComparisonLiteral::Bool(b)) => (*a != 0) == *b,
+            (ComparisonLiteral::Bool(a), ComparisonLiteral::Integer(b)) => *a == (*b != 0),
+            _ => a == b,
+        }
+    }
+
+    fn floats_equal(a: f64, b: f64, tolerance: f64) -> bool {
+        a == b || (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+    }
+}
+
+/// How strings are compared by a [`LegacyFilterExpression`], like the `collator` expression of
+/// the style spec.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collator {
+    #[serde(rename = "case-sensitive", default = "Collator::default_case_sensitive")]
+    pub case_sensitive: bool,
+}
+
+impl Collator {
+    pub fn case_insensitive() -> Self {
+        Self {
+            case_sensitive: false,
+        }
+    }
+
+    fn default_case_sensitive() -> bool {
+        true
+    }
+
+    /// Prepares a string literal, or the strings within an array, for comparison.
+    fn fold_literal<'a>(&self, literal: &'a ComparisonLiteral) -> Cow<'a, ComparisonLiteral> {
+        match literal {
+            ComparisonLiteral::String(value) if !self.case_sensitive => {
+                Cow::Owned(ComparisonLiteral::String(value.to_lowercase()))
+            }
+            ComparisonLiteral::Array(elements) if !self.case_sensitive => Cow::Owned(
+                ComparisonLiteral::Array(
+                    elements
+                        .iter()
+                        .map(|element| self.fold_literal(element).into_owned())
+                        .collect(),
+                ),
+            ),
+            _ => Cow::Borrowed(literal),
+        }
+    }
+
+    /// Whether `value` equals one of the `predicates`. Numbers are equal regardless of whether
+    /// they are integers or floats.
+    fn contains(&self, predicates: &[ComparisonLiteral], value: &ComparisonLiteral) -> bool {
+        let value = self.fold_literal(value);
+        predicates.iter().any(|predicate| {
+            ExpressionComparisonOp::Eq.compare(&self.fold_literal(predicate), &value)
+        })
+    }
+}
+
+impl Default for Collator {
+    fn default() -> Self {
+        Self {
+            case_sensitive: true,
+        }
+    }
+}
+
+/// A `["collator", {...}]` expression, which may follow the operands of comparison and membership
+/// filters.
+#[derive(Deserialize)]
+struct CollatorExpression(String, Collator);
+
+impl CollatorExpression {
+    fn into_collator<E: de::Error>(self) -> Result<Collator, E> {
+        if self.0 == "collator" {
+            Ok(self.1)
+        } else {
+            Err(E::custom(format!("expected collator but found {}", self.0)))
+        }
+    }
+}
+
+/// How literals of different types are compared by a [`LegacyFilterExpression`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComparisonMode {
+    /// Literals of different types never match, as required by the style spec.
+    #[default]
+    Strict,
+    /// Strings which contain a number are coerced to a number when they are compared to a number.
+    /// Many styles in the wild rely on this, e.g. `["==", "level", "2"]` for integer levels.
+    Lenient,
+    /// Like [`ComparisonMode::Lenient`], but two strings which both contain a number are compared
+    /// as numbers too, so that `"10" > "9"`. Other strings are still compared lexically.
+    Numeric,
+}
+
+/// Options which control how a [`LegacyFilterExpression`] is evaluated, see
+/// [`LegacyFilterExpression::evaluate_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct EvaluationOptions<'a> {
+    /// How literals of different types are compared.
+    pub mode: ComparisonMode,
+    /// The vertices of the feature's geometry. A feature is `within` a polygon if all of its
+    /// vertices are inside. Without a geometry, `within` never matches.
+    pub geometry: Option<&'a [LatLon]>,
+    /// Numbers are equal within this tolerance, see
+    /// [`ExpressionComparisonOp::compare_with_tolerance`].
+    pub tolerance: f64,
+    /// How strings are compared. Nested filters which specify their own collator keep it.
+    pub collator: Collator,
+}
+
+impl Default for EvaluationOptions<'_> {
+    fn default() -> Self {
+        Self {
+            mode: ComparisonMode::default(),
+            geometry: None,
+            tolerance: DEFAULT_FLOAT_TOLERANCE,
+            collator: Collator::default(),
+        }
+    }
+}
+
 impl TryFrom<String> for ExpressionComparisonOp {
     type Error = ();
 
@@ -79,13 +288,60 @@ impl TryFrom<String> for ExpressionComparisonOp {
     }
 }
 
+/// Untagged variants are tried in order, so `Integer` precedes `Float` to deserialize whole
+/// numbers as integers. Only numbers with a fractional part or exponent become `Float`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum ComparisonLiteral {
-    Float(f64),
     Integer(isize),
+    Float(f64),
     Bool(bool),
     String(String),
+    /// The elements of a property which holds a JSON array, see [`ComparisonLiteral::from_json_column`],
+    /// or of an array literal of a comparison filter like `["==", "rgb", [255, 0, 0]]`. Arrays
+    /// only support `==` and `!=`, they are neither less nor greater than any value.
+    Array(Vec<ComparisonLiteral>),
+    /// A property which is present but explicitly `null`, as opposed to a property which is absent
+    /// from the feature. Null only equals null and is neither less nor greater than any value.
+    Null,
+}
+
+impl ComparisonLiteral {
+    /// Parses a JSON encoded property. Arrays of strings, numbers and booleans become
+    /// [`ComparisonLiteral::Array`], such that `in` filters match their elements, and `null`
+    /// becomes [`ComparisonLiteral::Null`]. Other values are compared as the raw JSON string.
+    fn from_json_column(json: &str) -> Self {
+        match serde_json::from_str::<serde_json::Value>(json) {
+            Ok(serde_json::Value::Null) => ComparisonLiteral::Null,
+            Ok(serde_json::Value::Array(elements))
+                if elements.iter().all(|element| {
+                    element.is_string() || element.is_number() || element.is_boolean()
+                }) =>
+            {
+                ComparisonLiteral::Array(
+                    elements
+                        .iter()
+                        .filter_map(|element| ComparisonLiteral::try_from(element).ok())
+                        .collect(),
+                )
+            }
+            _ => ComparisonLiteral::String(json.to_string()),
+        }
+    }
+
+    /// Whether this is one of the `predicates` or, for arrays, one of its elements is.
+    fn is_in(&self, predicates: &[ComparisonLiteral], collator: &Collator) -> bool {
+        match self {
+            ComparisonLiteral::Integer(_)
+            | ComparisonLiteral::Float(_)
+            | ComparisonLiteral::Bool(_)
+            | ComparisonLiteral::String(_) => collator.contains(predicates, self),
+            ComparisonLiteral::Array(elements) => elements
+                .iter()
+                .any(|element| collator.contains(predicates, element)),
+            ComparisonLiteral::Null => false,
+        }
+    }
 }
 
 impl From<&ColumnValue<'_>> for ComparisonLiteral {
@@ -102,13 +358,126 @@ impl From<&ColumnValue<'_>> for ComparisonLiteral {
             ColumnValue::ULong(v) => ComparisonLiteral::Integer(*v as isize),
             ColumnValue::Float(v) => ComparisonLiteral::Float(*v as f64),
             ColumnValue::Double(v) => ComparisonLiteral::Float(*v),
-            ColumnValue::String(v) | ColumnValue::Json(v) => ComparisonLiteral::String(v.to_string()),
+            ColumnValue::String(v) => ComparisonLiteral::String(v.to_string()),
+            ColumnValue::Json(v) => ComparisonLiteral::from_json_column(v),
             ColumnValue::DateTime(_) => unimplemented!("Date property comparisons are not supported"),
             ColumnValue::Binary(_) => unimplemented!("Binary property comparisons are not supported"),
         }
     }
 }
 
+/// A JSON value which has no [`ComparisonLiteral`] representation, i.e. an array or an object.
+#[derive(Error, Debug, PartialEq)]
+#[error("the JSON value {0} can not be compared")]
+pub struct UnsupportedJsonValue(pub serde_json::Value);
+
+impl TryFrom<&serde_json::Value> for ComparisonLiteral {
+    type Error = UnsupportedJsonValue;
+
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            serde_json::Value::Bool(v) => ComparisonLiteral::Bool(*v),
+            serde_json::Value::Number(v) => match v.as_i64() {
+                Some(v) => ComparisonLiteral::Integer(v as isize),
+                // Floats and unsigned integers which do not fit into an i64
+                None => ComparisonLiteral::Float(v.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(v) => ComparisonLiteral::String(v.clone()),
+            serde_json::Value::Null => ComparisonLiteral::Null,
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                return Err(UnsupportedJsonValue(value.clone()))
+            }
+        })
+    }
+}
+
+impl From<ComparisonLiteral> for serde_json::Value {
+    fn from(value: ComparisonLiteral) -> Self {
+        match value {
+            // Non-finite floats have no JSON representation and become null
+            ComparisonLiteral::Float(v) => serde_json::Number::from_f64(v)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            ComparisonLiteral::Integer(v) => serde_json::Value::from(v as i64),
+            ComparisonLiteral::Bool(v) => serde_json::Value::Bool(v),
+            ComparisonLiteral::String(v) => serde_json::Value::String(v),
+            ComparisonLiteral::Array(v) => {
+                serde_json::Value::Array(v.into_iter().map(serde_json::Value::from).collect())
+            }
+            ComparisonLiteral::Null => serde_json::Value::Null,
+        }
+    }
+}
+
+/// A ring of `[longitude, latitude]` positions, as in GeoJSON.
+pub type GeoJsonRing = Vec<[f64; 2]>;
+
+/// A GeoJSON polygon geometry which is used by the `within` filter. The first ring of each polygon
+/// is its exterior, all others are holes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum GeoJsonPolygon {
+    Polygon { coordinates: Vec<GeoJsonRing> },
+    MultiPolygon { coordinates: Vec<Vec<GeoJsonRing>> },
+}
+
+impl GeoJsonPolygon {
+    /// Whether `point` lies inside of one of the polygons and outside of their holes.
+    pub fn contains(&self, point: &LatLon) -> bool {
+        match self {
+            GeoJsonPolygon::Polygon { coordinates } => polygon_contains(coordinates, point),
+            GeoJsonPolygon::MultiPolygon { coordinates } => coordinates
+                .iter()
+                .any(|polygon| polygon_contains(polygon, point)),
+        }
+    }
+}
+
+/// Counts the crossings of a ray from `point` with all rings, which excludes holes as well.
+fn polygon_contains(rings: &[GeoJsonRing], point: &LatLon) -> bool {
+    let (x, y) = (point.longitude, point.latitude);
+    let mut inside = false;
+    for ring in rings {
+        for (i, [x1, y1]) in ring.iter().enumerate() {
+            let [x2, y2] = ring[(i + 1) % ring.len()];
+            if (*y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// The result of evaluating a filter and its sub-filters, see [`LegacyFilterExpression::trace`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FilterTrace {
+    /// The keyword of a combining filter, e.g. `all`, or the description of any other filter
+    pub filter: String,
+    pub matched: bool,
+    /// The traces of the children of a combining filter which were evaluated
+    pub children: Vec<FilterTrace>,
+}
+
+impl FilterTrace {
+    /// Returns the sub-filters which caused a feature to be rejected: the failing filters
+    /// within `all` and `any` and the matching children of `none`. Empty if the filter matched.
+    pub fn rejected_by(&self) -> Vec<&FilterTrace> {
+        if self.matched {
+            return vec![];
+        }
+        if self.children.is_empty() {
+            return vec![self];
+        }
+        if self.filter == "none" {
+            return self.children.iter().filter(|child| child.matched).collect();
+        }
+        self.children
+            .iter()
+            .flat_map(FilterTrace::rejected_by)
+            .collect()
+    }
+}
+
 // https://maplibre.org/maplibre-style-spec/deprecations/#other-filter
 // TODO(aidangoettsch): create custom serialization
 #[derive(Serialize, Debug, Clone)]
@@ -119,12 +488,17 @@ pub enum LegacyFilterExpression {
     // Comparison
     Comparison(ExpressionComparisonOp, String, ComparisonLiteral),
     // Membership
-    In(String, Vec<String>),
-    NotIn(String, Vec<String>),
+    In(String, Vec<ComparisonLiteral>),
+    NotIn(String, Vec<ComparisonLiteral>),
     // Combining
     All(Vec<LegacyFilterExpression>),
     Any(Vec<LegacyFilterExpression>),
     None(Vec<LegacyFilterExpression>),
+    // Geometry
+    Within(GeoJsonPolygon),
+    /// Compares strings within the child filter according to the collator, unless a nested
+    /// filter specifies its own.
+    Collate(Collator, Box<LegacyFilterExpression>),
 }
 
 impl<'de> Deserialize<'de> for LegacyFilterExpression {
@@ -171,34 +545,38 @@ impl<'de> Deserialize<'de> for LegacyFilterExpression {
                         let literal = seq.next_element::<ComparisonLiteral>()?.ok_or_else(||
                             de::Error::custom("!has filter was missing literal")
                         )?;
+                        let filter = LegacyFilterExpression::Comparison(op, property, literal);
 
-                        Ok(LegacyFilterExpression::Comparison(op, property, literal))
+                        match seq.next_element::<CollatorExpression>()? {
+                            Some(collator) => Ok(filter.with_collator(collator.into_collator()?)),
+                            None => Ok(filter),
+                        }
                     },
                     "in" => {
                         let property = seq.next_element::<String>()?.ok_or_else(||
                             de::Error::custom("comparison filter was missing property")
                         )?;
 
-                        let mut predicates = vec![];
+                        let (predicates, collator) = visit_predicates(&mut seq)?;
+                        let filter = LegacyFilterExpression::In(property, predicates);
 
-                        while let Some(predicate) = seq.next_element::<String>()? {
-                            predicates.push(predicate);
+                        match collator {
+                            Some(collator) => Ok(filter.with_collator(collator)),
+                            None => Ok(filter),
                         }
-
-                        Ok(LegacyFilterExpression::In(property, predicates))
                     },
                     "!in" => {
                         let property = seq.next_element::<String>()?.ok_or_else(||
                             de::Error::custom("comparison filter was missing property")
                         )?;
 
-                        let mut predicates = vec![];
+                        let (predicates, collator) = visit_predicates(&mut seq)?;
+                        let filter = LegacyFilterExpression::NotIn(property, predicates);
 
-                        while let Some(predicate) = seq.next_element::<String>()? {
-                            predicates.push(predicate);
+                        match collator {
+                            Some(collator) => Ok(filter.with_collator(collator)),
+                            None => Ok(filter),
                         }
-
-                        Ok(LegacyFilterExpression::NotIn(property, predicates))
                     },
                     "all" => {
                         let mut filters = vec![];
@@ -227,38 +605,1140 @@ impl<'de> Deserialize<'de> for LegacyFilterExpression {
 
                         Ok(LegacyFilterExpression::None(filters))
                     },
+                    "within" => {
+                        let polygon = seq.next_element::<GeoJsonPolygon>()?.ok_or_else(||
+                            de::Error::custom("within filter was missing polygon")
+                        )?;
+
+                        Ok(LegacyFilterExpression::Within(polygon))
+                    },
                     _ => Err(de::Error::custom(format!("Invalid filter keyword {kw}"))),
                 }
             }
         }
 
+        /// Reads the predicates of a membership filter, which may be followed by a collator.
+        /// Predicates can be strings, numbers, booleans and `null`.
+        fn visit_predicates<'de, S: SeqAccess<'de>>(
+            seq: &mut S,
+        ) -> Result<(Vec<ComparisonLiteral>, Option<Collator>), S::Error> {
+            let mut predicates = vec![];
+
+            while let Some(element) = seq.next_element::<serde_json::Value>()? {
+                match element {
+                    serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                        let collator = element;
+                        let collator = CollatorExpression::deserialize(collator)
+                            .map_err(de::Error::custom)?
+                            .into_collator()?;
+                        return Ok((predicates, Some(collator)));
+                    }
+                    predicate => predicates.push(
+                        ComparisonLiteral::try_from(&predicate).map_err(de::Error::custom)?,
+                    ),
+                }
+            }
+
+            Ok((predicates, None))
+        }
+
         deserializer.deserialize_seq(LegacyFilterVisitor)
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    /// The number of filters which [`LegacyFilterExpression::evaluate_with`] evaluated on this
+    /// thread, which the tests of short-circuiting read.
+    static EVALUATED_FILTERS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 impl LegacyFilterExpression {
+    /// Evaluates the filter against the `properties` of a feature.
+    ///
+    /// The combining filters follow the MapLibre style spec also for empty children: `["all"]` and
+    /// `["none"]` always match, while `["any"]` never matches.
+    ///
+    /// Children of combining filters are evaluated from left to right and evaluation stops as soon
+    /// as the result is known. Style authors can therefore list expensive filters last.
+    ///
+    /// An absent property fails every comparison, also `["==", key, null]`. A property which is
+    /// present with [`ComparisonLiteral::Null`] matches `has` and compares equal to `null` only.
     pub fn evaluate(&self, properties: &HashMap<String, ComparisonLiteral>) -> bool {
+        self.evaluate_with(properties, &EvaluationOptions::default())
+    }
+
+    /// Evaluates the filter like [`Self::evaluate`], but according to `options`, e.g. against
+    /// the geometry of the feature or with another [`ComparisonMode`].
+    pub fn evaluate_with(
+        &self,
+        properties: &HashMap<String, ComparisonLiteral>,
+        options: &EvaluationOptions,
+    ) -> bool {
+        #[cfg(test)]
+        EVALUATED_FILTERS.with(|evaluated| evaluated.set(evaluated.get() + 1));
+
+        let collator = &options.collator;
         match self {
             LegacyFilterExpression::Has(key) => properties.contains_key(key),
             LegacyFilterExpression::NotHas(key) => !properties.contains_key(key),
             LegacyFilterExpression::Comparison(op, key, value) => {
                 if let Some(v) = properties.get(key) {
-                    op.compare(v.clone(), value.clone())
+                    op.compare_with_mode(&collator.fold_literal(v), &collator.fold_literal(value), options.mode, options.tolerance)
                 } else {
                     false
                 }
             },
-            LegacyFilterExpression::In(key, predicates) => properties.get(key).is_some_and(|v| match v {
-                ComparisonLiteral::String(s) => predicates.contains(s),
-                _ => unimplemented!("In expression is not supported for non-string types"),
-            }),
-            LegacyFilterExpression::NotIn(key, predicates) => properties.get(key).is_some_and(|v| match v {
-                ComparisonLiteral::String(s) => !predicates.contains(s),
-                _ => unimplemented!("In expression is not supported for non-string types"),
+            LegacyFilterExpression::In(key, predicates) => properties.get(key).is_some_and(|v| v.is_in(predicates, collator)),
+            LegacyFilterExpression::NotIn(key, predicates) => properties.get(key).is_some_and(|v| !v.is_in(predicates, collator)),
+            LegacyFilterExpression::All(children) => children.iter().all(|c| c.evaluate_with(properties, options)),
+            LegacyFilterExpression::Any(children) => children.iter().any(|c| c.evaluate_with(properties, options)),
+            LegacyFilterExpression::None(children) => children.iter().all(|c| !c.evaluate_with(properties, options)),
+            LegacyFilterExpression::Within(polygon) => options.geometry.is_some_and(|geometry| {
+                !geometry.is_empty() && geometry.iter().all(|point| polygon.contains(point))
             }),
-            LegacyFilterExpression::All(children) => children.iter().all(|c| c.evaluate(properties)),
-            LegacyFilterExpression::Any(children) => children.iter().any(|c| c.evaluate(properties)),
-            LegacyFilterExpression::None(children) => children.iter().all(|c| !c.evaluate(properties)),
+            LegacyFilterExpression::Collate(collator, child) => child.evaluate_with(
+                properties,
+                &EvaluationOptions {
+                    collator: *collator,
+                    ..*options
+                },
+            ),
+        }
+    }
+
+    /// Evaluates the filter like [`Self::evaluate_with`] and records the result of each
+    /// evaluated sub-filter, e.g. to find out which part of a filter rejected a feature. Like
+    /// [`Self::evaluate`], combining filters stop at the first child which decides the result.
+    pub fn trace(
+        &self,
+        properties: &HashMap<String, ComparisonLiteral>,
+        options: &EvaluationOptions,
+    ) -> FilterTrace {
+        let trace_children = |children: &[LegacyFilterExpression], stop_at: bool| {
+            let mut traces = Vec::new();
+            for child in children {
+                let trace = child.trace(properties, options);
+                let stop = trace.matched == stop_at;
+                traces.push(trace);
+                if stop {
+                    break;
+                }
+            }
+            traces
+        };
+
+        let (filter, children) = match self {
+            LegacyFilterExpression::All(children) => ("all".to_string(), trace_children(children, false)),
+            LegacyFilterExpression::Any(children) => ("any".to_string(), trace_children(children, true)),
+            LegacyFilterExpression::None(children) => ("none".to_string(), trace_children(children, true)),
+            LegacyFilterExpression::Collate(child_collator, child) => (
+                format!("{child_collator:?}"),
+                vec![child.trace(
+                    properties,
+                    &EvaluationOptions {
+                        collator: *child_collator,
+                        ..*options
+                    },
+                )],
+            ),
+            leaf => {
+                return FilterTrace {
+                    filter: format!("{leaf:?}"),
+                    matched: leaf.evaluate_with(properties, options),
+                    children: vec![],
+                }
+            }
+        };
+
+        let matched = match self {
+            LegacyFilterExpression::All(_) => children.iter().all(|child| child.matched),
+            LegacyFilterExpression::Any(_) => children.iter().any(|child| child.matched),
+            LegacyFilterExpression::None(_) => children.iter().all(|child| !child.matched),
+            _ => children[0].matched,
+        };
+
+        FilterTrace {
+            filter,
+            matched,
+            children,
+        }
+    }
+
+    /// Compares the strings in this filter according to `collator`, e.g. case-insensitively.
+    /// Nested filters which specify their own collator keep it.
+    pub fn with_collator(self, collator: Collator) -> Self {
+        LegacyFilterExpression::Collate(collator, Box::new(self))
+    }
+
+    /// Returns all property keys which this filter reads. Properties of a feature which are not
+    /// contained can be skipped without changing the result of [`Self::evaluate`].
+    pub fn referenced_keys(&self) -> HashSet<String> {
+        let mut keys = HashSet::new();
+        self.collect_referenced_keys(&mut keys);
+        keys
+    }
+
+    fn collect_referenced_keys(&self, keys: &mut HashSet<String>) {
+        match self {
+            LegacyFilterExpression::Has(key)
+            | LegacyFilterExpression::NotHas(key)
+            | LegacyFilterExpression::Comparison(_, key, _)
+            | LegacyFilterExpression::In(key, _)
+            | LegacyFilterExpression::NotIn(key, _) => {
+                keys.insert(key.clone());
+            }
+            LegacyFilterExpression::All(children)
+            | LegacyFilterExpression::Any(children)
+            | LegacyFilterExpression::None(children) => {
+                for child in children {
+                    child.collect_referenced_keys(keys);
+                }
+            }
+            LegacyFilterExpression::Within(_) => {}
+            LegacyFilterExpression::Collate(_, child) => child.collect_referenced_keys(keys),
+        }
+    }
+
+    /// Converts the filter into the equivalent expression of the modern filter syntax, e.g.
+    /// `["==", "class", "park"]` into `["==", ["get", "class"], "park"]`, for tools which do not
+    /// understand legacy filters. Membership filters become `in` expressions and collators are
+    /// passed to the comparisons, or lowercase both sides of membership filters.
+    ///
+    /// Like in the conversion of the style spec, `!=`, `!in` and `none` match features without
+    /// the property in modern expressions, unlike in [`Self::evaluate`].
+    pub fn to_modern(&self) -> serde_json::Value {
+        self.to_modern_with_collator(None)
+    }
+
+    fn to_modern_with_collator(&self, collator: Option<&Collator>) -> serde_json::Value {
+        use serde_json::{json, Value};
+
+        let children = |children: &[LegacyFilterExpression]| {
+            children
+                .iter()
+                .map(|child| child.to_modern_with_collator(collator))
+                .collect::<Vec<_>>()
+        };
+        let membership = |key: &str, predicates: &[ComparisonLiteral]| {
+            let case_insensitive = collator.is_some_and(|collator| !collator.case_sensitive);
+            let predicates: Vec<_> = predicates
+                .iter()
+                .map(|predicate| match (predicate, case_insensitive) {
+                    (ComparisonLiteral::String(predicate), true) => json!(predicate.to_lowercase()),
+                    _ => Value::from(predicate.clone()),
+                })
+                .collect();
+            let value = match case_insensitive {
+                true => json!(["downcase", ["to-string", modern_getter(key)]]),
+                false => modern_getter(key),
+            };
+            json!(["in", value, ["literal", predicates]])
+        };
+
+        match self {
+            LegacyFilterExpression::Has(key) => json!(["has", key]),
+            LegacyFilterExpression::NotHas(key) => json!(["!", ["has", key]]),
+            LegacyFilterExpression::Comparison(op, key, value) => {
+                let value = match value {
+                    ComparisonLiteral::Array(_) => json!(["literal", Value::from(value.clone())]),
+                    _ => Value::from(value.clone()),
+                };
+                let mut expression = vec![json!(op.keyword()), modern_getter(key), value];
+                if let Some(collator) = collator {
+                    expression.push(json!(["collator", collator]));
+                }
+                Value::Array(expression)
+            }
+            LegacyFilterExpression::In(key, predicates) => membership(key, predicates),
+            LegacyFilterExpression::NotIn(key, predicates) => {
+                json!(["!", membership(key, predicates)])
+            }
+            LegacyFilterExpression::All(filters) => {
+                Value::Array([vec![json!("all")], children(filters)].concat())
+            }
+            LegacyFilterExpression::Any(filters) => {
+                Value::Array([vec![json!("any")], children(filters)].concat())
+            }
+            LegacyFilterExpression::None(filters) => json!([
+                "!",
+                Value::Array([vec![json!("any")], children(filters)].concat())
+            ]),
+            LegacyFilterExpression::Within(polygon) => json!(["within", polygon]),
+            LegacyFilterExpression::Collate(collator, child) => {
+                child.to_modern_with_collator(Some(collator))
+            }
+        }
+    }
+
+    /// Whether this filter reads the geometry of features, see [`EvaluationOptions::geometry`].
+    pub fn uses_geometry(&self) -> bool {
+        match self {
+            LegacyFilterExpression::Within(_) => true,
+            LegacyFilterExpression::All(children)
+            | LegacyFilterExpression::Any(children)
+            | LegacyFilterExpression::None(children) => children.iter().any(Self::uses_geometry),
+            LegacyFilterExpression::Collate(_, child) => child.uses_geometry(),
+            _ => false,
+        }
+    }
+
+    /// Whether the filter matches features of `geometry_type`, like `Polygon`, regardless of
+    /// their other properties. Returns `None` if the result also depends on other properties or
+    /// on the geometry itself, e.g. for `["all", ["==", "$type", "Polygon"], ["has", "name"]]`
+    /// and `LineString` it is `Some(false)`, but for `Polygon` it is `None`.
+    pub fn matches_geometry_type(&self, geometry_type: &str) -> Option<bool> {
+        if !self.uses_geometry() && self.referenced_keys().iter().all(|key| key == "$type") {
+            let properties = HashMap::from([(
+                "$type".to_string(),
+                ComparisonLiteral::String(geometry_type.to_string()),
+            )]);
+            return Some(self.evaluate(&properties));
+        }
+
+        // Otherwise only a single child can decide the result
+        let any_child_is = |children: &[LegacyFilterExpression], result| {
+            children
+                .iter()
+                .any(|child| child.matches_geometry_type(geometry_type) == Some(result))
+        };
+        match self {
+            LegacyFilterExpression::All(children) => any_child_is(children, false).then_some(false),
+            LegacyFilterExpression::Any(children) => any_child_is(children, true).then_some(true),
+            LegacyFilterExpression::None(children) => any_child_is(children, true).then_some(false),
+            _ => None,
+        }
+    }
+}
+
+/// The modern expression which reads the property `key`. The special keys of legacy filters
+/// become the corresponding expressions.
+fn modern_getter(key: &str) -> serde_json::Value {
+    match key {
+        "$type" => serde_json::json!(["geometry-type"]),
+        "$id" => serde_json::json!(["id"]),
+        "$zoom" => serde_json::json!(["zoom"]),
+        _ => serde_json::json!(["get", key]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use geozero::ColumnValue;
+
+    use crate::{
+        coords::LatLon,
+        style::expression::{
+            Collator, ComparisonLiteral, ComparisonMode, EvaluationOptions, ExpressionComparisonOp,
+            LegacyFilterExpression, UnsupportedJsonValue, EVALUATED_FILTERS,
+        },
+    };
+
+    /// A filter which must not be evaluated by the tests of short-circuiting. Whether it was
+    /// is visible in the count of [`evaluate_counting`] and in the [`FilterTrace`], which stops
+    /// at the same child as the evaluation.
+    fn unreached() -> LegacyFilterExpression {
+        LegacyFilterExpression::Has("unreached".to_string())
+    }
+
+    fn rank_properties() -> HashMap<String, ComparisonLiteral> {
+        HashMap::from([("rank".to_string(), ComparisonLiteral::Integer(1))])
+    }
+
+    /// The descriptions of the children of the combining `filter` which were evaluated.
+    fn evaluated_children(filter: &LegacyFilterExpression) -> Vec<String> {
+        filter
+            .trace(&rank_properties(), &EvaluationOptions::default())
+            .children
+            .into_iter()
+            .map(|child| child.filter)
+            .collect()
+    }
+
+    /// Evaluates `filter` and counts the filters which were evaluated, including `filter` itself.
+    fn evaluate_counting(
+        filter: &LegacyFilterExpression,
+        properties: &HashMap<String, ComparisonLiteral>,
+    ) -> (bool, usize) {
+        EVALUATED_FILTERS.with(|evaluated| evaluated.set(0));
+        let matched = filter.evaluate(properties);
+        (matched, EVALUATED_FILTERS.with(|evaluated| evaluated.get()))
+    }
+
+    #[test]
+    fn test_evaluate_short_circuits() {
+        let properties = rank_properties();
+        let matching = || LegacyFilterExpression::Has("rank".to_string());
+        let not_matching = || LegacyFilterExpression::NotHas("rank".to_string());
+
+        let all = LegacyFilterExpression::All(vec![not_matching(), unreached(), unreached()]);
+        let any = LegacyFilterExpression::Any(vec![matching(), unreached(), unreached()]);
+        let none = LegacyFilterExpression::None(vec![matching(), unreached(), unreached()]);
+        assert_eq!(evaluate_counting(&all, &properties), (false, 2));
+        assert_eq!(evaluate_counting(&any, &properties), (true, 2));
+        assert_eq!(evaluate_counting(&none, &properties), (false, 2));
+
+        // Children are evaluated from left to right until one decides the result
+        let all = LegacyFilterExpression::All(vec![matching(), not_matching(), unreached()]);
+        assert_eq!(evaluate_counting(&all, &properties), (false, 3));
+
+        // Nested filters short-circuit as well
+        let nested = LegacyFilterExpression::All(vec![
+            LegacyFilterExpression::Any(vec![not_matching()]),
+            LegacyFilterExpression::Any(vec![unreached()]),
+        ]);
+        assert_eq!(evaluate_counting(&nested, &properties), (false, 3));
+    }
+
+    #[test]
+    fn test_trace_short_circuits() {
+        let properties = rank_properties();
+        let matching = || LegacyFilterExpression::Has("rank".to_string());
+        let not_matching = || LegacyFilterExpression::NotHas("rank".to_string());
+
+        let all = LegacyFilterExpression::All(vec![not_matching(), unreached()]);
+        let any = LegacyFilterExpression::Any(vec![matching(), unreached()]);
+        let none = LegacyFilterExpression::None(vec![matching(), unreached()]);
+        assert!(!all.evaluate(&properties));
+        assert!(any.evaluate(&properties));
+        assert!(!none.evaluate(&properties));
+        for filter in [all, any, none] {
+            assert_eq!(evaluated_children(&filter).len(), 1);
+        }
+
+        // Nested filters short-circuit as well
+        let nested = LegacyFilterExpression::All(vec![
+            LegacyFilterExpression::Any(vec![not_matching()]),
+            LegacyFilterExpression::Any(vec![unreached()]),
+        ]);
+        assert!(!nested.evaluate(&properties));
+        assert_eq!(evaluated_children(&nested), vec!["any".to_string()]);
+    }
+
+    #[test]
+    fn test_trace_evaluates_left_to_right() {
+        let not_matching = LegacyFilterExpression::NotHas("rank".to_string());
+
+        // The unreached filter comes first, so it is evaluated before the short-circuiting child
+        let all = LegacyFilterExpression::All(vec![unreached(), not_matching.clone()]);
+        assert_eq!(evaluated_children(&all), vec![format!("{:?}", unreached())]);
+        let all = LegacyFilterExpression::All(vec![not_matching.clone(), unreached()]);
+        assert_eq!(evaluated_children(&all), vec![format!("{not_matching:?}")]);
+    }
+
+    #[test]
+    fn test_in_scalar_properties() {
+        let properties = HashMap::from([
+            ("rank".to_string(), ComparisonLiteral::Integer(2)),
+            ("oneway".to_string(), ComparisonLiteral::Bool(true)),
+        ]);
+        let filter = |json: &str| serde_json::from_str::<LegacyFilterExpression>(json).unwrap();
+
+        assert!(filter(r#"["in", "rank", 1, 2]"#).evaluate(&properties));
+        assert!(!filter(r#"["in", "rank", 3, "2"]"#).evaluate(&properties));
+        assert!(!filter(r#"["!in", "rank", 1, 2]"#).evaluate(&properties));
+        assert!(filter(r#"["!in", "rank", 3]"#).evaluate(&properties));
+
+        assert!(filter(r#"["in", "oneway", true]"#).evaluate(&properties));
+        assert!(!filter(r#"["in", "oneway", false]"#).evaluate(&properties));
+        assert!(filter(r#"["!in", "oneway", false]"#).evaluate(&properties));
+    }
+
+    #[test]
+    fn test_empty_combining_filters() {
+        let properties = HashMap::new();
+
+        assert!(LegacyFilterExpression::All(vec![]).evaluate(&properties));
+        assert!(!LegacyFilterExpression::Any(vec![]).evaluate(&properties));
+        assert!(LegacyFilterExpression::None(vec![]).evaluate(&properties));
+
+        let all: LegacyFilterExpression = serde_json::from_str(r#"["all"]"#).unwrap();
+        let any: LegacyFilterExpression = serde_json::from_str(r#"["any"]"#).unwrap();
+        let none: LegacyFilterExpression = serde_json::from_str(r#"["none"]"#).unwrap();
+        assert!(all.evaluate(&properties));
+        assert!(!any.evaluate(&properties));
+        assert!(none.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_mixed_numeric_equality() {
+        assert!(ExpressionComparisonOp::Eq
+            .compare(&ComparisonLiteral::Integer(3), &ComparisonLiteral::Float(3.0)));
+        assert!(ExpressionComparisonOp::Eq
+            .compare(&ComparisonLiteral::Float(3.0), &ComparisonLiteral::Integer(3)));
+        assert!(!ExpressionComparisonOp::Eq
+            .compare(&ComparisonLiteral::Integer(3), &ComparisonLiteral::Float(3.5)));
+
+        assert!(!ExpressionComparisonOp::Neq
+            .compare(&ComparisonLiteral::Integer(3), &ComparisonLiteral::Float(3.0)));
+        assert!(ExpressionComparisonOp::Neq
+            .compare(&ComparisonLiteral::Float(3.5), &ComparisonLiteral::Integer(3)));
+    }
+
+    #[test]
+    fn test_bool_integer_equality() {
+        let eq = |a, b| ExpressionComparisonOp::Eq.compare(&a, &b);
+        assert!(eq(ComparisonLiteral::Integer(1), ComparisonLiteral::Bool(true)));
+        assert!(eq(ComparisonLiteral::Bool(true), ComparisonLiteral::Integer(1)));
+        assert!(eq(ComparisonLiteral::Integer(0), ComparisonLiteral::Bool(false)));
+        assert!(eq(ComparisonLiteral::Integer(-2), ComparisonLiteral::Bool(true)));
+        assert!(!eq(ComparisonLiteral::Integer(0), ComparisonLiteral::Bool(true)));
+        assert!(!eq(ComparisonLiteral::Bool(false), ComparisonLiteral::Integer(1)));
+
+        let properties = HashMap::from([
+            ("enabled".to_string(), ComparisonLiteral::Integer(1)),
+            ("hidden".to_string(), ComparisonLiteral::Integer(0)),
+        ]);
+        let filter = |json: &str| serde_json::from_str::<LegacyFilterExpression>(json).unwrap();
+        assert!(filter(r#"["==", "enabled", true]"#).evaluate(&properties));
+        assert!(filter(r#"["!=", "hidden", true]"#).evaluate(&properties));
+        assert!(!filter(r#"["==", "hidden", true]"#).evaluate(&properties));
+    }
+
+    #[test]
+    fn test_trace_nested_all() {
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["all", ["==", "a", 1], ["all", ["has", "b"], ["==", "c", "x"]]]"#,
+        )
+        .unwrap();
+        let properties = HashMap::from([
+            ("a".to_string(), ComparisonLiteral::Integer(1)),
+            ("b".to_string(), ComparisonLiteral::Bool(true)),
+            ("c".to_string(), ComparisonLiteral::String("y".to_string())),
+        ]);
+
+        let trace = filter.trace(&properties, &EvaluationOptions::default());
+        assert!(!trace.matched);
+        assert_eq!(trace.filter, "all");
+        assert_eq!(trace.children.len(), 2);
+
+        let inner = &trace.children[1];
+        assert_eq!(
+            inner.children.iter().map(|child| child.matched).collect::<Vec<_>>(),
+            vec![true, false]
+        );
+
+        // Only the failing leaf of the nested `all` is reported
+        let rejected_by = trace.rejected_by();
+        assert_eq!(rejected_by, vec![&inner.children[1]]);
+        assert!(rejected_by[0].filter.contains("\"c\""));
+
+        // A matching feature is not rejected by anything
+        let properties = HashMap::from([
+            ("a".to_string(), ComparisonLiteral::Integer(1)),
+            ("b".to_string(), ComparisonLiteral::Bool(true)),
+            ("c".to_string(), ComparisonLiteral::String("x".to_string())),
+        ]);
+        let trace = filter.trace(&properties, &EvaluationOptions::default());
+        assert!(trace.matched);
+        assert!(trace.rejected_by().is_empty());
+        assert_eq!(trace.matched, filter.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_float_tolerance() {
+        let eq = |a, b| ExpressionComparisonOp::Eq.compare(&a, &b);
+        assert!(eq(ComparisonLiteral::Float(3.0000001), ComparisonLiteral::Integer(3)));
+        assert!(eq(ComparisonLiteral::Integer(3), ComparisonLiteral::Float(2.9999999)));
+        assert!(eq(ComparisonLiteral::Float(0.1 + 0.2), ComparisonLiteral::Float(0.3)));
+        assert!(!eq(ComparisonLiteral::Float(3.001), ComparisonLiteral::Integer(3)));
+        assert!(!ExpressionComparisonOp::Neq
+            .compare(&ComparisonLiteral::Float(3.0000001), &ComparisonLiteral::Integer(3)));
+
+        // The tolerance is relative for large values
+        assert!(eq(ComparisonLiteral::Float(1e9 + 0.5), ComparisonLiteral::Integer(1_000_000_000)));
+        assert!(!eq(ComparisonLiteral::Float(1e9 + 5000.0), ComparisonLiteral::Integer(1_000_000_000)));
+
+        // Orderings are not affected
+        assert!(ExpressionComparisonOp::Gt
+            .compare(&ComparisonLiteral::Float(3.0000001), &ComparisonLiteral::Integer(3)));
+
+        // The tolerance can be overridden
+        let exact = |a, b| ExpressionComparisonOp::Eq.compare_with_tolerance(&a, &b, 0.0);
+        assert!(!exact(ComparisonLiteral::Float(3.0000001), ComparisonLiteral::Integer(3)));
+        assert!(exact(ComparisonLiteral::Float(3.0), ComparisonLiteral::Integer(3)));
+
+        let filter: LegacyFilterExpression = serde_json::from_str(r#"["==", "x", 3]"#).unwrap();
+        let properties = HashMap::from([("x".to_string(), ComparisonLiteral::Float(3.0000001))]);
+        assert!(filter.evaluate(&properties));
+        let with_tolerance = |tolerance| EvaluationOptions {
+            tolerance,
+            ..Default::default()
+        };
+        assert!(!filter.evaluate_with(&properties, &with_tolerance(0.0)));
+        assert!(filter.evaluate_with(&properties, &with_tolerance(1e-3)));
+
+        // Traces use the same tolerance as the evaluation
+        assert!(!filter.trace(&properties, &with_tolerance(0.0)).matched);
+        assert!(filter.trace(&properties, &with_tolerance(1e-3)).matched);
+    }
+
+    #[test]
+    fn test_referenced_keys() {
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["all", ["==", "$type", "Polygon"], ["any", ["has", "name"], ["in", "class", "a", "b"]], ["none", [">", "rank", 2]]]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            filter.referenced_keys(),
+            HashSet::from(["$type", "name", "class", "rank"].map(str::to_string))
+        );
+        assert!(LegacyFilterExpression::All(vec![]).referenced_keys().is_empty());
+    }
+
+    #[test]
+    fn test_string_number_comparison() {
+        let properties = HashMap::from([
+            ("level".to_string(), ComparisonLiteral::Integer(2)),
+            ("name".to_string(), ComparisonLiteral::String("10".to_string())),
+        ]);
+        let filter = |json: &str| serde_json::from_str::<LegacyFilterExpression>(json).unwrap();
+
+        let level_eq = filter(r#"["==", "level", "2"]"#);
+        let level_neq = filter(r#"["!=", "level", "2.0"]"#);
+        let name_gt = filter(r#"[">", "name", 9]"#);
+        let not_a_number = filter(r#"["==", "level", "two"]"#);
+
+        // The spec requires strict typing
+        assert!(!level_eq.evaluate(&properties));
+        assert!(level_neq.evaluate(&properties));
+        assert!(!name_gt.evaluate(&properties));
+        assert!(!not_a_number.evaluate(&properties));
+
+        let lenient = EvaluationOptions {
+            mode: ComparisonMode::Lenient,
+            ..Default::default()
+        };
+        assert!(level_eq.evaluate_with(&properties, &lenient));
+        assert!(!level_neq.evaluate_with(&properties, &lenient));
+        assert!(name_gt.evaluate_with(&properties, &lenient));
+        assert!(!not_a_number.evaluate_with(&properties, &lenient));
+
+        // The mode also applies to nested filters
+        let all = LegacyFilterExpression::All(vec![level_eq, name_gt]);
+        assert!(!all.evaluate(&properties));
+        assert!(all.evaluate_with(&properties, &lenient));
+    }
+
+    #[test]
+    fn test_matches_geometry_type() {
+        let filter = |json: &str| serde_json::from_str::<LegacyFilterExpression>(json).unwrap();
+
+        let polygons = filter(r#"["==", "$type", "Polygon"]"#);
+        assert_eq!(polygons.matches_geometry_type("Polygon"), Some(true));
+        assert_eq!(polygons.matches_geometry_type("LineString"), Some(false));
+
+        let named_polygons = filter(r#"["all", ["==", "$type", "Polygon"], ["has", "name"]]"#);
+        assert_eq!(named_polygons.matches_geometry_type("LineString"), Some(false));
+        assert_eq!(named_polygons.matches_geometry_type("Polygon"), None);
+
+        let lines_or_named = filter(r#"["any", ["in", "$type", "LineString", "Point"], ["has", "name"]]"#);
+        assert_eq!(lines_or_named.matches_geometry_type("LineString"), Some(true));
+        assert_eq!(lines_or_named.matches_geometry_type("Polygon"), None);
+
+        let no_lines = filter(r#"["none", ["==", "$type", "LineString"], ["has", "name"]]"#);
+        assert_eq!(no_lines.matches_geometry_type("LineString"), Some(false));
+        assert_eq!(no_lines.matches_geometry_type("Polygon"), None);
+
+        assert_eq!(filter(r#"["has", "name"]"#).matches_geometry_type("Polygon"), None);
+        assert_eq!(filter(r#"["all"]"#).matches_geometry_type("Polygon"), Some(true));
+    }
+
+    #[test]
+    fn test_numeric_string_comparison() {
+        let properties = HashMap::from([
+            ("rank".to_string(), ComparisonLiteral::String("10".to_string())),
+            ("name".to_string(), ComparisonLiteral::String("b".to_string())),
+        ]);
+        let filter = |json: &str| serde_json::from_str::<LegacyFilterExpression>(json).unwrap();
+
+        let rank_gt = filter(r#"[">", "rank", "9"]"#);
+        let rank_eq = filter(r#"["==", "rank", "10.0"]"#);
+        let name_gt = filter(r#"[">", "name", "a"]"#);
+        let mixed_gt = filter(r#"[">", "rank", "a"]"#);
+
+        let with_mode = |mode| EvaluationOptions {
+            mode,
+            ..Default::default()
+        };
+
+        // Strings are compared lexically by default
+        for mode in [ComparisonMode::Strict, ComparisonMode::Lenient] {
+            assert!(!rank_gt.evaluate_with(&properties, &with_mode(mode)));
+            assert!(!rank_eq.evaluate_with(&properties, &with_mode(mode)));
+            assert!(name_gt.evaluate_with(&properties, &with_mode(mode)));
+        }
+
+        let numeric = with_mode(ComparisonMode::Numeric);
+        assert!(rank_gt.evaluate_with(&properties, &numeric));
+        assert!(rank_eq.evaluate_with(&properties, &numeric));
+        // Falls back to lexical comparison if one of the strings is not a number
+        assert!(name_gt.evaluate_with(&properties, &numeric));
+        assert!(!mixed_gt.evaluate_with(&properties, &numeric));
+        // Strings are still coerced when compared to numbers
+        assert!(filter(r#"[">", "rank", 9]"#).evaluate_with(&properties, &numeric));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let literals = [
+            ComparisonLiteral::Float(1.5),
+            ComparisonLiteral::Float(-2.0),
+            ComparisonLiteral::Integer(42),
+            ComparisonLiteral::Integer(-7),
+            ComparisonLiteral::Bool(true),
+            ComparisonLiteral::String("road".to_string()),
+            ComparisonLiteral::Null,
+        ];
+
+        for literal in literals {
+            let value = serde_json::Value::from(literal.clone());
+            assert_eq!(ComparisonLiteral::try_from(&value).unwrap(), literal);
+        }
+
+        // Arrays can be converted to JSON, but not back
+        let array = ComparisonLiteral::Array(vec![ComparisonLiteral::Integer(255), ComparisonLiteral::Null]);
+        let value = serde_json::Value::from(array);
+        assert_eq!(value, serde_json::json!([255, null]));
+        assert!(ComparisonLiteral::try_from(&value).is_err());
+    }
+
+    /// Parses the legacy filter `legacy` and converts it into a modern expression.
+    fn to_modern(legacy: serde_json::Value) -> serde_json::Value {
+        serde_json::from_value::<LegacyFilterExpression>(legacy)
+            .unwrap()
+            .to_modern()
+    }
+
+    #[test]
+    fn test_to_modern() {
+        use serde_json::json;
+
+        let square = json!([[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]]);
+        let case_insensitive = json!(["collator", {"case-sensitive": false}]);
+        let cases = [
+            (json!(["has", "name"]), json!(["has", "name"])),
+            (json!(["!has", "name"]), json!(["!", ["has", "name"]])),
+            (json!(["==", "class", "park"]), json!(["==", ["get", "class"], "park"])),
+            (json!(["!=", "class", "park"]), json!(["!=", ["get", "class"], "park"])),
+            (json!([">", "rank", 1.5]), json!([">", ["get", "rank"], 1.5])),
+            (json!([">=", "rank", 2]), json!([">=", ["get", "rank"], 2])),
+            (json!(["<", "rank", 3]), json!(["<", ["get", "rank"], 3])),
+            (json!(["<=", "oneway", true]), json!(["<=", ["get", "oneway"], true])),
+            (json!(["==", "tags", ["a"]]), json!(["==", ["get", "tags"], ["literal", ["a"]]])),
+            (json!(["==", "$type", "Polygon"]), json!(["==", ["geometry-type"], "Polygon"])),
+            (json!(["==", "$id", 7]), json!(["==", ["id"], 7])),
+            (
+                json!(["in", "class", "park", "forest"]),
+                json!(["in", ["get", "class"], ["literal", ["park", "forest"]]]),
+            ),
+            (
+                json!(["!in", "class", "park"]),
+                json!(["!", ["in", ["get", "class"], ["literal", ["park"]]]]),
+            ),
+            (
+                json!(["all", ["has", "name"], ["==", "rank", 1]]),
+                json!(["all", ["has", "name"], ["==", ["get", "rank"], 1]]),
+            ),
+            (json!(["any"]), json!(["any"])),
+            (
+                json!(["any", ["has", "name"], ["!has", "ref"]]),
+                json!(["any", ["has", "name"], ["!", ["has", "ref"]]]),
+            ),
+            (json!(["none", ["has", "name"]]), json!(["!", ["any", ["has", "name"]]])),
+            (
+                json!(["within", {"type": "Polygon", "coordinates": square}]),
+                json!(["within", {"type": "Polygon", "coordinates": square}]),
+            ),
+            (
+                json!(["==", "name", "Main", case_insensitive]),
+                json!(["==", ["get", "name"], "Main", case_insensitive]),
+            ),
+            (
+                json!(["in", "name", "Main", case_insensitive]),
+                json!(["in", ["downcase", ["to-string", ["get", "name"]]], ["literal", ["main"]]]),
+            ),
+        ];
+
+        for (legacy, modern) in cases {
+            assert_eq!(to_modern(legacy.clone()), modern, "{legacy}");
+        }
+    }
+
+    #[test]
+    fn test_from_json_numbers() {
+        assert_eq!(
+            ComparisonLiteral::try_from(&serde_json::json!(3)).unwrap(),
+            ComparisonLiteral::Integer(3)
+        );
+        assert_eq!(
+            ComparisonLiteral::try_from(&serde_json::json!(3.0)).unwrap(),
+            ComparisonLiteral::Float(3.0)
+        );
+        assert_eq!(
+            ComparisonLiteral::try_from(&serde_json::json!(u64::MAX)).unwrap(),
+            ComparisonLiteral::Float(u64::MAX as f64)
+        );
+        assert_eq!(
+            serde_json::Value::from(ComparisonLiteral::Float(f64::NAN)),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_in_literal_predicates() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["in", "rank", 1, 2]"#).unwrap();
+        let LegacyFilterExpression::In(key, predicates) = &filter else {
+            panic!("expected in filter but found {filter:?}");
+        };
+        assert_eq!(key, "rank");
+        assert_eq!(
+            predicates,
+            &vec![ComparisonLiteral::Integer(1), ComparisonLiteral::Integer(2)]
+        );
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["!in", "class", "park", 1.5, true, null]"#).unwrap();
+        let LegacyFilterExpression::NotIn(_, predicates) = &filter else {
+            panic!("expected !in filter but found {filter:?}");
+        };
+        assert_eq!(
+            predicates,
+            &vec![
+                ComparisonLiteral::String("park".to_string()),
+                ComparisonLiteral::Float(1.5),
+                ComparisonLiteral::Bool(true),
+                ComparisonLiteral::Null,
+            ]
+        );
+
+        // Elements of array properties are compared like numbers
+        let properties = HashMap::from([(
+            "ranks".to_string(),
+            ComparisonLiteral::from(&ColumnValue::Json("[2.0, 3]")),
+        )]);
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["in", "ranks", 1, 2]"#).unwrap();
+        assert!(filter.evaluate(&properties));
+
+        // Strings never equal numbers
+        let properties = HashMap::from([(
+            "class".to_string(),
+            ComparisonLiteral::String("1".to_string()),
+        )]);
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["in", "class", 1]"#).unwrap();
+        assert!(!filter.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_in_json_array_property() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["in", "tags", "park", "forest"]"#).unwrap();
+        let not_in: LegacyFilterExpression =
+            serde_json::from_str(r#"["!in", "tags", "park", "forest"]"#).unwrap();
+        let properties = |json: &str| {
+            HashMap::from([(
+                "tags".to_string(),
+                ComparisonLiteral::from(&ColumnValue::Json(json)),
+            )])
+        };
+
+        let matching = properties(r#"["garden", "park"]"#);
+        assert_eq!(
+            matching["tags"],
+            ComparisonLiteral::Array(vec![
+                ComparisonLiteral::String("garden".to_string()),
+                ComparisonLiteral::String("park".to_string()),
+            ])
+        );
+        assert!(filter.evaluate(&matching));
+        assert!(!not_in.evaluate(&matching));
+
+        let other = properties(r#"["garden", 1, true]"#);
+        assert!(!filter.evaluate(&other));
+        assert!(not_in.evaluate(&other));
+        assert!(!filter.evaluate(&properties("[]")));
+
+        // JSON which is not an array of scalars is compared as string
+        let object = properties(r#"{"kind": "park"}"#);
+        assert_eq!(
+            object["tags"],
+            ComparisonLiteral::String(r#"{"kind": "park"}"#.to_string())
+        );
+
+        let collated = filter.with_collator(Collator::case_insensitive());
+        assert!(collated.evaluate(&properties(r#"["PARK"]"#)));
+    }
+
+    #[test]
+    fn test_array_equality() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["==", "rgb", [255, 0, 0]]"#).unwrap();
+        let not_equal: LegacyFilterExpression =
+            serde_json::from_str(r#"["!=", "rgb", [255, 0, 0]]"#).unwrap();
+        let properties = |json: &str| {
+            HashMap::from([(
+                "rgb".to_string(),
+                ComparisonLiteral::from(&ColumnValue::Json(json)),
+            )])
+        };
+
+        let red = properties("[255, 0, 0]");
+        assert!(filter.evaluate(&red));
+        assert!(!not_equal.evaluate(&red));
+
+        // Numbers are equal regardless of whether they are integers or floats
+        assert!(filter.evaluate(&properties("[255.0, 0, 0.0]")));
+
+        for other in ["[255, 0, 1]", "[255, 0]", "[255, 0, 0, 0]", "[0, 0, 255]", "255"] {
+            assert!(!filter.evaluate(&properties(other)), "{other}");
+            assert!(not_equal.evaluate(&properties(other)), "{other}");
+        }
+
+        // Arrays are unordered
+        let less: LegacyFilterExpression =
+            serde_json::from_str(r#"["<", "rgb", [255, 0, 1]]"#).unwrap();
+        assert!(!less.evaluate(&red));
+
+        let names: LegacyFilterExpression = serde_json::from_str(
+            r#"["==", "names", ["Main", "Street"], ["collator", {"case-sensitive": false}]]"#,
+        )
+        .unwrap();
+        assert!(names.evaluate(&HashMap::from([(
+            "names".to_string(),
+            ComparisonLiteral::from(&ColumnValue::Json(r#"["MAIN", "street"]"#)),
+        )])));
+    }
+
+    #[test]
+    fn test_deserialize_numbers() {
+        assert_eq!(
+            serde_json::from_str::<ComparisonLiteral>("2").unwrap(),
+            ComparisonLiteral::Integer(2)
+        );
+        assert_eq!(
+            serde_json::from_str::<ComparisonLiteral>("2.5").unwrap(),
+            ComparisonLiteral::Float(2.5)
+        );
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["==", "level", 2]"#).unwrap();
+        let properties = HashMap::from([("level".to_string(), ComparisonLiteral::Integer(2))]);
+        assert!(filter.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_from_json_array() {
+        let array = serde_json::json!([1, "a", [true]]);
+        assert_eq!(
+            ComparisonLiteral::try_from(&array),
+            Err(UnsupportedJsonValue(array))
+        );
+    }
+
+    #[test]
+    fn test_from_json_object() {
+        let object = serde_json::json!({"a": 1});
+        assert_eq!(
+            ComparisonLiteral::try_from(&object),
+            Err(UnsupportedJsonValue(object))
+        );
+    }
+
+    #[test]
+    fn test_within() {
+        // A square around null island with a hole in its north-east quarter
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["within", {"type": "Polygon", "coordinates": [
+                [[-10, -10], [10, -10], [10, 10], [-10, 10], [-10, -10]],
+                [[2, 2], [8, 2], [8, 8], [2, 8], [2, 2]]
+            ]}]"#,
+        )
+        .unwrap();
+        assert!(filter.uses_geometry());
+        assert!(filter.referenced_keys().is_empty());
+
+        let properties = HashMap::new();
+        let evaluate = |points: &[(f64, f64)]| {
+            let geometry: Vec<LatLon> = points
+                .iter()
+                .map(|(longitude, latitude)| LatLon::new(*latitude, *longitude))
+                .collect();
+            filter.evaluate_with(
+                &properties,
+                &EvaluationOptions {
+                    geometry: Some(&geometry),
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(evaluate(&[(0.0, 0.0)]));
+        assert!(evaluate(&[(-5.0, -5.0), (5.0, -5.0)]));
+        assert!(!evaluate(&[(20.0, 0.0)]));
+        assert!(!evaluate(&[(0.0, 0.0), (20.0, 0.0)]));
+        assert!(!evaluate(&[(5.0, 5.0)]));
+        assert!(!evaluate(&[]));
+
+        // Without a geometry the feature can not be within the polygon
+        assert!(!filter.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_within_multi_polygon() {
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["all", ["==", "class", "park"], ["within", {"type": "MultiPolygon", "coordinates": [
+                [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]],
+                [[[10, 10], [11, 10], [11, 11], [10, 11], [10, 10]]]
+            ]}]]"#,
+        )
+        .unwrap();
+        assert!(filter.uses_geometry());
+
+        let properties = HashMap::from([(
+            "class".to_string(),
+            ComparisonLiteral::String("park".to_string()),
+        )]);
+        let with_geometry = |geometry| EvaluationOptions {
+            geometry: Some(geometry),
+            ..Default::default()
+        };
+        let inside = [LatLon::new(10.5, 10.5)];
+        let outside = [LatLon::new(5.0, 5.0)];
+        assert!(filter.evaluate_with(&properties, &with_geometry(&inside)));
+        assert!(!filter.evaluate_with(&properties, &with_geometry(&outside)));
+    }
+
+    #[test]
+    fn test_case_insensitive_comparison() {
+        let properties = HashMap::from([(
+            "class".to_string(),
+            ComparisonLiteral::String("Primary".to_string()),
+        )]);
+
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["==", "class", "primary", ["collator", {"case-sensitive": false}]]"#,
+        )
+        .unwrap();
+        assert!(filter.evaluate(&properties));
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["==", "class", "primary"]"#).unwrap();
+        assert!(!filter.evaluate(&properties));
+
+        // A collator for the whole filter
+        let filter = filter.with_collator(Collator::case_insensitive());
+        assert!(filter.evaluate(&properties));
+        assert_eq!(filter.referenced_keys(), HashSet::from(["class".to_string()]));
+
+        // Nested collators take precedence
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["all", ["!=", "class", "PRIMARY", ["collator", {"case-sensitive": true}]]]"#,
+        )
+        .unwrap();
+        assert!(filter.with_collator(Collator::case_insensitive()).evaluate(&properties));
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["<", "class", "SECONDARY", ["collator", {"case-sensitive": false}]]"#)
+                .unwrap();
+        assert!(filter.evaluate(&properties));
+    }
+
+    #[test]
+    fn test_case_insensitive_membership() {
+        let properties = HashMap::from([(
+            "class".to_string(),
+            ComparisonLiteral::String("Primary".to_string()),
+        )]);
+
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["in", "class", "primary", "secondary", ["collator", {"case-sensitive": false}]]"#,
+        )
+        .unwrap();
+        assert!(filter.evaluate(&properties));
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["!in", "class", "PRIMARY", ["collator", {}]]"#).unwrap();
+        assert!(filter.evaluate(&properties));
+
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["!in", "class", "PRIMARY"]"#).unwrap();
+        assert!(!filter.with_collator(Collator::case_insensitive()).evaluate(&properties));
+
+        assert!(serde_json::from_str::<LegacyFilterExpression>(
+            r#"["in", "class", "primary", ["literal", {}]]"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_absent_and_null_properties() {
+        let null = HashMap::from([("name".to_string(), ComparisonLiteral::Null)]);
+        let absent = HashMap::new();
+        let named = HashMap::from([(
+            "name".to_string(),
+            ComparisonLiteral::String("Main Street".to_string()),
+        )]);
+
+        let is_null: LegacyFilterExpression =
+            serde_json::from_str(r#"["==", "name", null]"#).unwrap();
+        assert!(matches!(
+            &is_null,
+            LegacyFilterExpression::Comparison(_, _, ComparisonLiteral::Null)
+        ));
+        assert!(is_null.evaluate(&null));
+        assert!(!is_null.evaluate(&absent));
+        assert!(!is_null.evaluate(&named));
+
+        let is_not_null: LegacyFilterExpression =
+            serde_json::from_str(r#"["!=", "name", null]"#).unwrap();
+        assert!(!is_not_null.evaluate(&null));
+        assert!(!is_not_null.evaluate(&absent));
+        assert!(is_not_null.evaluate(&named));
+
+        let has: LegacyFilterExpression = serde_json::from_str(r#"["has", "name"]"#).unwrap();
+        assert!(has.evaluate(&null));
+        assert!(!has.evaluate(&absent));
+
+        let is_in: LegacyFilterExpression =
+            serde_json::from_str(r#"["in", "name", "Main Street"]"#).unwrap();
+        assert!(!is_in.evaluate(&null));
+    }
+
+    #[test]
+    fn test_null_is_unordered() {
+        let null = ComparisonLiteral::Null;
+        for other in [
+            ComparisonLiteral::Null,
+            ComparisonLiteral::Integer(0),
+            ComparisonLiteral::String(String::new()),
+        ] {
+            for op in [
+                ExpressionComparisonOp::Gt,
+                ExpressionComparisonOp::Geq,
+                ExpressionComparisonOp::Lt,
+                ExpressionComparisonOp::Leq,
+            ] {
+                assert!(!op.compare(&null, &other));
+                assert!(!op.compare(&other, &null));
+            }
         }
+
+        assert!(ExpressionComparisonOp::Eq.compare(&null, &ComparisonLiteral::Null));
+        assert!(!ExpressionComparisonOp::Eq.compare(&null, &ComparisonLiteral::Bool(false)));
+        assert!(!ExpressionComparisonOp::Eq.compare(&ComparisonLiteral::Integer(0), &null));
+        assert_eq!(
+            ComparisonLiteral::try_from(&serde_json::Value::Null).unwrap(),
+            ComparisonLiteral::Null
+        );
+        assert_eq!(
+            ComparisonLiteral::from(&ColumnValue::Json("null")),
+            ComparisonLiteral::Null
+        );
     }
-}
\ No newline at end of file
+}
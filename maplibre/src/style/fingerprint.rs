@@ -0,0 +1,95 @@
+//! Stable fingerprints of styles, which identify their content across runs and platforms.
+
+use serde::Serialize;
+use serde_json::Value;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Hashes the serialized content of `value` with 64-bit FNV-1a. The hash does not depend on the
+/// order of the keys of maps, e.g. of `HashMap`s, and is the same on every platform and run.
+pub fn fingerprint(value: &impl Serialize) -> u64 {
+    // Style types only have maps with string keys, which can always be serialized
+    let value = serde_json::to_value(value).unwrap_or_default();
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    hasher.value(&value);
+    hasher.0
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Hashes a string with its length, such that adjacent strings can not be confused.
+    fn str(&mut self, string: &str) {
+        self.bytes(&(string.len() as u64).to_le_bytes());
+        self.bytes(string.as_bytes());
+    }
+
+    /// Hashes `value` with a tag for its type, such that e.g. `1` and `"1"` differ.
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.bytes(&[0]),
+            Value::Bool(bool) => self.bytes(&[1, *bool as u8]),
+            Value::Number(number) => {
+                self.bytes(&[2]);
+                self.str(&number.to_string());
+            }
+            Value::String(string) => {
+                self.bytes(&[3]);
+                self.str(string);
+            }
+            Value::Array(values) => {
+                self.bytes(&[4]);
+                self.bytes(&(values.len() as u64).to_le_bytes());
+                values.iter().for_each(|value| self.value(value));
+            }
+            Value::Object(map) => {
+                self.bytes(&[5]);
+                self.bytes(&(map.len() as u64).to_le_bytes());
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                for (key, value) in entries {
+                    self.str(key);
+                    self.value(value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::fingerprint;
+
+    #[test]
+    fn test_key_order_is_ignored() {
+        let a: HashMap<String, usize> = (0..32).map(|i| (i.to_string(), i)).collect();
+        let b: HashMap<String, usize> = (0..32).rev().map(|i| (i.to_string(), i)).collect();
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+
+        assert_eq!(
+            fingerprint(&json!({"a": 1, "b": [true, null]})),
+            fingerprint(&json!({"b": [true, null], "a": 1}))
+        );
+    }
+
+    #[test]
+    fn test_types_and_values_differ() {
+        assert_ne!(fingerprint(&json!(1)), fingerprint(&json!("1")));
+        assert_ne!(fingerprint(&json!(["ab", "c"])), fingerprint(&json!(["a", "bc"])));
+        assert_ne!(fingerprint(&json!({"a": 1})), fingerprint(&json!({"a": 2})));
+        // The hash is stable across runs
+        assert_eq!(fingerprint(&json!(null)), 0xaf63_bd4c_8601_b7df);
+    }
+}
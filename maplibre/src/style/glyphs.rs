@@ -0,0 +1,101 @@
+//! Font stacks and the glyph ranges which are requested for rendering text.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Glyphs are served in PBF files which each cover this many consecutive code points.
+pub const GLYPH_RANGE_SIZE: u32 = 256;
+
+/// The fonts of a `text-font` layout property, in order of preference. Glyphs which are missing in
+/// a font are taken from the next one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct FontStack(Vec<String>);
+
+impl FontStack {
+    pub fn new(fonts: Vec<String>) -> Self {
+        Self(fonts)
+    }
+
+    pub fn fonts(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Formats the URL of the glyph `range` by replacing `{fontstack}` and `{range}` in the
+    /// `glyphs` template of a style, e.g. `https://example.com/fonts/{fontstack}/{range}.pbf`.
+    pub fn glyph_url(&self, template: &str, range: (u32, u32)) -> String {
+        let (start, end) = range;
+        template
+            .replace("{fontstack}", &encode_url_component(&self.to_string()))
+            .replace("{range}", &format!("{start}-{end}"))
+    }
+}
+
+/// Separates the fonts by commas, like in the `{fontstack}` of glyph URLs.
+impl fmt::Display for FontStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+/// The glyph ranges which cover all characters of `text`, as inclusive first and last code point.
+/// The ranges are sorted and each is listed once.
+pub fn glyph_ranges_for(text: &str) -> Vec<(u32, u32)> {
+    let mut starts: Vec<u32> = text
+        .chars()
+        .map(|c| c as u32 / GLYPH_RANGE_SIZE * GLYPH_RANGE_SIZE)
+        .collect();
+    starts.sort_unstable();
+    starts.dedup();
+
+    starts
+        .into_iter()
+        .map(|start| (start, start + GLYPH_RANGE_SIZE - 1))
+        .collect()
+}
+
+/// Percent-encodes everything except unreserved characters and the commas which separate fonts.
+fn encode_url_component(component: &str) -> String {
+    component
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b',' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph_ranges_for, FontStack};
+
+    #[test]
+    fn test_ascii_glyph_ranges() {
+        assert_eq!(glyph_ranges_for("Main Street 42"), vec![(0, 255)]);
+        assert!(glyph_ranges_for("").is_empty());
+    }
+
+    #[test]
+    fn test_glyph_ranges() {
+        // ü is within the first range, 京 (U+4EAC) and 東 (U+6771) are not
+        assert_eq!(
+            glyph_ranges_for("東京 Zürich"),
+            vec![(0, 255), (0x4E00, 0x4EFF), (0x6700, 0x67FF)]
+        );
+    }
+
+    #[test]
+    fn test_glyph_url() {
+        let font_stack: FontStack =
+            serde_json::from_str(r#"["Open Sans Regular", "Arial Unicode MS Regular"]"#).unwrap();
+        assert_eq!(font_stack.fonts().len(), 2);
+
+        assert_eq!(
+            font_stack.glyph_url("https://example.com/fonts/{fontstack}/{range}.pbf", (0, 255)),
+            "https://example.com/fonts/Open%20Sans%20Regular,Arial%20Unicode%20MS%20Regular/0-255.pbf"
+        );
+    }
+}
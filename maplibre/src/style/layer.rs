@@ -1,13 +1,19 @@
 //! Vector tile layer drawing utilities.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use cint::{Alpha, EncodedSrgb};
-use csscolorparser::Color;
-use serde::{Deserialize, Serialize};
-use crate::coords::ZoomLevel;
-use crate::style::expression::LegacyFilterExpression;
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+use crate::coords::Zoom;
+use crate::style::color::{deserialize_color, find_invalid_color, InterpolatedColor};
+use crate::style::fingerprint::fingerprint;
+use crate::style::expression::{ComparisonLiteral, ExpressionComparisonOp, LegacyFilterExpression};
+use crate::style::glyphs::FontStack;
 use crate::style::raster::RasterLayer;
-use crate::style::util::interpolate;
+use crate::style::util::{interpolate, interpolate_stops, interpolate_stops_at};
+
+/// Properties which were present in a style but are not supported by this renderer.
+pub type UnsupportedProperties = HashMap<String, serde_json::Value>;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
@@ -19,44 +25,278 @@ pub enum InterpolatedQuantity<T> {
     }
 }
 
+/// The input of a stop of a [`PropertyFunction`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(untagged)]
+pub enum PropertyStop {
+    /// The value of the feature property
+    Value(f64),
+    /// The zoom level and the value of the feature property
+    ZoomAndValue { zoom: f64, value: f64 },
+}
+
+/// How a [`PropertyFunction`] maps the value of a property to its output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FunctionType {
+    /// Interpolates between the stops
+    #[default]
+    #[serde(rename = "exponential")]
+    Exponential,
+    /// Uses the value of the property as output
+    #[serde(rename = "identity")]
+    Identity,
+}
+
+/// A quantity which is computed for each feature from one of its properties. If the stops contain
+/// zoom levels, the result is additionally interpolated over the zoom level.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PropertyFunction {
+    pub property: String,
+    #[serde(rename = "type", default)]
+    pub function_type: FunctionType,
+    #[serde(default = "default_base")]
+    pub base: f32,
+    #[serde(default)]
+    pub stops: Vec<(PropertyStop, f32)>,
+    /// Used for features which do not have a numeric value for `property`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<f32>,
+}
+
+fn default_base() -> f32 {
+    1.0
+}
+
+impl PropertyFunction {
+    pub fn evaluate(
+        &self,
+        zoom: impl Into<Zoom>,
+        properties: &HashMap<String, ComparisonLiteral>,
+    ) -> Option<f32> {
+        self.evaluate_value(zoom, self.value(properties))
+    }
+
+    /// Reads the numeric value of `property` from the `properties` of a feature.
+    pub fn value(&self, properties: &HashMap<String, ComparisonLiteral>) -> Option<f64> {
+        match properties.get(&self.property) {
+            Some(ComparisonLiteral::Integer(value)) => Some(*value as f64),
+            Some(ComparisonLiteral::Float(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Evaluates the function for a feature with the `value` of `property`, see
+    /// [`PropertyFunction::value`]. Features without a value get the default.
+    pub fn evaluate_value(&self, zoom: impl Into<Zoom>, value: Option<f64>) -> Option<f32> {
+        let Some(value) = value else {
+            return self.default;
+        };
+        if self.function_type == FunctionType::Identity {
+            return Some(value as f32);
+        }
+
+        let lerp = |a: &f32, b: &f32, t: f32| *a + (*b - *a) * t;
+
+        let is_zoom_dependent = self
+            .stops
+            .iter()
+            .any(|(stop, _)| matches!(stop, PropertyStop::ZoomAndValue { .. }));
+
+        if !is_zoom_dependent {
+            let stops: Vec<_> = self
+                .stops
+                .iter()
+                .filter_map(|(stop, output)| match stop {
+                    PropertyStop::Value(input) => Some((*input, *output)),
+                    PropertyStop::ZoomAndValue { .. } => None,
+                })
+                .collect();
+            return interpolate_stops_at(&stops, self.base, value, lerp);
+        }
+
+        // Stops are ordered by zoom level, so stops of the same zoom level are adjacent
+        let mut zoom_stops: Vec<(f64, Vec<(f64, f32)>)> = Vec::new();
+        for (stop, output) in &self.stops {
+            let PropertyStop::ZoomAndValue { zoom, value } = stop else {
+                continue;
+            };
+            match zoom_stops.last_mut() {
+                Some((last_zoom, stops)) if last_zoom == zoom => stops.push((*value, *output)),
+                _ => zoom_stops.push((*zoom, vec![(*value, *output)])),
+            }
+        }
+
+        let outputs: Vec<(f64, f32)> = zoom_stops
+            .iter()
+            .filter_map(|(zoom, stops)| {
+                interpolate_stops_at(stops, self.base, value, lerp).map(|output| (*zoom, output))
+            })
+            .collect();
+        interpolate_stops(&outputs, self.base, zoom, lerp)
+    }
+}
+
+/// A quantity which depends on the zoom level and optionally on a property of each feature.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum DataDrivenQuantity {
+    Property(PropertyFunction),
+    Zoom(InterpolatedQuantity<f32>),
+}
+
+impl From<InterpolatedQuantity<f32>> for DataDrivenQuantity {
+    fn from(quantity: InterpolatedQuantity<f32>) -> Self {
+        DataDrivenQuantity::Zoom(quantity)
+    }
+}
+
+impl DataDrivenQuantity {
+    /// Evaluates the quantity for a feature with `properties`. Quantities which only depend on the
+    /// zoom level ignore the properties.
+    pub fn evaluate(
+        &self,
+        zoom: impl Into<Zoom>,
+        properties: &HashMap<String, ComparisonLiteral>,
+    ) -> Option<f32> {
+        match self {
+            DataDrivenQuantity::Property(function) => function.evaluate(zoom, properties),
+            DataDrivenQuantity::Zoom(quantity) => interpolate(quantity, zoom),
+        }
+    }
+
+    /// Reads the value of the feature property which the quantity depends on, see
+    /// [`DataDrivenQuantity::evaluate_value`]. Quantities which only depend on the zoom level
+    /// have no value.
+    pub fn property_value(&self, properties: &HashMap<String, ComparisonLiteral>) -> Option<f64> {
+        match self {
+            DataDrivenQuantity::Property(function) => function.value(properties),
+            DataDrivenQuantity::Zoom(_) => None,
+        }
+    }
+
+    /// Evaluates the quantity for a feature with the `value` of its property, see
+    /// [`DataDrivenQuantity::property_value`]. Unlike [`DataDrivenQuantity::evaluate`], the
+    /// properties of the feature are not needed, which allows evaluating the quantity at any
+    /// zoom after the tile has been tessellated.
+    pub fn evaluate_value(&self, zoom: impl Into<Zoom>, value: Option<f64>) -> Option<f32> {
+        match self {
+            DataDrivenQuantity::Property(function) => function.evaluate_value(zoom, value),
+            DataDrivenQuantity::Zoom(quantity) => interpolate(quantity, zoom),
+        }
+    }
+
+    /// Returns the feature property which the quantity depends on.
+    pub fn property(&self) -> Option<&str> {
+        match self {
+            DataDrivenQuantity::Property(function) => Some(&function.property),
+            DataDrivenQuantity::Zoom(_) => None,
+        }
+    }
+}
+
+/// The frame of reference of a `*-translate` offset.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranslateAnchor {
+    /// The offset is relative to the map and rotates with it.
+    #[default]
+    #[serde(rename = "map")]
+    Map,
+    /// The offset is relative to the screen.
+    #[serde(rename = "viewport")]
+    Viewport,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BackgroundPaint {
     #[serde(rename = "background-color")]
+    #[serde(default, deserialize_with = "deserialize_background_color")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub background_color: Option<Color>,
+    pub background_color: Option<InterpolatedColor>,
     #[serde(rename = "background-opacity")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub background_opacity: Option<InterpolatedQuantity<f32>>,
     // TODO a lot
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FillPaint {
     #[serde(rename = "fill-color")]
+    #[serde(default, deserialize_with = "deserialize_fill_color")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub fill_color: Option<Color>,
+    pub fill_color: Option<InterpolatedColor>,
     #[serde(rename = "fill-opacity")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub fill_opacity: Option<InterpolatedQuantity<f32>>,
+    pub fill_opacity: Option<DataDrivenQuantity>,
+    #[serde(rename = "fill-translate")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_translate: Option<[f32; 2]>,
+    #[serde(rename = "fill-translate-anchor")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_translate_anchor: Option<TranslateAnchor>,
     // TODO a lot
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinePaint {
     #[serde(rename = "line-color")]
+    #[serde(default, deserialize_with = "deserialize_line_color")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub line_color: Option<Color>,
+    pub line_color: Option<InterpolatedColor>,
     #[serde(rename = "line-opacity")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub line_opacity: Option<InterpolatedQuantity<f32>>,
+    pub line_opacity: Option<DataDrivenQuantity>,
     #[serde(rename = "line-width")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub line_width: Option<InterpolatedQuantity<f32>>,
+    pub line_width: Option<DataDrivenQuantity>,
+    /// Draws a line casing outside of the actual path. The value is the width of the inner gap.
+    #[serde(rename = "line-gap-width")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_gap_width: Option<InterpolatedQuantity<f32>>,
+    /// Fades out the edges of the line over this width in pixels, e.g. for glowing lines.
+    #[serde(rename = "line-blur")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_blur: Option<InterpolatedQuantity<f32>>,
+    #[serde(rename = "line-translate")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_translate: Option<[f32; 2]>,
+    #[serde(rename = "line-translate-anchor")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_translate_anchor: Option<TranslateAnchor>,
     // TODO a lot
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
 }
 
-/// The different types of paints.
+/// The paint of 3D buildings, which are extruded from polygon footprints.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FillExtrusionPaint {
+    #[serde(rename = "fill-extrusion-color")]
+    #[serde(default, deserialize_with = "deserialize_fill_extrusion_color")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_extrusion_color: Option<InterpolatedColor>,
+    /// The height of the roof above the ground, usually read from a property of the feature.
+    #[serde(rename = "fill-extrusion-height")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_extrusion_height: Option<DataDrivenQuantity>,
+    /// The height at which the walls start, e.g. for parts of buildings which overhang others.
+    #[serde(rename = "fill-extrusion-base")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_extrusion_base: Option<DataDrivenQuantity>,
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
+}
+
+/// The different types of paints. Deserialized from the `type` and `paint` of a layer, see
+/// [`LayerPaintError`] for the errors.
+#[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "paint")]
 pub enum LayerPaint {
     #[serde(rename = "background")]
@@ -67,33 +307,254 @@ pub enum LayerPaint {
     Fill(FillPaint),
     #[serde(rename = "raster")]
     Raster(RasterLayer),
+    #[serde(rename = "fill-extrusion")]
+    FillExtrusion(FillExtrusionPaint),
 }
 
-fn cint_color_from_css_color_and_opacity(css_color: &Option<Color>, opacity: &Option<InterpolatedQuantity<f32>>, zoom_level: ZoomLevel) -> Option<Alpha<EncodedSrgb<f32>>> {
-    let color: Option<Alpha<EncodedSrgb<f32>>> = css_color
-        .as_ref()
-        .map(|color| color.clone().into());
+/// Errors which occur while deserializing a [`LayerPaint`]. The id of the layer is included if
+/// it is known.
+#[derive(Error, Debug)]
+pub enum LayerPaintError {
+    #[error("{}missing layer type", layer_prefix(.id))]
+    MissingType { id: Option<String> },
+    #[error("{}unsupported layer type: {layer_type}", layer_prefix(.id))]
+    UnsupportedType {
+        id: Option<String>,
+        layer_type: String,
+    },
+    #[error("{}invalid {layer_type} paint: {source}", layer_prefix(.id))]
+    InvalidPaint {
+        id: Option<String>,
+        layer_type: String,
+        source: serde_json::Error,
+    },
+}
+
+pub type LayerPaintResult<T> = Result<T, LayerPaintError>;
+
+fn layer_prefix(id: &Option<String>) -> String {
+    id.as_ref()
+        .map(|id| format!("layer {id}: "))
+        .unwrap_or_default()
+}
+
+/// The fields of a layer which describe its [`LayerPaint`].
+#[derive(Deserialize)]
+struct RawLayerPaint {
+    id: Option<String>,
+    #[serde(rename = "type")]
+    layer_type: Option<String>,
+    /// A missing paint is the same as an empty one
+    #[serde(default)]
+    paint: Option<serde_json::Value>,
+}
+
+impl TryFrom<RawLayerPaint> for LayerPaint {
+    type Error = LayerPaintError;
 
-    color.map(|mut c| {
-        if let Some(interpolant) = opacity {
-            if let Some(alpha) = interpolate(interpolant, zoom_level) {
-                c.alpha = alpha;
+    fn try_from(RawLayerPaint { id, layer_type, paint }: RawLayerPaint) -> LayerPaintResult<Self> {
+        let Some(layer_type) = layer_type else {
+            return Err(LayerPaintError::MissingType { id });
+        };
+        let paint = paint.unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+
+        let paint = match layer_type.as_str() {
+            "background" => deserialize_paint(&id, paint, LayerPaint::Background),
+            "line" => deserialize_paint(&id, paint, LayerPaint::Line),
+            "fill" => deserialize_paint(&id, paint, LayerPaint::Fill),
+            "raster" => deserialize_paint(&id, paint, LayerPaint::Raster),
+            "fill-extrusion" => deserialize_paint(&id, paint, LayerPaint::FillExtrusion),
+            _ => return Err(LayerPaintError::UnsupportedType { id, layer_type }),
+        };
+
+        paint.map_err(|source| LayerPaintError::InvalidPaint {
+            id,
+            layer_type,
+            source,
+        })
+    }
+}
+
+/// Deserializes the `paint` of a layer into `variant`. Properties whose values are not
+/// supported, e.g. expressions like `["interpolate", ...]`, are ignored with a warning and kept
+/// as unsupported properties, instead of failing the whole style. Only invalid colors fail the
+/// paint, to report the color string, see [`deserialize_color`].
+fn deserialize_paint<T: DeserializeOwned>(
+    id: &Option<String>,
+    mut paint: serde_json::Value,
+    variant: fn(T) -> LayerPaint,
+) -> Result<LayerPaint, serde_json::Error> {
+    let mut unsupported = UnsupportedProperties::new();
+
+    if let serde_json::Value::Object(properties) = &mut paint {
+        properties.retain(|property, value| {
+            let is_invalid_color =
+                property.ends_with("-color") && find_invalid_color(value).is_some();
+            let single = serde_json::json!({ property.as_str(): value });
+            if is_invalid_color || serde_json::from_value::<T>(single).is_ok() {
+                return true;
             }
-        }
-        
-        c
-    })
+            log::warn!("{}ignoring unsupported value of {property}: {value}", layer_prefix(id));
+            unsupported.insert(property.clone(), value.take());
+            false
+        });
+    }
+
+    let mut paint = variant(serde_json::from_value(paint)?);
+    paint.unsupported_mut().extend(unsupported);
+    Ok(paint)
+}
+
+impl<'de> Deserialize<'de> for LayerPaint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        LayerPaint::try_from(RawLayerPaint::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+fn deserialize_background_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<InterpolatedColor>, D::Error> {
+    deserialize_color(deserializer, "background-color")
+}
+
+fn deserialize_fill_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<InterpolatedColor>, D::Error> {
+    deserialize_color(deserializer, "fill-color")
+}
+
+fn deserialize_line_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<InterpolatedColor>, D::Error> {
+    deserialize_color(deserializer, "line-color")
+}
+
+fn deserialize_fill_extrusion_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<InterpolatedColor>, D::Error> {
+    deserialize_color(deserializer, "fill-extrusion-color")
+}
+
+fn cint_color_from_css_color(css_color: &Option<InterpolatedColor>, zoom: impl Into<Zoom>) -> Option<Alpha<EncodedSrgb<f32>>> {
+    css_color
+        .as_ref()
+        .and_then(|color| color.evaluate_srgb(zoom))
 }
 
 impl LayerPaint {
-    pub fn get_color(&self, zoom_level: ZoomLevel) -> Option<Alpha<EncodedSrgb<f32>>> {
+    /// Returns the color of the layer including its own alpha. The opacity of the layer is not
+    /// applied, see [`LayerPaint::get_opacity`].
+    pub fn get_color(&self, zoom: impl Into<Zoom>) -> Option<Alpha<EncodedSrgb<f32>>> {
         match self {
-            LayerPaint::Background(paint) => cint_color_from_css_color_and_opacity(&paint.background_color, &paint.background_opacity, zoom_level),
-            LayerPaint::Line(paint) => cint_color_from_css_color_and_opacity(&paint.line_color, &paint.line_opacity, zoom_level),
-            LayerPaint::Fill(paint) => cint_color_from_css_color_and_opacity(&paint.fill_color, &paint.fill_opacity, zoom_level),
+            LayerPaint::Background(paint) => cint_color_from_css_color(&paint.background_color, zoom),
+            LayerPaint::Line(paint) => cint_color_from_css_color(&paint.line_color, zoom),
+            LayerPaint::Fill(paint) => cint_color_from_css_color(&paint.fill_color, zoom),
+            LayerPaint::FillExtrusion(paint) => {
+                cint_color_from_css_color(&paint.fill_extrusion_color, zoom)
+            }
             LayerPaint::Raster(_) => None,
         }
     }
+
+    /// Returns the opacity of the layer, which is multiplied with the alpha of the color when
+    /// rendering. Defaults to fully opaque. Opacities which depend on feature properties yield
+    /// their default here, see [`LayerPaint::get_feature_opacity`].
+    pub fn get_opacity(&self, zoom: impl Into<Zoom>) -> f32 {
+        let opacity = match self {
+            LayerPaint::Background(paint) => paint
+                .background_opacity
+                .as_ref()
+                .and_then(|opacity| interpolate(opacity, zoom)),
+            LayerPaint::Line(paint) => paint
+                .line_opacity
+                .as_ref()
+                .and_then(|opacity| opacity.evaluate(zoom, &Default::default())),
+            LayerPaint::Fill(paint) => paint
+                .fill_opacity
+                .as_ref()
+                .and_then(|opacity| opacity.evaluate(zoom, &Default::default())),
+            LayerPaint::Raster(paint) => paint
+                .raster_opacity
+                .as_ref()
+                .and_then(|opacity| interpolate(opacity, zoom)),
+            LayerPaint::FillExtrusion(_) => None,
+        };
+
+        opacity.unwrap_or(1.0)
+    }
+
+    /// Returns the opacity of line and fill layers if it is computed for each feature from one of
+    /// its properties.
+    pub fn get_feature_opacity(&self) -> Option<&DataDrivenQuantity> {
+        let opacity = match self {
+            LayerPaint::Line(paint) => paint.line_opacity.as_ref(),
+            LayerPaint::Fill(paint) => paint.fill_opacity.as_ref(),
+            LayerPaint::Background(_) | LayerPaint::Raster(_) | LayerPaint::FillExtrusion(_) => None,
+        };
+
+        opacity.filter(|opacity| opacity.property().is_some())
+    }
+
+    /// Returns the pixel offset of the layer and its anchor.
+    pub fn get_translate(&self) -> Option<([f32; 2], TranslateAnchor)> {
+        let (translate, anchor) = match self {
+            LayerPaint::Line(paint) => (paint.line_translate, paint.line_translate_anchor),
+            LayerPaint::Fill(paint) => (paint.fill_translate, paint.fill_translate_anchor),
+            LayerPaint::Background(_) | LayerPaint::Raster(_) | LayerPaint::FillExtrusion(_) => {
+                return None
+            }
+        };
+
+        translate.map(|translate| (translate, anchor.unwrap_or_default()))
+    }
+
+    fn unsupported(&self) -> &UnsupportedProperties {
+        match self {
+            LayerPaint::Background(paint) => &paint.unsupported,
+            LayerPaint::Line(paint) => &paint.unsupported,
+            LayerPaint::Fill(paint) => &paint.unsupported,
+            LayerPaint::Raster(paint) => &paint.unsupported,
+            LayerPaint::FillExtrusion(paint) => &paint.unsupported,
+        }
+    }
+
+    fn unsupported_mut(&mut self) -> &mut UnsupportedProperties {
+        match self {
+            LayerPaint::Background(paint) => &mut paint.unsupported,
+            LayerPaint::Line(paint) => &mut paint.unsupported,
+            LayerPaint::Fill(paint) => &mut paint.unsupported,
+            LayerPaint::Raster(paint) => &mut paint.unsupported,
+            LayerPaint::FillExtrusion(paint) => &mut paint.unsupported,
+        }
+    }
+}
+
+/// Whether a layer is rendered or hidden.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+    #[default]
+    #[serde(rename = "visible")]
+    Visible,
+    #[serde(rename = "none")]
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LayerLayout {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// Features with a lower sort key are drawn first. Features without a sort key keep their
+    /// order and are drawn before all others.
+    #[serde(rename = "symbol-sort-key")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol_sort_key: Option<DataDrivenQuantity>,
+    /// The fonts which are used to render the text of symbols.
+    #[serde(rename = "text-font")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_font: Option<FontStack>,
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
 }
 
 /// Stores all the styles for a specific layer.
@@ -102,8 +563,6 @@ pub struct StyleLayer {
     #[serde(skip)]
     pub index: u32,
     pub id: String,
-    // TODO filter
-    // TODO layout
     #[serde(skip_serializing_if = "Option::is_none")]
     pub maxzoom: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -111,7 +570,7 @@ pub struct StyleLayer {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(flatten)]
+    #[serde(flatten, deserialize_with = "deserialize_layer_paint")]
     pub paint: Option<LayerPaint>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
@@ -119,6 +578,166 @@ pub struct StyleLayer {
     pub source_layer: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<LegacyFilterExpression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayerLayout>,
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten, deserialize_with = "deserialize_unsupported_layer_properties")]
+    pub unsupported: UnsupportedProperties,
+}
+
+/// Layer types which are described by a [`LayerPaint`].
+const SUPPORTED_LAYER_TYPES: [&str; 5] = ["background", "line", "fill", "raster", "fill-extrusion"];
+
+fn is_supported_layer_type(layer_type: Option<&serde_json::Value>) -> bool {
+    layer_type
+        .and_then(|layer_type| layer_type.as_str())
+        .is_some_and(|layer_type| SUPPORTED_LAYER_TYPES.contains(&layer_type))
+}
+
+/// Deserializes the paint of a layer. In contrast to a flattened `Option`, invalid colors in the
+/// paint of supported layer types are reported instead of silently dropping the paint.
+fn deserialize_layer_paint<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<LayerPaint>, D::Error> {
+    let mut properties = serde_json::Map::deserialize(deserializer)?;
+
+    if !is_supported_layer_type(properties.get("type")) {
+        return Ok(None);
+    }
+    let (Some(layer_type), Some(paint)) = (properties.remove("type"), properties.remove("paint"))
+    else {
+        return Ok(None);
+    };
+
+    LayerPaint::deserialize(serde_json::json!({ "type": layer_type, "paint": paint }))
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+/// Deserializes the remaining properties of a layer. The type and the paint of supported layers
+/// are already part of [`StyleLayer::paint`], unsupported types are kept to report them.
+fn deserialize_unsupported_layer_properties<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<UnsupportedProperties, D::Error> {
+    let mut unsupported = UnsupportedProperties::deserialize(deserializer)?;
+
+    if is_supported_layer_type(unsupported.get("type")) {
+        unsupported.remove("paint");
+        unsupported.remove("type");
+    }
+
+    Ok(unsupported)
+}
+
+impl StyleLayer {
+    /// Layers are visible unless their layout sets `visibility` to `none`.
+    pub fn is_visible(&self) -> bool {
+        self.layout
+            .as_ref()
+            .and_then(|layout| layout.visibility)
+            .unwrap_or_default()
+            == Visibility::Visible
+    }
+
+    pub fn is_background(&self) -> bool {
+        matches!(self.paint, Some(LayerPaint::Background(_)))
+    }
+
+    /// The feature properties which the filter and the data-driven paint and layout properties
+    /// of the layer read. Keys which start with `$`, like `$type`, refer to the feature itself
+    /// rather than to one of its properties.
+    pub fn referenced_properties(&self) -> HashSet<String> {
+        let mut keys = self
+            .filter
+            .as_ref()
+            .map(LegacyFilterExpression::referenced_keys)
+            .unwrap_or_default();
+
+        let paint_quantities = match &self.paint {
+            Some(LayerPaint::Line(paint)) => vec![&paint.line_width, &paint.line_opacity],
+            Some(LayerPaint::Fill(paint)) => vec![&paint.fill_opacity],
+            Some(LayerPaint::FillExtrusion(paint)) => {
+                vec![&paint.fill_extrusion_height, &paint.fill_extrusion_base]
+            }
+            Some(LayerPaint::Background(_) | LayerPaint::Raster(_)) | None => vec![],
+        };
+        let layout_quantities = self.layout.as_ref().map(|layout| &layout.symbol_sort_key);
+
+        keys.extend(
+            paint_quantities
+                .into_iter()
+                .chain(layout_quantities)
+                .flatten()
+                .filter_map(DataDrivenQuantity::property)
+                .map(str::to_string),
+        );
+        keys
+    }
+
+    /// A stable hash of the content of the layer, like [`Style::fingerprint`](crate::style::Style::fingerprint).
+    /// The position of the layer within its style is not part of it.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(self)
+    }
+
+    /// The filter of the layer combined with a predicate on the `$zoom` of the tile, which holds
+    /// within `minzoom` (inclusive) and `maxzoom` (exclusive). This allows evaluating both in one
+    /// pass. Returns the plain filter if the layer has no zoom range.
+    pub fn filter_with_zoom(&self) -> Option<LegacyFilterExpression> {
+        let zoom_range = [
+            (ExpressionComparisonOp::Geq, self.minzoom),
+            (ExpressionComparisonOp::Lt, self.maxzoom),
+        ]
+        .into_iter()
+        .filter_map(|(op, zoom)| {
+            let zoom = ComparisonLiteral::Integer(zoom? as isize);
+            Some(LegacyFilterExpression::Comparison(op, "$zoom".to_string(), zoom))
+        });
+
+        let filters: Vec<_> = self.filter.clone().into_iter().chain(zoom_range).collect();
+        match filters.len() {
+            0 => None,
+            1 => filters.into_iter().next(),
+            _ => Some(LegacyFilterExpression::All(filters)),
+        }
+    }
+
+    /// Describes all properties of this layer which are ignored because they are not supported.
+    pub fn warnings(&self) -> Vec<String> {
+        let id = &self.id;
+        let mut warnings = Vec::new();
+
+        for (key, value) in &self.unsupported {
+            match (key.as_str(), value) {
+                ("type", serde_json::Value::String(layer_type)) => warnings.push(
+                    LayerPaintError::UnsupportedType {
+                        id: Some(id.clone()),
+                        layer_type: layer_type.clone(),
+                    }
+                    .to_string(),
+                ),
+                // The whole paint or layout is unsupported, e.g. for symbol layers
+                ("paint" | "layout", serde_json::Value::Object(properties)) => {
+                    warnings.extend(properties.keys().map(|property| {
+                        format!("layer {id}: unsupported {key} property {property}")
+                    }))
+                }
+                _ => warnings.push(format!("layer {id}: unsupported property {key}")),
+            }
+        }
+
+        let paint = self.paint.as_ref().map(|paint| ("paint", paint.unsupported()));
+        let layout = self.layout.as_ref().map(|layout| ("layout", &layout.unsupported));
+        for (kind, unsupported) in paint.into_iter().chain(layout) {
+            warnings.extend(
+                unsupported
+                    .keys()
+                    .map(|property| format!("layer {id}: unsupported {kind} property {property}")),
+            );
+        }
+
+        warnings
+    }
 }
 
 impl Default for StyleLayer {
@@ -129,10 +748,217 @@ impl Default for StyleLayer {
             maxzoom: None,
             minzoom: None,
             filter: None,
+            layout: None,
             metadata: None,
             paint: None,
             source: None,
             source_layer: Some("does not exist".to_string()),
+            unsupported: Default::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        coords::{Zoom, ZoomLevel},
+        style::{
+            expression::ComparisonLiteral,
+            layer::{DataDrivenQuantity, LayerPaint, StyleLayer},
+        },
+    };
+
+    #[test]
+    fn test_filter_with_zoom() {
+        let layer: StyleLayer = serde_json::from_str(
+            r#"{"id": "roads", "type": "line", "minzoom": 10, "maxzoom": 14, "filter": ["==", "class", "primary"]}"#,
+        )
+        .unwrap();
+        let filter = layer.filter_with_zoom().unwrap();
+
+        let properties = |zoom: isize, class: &str| {
+            HashMap::from([
+                ("$zoom".to_string(), ComparisonLiteral::Integer(zoom)),
+                ("class".to_string(), ComparisonLiteral::String(class.to_string())),
+            ])
+        };
+        assert!(filter.evaluate(&properties(10, "primary")));
+        assert!(filter.evaluate(&properties(13, "primary")));
+        assert!(!filter.evaluate(&properties(9, "primary")));
+        assert!(!filter.evaluate(&properties(14, "primary")));
+        assert!(!filter.evaluate(&properties(12, "minor")));
+
+        // Layers without a zoom range keep their filter
+        let layer = StyleLayer {
+            minzoom: None,
+            maxzoom: None,
+            ..layer
+        };
+        assert_eq!(
+            layer.filter_with_zoom().map(|filter| format!("{filter:?}")),
+            layer.filter.as_ref().map(|filter| format!("{filter:?}"))
+        );
+        assert!(StyleLayer::default().filter_with_zoom().is_none());
+    }
+
+    fn rank(rank: isize) -> HashMap<String, ComparisonLiteral> {
+        HashMap::from([("rank".to_string(), ComparisonLiteral::Integer(rank))])
+    }
+
+    #[test]
+    fn test_property_function() {
+        let width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "stops": [[1, 1], [5, 9]], "default": 3}"#)
+                .unwrap();
+        assert_eq!(width.property(), Some("rank"));
+
+        let zoom = ZoomLevel::new(10);
+        assert_eq!(width.evaluate(zoom, &rank(1)), Some(1.0));
+        assert_eq!(width.evaluate(zoom, &rank(3)), Some(5.0));
+        assert_eq!(width.evaluate(zoom, &rank(8)), Some(9.0));
+        assert_eq!(width.evaluate(zoom, &HashMap::new()), Some(3.0));
+    }
+
+    #[test]
+    fn test_zoom_and_property_function() {
+        let width: DataDrivenQuantity = serde_json::from_str(
+            r#"{
+                "property": "rank",
+                "stops": [
+                    [{"zoom": 0, "value": 1}, 0],
+                    [{"zoom": 0, "value": 5}, 4],
+                    [{"zoom": 10, "value": 1}, 10],
+                    [{"zoom": 10, "value": 5}, 20]
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(width.evaluate(ZoomLevel::new(0), &rank(3)), Some(2.0));
+        assert_eq!(width.evaluate(ZoomLevel::new(10), &rank(3)), Some(15.0));
+        assert_eq!(width.evaluate(ZoomLevel::new(5), &rank(3)), Some(8.5));
+    }
+
+    #[test]
+    fn test_evaluate_property_value() {
+        let width: DataDrivenQuantity = serde_json::from_str(
+            r#"{
+                "property": "rank",
+                "stops": [[{"zoom": 0, "value": 1}, 0], [{"zoom": 10, "value": 1}, 10]],
+                "default": 3
+            }"#,
+        )
+        .unwrap();
+
+        // The value is read once and evaluated at any zoom later on
+        let value = width.property_value(&rank(1));
+        assert_eq!(value, Some(1.0));
+        assert_eq!(width.evaluate_value(Zoom::new(5.5), value), Some(5.5));
+        assert_eq!(
+            width.evaluate_value(ZoomLevel::new(10), value),
+            width.evaluate(ZoomLevel::new(10), &rank(1))
+        );
+        assert_eq!(width.property_value(&HashMap::new()), None);
+        assert_eq!(width.evaluate_value(Zoom::new(5.5), None), Some(3.0));
+
+        let width: DataDrivenQuantity = serde_json::from_str("2").unwrap();
+        assert_eq!(width.property_value(&rank(1)), None);
+        assert_eq!(width.evaluate_value(Zoom::new(5.5), None), Some(2.0));
+    }
+
+    #[test]
+    fn test_identity_function() {
+        let sort_key: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "type": "identity"}"#).unwrap();
+
+        assert_eq!(sort_key.evaluate(ZoomLevel::new(10), &rank(7)), Some(7.0));
+        assert_eq!(sort_key.evaluate(ZoomLevel::new(10), &HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_zoom_quantity_ignores_properties() {
+        let width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"base": 1, "stops": [[0, 0], [10, 2]]}"#).unwrap();
+
+        assert_eq!(width.property(), None);
+        assert_eq!(width.evaluate(ZoomLevel::new(5), &rank(3)), Some(1.0));
+    }
+
+    #[test]
+    fn test_fractional_zoom() {
+        let width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"base": 1, "stops": [[10, 2], [11, 4]]}"#).unwrap();
+        assert_eq!(width.evaluate(ZoomLevel::new(10), &HashMap::new()), Some(2.0));
+        assert_eq!(width.evaluate(Zoom::new(10.5), &HashMap::new()), Some(3.0));
+
+        let paint: LayerPaint = serde_json::from_str(
+            r##"{"type": "fill", "paint": {
+                "fill-color": {"base": 1, "stops": [[10, "#000000"], [11, "#ffffff"]]},
+                "fill-opacity": {"base": 1, "stops": [[10, 0], [11, 1]]}
+            }}"##,
+        )
+        .unwrap();
+        assert_eq!(paint.get_opacity(ZoomLevel::new(10)), 0.0);
+        assert_eq!(paint.get_opacity(Zoom::new(10.5)), 0.5);
+
+        let color = paint.get_color(Zoom::new(10.5)).unwrap();
+        assert!((color.color.r - 0.5).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_fill_extrusion_paint() {
+        let layer: StyleLayer = serde_json::from_str(
+            r##"{"id": "buildings", "type": "fill-extrusion", "source-layer": "building", "paint": {
+                "fill-extrusion-color": "#aaaaaa",
+                "fill-extrusion-height": {"property": "render_height", "type": "identity"},
+                "fill-extrusion-base": {"property": "render_min_height", "type": "identity"}
+            }}"##,
+        )
+        .unwrap();
+        assert!(layer.warnings().is_empty());
+
+        let Some(LayerPaint::FillExtrusion(paint)) = &layer.paint else {
+            panic!("expected a fill-extrusion paint");
+        };
+        let height = paint.fill_extrusion_height.as_ref().unwrap();
+        assert_eq!(height.property(), Some("render_height"));
+        assert_eq!(
+            paint.fill_extrusion_base.as_ref().unwrap().property(),
+            Some("render_min_height")
+        );
+
+        let properties = HashMap::from([(
+            "render_height".to_string(),
+            ComparisonLiteral::Integer(10),
+        )]);
+        assert_eq!(height.evaluate(ZoomLevel::new(15), &properties), Some(10.0));
+        assert!(layer.paint.as_ref().unwrap().get_color(ZoomLevel::new(15)).is_some());
+    }
+
+    #[test]
+    fn test_unsupported_layer_type() {
+        let error = serde_json::from_str::<LayerPaint>(
+            r#"{"id": "poi", "type": "symbol", "paint": {"text-color": "red"}}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("layer poi: unsupported layer type: symbol"), "{error}");
+
+        let error = serde_json::from_str::<LayerPaint>(r#"{"paint": {}}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("missing layer type"), "{error}");
+
+        // Symbol layers are skipped by styles and reported as warning
+        let layer: StyleLayer =
+            serde_json::from_str(r#"{"id": "poi", "type": "symbol", "source-layer": "poi"}"#)
+                .unwrap();
+        assert!(layer.paint.is_none());
+        assert_eq!(
+            layer.warnings(),
+            vec!["layer poi: unsupported layer type: symbol".to_string()]
+        );
+    }
+}
@@ -3,9 +3,13 @@
 pub use cint::*;
 pub use style::*;
 
+pub mod color;
+pub mod fingerprint;
+pub mod glyphs;
 pub mod layer;
 pub mod raster;
 pub mod source;
 mod style;
+pub mod url;
 pub mod util;
 pub mod expression;
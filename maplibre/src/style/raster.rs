@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::style::layer::{InterpolatedQuantity, UnsupportedProperties};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum RasterResampling {
     #[serde(rename = "linear")]
@@ -29,13 +31,16 @@ pub struct RasterLayer {
     pub raster_hue_rotate: Option<f32>,
     #[serde(rename = "raster-opacity")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub raster_opacity: Option<f32>,
+    pub raster_opacity: Option<InterpolatedQuantity<f32>>,
     #[serde(rename = "raster-resampling")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raster_resampling: Option<RasterResampling>,
     #[serde(rename = "raster-saturation")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raster_saturation: Option<f32>,
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
 }
 
 impl Default for RasterLayer {
@@ -46,9 +51,10 @@ impl Default for RasterLayer {
             raster_contrast: Some(0.0),
             raster_fade_duration: Some(0),
             raster_hue_rotate: Some(0.0),
-            raster_opacity: Some(1.0),
+            raster_opacity: Some(InterpolatedQuantity::Fixed(1.0)),
             raster_resampling: Some(RasterResampling::Linear),
             raster_saturation: Some(0.0),
+            unsupported: Default::default(),
         }
     }
 }
@@ -1,6 +1,8 @@
 //! Vector tile data utilities.
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::style::url::resolve_url;
 
 /// String url to a tile.
 pub type TileUrl = String;
@@ -45,15 +47,174 @@ pub struct VectorSource {
     /// Array of URLs which can contain place holders like {x}, {y}, {z}.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tiles: Option<TileUrl>,
-    // url: Option<TileJSONUrl>,
-    // TODO volatile
+    /// URL of a TileJSON which describes the tiles, used if `tiles` is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<TileJSONUrl>,
+    /// Tiles of volatile sources change frequently, e.g. live traffic, and are never cached.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub volatile: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum Source {
     #[serde(rename = "vector")]
     Vector(VectorSource),
     #[serde(rename = "raster")]
     Raster(VectorSource), // FIXME: Does it make sense that a raster have a VectorSource?
+    /// A source without a `type`, whose type is determined by probing the TileJSON at its
+    /// `url`, see [`Source::probe`].
+    #[serde(untagged)]
+    Untyped(VectorSource),
+}
+
+/// The sources which declare a `type`. Unlike [`Source`], it has no untagged fallback, such that
+/// unsupported types like `raster-dem` fail to deserialize.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum TypedSource {
+    #[serde(rename = "vector")]
+    Vector(VectorSource),
+    #[serde(rename = "raster")]
+    Raster(VectorSource),
+}
+
+impl<'de> Deserialize<'de> for Source {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        if value.get("type").is_none() {
+            return VectorSource::deserialize(value)
+                .map(Source::Untyped)
+                .map_err(de::Error::custom);
+        }
+
+        match TypedSource::deserialize(value).map_err(de::Error::custom)? {
+            TypedSource::Vector(source) => Ok(Source::Vector(source)),
+            TypedSource::Raster(source) => Ok(Source::Raster(source)),
+        }
+    }
+}
+
+impl Source {
+    /// Determines the type of an [`Source::Untyped`] source from the TileJSON at its `url`. The
+    /// tiles and zoom levels of the TileJSON are used unless the source declares its own. Typed
+    /// sources are returned unchanged.
+    pub fn probe(self, tilejson: &TileJson) -> Source {
+        let Source::Untyped(mut source) = self else {
+            return self;
+        };
+
+        if source.tiles.is_none() {
+            source.tiles = tilejson.tiles.first().map(|tiles| match &source.url {
+                Some(url) => resolve_url(url, tiles),
+                None => tiles.clone(),
+            });
+        }
+        source.minzoom = source.minzoom.or(tilejson.minzoom);
+        source.maxzoom = source.maxzoom.or(tilejson.maxzoom);
+
+        if tilejson.is_vector() {
+            Source::Vector(source)
+        } else {
+            Source::Raster(source)
+        }
+    }
+}
+
+/// The parts of a [TileJSON](https://github.com/mapbox/tilejson-spec) document which are needed
+/// to load the tiles of a source.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TileJson {
+    /// Array of URLs which can contain place holders like {x}, {y}, {z}.
+    #[serde(default)]
+    pub tiles: Vec<TileUrl>,
+    /// The file format of the tiles, like `pbf` or `png`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// The layers of vector tiles. Only vector tilesets declare them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_layers: Option<Vec<serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxzoom: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minzoom: Option<u8>,
+}
+
+impl TileJson {
+    /// Whether the TileJSON describes vector tiles. Tilesets which declare `vector_layers` or the
+    /// `pbf` or `mvt` format are vector tilesets, all others are raster tilesets.
+    pub fn is_vector(&self) -> bool {
+        self.vector_layers.is_some()
+            || self
+                .format
+                .as_deref()
+                .is_some_and(|format| matches!(format, "pbf" | "mvt"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Source, TileJson};
+
+    fn untyped(json: &str) -> Source {
+        let source: Source = serde_json::from_str(json).unwrap();
+        assert!(matches!(source, Source::Untyped(_)));
+        source
+    }
+
+    #[test]
+    fn test_probe_vector() {
+        let tilejson: TileJson = serde_json::from_str(
+            r#"{"tilejson": "3.0.0", "tiles": ["tiles/{z}/{x}/{y}.pbf"], "maxzoom": 14, "vector_layers": [{"id": "water", "fields": {}}]}"#,
+        )
+        .unwrap();
+        assert!(tilejson.is_vector());
+
+        let source = untyped(r#"{"url": "https://example.com/data/tiles.json"}"#);
+        let Source::Vector(source) = source.probe(&tilejson) else {
+            panic!("expected a vector source");
+        };
+        assert_eq!(
+            source.tiles.as_deref(),
+            Some("https://example.com/data/tiles/{z}/{x}/{y}.pbf")
+        );
+        assert_eq!(source.maxzoom, Some(14));
+    }
+
+    #[test]
+    fn test_probe_raster() {
+        let tilejson: TileJson = serde_json::from_str(
+            r#"{"tilejson": "2.2.0", "tiles": ["https://example.com/{z}/{x}/{y}.png"], "format": "png"}"#,
+        )
+        .unwrap();
+        assert!(!tilejson.is_vector());
+
+        let source = untyped(r#"{"url": "https://example.com/satellite.json", "maxzoom": 10}"#);
+        let Source::Raster(source) = source.probe(&tilejson) else {
+            panic!("expected a raster source");
+        };
+        assert_eq!(source.tiles.as_deref(), Some("https://example.com/{z}/{x}/{y}.png"));
+        assert_eq!(source.maxzoom, Some(10));
+
+        // Typed sources keep their type
+        let typed: Source = serde_json::from_str(r#"{"type": "vector"}"#).unwrap();
+        assert!(matches!(typed.probe(&tilejson), Source::Vector(_)));
+    }
+
+    #[test]
+    fn test_unsupported_type_is_not_untyped() {
+        // Sources of unsupported types must not fall back to be probed from their TileJSON
+        let result = serde_json::from_str::<Source>(
+            r#"{"type": "raster-dem", "url": "https://example.com/terrain.json"}"#,
+        );
+        assert!(result.is_err());
+
+        let result = serde_json::from_str::<Source>(r#"{"type": 42}"#);
+        assert!(result.is_err());
+    }
 }
@@ -1,14 +1,26 @@
 //! Default vector tile styles configuration.
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    str::FromStr,
+};
 
 use csscolorparser::Color;
 use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
 
-use crate::style::{
-    layer::{FillPaint, LayerPaint, LinePaint, StyleLayer},
-    raster::RasterLayer,
-    source::Source,
+use crate::{
+    coords::ZoomLevel,
+    io::source_type::{RasterSource, SourceType, TessellateSource},
+    style::{
+        fingerprint::fingerprint,
+        glyphs::{glyph_ranges_for, FontStack},
+        layer::{FillPaint, LayerPaint, LinePaint, StyleLayer, UnsupportedProperties},
+        raster::RasterLayer,
+        source::{Source, VectorSource},
+        url::resolve_url,
+    },
 };
 
 fn deserialize_style_layers<'de, D>(de: D) -> Result<Vec<StyleLayer>, D::Error>
@@ -42,6 +54,355 @@ pub struct Style {
     pub center: Option<[f64; 2]>, // TODO: Use LatLon type here
     pub zoom: Option<f64>,
     pub pitch: Option<f64>,
+    /// The URL template of the glyph PBFs with the placeholders `{fontstack}` and `{range}`, see
+    /// [`Style::glyph_urls`].
+    pub glyphs: Option<String>,
+    /// Properties which are not supported and therefore ignored.
+    #[serde(flatten)]
+    pub unsupported: UnsupportedProperties,
+}
+
+impl Style {
+    /// Describes all properties of the style and its layers which are ignored because they are
+    /// not supported.
+    pub fn warnings(&self) -> Vec<String> {
+        self.unsupported
+            .keys()
+            .map(|property| format!("unsupported property {property}"))
+            .chain(self.layers.iter().flat_map(|layer| layer.warnings()))
+            .collect()
+    }
+
+    /// Finds the layers whose metadata maps `key` to `value`, e.g. to toggle a group of layers.
+    pub fn layers_with_metadata(&self, key: &str, value: &str) -> Vec<&StyleLayer> {
+        self.layers
+            .iter()
+            .filter(|layer| {
+                layer
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.get(key))
+                    .is_some_and(|layer_value| layer_value == value)
+            })
+            .collect()
+    }
+
+    /// Returns a copy of the style which only contains the layer `layer_id`, e.g. to compare a
+    /// layer in isolation. The layer keeps its index, so it is drawn at the same z-index. Because
+    /// requests, uploads and draws follow the layers of the style, the other layers are neither
+    /// tessellated nor rendered.
+    pub fn only_layer(&self, layer_id: &str) -> Style {
+        Style {
+            layers: self
+                .layers
+                .iter()
+                .filter(|layer| layer.id == layer_id)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Compares the layers of this style with the layers of `new` by their id. Layers whose
+    /// tessellation depends on changed properties, like the source or the filter, have to be
+    /// tessellated again. Layers where only the paint or the order changed just need to be
+    /// uploaded again.
+    pub fn diff(&self, new: &Style) -> StyleDiff {
+        let mut diff = StyleDiff::default();
+
+        for layer in &new.layers {
+            let Some(old_layer) = self.layers.iter().find(|old| old.id == layer.id) else {
+                diff.retessellate.insert(layer.id.clone());
+                continue;
+            };
+
+            if tessellation_inputs(old_layer) != tessellation_inputs(layer) {
+                diff.retessellate.insert(layer.id.clone());
+            } else if old_layer.index != layer.index
+                || serde_json::to_value(&old_layer.paint).ok()
+                    != serde_json::to_value(&layer.paint).ok()
+            {
+                diff.reupload.insert(layer.id.clone());
+            }
+        }
+
+        diff.removed = self
+            .layers
+            .iter()
+            .filter(|old| !new.layers.iter().any(|layer| layer.id == old.id))
+            .map(|old| old.id.clone())
+            .collect();
+
+        diff
+    }
+
+    /// The feature properties which the layers of the style read, see
+    /// [`StyleLayer::referenced_properties`]. Properties which are not contained can be dropped
+    /// from tiles without changing how they are rendered.
+    pub fn referenced_properties(&self) -> HashSet<String> {
+        self.layers
+            .iter()
+            .flat_map(StyleLayer::referenced_properties)
+            .collect()
+    }
+
+    /// Resolves the relative URLs of the `sprite`, the `glyphs` and the tiles of the sources
+    /// against `base_url`, which usually is the URL from which the style was loaded. For
+    /// example, `"sprite": "sprite"` becomes `https://example.com/sprite` for the base URL
+    /// `https://example.com/style.json`. Absolute URLs are kept, see [`resolve_url`].
+    pub fn resolve_urls(&mut self, base_url: &str) {
+        if let Some(glyphs) = &mut self.glyphs {
+            *glyphs = resolve_url(base_url, glyphs);
+        }
+
+        // The sprite is either a URL or a list of sprites with an id and a URL
+        match self.unsupported.get_mut("sprite") {
+            Some(serde_json::Value::String(sprite)) => *sprite = resolve_url(base_url, sprite),
+            Some(serde_json::Value::Array(sprites)) => {
+                for sprite in sprites {
+                    if let Some(serde_json::Value::String(url)) = sprite.get_mut("url") {
+                        *url = resolve_url(base_url, url);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for source in self.sources.values_mut() {
+            let (Source::Vector(source) | Source::Raster(source) | Source::Untyped(source)) = source;
+            if let Some(tiles) = &mut source.tiles {
+                *tiles = resolve_url(base_url, tiles);
+            }
+            if let Some(url) = &mut source.url {
+                *url = resolve_url(base_url, url);
+            }
+        }
+    }
+
+    /// A stable hash of the content of the style, e.g. to invalidate caches when the style
+    /// changes. Styles with the same content have the same fingerprint, regardless of the order
+    /// of their maps, see [`fingerprint`].
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(self)
+    }
+
+    /// The URLs of the glyph ranges of `font_stack` which are needed to render `text`. Returns
+    /// `None` if the style has no `glyphs` template.
+    pub fn glyph_urls(&self, font_stack: &FontStack, text: &str) -> Option<Vec<String>> {
+        let template = self.glyphs.as_ref()?;
+        Some(
+            glyph_ranges_for(text)
+                .into_iter()
+                .map(|range| font_stack.glyph_url(template, range))
+                .collect(),
+        )
+    }
+}
+
+/// The layers which changed between two styles, see [`Style::diff`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StyleDiff {
+    /// Layers which are new or whose tessellation changed, e.g. because of a different filter.
+    pub retessellate: HashSet<String>,
+    /// Layers whose paint or draw order changed, which only affects the uploaded metadata.
+    pub reupload: HashSet<String>,
+    /// Layers which are not part of the new style.
+    pub removed: HashSet<String>,
+}
+
+impl StyleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.retessellate.is_empty() && self.reupload.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The properties of a layer which are read while tessellating its features. Line widths and
+/// opacities are included, because they can be computed per feature.
+fn tessellation_inputs(layer: &StyleLayer) -> serde_json::Value {
+    let paint = match &layer.paint {
+        Some(LayerPaint::Line(paint)) => serde_json::json!({
+            "type": "line",
+            "line-width": paint.line_width,
+            "line-gap-width": paint.line_gap_width,
+            "line-blur": paint.line_blur,
+            "line-opacity": layer.paint.as_ref().and_then(LayerPaint::get_feature_opacity),
+        }),
+        Some(LayerPaint::Fill(paint)) => serde_json::json!({
+            "type": "fill",
+            "fill-opacity": paint.fill_opacity.as_ref().filter(|opacity| opacity.property().is_some()),
+        }),
+        Some(LayerPaint::FillExtrusion(_)) => serde_json::json!({ "type": "fill-extrusion" }),
+        Some(LayerPaint::Background(_)) => serde_json::json!({ "type": "background" }),
+        Some(LayerPaint::Raster(_)) => serde_json::json!({ "type": "raster" }),
+        None => serde_json::Value::Null,
+    };
+
+    serde_json::json!({
+        "source": layer.source,
+        "source-layer": layer.source_layer,
+        "filter": layer.filter,
+        "minzoom": layer.minzoom,
+        "maxzoom": layer.maxzoom,
+        "layout": layer.layout,
+        "paint": paint,
+    })
+}
+
+/// Errors which occur while linking the layers of a [`Style`] to their sources.
+#[derive(Error, Debug, PartialEq)]
+pub enum ResolveSourceError {
+    /// A layer references a source id which is not part of the style
+    #[error("layer {layer_id} references the unknown source {source_id}")]
+    MissingSource { layer_id: String, source_id: String },
+    /// The tile URL of a source does not end with `/{z}/{x}/{y}.<filetype>`
+    #[error("source {source_id} has the unsupported tile URL {url}")]
+    UnsupportedTileUrl { source_id: String, url: String },
+    /// The source has no `type` and it could not be determined from its TileJSON, see
+    /// [`Source::probe`]
+    #[error("the type of source {source_id} is unknown")]
+    UnknownSourceType { source_id: String },
+}
+
+/// A [`Style`] whose layers are linked to the sources which they reference.
+#[derive(Clone)]
+pub struct ResolvedStyle {
+    pub style: Style,
+    /// The sources of the style by their id.
+    pub sources: HashMap<String, SourceType>,
+}
+
+impl ResolvedStyle {
+    /// Returns the source of the layer with the id `layer_id`, if it references one.
+    pub fn layer_source(&self, layer_id: &str) -> Option<&SourceType> {
+        let layer = self.style.layers.iter().find(|layer| layer.id == layer_id)?;
+        self.sources.get(layer.source.as_ref()?)
+    }
+}
+
+/// Errors which occur while reading a [`Style`].
+#[derive(Error, Debug)]
+pub enum StyleError {
+    #[error("the style is not valid JSON: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error(transparent)]
+    Source(#[from] ResolveSourceError),
+}
+
+impl Style {
+    /// Resolves the sources of the style and checks that every source which is referenced by a
+    /// layer exists. Sources without tile URLs fall back to the default [`TessellateSource`] or
+    /// [`RasterSource`].
+    pub fn resolve_sources(self) -> Result<ResolvedStyle, ResolveSourceError> {
+        let sources = self.resolved_sources()?;
+
+        Ok(ResolvedStyle {
+            style: self,
+            sources,
+        })
+    }
+
+    fn resolved_sources(&self) -> Result<HashMap<String, SourceType>, ResolveSourceError> {
+        let sources = self
+            .sources
+            .iter()
+            .map(|(source_id, source)| Ok((source_id.clone(), resolve_source(source_id, source)?)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        for layer in &self.layers {
+            if let Some(source_id) = &layer.source {
+                if !sources.contains_key(source_id) {
+                    return Err(ResolveSourceError::MissingSource {
+                        layer_id: layer.id.clone(),
+                        source_id: source_id.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(sources)
+    }
+
+    /// Reads a style from JSON, like [`Style::from_str`].
+    pub fn from_reader(reader: impl io::Read) -> Result<Self, StyleError> {
+        let style: Style = serde_json::from_reader(reader)?;
+        style.resolved_sources()?;
+        Ok(style)
+    }
+}
+
+impl FromStr for Style {
+    type Err = StyleError;
+
+    /// Parses a style from JSON. The layers are indexed by their order and the sources are
+    /// validated like in [`Style::resolve_sources`].
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        let style: Style = serde_json::from_str(json)?;
+        style.resolved_sources()?;
+        Ok(style)
+    }
+}
+
+/// Splits a tile URL like `https://example.com/tiles/{z}/{x}/{y}.pbf` into its base URL and
+/// filetype.
+fn split_tile_url(url: &str) -> Option<(&str, &str)> {
+    let (base, filetype) = url.split_once("/{z}/{x}/{y}.")?;
+    (!filetype.is_empty() && !filetype.contains('/')).then_some((base, filetype))
+}
+
+fn resolve_source(source_id: &str, source: &Source) -> Result<SourceType, ResolveSourceError> {
+    let (Source::Vector(VectorSource {
+        tiles,
+        maxzoom,
+        volatile,
+        ..
+    })
+    | Source::Raster(VectorSource {
+        tiles,
+        maxzoom,
+        volatile,
+        ..
+    })
+    | Source::Untyped(VectorSource {
+        tiles,
+        maxzoom,
+        volatile,
+        ..
+    })) = source;
+
+    let tile_url = tiles
+        .as_ref()
+        .map(|url| {
+            split_tile_url(url).ok_or_else(|| ResolveSourceError::UnsupportedTileUrl {
+                source_id: source_id.to_string(),
+                url: url.clone(),
+            })
+        })
+        .transpose()?;
+
+    Ok(match (source, tile_url) {
+        (Source::Vector(_), Some((url, filetype))) => SourceType::Tessellate(
+            TessellateSource::new(
+                url,
+                filetype,
+                maxzoom.map_or(ZoomLevel::new(14), ZoomLevel::new),
+            )
+            .with_volatile(*volatile),
+        ),
+        (Source::Vector(_), None) => {
+            SourceType::Tessellate(TessellateSource::default().with_volatile(*volatile))
+        }
+        (Source::Raster(_), Some((url, filetype))) => {
+            SourceType::Raster(RasterSource::new(url, filetype).with_volatile(*volatile))
+        }
+        (Source::Raster(_), None) => {
+            SourceType::Raster(RasterSource::default().with_volatile(*volatile))
+        }
+        (Source::Untyped(_), _) => {
+            return Err(ResolveSourceError::UnknownSourceType {
+                source_id: source_id.to_string(),
+            })
+        }
+    })
 }
 
 impl Default for Style {
@@ -54,6 +415,8 @@ impl Default for Style {
             center: Some([50.85045, 4.34878]),
             pitch: Some(0.0),
             zoom: Some(13.0),
+            glyphs: None,
+            unsupported: Default::default(),
             layers: vec![
                 StyleLayer {
                     index: 0,
@@ -61,13 +424,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#c8facc").unwrap()),
+                        fill_color: Some(Color::from_str("#c8facc").unwrap().into()),
                         fill_opacity: None,
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("park".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 1,
@@ -75,13 +443,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#e0dfdf").unwrap()),
+                        fill_color: Some(Color::from_str("#e0dfdf").unwrap().into()),
                         fill_opacity: None,
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("landuse".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 2,
@@ -89,13 +462,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#aedfa3").unwrap()),
+                        fill_color: Some(Color::from_str("#aedfa3").unwrap().into()),
                         fill_opacity: None,
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("landcover".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 3,
@@ -103,14 +481,21 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Line(LinePaint {
-                        line_color: Some(Color::from_str("#ffffff").unwrap()),
+                        line_color: Some(Color::from_str("#ffffff").unwrap().into()),
                         line_opacity: None,
                         line_width: None,
+                        line_gap_width: None,
+                        line_blur: None,
+                        line_translate: None,
+                        line_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("transportation".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 4,
@@ -118,13 +503,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#d9d0c9").unwrap()),
+                        fill_color: Some(Color::from_str("#d9d0c9").unwrap().into()),
                         fill_opacity: None,
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("building".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 4,
@@ -132,13 +522,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#aad3df").unwrap()),
+                        fill_color: Some(Color::from_str("#aad3df").unwrap().into()),
                         fill_opacity: None,
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("water".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 6,
@@ -146,13 +541,18 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
                         fill_opacity: None,
-                        fill_color: Some(Color::from_str("#aad3df").unwrap()),
+                        fill_translate: None,
+                        fill_translate_anchor: None,
+                        unsupported: Default::default(),
+                        fill_color: Some(Color::from_str("#aad3df").unwrap().into()),
                     })),
                     source: None,
                     source_layer: Some("waterway".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 7,
@@ -160,14 +560,21 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Line(LinePaint {
-                        line_color: Some(Color::from_str("black").unwrap()),
+                        line_color: Some(Color::from_str("black").unwrap().into()),
                         line_opacity: None,
                         line_width: None,
+                        line_gap_width: None,
+                        line_blur: None,
+                        line_translate: None,
+                        line_translate_anchor: None,
+                        unsupported: Default::default(),
                     })),
                     source: None,
                     source_layer: Some("boundary".to_string()),
+                    unsupported: Default::default(),
                 },
                 StyleLayer {
                     index: 8,
@@ -175,10 +582,12 @@ impl Default for Style {
                     maxzoom: None,
                     minzoom: None,
                     filter: None,
+                    layout: None,
                     metadata: None,
                     paint: Some(LayerPaint::Raster(RasterLayer::default())),
                     source: None,
                     source_layer: Some("raster".to_string()),
+                    unsupported: Default::default(),
                 },
             ],
         }
@@ -188,6 +597,7 @@ impl Default for Style {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::style::layer::InterpolatedQuantity;
 
     #[test]
     fn test_reading() {
@@ -244,4 +654,506 @@ mod tests {
 
         let _style: Style = serde_json::from_str(style_json_str).unwrap();
     }
+
+    #[test]
+    fn test_unsupported_properties() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "sprite": "https://example.com/sprite",
+          "layers": [
+            {
+              "id": "roads",
+              "type": "line",
+              "source": "openmaptiles",
+              "source-layer": "transportation",
+              "paint": {
+                "line-color": "#3D3D3D",
+                "line-dasharray": [2, 1]
+              }
+            },
+            {
+              "id": "labels",
+              "type": "symbol",
+              "source": "openmaptiles",
+              "source-layer": "place",
+              "paint": {
+                "text-halo-color": "#ffffff"
+              }
+            }
+          ]
+        }
+        "##;
+
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+        let warnings = style.warnings();
+
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings.contains(&"unsupported property sprite".to_string()));
+        assert!(warnings.contains(&"layer labels: unsupported layer type: symbol".to_string()));
+        assert!(warnings.contains(&"layer roads: unsupported paint property line-dasharray".to_string()));
+        assert!(warnings
+            .contains(&"layer labels: unsupported paint property text-halo-color".to_string()));
+    }
+
+    #[test]
+    fn test_style_with_unsupported_paint_values() {
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Expressions",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {
+              "id": "roads",
+              "type": "line",
+              "source": "openmaptiles",
+              "source-layer": "transportation",
+              "paint": {
+                "line-color": "#3D3D3D",
+                "line-width": ["interpolate", ["linear"], ["zoom"], 5, 1, 10, 4]
+              }
+            },
+            {
+              "id": "landuse",
+              "type": "fill",
+              "source": "openmaptiles",
+              "source-layer": "landuse",
+              "paint": {
+                "fill-color": ["match", ["get", "class"], "park", "#d8e8c8", "#eeeeee"],
+                "fill-opacity": {"type": "categorical", "property": "class", "stops": [["park", 0.5]]}
+              }
+            }
+          ]
+        }
+        "##;
+
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+
+        let Some(LayerPaint::Line(roads)) = &style.layers[0].paint else {
+            panic!("expected a line paint");
+        };
+        assert!(roads.line_color.is_some());
+        assert!(roads.line_width.is_none());
+
+        let Some(LayerPaint::Fill(landuse)) = &style.layers[1].paint else {
+            panic!("expected a fill paint");
+        };
+        assert!(landuse.fill_color.is_none());
+        assert!(landuse.fill_opacity.is_none());
+
+        let warnings = style.warnings();
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.contains(&"layer roads: unsupported paint property line-width".to_string()));
+        assert!(warnings.contains(&"layer landuse: unsupported paint property fill-color".to_string()));
+        assert!(warnings
+            .contains(&"layer landuse: unsupported paint property fill-opacity".to_string()));
+    }
+
+    #[test]
+    fn test_default_style_has_no_warnings() {
+        assert!(Style::default().warnings().is_empty());
+    }
+
+    #[test]
+    fn test_layers_with_metadata() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {
+              "id": "roads",
+              "type": "line",
+              "source-layer": "transportation",
+              "metadata": {"group": "roads"}
+            },
+            {
+              "id": "place-labels",
+              "type": "symbol",
+              "source-layer": "place",
+              "metadata": {"group": "labels"}
+            },
+            {
+              "id": "road-labels",
+              "type": "symbol",
+              "source-layer": "transportation_name",
+              "metadata": {"group": "labels", "kind": "road"}
+            },
+            {
+              "id": "water",
+              "type": "fill",
+              "source-layer": "water"
+            }
+          ]
+        }
+        "##;
+
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+
+        let labels: Vec<&str> = style
+            .layers_with_metadata("group", "labels")
+            .into_iter()
+            .map(|layer| layer.id.as_str())
+            .collect();
+        assert_eq!(labels, vec!["place-labels", "road-labels"]);
+
+        assert_eq!(style.layers_with_metadata("kind", "road").len(), 1);
+        assert!(style.layers_with_metadata("group", "water").is_empty());
+        assert!(style.layers_with_metadata("missing", "labels").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_sources() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {
+            "openmaptiles": {
+              "type": "vector",
+              "tiles": "https://example.com/tiles/{z}/{x}/{y}.pbf",
+              "maxzoom": 12
+            },
+            "satellite": {
+              "type": "raster",
+              "volatile": true
+            }
+          },
+          "layers": [
+            {
+              "id": "background",
+              "type": "background"
+            },
+            {
+              "id": "roads",
+              "type": "line",
+              "source": "openmaptiles",
+              "source-layer": "transportation"
+            },
+            {
+              "id": "imagery",
+              "type": "raster",
+              "source": "satellite",
+              "source-layer": "raster"
+            }
+          ]
+        }
+        "##;
+
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+        let resolved = style.resolve_sources().unwrap();
+
+        let Some(SourceType::Tessellate(roads)) = resolved.layer_source("roads") else {
+            panic!("roads are not tessellated");
+        };
+        assert_eq!(roads.url, "https://example.com/tiles");
+        assert_eq!(roads.filetype, "pbf");
+        assert_eq!(roads.max_zoom, ZoomLevel::new(12));
+        assert!(!roads.volatile);
+        assert!(matches!(
+            resolved.layer_source("imagery"),
+            Some(SourceType::Raster(RasterSource { volatile: true, .. }))
+        ));
+        assert!(resolved.layer_source("background").is_none());
+    }
+
+    #[test]
+    fn test_resolve_missing_source() {
+        let mut style = Style::default();
+        style.layers[0].source = Some("openmaptiles".to_string());
+
+        assert_eq!(
+            style.resolve_sources().err(),
+            Some(ResolveSourceError::MissingSource {
+                layer_id: "park".to_string(),
+                source_id: "openmaptiles".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_unsupported_tile_url() {
+        let mut style = Style::default();
+        style.sources.insert(
+            "tilejson".to_string(),
+            serde_json::from_str(r#"{"type": "vector", "tiles": "https://example.com/tiles.json"}"#)
+                .unwrap(),
+        );
+
+        assert!(matches!(
+            style.resolve_sources(),
+            Err(ResolveSourceError::UnsupportedTileUrl { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_str() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {
+            "openmaptiles": {
+              "type": "vector",
+              "tiles": "https://example.com/tiles/{z}/{x}/{y}.pbf"
+            }
+          },
+          "layers": [
+            {
+              "id": "background",
+              "type": "background"
+            },
+            {
+              "id": "water",
+              "type": "fill",
+              "source": "openmaptiles",
+              "source-layer": "water"
+            },
+            {
+              "id": "roads",
+              "type": "line",
+              "source": "openmaptiles",
+              "source-layer": "transportation"
+            }
+          ]
+        }
+        "##;
+
+        let style = Style::from_str(style_json_str).unwrap();
+        let indices: Vec<(&str, u32)> = style
+            .layers
+            .iter()
+            .map(|layer| (layer.id.as_str(), layer.index))
+            .collect();
+        assert_eq!(indices, vec![("background", 0), ("water", 1), ("roads", 2)]);
+
+        // Reading yields the same style
+        let read = Style::from_reader(style_json_str.as_bytes()).unwrap();
+        assert_eq!(read.layers.len(), 3);
+        assert_eq!(read.layers[2].index, 2);
+
+        let resolved = style.resolve_sources().unwrap();
+        assert!(matches!(
+            resolved.layer_source("water"),
+            Some(SourceType::Tessellate(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_str_validates_sources() {
+        let mut style = Style::default();
+        style.layers[0].source = Some("openmaptiles".to_string());
+        let json = serde_json::to_string(&style).unwrap();
+
+        assert!(matches!(
+            Style::from_str(&json),
+            Err(StyleError::Source(ResolveSourceError::MissingSource { .. }))
+        ));
+        assert!(matches!(
+            Style::from_reader(json.as_bytes()),
+            Err(StyleError::Source(_))
+        ));
+        assert!(matches!(Style::from_str("{"), Err(StyleError::Parse(_))));
+        assert!(Style::from_str(&serde_json::to_string(&Style::default()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_diff() {
+        // language=JSON
+        let old = Style::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {"id": "water", "type": "fill", "source-layer": "water", "paint": {"fill-color": "#0000ff"}},
+            {"id": "roads", "type": "line", "source-layer": "transportation", "paint": {"line-color": "#000000", "line-width": 1}},
+            {"id": "parks", "type": "fill", "source-layer": "park", "paint": {"fill-color": "#00ff00"}}
+          ]
+        }
+        "##,
+        )
+        .unwrap();
+
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        if let Some(LayerPaint::Fill(paint)) = &mut new.layers[0].paint {
+            paint.fill_opacity = Some(InterpolatedQuantity::Fixed(0.5).into());
+        }
+        new.layers[1].filter = Some(serde_json::from_str(r#"["==", "class", "primary"]"#).unwrap());
+        new.layers.remove(2);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.reupload, HashSet::from(["water".to_string()]));
+        assert_eq!(diff.retessellate, HashSet::from(["roads".to_string()]));
+        assert_eq!(diff.removed, HashSet::from(["parks".to_string()]));
+
+        // Line widths are part of the tessellation
+        let mut wider = old.clone();
+        if let Some(LayerPaint::Line(paint)) = &mut wider.layers[1].paint {
+            paint.line_width = Some(InterpolatedQuantity::Fixed(4.0).into());
+        }
+        assert_eq!(
+            old.diff(&wider).retessellate,
+            HashSet::from(["roads".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_referenced_properties() {
+        // language=JSON
+        let style = Style::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {
+              "id": "boundaries",
+              "type": "line",
+              "source-layer": "boundary",
+              "filter": ["all", ["==", "$type", "LineString"], ["<=", "admin_level", 4]],
+              "paint": {"line-width": {"property": "width", "type": "identity"}}
+            },
+            {
+              "id": "roads",
+              "type": "line",
+              "source-layer": "transportation",
+              "filter": ["in", "class", "primary", "secondary"],
+              "layout": {"symbol-sort-key": {"property": "rank", "type": "identity"}}
+            },
+            {"id": "water", "type": "fill", "source-layer": "water", "paint": {"fill-opacity": 0.5}}
+          ]
+        }
+        "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            style.referenced_properties(),
+            HashSet::from(["$type", "admin_level", "width", "class", "rank"].map(str::to_string))
+        );
+        assert!(style.layers[2].referenced_properties().is_empty());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let style = Style::default();
+        // A structurally equal style, whose maps are built in a different order
+        let copy: Style = serde_json::from_str(&serde_json::to_string(&style).unwrap()).unwrap();
+        assert_eq!(style.fingerprint(), copy.fingerprint());
+        for (layer, copy) in style.layers.iter().zip(&copy.layers) {
+            assert_eq!(layer.fingerprint(), copy.fingerprint());
+        }
+
+        let mut metadata = Style::default();
+        metadata.metadata = (0..16).map(|i| (i.to_string(), i.to_string())).collect();
+        let mut reordered = metadata.clone();
+        reordered.metadata = (0..16).rev().map(|i| (i.to_string(), i.to_string())).collect();
+        assert_eq!(metadata.fingerprint(), reordered.fingerprint());
+
+        let mut changed = style.clone();
+        if let Some(LayerPaint::Fill(paint)) = &mut changed.layers[0].paint {
+            paint.fill_color = Some(Color::new(1.0, 0.0, 0.0, 1.0).into());
+        }
+        assert_ne!(style.fingerprint(), changed.fingerprint());
+        assert_ne!(style.layers[0].fingerprint(), changed.layers[0].fingerprint());
+        assert_eq!(style.layers[1].fingerprint(), changed.layers[1].fingerprint());
+    }
+
+    #[test]
+    fn test_resolve_urls() {
+        // language=JSON
+        let mut style = Style::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {
+            "openmaptiles": {"type": "vector", "tiles": "tiles/{z}/{x}/{y}.pbf"},
+            "satellite": {"type": "raster", "tiles": "https://example.com/satellite/{z}/{x}/{y}.png"}
+          },
+          "sprite": "sprite",
+          "glyphs": "../fonts/{fontstack}/{range}.pbf",
+          "layers": []
+        }
+        "##,
+        )
+        .unwrap();
+        style.resolve_urls("https://x/styles/style.json");
+
+        assert_eq!(style.unsupported["sprite"], "https://x/styles/sprite");
+        assert_eq!(style.glyphs.as_deref(), Some("https://x/fonts/{fontstack}/{range}.pbf"));
+        let tiles = |source_id: &str| {
+            let (Source::Vector(source) | Source::Raster(source) | Source::Untyped(source)) =
+                &style.sources[source_id];
+            source.tiles.clone()
+        };
+        assert_eq!(tiles("openmaptiles").as_deref(), Some("https://x/styles/tiles/{z}/{x}/{y}.pbf"));
+        assert_eq!(
+            tiles("satellite").as_deref(),
+            Some("https://example.com/satellite/{z}/{x}/{y}.png")
+        );
+
+        // Sprites can also be a list of sprites with ids
+        let mut style = Style::default();
+        style.unsupported.insert(
+            "sprite".to_string(),
+            serde_json::json!([{"id": "default", "url": "sprite"}]),
+        );
+        style.resolve_urls("https://x/style.json");
+        assert_eq!(style.unsupported["sprite"][0]["url"], "https://x/sprite");
+    }
+
+    #[test]
+    fn test_glyph_urls() {
+        // language=JSON
+        let style = Style::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "glyphs": "https://example.com/fonts/{fontstack}/{range}.pbf",
+          "layers": [
+            {"id": "labels", "type": "fill", "source-layer": "place", "layout": {"text-font": ["Noto Sans Regular"]}}
+          ]
+        }
+        "##,
+        )
+        .unwrap();
+        assert!(style.warnings().is_empty());
+
+        let font_stack = style.layers[0]
+            .layout
+            .as_ref()
+            .and_then(|layout| layout.text_font.as_ref())
+            .unwrap();
+        assert_eq!(
+            style.glyph_urls(font_stack, "Brussels").unwrap(),
+            vec!["https://example.com/fonts/Noto%20Sans%20Regular/0-255.pbf"]
+        );
+
+        assert!(Style::default().glyph_urls(font_stack, "Brussels").is_none());
+    }
 }
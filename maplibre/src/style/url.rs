@@ -0,0 +1,115 @@
+//! Resolution of the relative URLs of a style, like `"sprite": "sprite"`, against the URL from
+//! which the style was loaded.
+
+/// Resolves `reference` against `base` like a browser resolves links, see RFC 3986. References
+/// with a scheme, like `https://` or `mapbox://`, are returned unchanged. Placeholders like
+/// `{z}` or `{fontstack}` are kept.
+pub fn resolve_url(base: &str, reference: &str) -> String {
+    if has_scheme(reference) {
+        return reference.to_string();
+    }
+
+    // The query and fragment of the base never apply to the reference
+    let base = base.split(['?', '#']).next().unwrap_or_default();
+    let (origin, base_path) = split_origin(base);
+
+    if let Some(authority) = reference.strip_prefix("//") {
+        let scheme = origin.split_once("://").map_or("", |(scheme, _)| scheme);
+        return format!("{scheme}://{authority}");
+    }
+
+    let (path, suffix) = match reference.find(['?', '#']) {
+        Some(index) => reference.split_at(index),
+        None => (reference, ""),
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else if path.is_empty() {
+        base_path.to_string()
+    } else {
+        let directory = &base_path[..base_path.rfind('/').map_or(0, |index| index + 1)];
+        format!("{directory}{path}")
+    };
+
+    format!("{origin}{}{suffix}", remove_dot_segments(&path))
+}
+
+/// Whether `url` starts with a scheme like `https:`.
+fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic())
+        && chars.all(|char| char.is_ascii_alphanumeric() || "+-.".contains(char))
+}
+
+/// Splits `url` into its scheme and authority, like `https://example.com`, and its path.
+fn split_origin(url: &str) -> (&str, &str) {
+    let Some(authority_start) = url.find("://").map(|index| index + 3) else {
+        return ("", url);
+    };
+    let path_start = url[authority_start..]
+        .find('/')
+        .map_or(url.len(), |index| authority_start + index);
+    url.split_at(path_start)
+}
+
+/// Removes the `.` and `..` segments of `path`.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.split('/').peekable();
+    while let Some(segment) = parts.next() {
+        let is_last = parts.peek().is_none();
+        match segment {
+            "." => {}
+            ".." => {
+                // Keep the leading empty segment of absolute paths
+                if segments.len() > 1 || segments.first().is_some_and(|first| !first.is_empty()) {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
+        }
+        // A trailing `.` or `..` refers to a directory
+        if is_last && matches!(segment, "." | "..") {
+            segments.push("");
+        }
+    }
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_url;
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://x/style.json";
+        assert_eq!(resolve_url(base, "sprite"), "https://x/sprite");
+        assert_eq!(resolve_url(base, "./sprite"), "https://x/sprite");
+        assert_eq!(resolve_url(base, "/sprite"), "https://x/sprite");
+        assert_eq!(resolve_url(base, "//cdn.example.com/sprite"), "https://cdn.example.com/sprite");
+
+        let base = "https://example.com/styles/dark/style.json?key=secret#map";
+        assert_eq!(
+            resolve_url(base, "../../fonts/{fontstack}/{range}.pbf"),
+            "https://example.com/fonts/{fontstack}/{range}.pbf"
+        );
+        assert_eq!(
+            resolve_url(base, "tiles/{z}/{x}/{y}.pbf?key=other"),
+            "https://example.com/styles/dark/tiles/{z}/{x}/{y}.pbf?key=other"
+        );
+        assert_eq!(resolve_url(base, "/../../sprite"), "https://example.com/sprite");
+
+        // Absolute URLs are kept
+        for absolute in ["https://y/sprite", "mapbox://sprites/streets", "file:///data/sprite"] {
+            assert_eq!(resolve_url(base, absolute), absolute);
+        }
+
+        assert_eq!(
+            resolve_url("file:///data/styles/style.json", "../tiles/{z}/{x}/{y}.pbf"),
+            "file:///data/tiles/{z}/{x}/{y}.pbf"
+        );
+    }
+}
@@ -1,43 +1,118 @@
-use crate::coords::ZoomLevel;
+use crate::coords::Zoom;
 use crate::style::layer::InterpolatedQuantity;
 
-pub fn interpolate(quantity: &InterpolatedQuantity<f32>, zoom_level: ZoomLevel) -> Option<f32> {
-    let zoom_level = <ZoomLevel as Into<f64>>::into(zoom_level);
-    
+/// Interpolates `quantity` at `zoom`. Fractional zooms are interpolated as well, while integer
+/// [`crate::coords::ZoomLevel`]s are accepted for tile-dependent values.
+pub fn interpolate(quantity: &InterpolatedQuantity<f32>, zoom: impl Into<Zoom>) -> Option<f32> {
     match quantity {
         InterpolatedQuantity::Fixed(val) => Some(*val),
         InterpolatedQuantity::Interpolated { base, stops } => {
-            if stops.is_empty() {
-                log::info!("empty stops! {:?}", stops);
-                return None
-            }
-
-            let (min_zoom, min_zoom_value) = stops.first().unwrap();
-            let (max_zoom, max_zoom_value) = stops.last().unwrap();
-
-            let window = stops
-                .iter()
-                .zip(stops.iter().skip(1))
-                .find(|((stop_a, _), (stop_b, _))| *stop_a <= zoom_level && *stop_b >= zoom_level);
-
-            if let Some(((stop_a, stop_a_value), (stop_b, stop_b_value))) = window {
-                let zoom_diff: f64 = *stop_b - *stop_a;
-                let zoom_prog: f64 = zoom_level - *stop_a;
-
-                let interp_factor = if zoom_diff == 0.0 {
-                    0.0f32
-                } else if *base == 1.0 {
-                    (zoom_prog as f32) / (zoom_diff as f32)
-                } else {
-                    (base.powf(zoom_prog as f32) - 1.0) / (base.powf(zoom_diff as f32) - 1.0)
-                };
-
-                Some(*stop_a_value + (*stop_b_value - *stop_a_value) * interp_factor)
-            } else if zoom_level <= *min_zoom {
-                Some(*min_zoom_value)
-            } else {
-                Some(*max_zoom_value)
-            }
+            interpolate_stops(stops, *base, zoom, |a, b, t| *a + (*b - *a) * t)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Interpolates between the two `stops` which surround `zoom`. `base` controls the rate at
+/// which the output increases and `lerp` blends two stop values given a factor in `[0, 1]`.
+pub fn interpolate_stops<T: Clone>(
+    stops: &[(f64, T)],
+    base: f32,
+    zoom: impl Into<Zoom>,
+    lerp: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let zoom: Zoom = zoom.into();
+    interpolate_stops_at(stops, base, zoom.into(), lerp)
+}
+
+/// Interpolates between the two `stops` which surround `input`, like [`interpolate_stops`] does
+/// for zoom levels. The input can for example be the value of a feature property.
+pub fn interpolate_stops_at<T: Clone>(
+    stops: &[(f64, T)],
+    base: f32,
+    input: f64,
+    lerp: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    if stops.is_empty() {
+        log::info!("empty stops!");
+        return None
+    }
+
+    let (min_input, min_input_value) = stops.first().unwrap();
+    let (_, max_input_value) = stops.last().unwrap();
+
+    let window = stops
+        .iter()
+        .zip(stops.iter().skip(1))
+        .find(|((stop_a, _), (stop_b, _))| *stop_a <= input && *stop_b >= input);
+
+    if let Some(((stop_a, stop_a_value), (stop_b, stop_b_value))) = window {
+        let input_diff: f64 = *stop_b - *stop_a;
+        let input_prog: f64 = input - *stop_a;
+
+        let interp_factor = interpolation_factor(base, input_prog, input_diff);
+
+        Some(lerp(stop_a_value, stop_b_value, interp_factor))
+    } else if input <= *min_input {
+        Some(min_input_value.clone())
+    } else {
+        Some(max_input_value.clone())
+    }
+}
+
+/// The factor in `[0, 1]` by which the value at `input_prog` past the lower stop is blended
+/// towards the upper stop, which is `input_diff` further. Interpolates exponentially with `base`.
+/// Bases which are not positive and finite, like in malformed styles, fall back to linear
+/// interpolation, as does a result which is not finite.
+fn interpolation_factor(base: f32, input_prog: f64, input_diff: f64) -> f32 {
+    if input_diff == 0.0 {
+        return 0.0;
+    }
+
+    let linear = (input_prog as f32) / (input_diff as f32);
+    if !base.is_finite() || base <= 0.0 || base == 1.0 {
+        return linear;
+    }
+
+    let exponential = (base.powf(input_prog as f32) - 1.0) / (base.powf(input_diff as f32) - 1.0);
+    if exponential.is_finite() {
+        exponential.clamp(0.0, 1.0)
+    } else {
+        linear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coords::ZoomLevel,
+        style::{layer::InterpolatedQuantity, util::interpolate},
+    };
+
+    fn quantity(base: f32) -> InterpolatedQuantity<f32> {
+        InterpolatedQuantity::Interpolated {
+            base,
+            stops: vec![(0.0, 0.0), (10.0, 100.0)],
+        }
+    }
+
+    #[test]
+    fn test_invalid_base_is_linear() {
+        for base in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let quantity = quantity(base);
+            assert_eq!(interpolate(&quantity, ZoomLevel::new(0)), Some(0.0));
+            assert_eq!(interpolate(&quantity, ZoomLevel::new(5)), Some(50.0));
+            assert_eq!(interpolate(&quantity, ZoomLevel::new(10)), Some(100.0));
+        }
+    }
+
+    #[test]
+    fn test_exponential_base() {
+        let value = interpolate(&quantity(2.0), ZoomLevel::new(5)).unwrap();
+        // (2^5 - 1) / (2^10 - 1) of the way
+        assert!((value - 3100.0 / 1023.0).abs() < 1e-3);
+
+        // The powers overflow, the result is still within the stops
+        let value = interpolate(&quantity(1e30), ZoomLevel::new(5)).unwrap();
+        assert!(value.is_finite() && (0.0..=100.0).contains(&value));
+    }
+}
@@ -8,13 +8,14 @@ use std::{
 use downcast_rs::{impl_downcast, Downcast};
 use geozero::{FeatureProcessor, GeomProcessor};
 use crate::{
-    coords::{Quadkey, WorldTileCoords},
-    io::geometry_index::GeometryIndex,
+    coords::{Quadkey, ViewRegion, WorldTileCoords},
+    io::{apc::CancellationToken, geometry_index::GeometryIndex},
 };
 use crate::coords::{ZoomLevel, EXTENT};
+use crate::style::{layer::StyleLayer, Style};
 use crate::tessellation::IndexDataType;
 use crate::tessellation::zero_tessellator::ZeroTessellator;
-use crate::vector::{AvailableVectorLayerData, VectorBufferPool, VectorLayerData, VectorLayersDataComponent};
+use crate::vector::{AvailableVectorLayerData, VectorLayerData, VectorLayersDataComponent};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Tile {
@@ -26,11 +27,23 @@ pub struct Tile {
 pub trait TileComponent: Downcast + 'static {}
 impl_downcast!(TileComponent);
 
+/// Creates a component for a newly spawned tile.
+type ComponentFactory = fn() -> Box<dyn TileComponent>;
+
 pub struct Tiles {
     pub tiles: BTreeMap<Quadkey, Tile>,
     pub components: BTreeMap<Quadkey, Vec<UnsafeCell<Box<dyn TileComponent>>>>,
     pub geometry_index: GeometryIndex,
     pub background_tile: AvailableVectorLayerData,
+    /// Backgrounds for tiles with a different extent than `background_tile`, keyed by extent.
+    pub background_tiles: BTreeMap<u32, AvailableVectorLayerData>,
+    /// Factories for the components which are inserted into every newly spawned tile.
+    default_components: Vec<(TypeId, ComponentFactory)>,
+    /// Tokens which cancel the in-flight processing of tiles once they are despawned.
+    cancellation_tokens: BTreeMap<Quadkey, CancellationToken>,
+    /// The sources of the components which were inserted with
+    /// [`TileSpawnResult::insert_from_source`].
+    component_sources: BTreeMap<Quadkey, Vec<(TypeId, String)>>,
 }
 
 impl Tiles {
@@ -57,6 +70,26 @@ impl Tiles {
         }
     }
 
+    /// Returns whether the tile at `coords` has a component of type `T`.
+    pub fn has_component<T: TileComponent>(&self, coords: WorldTileCoords) -> bool {
+        coords
+            .build_quad_key()
+            .and_then(|key| self.components.get(&key))
+            .is_some_and(|components| {
+                components.iter().any(|component| unsafe {
+                    component.get().as_ref().unwrap().as_ref().type_id() == TypeId::of::<T>()
+                })
+            })
+    }
+
+    /// Returns the number of components of the tile at `coords`.
+    pub fn component_count(&self, coords: WorldTileCoords) -> usize {
+        coords
+            .build_quad_key()
+            .and_then(|key| self.components.get(&key))
+            .map_or(0, |components| components.len())
+    }
+
     pub fn spawn_mut(&mut self, coords: WorldTileCoords) -> Option<TileSpawnResult> {
         if let Some(key) = coords.build_quad_key() {
             if let Some(tile) = self.tiles.get(&key) {
@@ -65,7 +98,12 @@ impl Tiles {
             } else {
                 let tile = Tile { coords };
                 self.tiles.insert(key, tile);
-                self.components.insert(key, Vec::new());
+                let components = self
+                    .default_components
+                    .iter()
+                    .map(|(_, factory)| UnsafeCell::new(factory()))
+                    .collect();
+                self.components.insert(key, components);
                 Some(TileSpawnResult { tiles: self, tile })
             }
         } else {
@@ -73,76 +111,371 @@ impl Tiles {
         }
     }
 
+    /// Removes the tile at `coords` and returns its components. The components can be moved to a
+    /// different [`Tiles`] using [`Tiles::insert_prebuilt`].
+    pub fn take_components(
+        &mut self,
+        coords: WorldTileCoords,
+    ) -> Option<Vec<Box<dyn TileComponent>>> {
+        let key = coords.build_quad_key()?;
+        self.tiles.remove(&key);
+        self.component_sources.remove(&key);
+        let components = self.components.remove(&key)?;
+
+        Some(
+            components
+                .into_iter()
+                .map(|component| component.into_inner())
+                .collect(),
+        )
+    }
+
+    /// Inserts a tile at `coords` together with already built `components`, e.g. from
+    /// [`Tiles::take_components`]. Existing components of the tile are replaced.
+    pub fn insert_prebuilt(
+        &mut self,
+        coords: WorldTileCoords,
+        components: Vec<Box<dyn TileComponent>>,
+    ) -> Option<TileSpawnResult> {
+        let key = coords.build_quad_key()?;
+        let tile = Tile { coords };
+        self.tiles.insert(key, tile);
+        self.component_sources.remove(&key);
+        self.components
+            .insert(key, components.into_iter().map(UnsafeCell::new).collect());
+        Some(TileSpawnResult { tiles: self, tile })
+    }
+
+    /// Registers `T` as a default component. Every tile which is newly spawned by
+    /// [`Tiles::spawn_mut`] starts with `T::default()`. Registering the same type twice has no
+    /// effect.
+    pub fn register_default<T: TileComponent + Default>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.default_components.iter().any(|(id, _)| *id == type_id) {
+            return;
+        }
+
+        self.default_components
+            .push((type_id, || Box::new(T::default())));
+    }
+
+    /// Returns the token which is cancelled when the tile at `coords` is despawned. Pass it to the
+    /// processing of the tile so that results for despawned tiles are dropped early.
+    pub fn cancellation_token(&mut self, coords: WorldTileCoords) -> Option<CancellationToken> {
+        let key = coords.build_quad_key()?;
+        Some(self.cancellation_tokens.entry(key).or_default().clone())
+    }
+
+    /// Removes the tile at `coords` together with its components and cancels its in-flight
+    /// processing. Returns whether the tile existed.
+    pub fn despawn(&mut self, coords: WorldTileCoords) -> bool {
+        let Some(key) = coords.build_quad_key() else {
+            return false;
+        };
+
+        if let Some(cancellation) = self.cancellation_tokens.remove(&key) {
+            cancellation.cancel();
+        }
+        self.components.remove(&key);
+        self.component_sources.remove(&key);
+        self.tiles.remove(&key).is_some()
+    }
+
+    /// Cancels the in-flight processing of the tiles outside of `view_region` and despawns them,
+    /// such that they are requested again once they are back in view. Only tiles whose processing
+    /// started and for which `is_pending` returns true are affected. Loaded tiles are kept, e.g. to
+    /// be shown while their children load. Returns the coordinates of the despawned tiles.
+    pub fn cancel_outside(
+        &mut self,
+        view_region: &ViewRegion,
+        is_pending: impl Fn(&Tiles, WorldTileCoords) -> bool,
+    ) -> Vec<WorldTileCoords> {
+        let cancelled: Vec<WorldTileCoords> = self
+            .cancellation_tokens
+            .keys()
+            .filter_map(|key| self.tiles.get(key))
+            .map(|tile| tile.coords)
+            .filter(|coords| !view_region.is_in_view(coords) && is_pending(self, *coords))
+            .collect();
+
+        for coords in &cancelled {
+            self.despawn(*coords);
+        }
+        cancelled
+    }
+
     pub fn clear(&mut self) {
+        for cancellation in self.cancellation_tokens.values() {
+            cancellation.cancel();
+        }
+        self.cancellation_tokens.clear();
         self.tiles.clear();
         self.components.clear();
+        self.component_sources.clear();
+    }
+
+    /// Removes the components which were inserted from the source `source_id` while keeping the
+    /// data of other sources. Tiles which are left without components are despawned.
+    pub fn clear_source(&mut self, source_id: &str) {
+        let mut emptied = Vec::new();
+
+        for (key, sources) in &mut self.component_sources {
+            let removed: Vec<TypeId> = sources
+                .iter()
+                .filter(|(_, source)| source == source_id)
+                .map(|(type_id, _)| *type_id)
+                .collect();
+            if removed.is_empty() {
+                continue;
+            }
+            sources.retain(|(type_id, _)| !removed.contains(type_id));
+
+            if let Some(components) = self.components.get_mut(key) {
+                components.retain_mut(|component| {
+                    !removed.contains(&component.get_mut().as_ref().type_id())
+                });
+                if components.is_empty() {
+                    emptied.push(*key);
+                }
+            }
+        }
+
+        self.component_sources.retain(|_, sources| !sources.is_empty());
+
+        for key in emptied {
+            if let Some(tile) = self.tiles.get(&key) {
+                self.despawn(tile.coords);
+            }
+        }
     }
 
+    /// Finds the tessellated data for `style_layer` at `coords` which is not yet part of
+    /// `loaded_layers`. Style layers without a source layer are only backed by the background tile
+    /// if they are visible `background` layers.
     pub fn find_layer(
         &mut self,
         coords: WorldTileCoords,
-        source_layer_name: &Option<String>,
-        style_layer_id: &str,
-        buffer_pool: &VectorBufferPool
+        style_layer: &StyleLayer,
+        loaded_layers: &HashSet<String>,
     ) -> Option<&AvailableVectorLayerData> {
-        let loaded_layers = buffer_pool
-            .get_loaded_layers_at(coords)
-            .unwrap_or_default();
+        if !style_layer.is_visible() || loaded_layers.contains(&style_layer.id) {
+            return None;
+        }
 
-        if source_layer_name.is_some() {
-            let Some(vector_layers) = self.query_mut::<&VectorLayersDataComponent>(coords) else {
-                return None
-            };
+        if style_layer.source_layer.is_some() {
+            let vector_layers = self.query_mut::<&VectorLayersDataComponent>(coords)?;
 
-            let available_layers = vector_layers
+            vector_layers
                 .layers
                 .iter()
                 .flat_map(|data| match data {
                     VectorLayerData::Available(data) => Some(data),
                     VectorLayerData::Missing(_) => None,
                 })
-                .filter(|data| !loaded_layers.contains(&data.style_layer_id))
-                .collect::<Vec<_>>();
+                .find(|layer| style_layer.id == layer.style_layer_id)
+        } else if style_layer.is_background() {
+            let extent = self.extent(coords);
+            let background = self.background_for_extent(extent);
+            background.style_layer_id = style_layer.id.clone();
+            Some(background)
+        } else {
+            None
+        }
+    }
 
-            available_layers
-                .iter()
-                .find(|layer| style_layer_id == layer.style_layer_id)
-                .map(|data| *data)
-        } else if !loaded_layers.contains(style_layer_id) {
-            self.background_tile.style_layer_id = style_layer_id.to_string();
+    /// Iterates the tessellated layers of the tile at `coords` together with their style layers,
+    /// ordered by the index of the style layer. Like in [`Tiles::find_layer`], invisible layers
+    /// are skipped and `background` layers are backed by the background tile. Backgrounds are only
+    /// yielded if they are cached for the extent of the tile, see [`Tiles::prepare_backgrounds`].
+    pub fn layers_in_draw_order<'a>(
+        &'a self,
+        coords: WorldTileCoords,
+        style: &'a Style,
+    ) -> impl Iterator<Item = (&'a StyleLayer, &'a AvailableVectorLayerData)> + 'a {
+        let mut style_layers: Vec<&StyleLayer> =
+            style.layers.iter().filter(|layer| layer.is_visible()).collect();
+        style_layers.sort_by_key(|layer| layer.index);
+
+        let vector_layers = self.query::<&VectorLayersDataComponent>(coords);
+        let background = self.background(self.extent(coords));
+
+        style_layers.into_iter().filter_map(move |style_layer| {
+            let data = if style_layer.source_layer.is_some() {
+                vector_layers?.layers.iter().find_map(|data| match data {
+                    VectorLayerData::Available(data) if data.style_layer_id == style_layer.id => {
+                        Some(data)
+                    }
+                    _ => None,
+                })
+            } else if style_layer.is_background() {
+                background
+            } else {
+                None
+            };
+            Some((style_layer, data?))
+        })
+    }
+
+    /// Whether all visible style layers of `style` which have a source layer are loaded at
+    /// `coords`, such that the tile can be drawn completely. A style layer is loaded if its data
+    /// is available or missing, e.g. because its tessellation failed. Once the tile finished
+    /// processing, style layers without any data are empty and loaded as well. Layers which are
+    /// tessellated again or whose chunks are still arriving are not loaded. Style layers without a
+    /// source layer, like backgrounds, need no data.
+    pub fn is_tile_complete(&self, coords: WorldTileCoords, style: &Style) -> bool {
+        let mut required = style
+            .layers
+            .iter()
+            .filter(|layer| layer.is_visible() && layer.source_layer.is_some())
+            .peekable();
+        if required.peek().is_none() {
+            return true;
+        }
+
+        let Some(vector_layers) = self.query::<&VectorLayersDataComponent>(coords) else {
+            return false;
+        };
+
+        required.all(|style_layer| {
+            let id = &style_layer.id;
+            let outdated = vector_layers.retessellate.contains(id)
+                || vector_layers
+                    .pending_chunks
+                    .iter()
+                    .any(|pending| &pending.style_layer_id == id);
+            let loaded = vector_layers.layers.iter().any(|data| match data {
+                VectorLayerData::Available(data) => &data.style_layer_id == id,
+                VectorLayerData::Missing(data) => &data.style_layer_id == id,
+            });
+            !outdated && (loaded || vector_layers.done)
+        })
+    }
+
+    /// The extent of the tessellated data at `coords`, or of the default background if no data
+    /// is available yet.
+    pub fn extent(&self, coords: WorldTileCoords) -> u32 {
+        self.query::<&VectorLayersDataComponent>(coords)
+            .and_then(|vector_layers| {
+                vector_layers.layers.iter().find_map(|data| match data {
+                    VectorLayerData::Available(data) => Some(data.extent),
+                    VectorLayerData::Missing(_) => None,
+                })
+            })
+            .unwrap_or(self.background_tile.extent)
+    }
+
+    /// Tessellates and caches the backgrounds for tiles with the given `extents` ahead of time.
+    /// Otherwise, they are tessellated when they are needed for the first time.
+    pub fn prepare_backgrounds(&mut self, extents: impl IntoIterator<Item = u32>) {
+        for extent in extents {
+            self.background_for_extent(extent);
+        }
+    }
+
+    /// Returns the background for tiles with `extent`, if it is cached.
+    pub fn background(&self, extent: u32) -> Option<&AvailableVectorLayerData> {
+        if extent == self.background_tile.extent {
             Some(&self.background_tile)
         } else {
-            None
+            self.background_tiles.get(&extent)
+        }
+    }
+
+    fn background_for_extent(&mut self, extent: u32) -> &mut AvailableVectorLayerData {
+        if extent == self.background_tile.extent {
+            return &mut self.background_tile;
+        }
+
+        let style_layer_id = &self.background_tile.style_layer_id;
+        self.background_tiles.entry(extent).or_insert_with(|| {
+            BackgroundTileBuilder::new()
+                .with_extent(extent as f64)
+                .with_style_layer_id(style_layer_id)
+                .build()
+        })
+    }
+}
+
+impl Tiles {
+    /// Creates an empty tile storage which uses `background_tile` for style layers that have no
+    /// source layer. Backgrounds for tiles with other extents are derived from it.
+    pub fn with_background(background_tile: AvailableVectorLayerData) -> Self {
+        Self {
+            tiles: Default::default(),
+            components: Default::default(),
+            geometry_index: Default::default(),
+            background_tile,
+            background_tiles: Default::default(),
+            default_components: Vec::new(),
+            cancellation_tokens: Default::default(),
+            component_sources: Default::default(),
         }
     }
 }
 
 impl Default for Tiles {
     fn default() -> Self {
+        Self::with_background(BackgroundTileBuilder::new().build())
+    }
+}
+
+/// A convenient builder for the background layer of [`Tiles`]. The background is a single quad
+/// which covers a tile from `(0, 0)` to `(extent, extent)`.
+pub struct BackgroundTileBuilder {
+    extent: f64,
+    style_layer_id: String,
+}
+
+impl Default for BackgroundTileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundTileBuilder {
+    pub fn new() -> Self {
+        Self {
+            extent: EXTENT,
+            style_layer_id: "background".to_string(),
+        }
+    }
+
+    pub fn with_extent(mut self, extent: f64) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    pub fn with_style_layer_id(mut self, style_layer_id: &str) -> Self {
+        self.style_layer_id = style_layer_id.to_string();
+        self
+    }
+
+    pub fn build(self) -> AvailableVectorLayerData {
+        let extent = self.extent;
+
         let mut tessellator = ZeroTessellator::<IndexDataType>::default();
         tessellator.dataset_begin(Some("background")).unwrap();
         tessellator.feature_begin(1).unwrap();
         tessellator.geometry_begin().unwrap();
         tessellator.polygon_begin(true, 4, 1).unwrap();
         tessellator.xy(0.0, 0.0, 1).unwrap();
-        tessellator.xy(0.0, EXTENT, 1).unwrap();
-        tessellator.xy(EXTENT, EXTENT, 1).unwrap();
-        tessellator.xy(EXTENT, 0.0, 1).unwrap();
+        tessellator.xy(0.0, extent, 1).unwrap();
+        tessellator.xy(extent, extent, 1).unwrap();
+        tessellator.xy(extent, 0.0, 1).unwrap();
         tessellator.polygon_end(true, 1).unwrap();
         tessellator.geometry_end().unwrap();
         tessellator.feature_end(1).unwrap();
         tessellator.dataset_end().unwrap();
 
-        Self {
-            tiles: Default::default(),
-            components: Default::default(),
-            geometry_index: Default::default(),
-            background_tile: AvailableVectorLayerData {
-                coords: (0, 0, ZoomLevel::new(0)).into(),
-                feature_indices: tessellator.feature_indices,
-                buffer: tessellator.buffer.into(),
-                style_layer_id: "background".to_string(),
-            },
+        AvailableVectorLayerData {
+            coords: (0, 0, ZoomLevel::new(0)).into(),
+            feature_indices: tessellator.feature_indices,
+            feature_line_width_values: tessellator.feature_line_width_values,
+            feature_opacity_values: tessellator.feature_opacity_values,
+            feature_properties: tessellator.feature_properties,
+            extent: extent as u32,
+            buffer: tessellator.buffer.into(),
+            style_layer_id: self.style_layer_id,
         }
     }
 }
@@ -153,6 +486,9 @@ pub struct TileSpawnResult<'t> {
 }
 
 impl<'w> TileSpawnResult<'w> {
+    /// Adds `component` to the tile. Components of the same type are not replaced, queries then
+    /// return the first one. Use [`TileSpawnResult::insert_or_replace`] to replace a component,
+    /// e.g. one which was registered by [`Tiles::register_default`].
     pub fn insert<T: TileComponent>(&mut self, component: T) -> &mut Self {
         let components = &mut self.tiles.components;
         let coords = self.tile.coords;
@@ -167,8 +503,62 @@ impl<'w> TileSpawnResult<'w> {
                 }
             }
         }
+        self.set_source::<T>(None);
         self
     }
+
+    /// Adds `component` to the tile like [`TileSpawnResult::insert`], but replaces a component of
+    /// the same type, e.g. the default which was registered by [`Tiles::register_default`].
+    pub fn insert_or_replace<T: TileComponent>(&mut self, component: T) -> &mut Self {
+        let components = self
+            .tile
+            .coords
+            .build_quad_key()
+            .and_then(|key| self.tiles.components.get_mut(&key));
+
+        let Some(components) = components else {
+            return self.insert(component);
+        };
+        match components
+            .iter_mut()
+            .position(|existing| existing.get_mut().is::<T>())
+        {
+            Some(index) => {
+                components[index] = UnsafeCell::new(Box::new(component));
+                self.set_source::<T>(None);
+                self
+            }
+            None => self.insert(component),
+        }
+    }
+
+    /// Adds `component` to the tile like [`TileSpawnResult::insert`] and records that it belongs
+    /// to the source `source_id`. See [`Tiles::clear_source`].
+    pub fn insert_from_source<T: TileComponent>(
+        &mut self,
+        source_id: &str,
+        component: T,
+    ) -> &mut Self {
+        self.insert(component);
+        self.set_source::<T>(Some(source_id));
+        self
+    }
+
+    fn set_source<T: TileComponent>(&mut self, source_id: Option<&str>) {
+        let Some(key) = self.tile.coords.build_quad_key() else {
+            return;
+        };
+        let type_id = TypeId::of::<T>();
+
+        let sources = self.tiles.component_sources.entry(key).or_default();
+        sources.retain(|(id, _)| *id != type_id);
+        if let Some(source_id) = source_id {
+            sources.push((type_id, source_id.to_string()));
+        }
+        if sources.is_empty() {
+            self.tiles.component_sources.remove(&key);
+        }
+    }
 }
 
 #[derive(Default)]
@@ -396,3 +786,483 @@ impl<
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cgmath::Point2;
+
+    use crate::{
+        coords::{ViewRegion, Zoom, ZoomLevel, EXTENT_UINT},
+        style::{
+            layer::{BackgroundPaint, FillPaint, LayerLayout, LayerPaint, StyleLayer, Visibility},
+            Style,
+        },
+        tcs::tiles::{BackgroundTileBuilder, TileComponent, Tiles},
+        util::math::Aabb2,
+        vector::{
+            AvailableVectorLayerData, MissingVectorLayerData, VectorLayerData,
+            VectorLayersDataComponent,
+        },
+    };
+
+    struct TestComponent(u32);
+    impl TileComponent for TestComponent {}
+
+    struct OtherComponent;
+    impl TileComponent for OtherComponent {}
+
+    #[test]
+    fn test_component_introspection() {
+        let coords = (1, 2, ZoomLevel::new(3)).into();
+        let other_coords = (2, 2, ZoomLevel::new(3)).into();
+
+        let mut tiles = Tiles::default();
+        tiles.spawn_mut(other_coords).unwrap();
+        tiles
+            .spawn_mut(coords)
+            .unwrap()
+            .insert(TestComponent(42))
+            .insert(OtherComponent);
+
+        assert_eq!(tiles.component_count(coords), 2);
+        assert!(tiles.has_component::<TestComponent>(coords));
+        assert!(tiles.has_component::<OtherComponent>(coords));
+
+        assert_eq!(tiles.component_count(other_coords), 0);
+        assert!(!tiles.has_component::<TestComponent>(other_coords));
+
+        let missing = (0, 0, ZoomLevel::new(3)).into();
+        assert_eq!(tiles.component_count(missing), 0);
+        assert!(!tiles.has_component::<TestComponent>(missing));
+    }
+
+    #[test]
+    fn test_move_components() {
+        let coords = (1, 2, ZoomLevel::new(3)).into();
+
+        let mut front = Tiles::default();
+        front
+            .spawn_mut(coords)
+            .unwrap()
+            .insert(TestComponent(42))
+            .insert(OtherComponent);
+
+        let mut back = Tiles::default();
+        let components = front.take_components(coords).unwrap();
+        assert_eq!(components.len(), 2);
+        assert!(!front.exists(coords));
+        assert!(front.query::<&TestComponent>(coords).is_none());
+
+        back.insert_prebuilt(coords, components).unwrap();
+        assert!(back.exists(coords));
+        assert_eq!(back.query::<&TestComponent>(coords).unwrap().0, 42);
+        assert!(back.query::<&OtherComponent>(coords).is_some());
+
+        assert!(front.take_components(coords).is_none());
+    }
+
+    fn source_less_layer(paint: LayerPaint, visibility: Option<Visibility>) -> StyleLayer {
+        StyleLayer {
+            id: "layer".to_string(),
+            paint: Some(paint),
+            source_layer: None,
+            layout: Some(LayerLayout {
+                visibility,
+                ..LayerLayout::default()
+            }),
+            ..StyleLayer::default()
+        }
+    }
+
+    fn background_paint() -> LayerPaint {
+        LayerPaint::Background(BackgroundPaint {
+            background_color: None,
+            background_opacity: None,
+            unsupported: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_find_background_layer() {
+        let mut tiles = Tiles::default();
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let loaded_layers = HashSet::new();
+
+        let visible = source_less_layer(background_paint(), None);
+        let layer = tiles.find_layer(coords, &visible, &loaded_layers);
+        assert_eq!(layer.unwrap().style_layer_id, "layer");
+
+        let hidden = source_less_layer(background_paint(), Some(Visibility::None));
+        assert!(tiles.find_layer(coords, &hidden, &loaded_layers).is_none());
+
+        let fill = source_less_layer(
+            LayerPaint::Fill(FillPaint {
+                fill_color: None,
+                fill_opacity: None,
+                fill_translate: None,
+                fill_translate_anchor: None,
+                unsupported: Default::default(),
+            }),
+            Some(Visibility::Visible),
+        );
+        assert!(tiles.find_layer(coords, &fill, &loaded_layers).is_none());
+
+        let loaded_layers = HashSet::from(["layer".to_string()]);
+        assert!(tiles.find_layer(coords, &visible, &loaded_layers).is_none());
+    }
+
+    #[test]
+    fn test_default_background() {
+        let tiles = Tiles::default();
+
+        assert_eq!(tiles.background_tile.style_layer_id, "background");
+        assert_eq!(tiles.background_tile.feature_indices, vec![6]);
+        assert_eq!(tiles.background_tile.buffer.usable_indices, 6);
+    }
+
+    #[test]
+    fn test_custom_background() {
+        let background = BackgroundTileBuilder::new()
+            .with_extent(512.0)
+            .with_style_layer_id("custom")
+            .build();
+
+        assert_eq!(background.feature_indices, vec![6]);
+        assert_eq!(background.buffer.usable_indices, 6);
+        assert_eq!(background.buffer.buffer.vertices.len(), 4);
+        assert!(background
+            .buffer
+            .buffer
+            .vertices
+            .iter()
+            .all(|vertex| vertex.position[0] <= 512.0 && vertex.position[1] <= 512.0));
+
+        let tiles = Tiles::with_background(background);
+        assert_eq!(tiles.background_tile.style_layer_id, "custom");
+        assert_eq!(
+            tiles.background_tile.coords,
+            (0, 0, ZoomLevel::new(0)).into()
+        );
+    }
+
+    #[derive(Default)]
+    struct DefaultComponent(u32);
+    impl TileComponent for DefaultComponent {}
+
+    #[test]
+    fn test_register_default_component() {
+        let coords = (1, 2, ZoomLevel::new(3)).into();
+        let other_coords = (2, 2, ZoomLevel::new(3)).into();
+
+        let mut tiles = Tiles::default();
+        tiles.spawn_mut(coords).unwrap();
+
+        tiles.register_default::<DefaultComponent>();
+        tiles.register_default::<DefaultComponent>();
+
+        // Tiles which already exist are not changed
+        tiles.spawn_mut(coords).unwrap();
+        assert!(!tiles.has_component::<DefaultComponent>(coords));
+
+        tiles.spawn_mut(other_coords).unwrap();
+        assert_eq!(tiles.component_count(other_coords), 1);
+        assert_eq!(tiles.query::<&DefaultComponent>(other_coords).unwrap().0, 0);
+
+        // Inserting a component of the same type adds it after the default
+        tiles
+            .spawn_mut(other_coords)
+            .unwrap()
+            .insert(DefaultComponent(7));
+        assert_eq!(tiles.component_count(other_coords), 2);
+        assert_eq!(tiles.query::<&DefaultComponent>(other_coords).unwrap().0, 0);
+
+        // The default can be replaced instead
+        let third_coords = (3, 2, ZoomLevel::new(3)).into();
+        tiles
+            .spawn_mut(third_coords)
+            .unwrap()
+            .insert_or_replace(DefaultComponent(7))
+            .insert(TestComponent(1));
+        assert_eq!(tiles.component_count(third_coords), 2);
+        assert_eq!(tiles.query::<&DefaultComponent>(third_coords).unwrap().0, 7);
+    }
+
+    #[test]
+    fn test_despawn_cancels_processing() {
+        let coords = (1, 2, ZoomLevel::new(3)).into();
+        let other_coords = (2, 2, ZoomLevel::new(3)).into();
+
+        let mut tiles = Tiles::default();
+        tiles.spawn_mut(coords).unwrap().insert(TestComponent(1));
+        tiles.spawn_mut(other_coords).unwrap();
+
+        let cancellation = tiles.cancellation_token(coords).unwrap();
+        let other_cancellation = tiles.cancellation_token(other_coords).unwrap();
+
+        assert!(tiles.despawn(coords));
+        assert!(cancellation.is_cancelled());
+        assert!(!other_cancellation.is_cancelled());
+        assert!(!tiles.exists(coords));
+        assert_eq!(tiles.component_count(coords), 0);
+        assert!(!tiles.despawn(coords));
+
+        // A respawned tile gets a fresh token
+        tiles.spawn_mut(coords).unwrap();
+        assert!(!tiles.cancellation_token(coords).unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_outside_view_region() {
+        // Contains only the tile (0, 0, 0)
+        let view_region = ViewRegion::new(
+            Aabb2::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)),
+            0,
+            1,
+            Zoom::new(0.0),
+            ZoomLevel::default(),
+        );
+        let in_view = (0, 0, ZoomLevel::default()).into();
+        let pending = (1, 2, ZoomLevel::new(3)).into();
+        let loaded = (2, 2, ZoomLevel::new(3)).into();
+        let not_requested = (3, 2, ZoomLevel::new(3)).into();
+
+        // Tiles with a `TestComponent(0)` are still processed
+        let mut tiles = Tiles::default();
+        let mut tokens = Vec::new();
+        for (coords, state) in [(in_view, 0), (pending, 0), (loaded, 1)] {
+            tiles.spawn_mut(coords).unwrap().insert(TestComponent(state));
+            tokens.push(tiles.cancellation_token(coords).unwrap());
+        }
+        tiles.spawn_mut(not_requested).unwrap().insert(TestComponent(0));
+
+        let cancelled = tiles.cancel_outside(&view_region, |tiles, coords| {
+            tiles
+                .query::<&TestComponent>(coords)
+                .is_some_and(|component| component.0 == 0)
+        });
+        assert_eq!(cancelled, vec![pending]);
+        assert_eq!(
+            tokens.iter().map(|token| token.is_cancelled()).collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert!(!tiles.exists(pending));
+        assert!(tiles.exists(in_view) && tiles.exists(loaded) && tiles.exists(not_requested));
+    }
+
+    #[test]
+    fn test_clear_source() {
+        let coords = (1, 2, ZoomLevel::new(3)).into();
+        let other_coords = (2, 2, ZoomLevel::new(3)).into();
+
+        let mut tiles = Tiles::default();
+        tiles
+            .spawn_mut(coords)
+            .unwrap()
+            .insert_from_source("vector", TestComponent(1))
+            .insert_from_source("raster", OtherComponent);
+        tiles
+            .spawn_mut(other_coords)
+            .unwrap()
+            .insert_from_source("vector", TestComponent(2));
+        let cancellation = tiles.cancellation_token(other_coords).unwrap();
+
+        tiles.clear_source("vector");
+
+        assert!(tiles.exists(coords));
+        assert!(!tiles.has_component::<TestComponent>(coords));
+        assert!(tiles.has_component::<OtherComponent>(coords));
+
+        // Tiles without remaining data are despawned
+        assert!(!tiles.exists(other_coords));
+        assert!(cancellation.is_cancelled());
+
+        // Replacing a component without a source no longer associates it with the source
+        tiles
+            .spawn_mut(coords)
+            .unwrap()
+            .insert(OtherComponent)
+            .insert_from_source("vector", TestComponent(3));
+        tiles.clear_source("raster");
+        assert!(tiles.has_component::<OtherComponent>(coords));
+        assert_eq!(tiles.query::<&TestComponent>(coords).unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_backgrounds_per_extent() {
+        let mut tiles = Tiles::default();
+        assert!(tiles.background(512).is_none());
+
+        tiles.prepare_backgrounds([EXTENT_UINT, 512]);
+        assert_eq!(tiles.background_tiles.len(), 1);
+
+        let max_position = |background: &AvailableVectorLayerData| {
+            background
+                .buffer
+                .buffer
+                .vertices
+                .iter()
+                .map(|vertex| vertex.position[0])
+                .fold(0.0, f32::max)
+        };
+        assert_eq!(max_position(tiles.background(EXTENT_UINT).unwrap()), 4096.0);
+        assert_eq!(max_position(tiles.background(512).unwrap()), 512.0);
+
+        // Tiles use the background which matches the extent of their data
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let mut data = BackgroundTileBuilder::new().with_extent(256.0).build();
+        data.style_layer_id = "data".to_string();
+        tiles.spawn_mut(coords).unwrap().insert(VectorLayersDataComponent {
+            done: true,
+            empty: false,
+            layers: vec![VectorLayerData::Available(data)],
+            pending_chunks: vec![],
+            retessellate: Default::default(),
+        });
+
+        let layer = source_less_layer(background_paint(), None);
+        let background = tiles.find_layer(coords, &layer, &HashSet::new()).unwrap();
+        assert_eq!(background.extent, 256);
+        assert_eq!(background.style_layer_id, "layer");
+        assert_eq!(tiles.background_tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_layers_in_draw_order() {
+        let mut tiles = Tiles::default();
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let data = |id: &str| {
+            VectorLayerData::Available(BackgroundTileBuilder::new().with_style_layer_id(id).build())
+        };
+        tiles.spawn_mut(coords).unwrap().insert(VectorLayersDataComponent {
+            done: true,
+            empty: false,
+            layers: vec![data("roads"), data("water"), data("hidden"), data("landuse")],
+            pending_chunks: vec![],
+            retessellate: Default::default(),
+        });
+
+        let layer = |id: &str, index: u32| StyleLayer {
+            id: id.to_string(),
+            index,
+            source_layer: Some(id.to_string()),
+            ..StyleLayer::default()
+        };
+        let mut hidden = layer("hidden", 1);
+        hidden.layout = Some(LayerLayout {
+            visibility: Some(Visibility::None),
+            ..LayerLayout::default()
+        });
+        let style = Style {
+            layers: vec![
+                layer("roads", 4),
+                layer("water", 2),
+                hidden,
+                layer("missing", 3),
+                layer("landuse", 0),
+                StyleLayer {
+                    index: 5,
+                    ..source_less_layer(background_paint(), None)
+                },
+            ],
+            ..Style::default()
+        };
+
+        let draw_order = |tiles: &Tiles| {
+            tiles
+                .layers_in_draw_order(coords, &style)
+                .map(|(style_layer, data)| {
+                    (style_layer.index, style_layer.id.clone(), data.style_layer_id.clone())
+                })
+                .collect::<Vec<_>>()
+        };
+        let layers = draw_order(&tiles);
+        assert_eq!(
+            layers,
+            vec![
+                (0, "landuse".to_string(), "landuse".to_string()),
+                (2, "water".to_string(), "water".to_string()),
+                (4, "roads".to_string(), "roads".to_string()),
+                (5, "layer".to_string(), "background".to_string()),
+            ]
+        );
+        assert!(layers.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_is_tile_complete() {
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let style = Style {
+            layers: vec![
+                source_less_layer(background_paint(), None),
+                StyleLayer {
+                    id: "water".to_string(),
+                    source_layer: Some("water".to_string()),
+                    ..StyleLayer::default()
+                },
+                StyleLayer {
+                    id: "roads".to_string(),
+                    source_layer: Some("transportation".to_string()),
+                    ..StyleLayer::default()
+                },
+                StyleLayer {
+                    id: "hidden".to_string(),
+                    source_layer: Some("transportation".to_string()),
+                    layout: Some(LayerLayout {
+                        visibility: Some(Visibility::None),
+                        ..LayerLayout::default()
+                    }),
+                    ..StyleLayer::default()
+                },
+            ],
+            ..Style::default()
+        };
+
+        let mut tiles = Tiles::default();
+        assert!(!tiles.is_tile_complete(coords, &style));
+        // Backgrounds need no data
+        let background_only = Style {
+            layers: vec![style.layers[0].clone()],
+            ..Style::default()
+        };
+        assert!(tiles.is_tile_complete(coords, &background_only));
+
+        // Half-loaded: the roads are still being tessellated
+        tiles.spawn_mut(coords).unwrap().insert(VectorLayersDataComponent {
+            done: false,
+            empty: false,
+            layers: vec![VectorLayerData::Available(
+                BackgroundTileBuilder::new().with_style_layer_id("water").build(),
+            )],
+            pending_chunks: vec![],
+            retessellate: Default::default(),
+        });
+        assert!(!tiles.is_tile_complete(coords, &style));
+
+        let component = tiles
+            .query_mut::<&mut VectorLayersDataComponent>(coords)
+            .unwrap();
+        component.layers.push(VectorLayerData::Missing(MissingVectorLayerData {
+            coords,
+            style_layer_id: "roads".to_string(),
+        }));
+        assert!(tiles.is_tile_complete(coords, &style));
+
+        // Layers which are tessellated again are not loaded, also once the tile is done
+        let component = tiles
+            .query_mut::<&mut VectorLayersDataComponent>(coords)
+            .unwrap();
+        component.layers.truncate(1);
+        component.done = true;
+        component.retessellate.insert("water".to_string());
+        assert!(!tiles.is_tile_complete(coords, &style));
+
+        // The roads are absent from the finished tile
+        let component = tiles
+            .query_mut::<&mut VectorLayersDataComponent>(coords)
+            .unwrap();
+        component.retessellate.clear();
+        assert!(tiles.is_tile_complete(coords, &style));
+    }
+}
@@ -0,0 +1,75 @@
+//! Exports tessellated geometry for inspection outside of the GPU, e.g. in Blender.
+
+use std::fmt::Write;
+
+use lyon::tessellation::VertexBuffers;
+
+use crate::{coords::WorldTileCoords, render::ShaderVertex};
+
+/// Serializes the triangles of a tessellated `buffer` to a Wavefront OBJ string. The vertices are
+/// placed in the z = 0 plane using their tile-local positions. The object is named after
+/// `layer_name` and the tile at `coords`.
+pub fn to_obj<I: Copy + Into<u32>>(
+    buffer: &VertexBuffers<ShaderVertex, I>,
+    coords: WorldTileCoords,
+    layer_name: &str,
+) -> String {
+    let mut obj = String::new();
+
+    writeln!(obj, "# {layer_name} at {coords}").unwrap();
+    writeln!(obj, "o {layer_name}_{}_{}_{}", coords.z, coords.x, coords.y).unwrap();
+
+    for vertex in &buffer.vertices {
+        let [x, y] = vertex.position;
+        writeln!(obj, "v {x} {y} 0").unwrap();
+    }
+
+    // Indices in OBJ are 1-based. Incomplete triangles at the end are skipped.
+    for triangle in buffer.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i].into() + 1);
+        writeln!(obj, "f {a} {b} {c}").unwrap();
+    }
+
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::tessellation::VertexBuffers;
+
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        render::ShaderVertex,
+        tessellation::{debug_export::to_obj, IndexDataType},
+    };
+
+    #[test]
+    fn test_quad_to_obj() {
+        let mut buffer = VertexBuffers::<ShaderVertex, IndexDataType>::new();
+        for position in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            buffer.vertices.push(ShaderVertex::new(position, [0.0, 0.0]));
+        }
+        buffer.indices.extend([0, 1, 2, 0, 2, 3]);
+
+        let obj = to_obj(&buffer, WorldTileCoords::from((1, 2, ZoomLevel::new(3))), "water");
+
+        let vertices: Vec<[f32; 3]> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("v "))
+            .map(|line| {
+                let mut coordinates = line.split(' ').map(|c| c.parse::<f32>().unwrap());
+                [0; 3].map(|_| coordinates.next().unwrap())
+            })
+            .collect();
+        let faces: Vec<Vec<u32>> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("f "))
+            .map(|line| line.split(' ').map(|i| i.parse().unwrap()).collect())
+            .collect();
+
+        assert!(obj.contains("o water_3_1_2"));
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(vertices[2], [1.0, 1.0, 0.0]);
+        assert_eq!(faces, vec![vec![1, 2, 3], vec![1, 3, 4]]);
+    }
+}
@@ -0,0 +1,111 @@
+//! Anchors for labels which follow lines, like the names of roads.
+
+use lyon::{
+    algorithms::walk::{walk_along_path, WalkerEvent},
+    math::Point,
+    path::{Path, PathEvent},
+};
+
+use crate::tessellation::DEFAULT_TOLERANCE;
+
+/// A position along a line at which a label is placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineAnchor {
+    pub point: Point,
+    /// The direction of the line at `point` in radians. Zero points along the x axis, positive
+    /// angles turn towards the y axis, which points down in tile coordinates.
+    pub angle: f32,
+}
+
+/// Samples anchors every `spacing` units along each sub-path of the line `path`. The first anchor
+/// of each sub-path is placed after half of the spacing, such that the anchors of a line are
+/// distributed evenly. Sub-paths which are shorter than that get no anchor. Returns no anchors if
+/// `spacing` is not positive.
+pub fn line_anchors(path: &Path, spacing: f32) -> Vec<LineAnchor> {
+    let mut anchors = Vec::new();
+    if spacing.is_nan() || spacing <= 0.0 {
+        return anchors;
+    }
+
+    // Each sub-path is walked on its own, otherwise the distance which is left at the end of a
+    // sub-path would be carried over to the next one
+    let mut sub_path = Vec::new();
+    for event in path.iter() {
+        sub_path.push(event);
+        if !matches!(event, PathEvent::End { .. }) {
+            continue;
+        }
+
+        let mut pattern = |event: WalkerEvent| {
+            anchors.push(LineAnchor {
+                point: event.position,
+                angle: event.tangent.angle_from_x_axis().radians,
+            });
+            Some(spacing)
+        };
+        walk_along_path(sub_path.drain(..), spacing / 2.0, DEFAULT_TOLERANCE, &mut pattern);
+    }
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use lyon::{math::point, path::Path};
+
+    use super::line_anchors;
+
+    fn line(points: &[[f32; 2]]) -> Path {
+        let mut builder = Path::builder();
+        builder.begin(point(points[0][0], points[0][1]));
+        for [x, y] in &points[1..] {
+            builder.line_to(point(*x, *y));
+        }
+        builder.end(false);
+        builder.build()
+    }
+
+    #[test]
+    fn test_horizontal_line() {
+        let anchors = line_anchors(&line(&[[0.0, 0.0], [100.0, 0.0]]), 25.0);
+
+        let xs: Vec<f32> = anchors.iter().map(|anchor| anchor.point.x).collect();
+        assert_eq!(xs, vec![12.5, 37.5, 62.5, 87.5]);
+        assert!(anchors
+            .iter()
+            .all(|anchor| anchor.point.y == 0.0 && anchor.angle == 0.0));
+    }
+
+    #[test]
+    fn test_bent_line() {
+        // Right along x and then down along y
+        let anchors = line_anchors(&line(&[[0.0, 0.0], [30.0, 0.0], [30.0, 30.0]]), 20.0);
+
+        assert_eq!(anchors.len(), 3);
+        assert_eq!(anchors[0].point, point(10.0, 0.0));
+        assert_eq!(anchors[0].angle, 0.0);
+        assert_eq!(anchors[1].point, point(30.0, 0.0));
+        assert!((anchors[2].point - point(30.0, 20.0)).length() < 1e-4);
+        assert!((anchors[2].angle - FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sub_paths_are_separate_lines() {
+        let mut builder = Path::builder();
+        for y in [0.0, 10.0] {
+            builder.begin(point(0.0, y));
+            builder.line_to(point(15.0, y));
+            builder.end(false);
+        }
+
+        let anchors = line_anchors(&builder.build(), 20.0);
+        assert_eq!(anchors.len(), 2);
+        assert_eq!(anchors[0].point, point(10.0, 0.0));
+        assert_eq!(anchors[1].point, point(10.0, 10.0));
+
+        assert!(line_anchors(&line(&[[0.0, 0.0], [100.0, 0.0]]), 0.0).is_empty());
+        // Too short for the first anchor
+        assert!(line_anchors(&line(&[[0.0, 0.0], [5.0, 0.0]]), 20.0).is_empty());
+    }
+}
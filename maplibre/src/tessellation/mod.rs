@@ -1,21 +1,98 @@
 //! Tessellation for lines and polygons is implemented here.
 
+use std::collections::HashMap;
+
 use bytemuck::Pod;
+use geozero::FeatureProcessor;
 use lyon::tessellation::{
     FillVertex, FillVertexConstructor, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
 };
 
-use crate::render::ShaderVertex;
+use crate::{
+    render::ShaderVertex,
+    style::{expression::ComparisonLiteral, layer::StyleLayer},
+    vector::VectorTileRequest,
+};
 
+#[cfg(feature = "debug-export")]
+pub mod debug_export;
+pub mod line_anchors;
+#[cfg(test)]
+mod snapshot;
+pub mod vertex_buffers_pool;
 pub mod zero_tessellator;
 
 const DEFAULT_TOLERANCE: f32 = 0.02;
 
-/// Vertex buffers index data type.
-pub type IndexDataType = u32; // Must match INDEX_FORMAT
+/// Vertex buffers index data type. Indices are narrowed to 16 bits when they are uploaded, if
+/// possible, see [`OverAlignedVertexBuffer::index_format`].
+pub type IndexDataType = u32;
+
+/// The smallest index format which can address `vertex_count` vertices.
+pub fn index_format_for(vertex_count: usize) -> wgpu::IndexFormat {
+    if vertex_count < 1 << 16 {
+        wgpu::IndexFormat::Uint16
+    } else {
+        wgpu::IndexFormat::Uint32
+    }
+}
+
+/// Turns the features of a vector tile layer into triangles. The features are passed through the
+/// `geozero` processor traits. [`zero_tessellator::ZeroTessellator`] is used by default, other
+/// backends can be plugged in with
+/// [`process_vector_tile_with`](crate::vector::process_vector_tile_with).
+pub trait Tessellator: FeatureProcessor + Sized {
+    /// Creates a tessellator for the features of `style_layer` within the tile of `request`.
+    /// `extent` is the extent of the tile layer.
+    fn for_style_layer(request: &VectorTileRequest, style_layer: &StyleLayer, extent: u32) -> Self;
+
+    fn buffer(&self) -> &VertexBuffers<ShaderVertex, IndexDataType>;
+
+    /// Holds for each feature the count of indices within [`Tessellator::buffer`]. Features are
+    /// listed in the order in which they appear in the tile, unless they are sorted by a sort key.
+    /// Tessellating the same layer again yields the same order, which is the draw order of the
+    /// features, see [`ShaderFeatureStyle::feature_order`](crate::render::shaders::ShaderFeatureStyle::feature_order).
+    fn feature_indices(&self) -> &[u32];
+
+    /// Holds for each feature the value of the property which the line width depends on, if it
+    /// depends on the properties of the features. The width is evaluated at the zoom of the view,
+    /// see [`DataDrivenQuantity::evaluate_value`](crate::style::layer::DataDrivenQuantity::evaluate_value).
+    fn feature_line_width_values(&self) -> &[Option<f64>] {
+        &[]
+    }
+
+    /// Holds for each feature the value of the property which the opacity depends on, if it
+    /// depends on the properties of the features.
+    fn feature_opacity_values(&self) -> &[Option<f64>] {
+        &[]
+    }
+
+    /// Holds for each feature its properties, if they are retained. Empty otherwise.
+    fn feature_properties(&self) -> &[HashMap<String, ComparisonLiteral>] {
+        &[]
+    }
+
+    /// Whether features were skipped, e.g. because the time budget was exceeded.
+    fn is_truncated(&self) -> bool {
+        false
+    }
+
+    /// Describes problems with the tessellated features, e.g. geometries which do not match the
+    /// paint of the layer.
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn into_buffer(self) -> VertexBuffers<ShaderVertex, IndexDataType>;
+}
 
 /// Constructor for Fill and Stroke vertices.
-pub struct VertexConstructor {}
+#[derive(Default)]
+pub struct VertexConstructor {
+    /// Tags stroke vertices with the side of the centerline and this blur, see
+    /// [`ShaderVertex::edge`].
+    pub line_blur: f32,
+}
 
 impl FillVertexConstructor<ShaderVertex> for VertexConstructor {
     fn new_vertex(&mut self, vertex: FillVertex) -> ShaderVertex {
@@ -24,11 +101,19 @@ impl FillVertexConstructor<ShaderVertex> for VertexConstructor {
 }
 
 impl StrokeVertexConstructor<ShaderVertex> for VertexConstructor {
-    fn new_vertex(&mut self, vertex: StrokeVertex) -> ShaderVertex {
+    fn new_vertex(&mut self, mut vertex: StrokeVertex) -> ShaderVertex {
+        let edge = if vertex.side().is_positive() { 1.0 } else { -1.0 };
+        // Paths of casings carry their offset as custom attributes, see `ShaderVertex::offset`
+        let offset = match vertex.interpolated_attributes() {
+            [x, y] => [*x, *y],
+            _ => [0.0, 0.0],
+        };
         ShaderVertex::new(
             vertex.position_on_path().to_array(),
             vertex.normal().to_array(),
         )
+        .with_edge(edge, self.line_blur)
+        .with_offset(offset)
     }
 }
 
@@ -66,6 +151,32 @@ impl<V, I> OverAlignedVertexBuffer<V, I> {
     }
 }
 
+impl<V, I: Copy + Into<u32>> OverAlignedVertexBuffer<V, I> {
+    /// The format in which the indices are uploaded: 16 bit indices if the buffer has less than
+    /// 65536 vertices, which halves the size of the indices, and 32 bit indices otherwise.
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        index_format_for(self.buffer.vertices.len())
+    }
+
+    /// The usable indices encoded in [`Self::index_format`] and padded to
+    /// `wgpu::COPY_BUFFER_ALIGNMENT`.
+    pub fn index_bytes(&self) -> Vec<u8> {
+        let indices = self.buffer.indices[..self.usable_indices as usize]
+            .iter()
+            .map(|index| (*index).into());
+
+        let mut bytes: Vec<u8> = match self.index_format() {
+            wgpu::IndexFormat::Uint16 => indices
+                .flat_map(|index| (index as u16).to_ne_bytes())
+                .collect(),
+            wgpu::IndexFormat::Uint32 => indices.flat_map(u32::to_ne_bytes).collect(),
+        };
+        let align = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+        bytes.resize(bytes.len().div_ceil(align) * align, 0);
+        bytes
+    }
+}
+
 impl<V: Pod, I: Pod> From<VertexBuffers<V, I>> for OverAlignedVertexBuffer<V, I> {
     fn from(mut buffer: VertexBuffers<V, I>) -> Self {
         let usable_indices = buffer.indices.len() as u32;
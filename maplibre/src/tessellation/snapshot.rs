@@ -0,0 +1,324 @@
+//! Snapshots of the tessellation output of fixture tiles, which detect unintended changes of the
+//! tessellation across versions.
+//!
+//! The vertex and index counts of each layer, as well as a hash of its buffers, are compared
+//! against golden files in `src/tessellation/snapshots`. Set `UPDATE_TESSELLATION_SNAPSHOTS=1`
+//! to regenerate the golden files after an intended change of the tessellation.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use geozero::mvt::{tile, Message as _};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    coords::ZoomLevel,
+    io::apc::{Context, IntoMessage, Message, SendError},
+    style::{layer::StyleLayer, Style},
+    vector::{
+        process_vector_tile, DefaultVectorTransferables, LayerTessellated, ProcessVectorContext,
+        VectorTileRequest, VectorTransferables,
+    },
+};
+
+type DefaultLayerTessellated = <DefaultVectorTransferables as VectorTransferables>::LayerTessellated;
+
+/// Regenerates the golden files instead of comparing against them, if set.
+const UPDATE_ENV: &str = "UPDATE_TESSELLATION_SNAPSHOTS";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// The tessellation output of a single style layer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct LayerSnapshot {
+    features: usize,
+    vertices: usize,
+    indices: usize,
+    /// 64-bit FNV-1a hash of the vertex and index bytes, formatted as hex.
+    hash: String,
+}
+
+/// The tessellation output of a tile, keyed by the id of the style layer.
+type TileSnapshot = BTreeMap<String, LayerSnapshot>;
+
+struct CollectingContext {
+    messages: Arc<Mutex<Vec<Message>>>,
+}
+
+impl Context for CollectingContext {
+    fn send_back<T: IntoMessage>(&self, message: T) -> Result<(), SendError> {
+        self.messages.lock().unwrap().push(IntoMessage::into(message));
+        Ok(())
+    }
+}
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn style() -> Style {
+    let layer = |value| serde_json::from_value::<StyleLayer>(value).unwrap();
+    Style {
+        layers: vec![
+            layer(json!({
+                "id": "water",
+                "type": "fill",
+                "source": "openmaptiles",
+                "source-layer": "water",
+                "paint": {"fill-color": "#0000ff"}
+            })),
+            layer(json!({
+                "id": "roads",
+                "type": "line",
+                "source": "openmaptiles",
+                "source-layer": "roads",
+                "paint": {"line-color": "#ff0000", "line-width": 4}
+            })),
+        ],
+        ..Style::default()
+    }
+}
+
+/// Tessellates `data` with the fixture style and summarizes the output of each layer.
+fn snapshot(data: &[u8]) -> TileSnapshot {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    process_vector_tile(
+        data,
+        VectorTileRequest {
+            coords: (0, 0, ZoomLevel::default()).into(),
+            layers: HashSet::from(["water".to_string(), "roads".to_string()]),
+            style: style(),
+            style_layer_ids: None,
+            tessellation_budget: None,
+            chunk_size: None,
+            retain_feature_properties: false,
+            clip: None,
+        },
+        &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+            messages: messages.clone(),
+        }),
+    )
+    .unwrap();
+
+    let messages = std::mem::take(&mut *messages.lock().unwrap());
+    messages
+        .into_iter()
+        .filter(|message| message.has_tag(DefaultLayerTessellated::message_tag()))
+        .map(|message| {
+            let layer = message
+                .into_transferable::<DefaultLayerTessellated>()
+                .to_layer();
+            let vertices = &layer.buffer.buffer.vertices;
+            let indices = &layer.buffer.buffer.indices[..layer.buffer.usable_indices as usize];
+            let hash = fnv1a(
+                fnv1a(FNV_OFFSET_BASIS, bytemuck::cast_slice(vertices)),
+                bytemuck::cast_slice(indices),
+            );
+            let snapshot = LayerSnapshot {
+                features: layer.feature_indices.len(),
+                vertices: vertices.len(),
+                indices: indices.len(),
+                hash: format!("{hash:016x}"),
+            };
+            (layer.style_layer_id, snapshot)
+        })
+        .collect()
+}
+
+/// Lists the differences between the `expected` and `actual` snapshots, one per line.
+fn diff(expected: &TileSnapshot, actual: &TileSnapshot) -> String {
+    let mut diff = String::new();
+    let ids: HashSet<&String> = expected.keys().chain(actual.keys()).collect();
+    let mut ids: Vec<_> = ids.into_iter().collect();
+    ids.sort();
+
+    for id in ids {
+        match (expected.get(id), actual.get(id)) {
+            (Some(_), None) => writeln!(diff, "  {id}: layer is missing").unwrap(),
+            (None, Some(_)) => writeln!(diff, "  {id}: layer is unexpected").unwrap(),
+            (Some(expected), Some(actual)) => {
+                let fields = [
+                    ("features", expected.features.to_string(), actual.features.to_string()),
+                    ("vertices", expected.vertices.to_string(), actual.vertices.to_string()),
+                    ("indices", expected.indices.to_string(), actual.indices.to_string()),
+                    ("hash", expected.hash.clone(), actual.hash.clone()),
+                ];
+                for (field, expected, actual) in fields {
+                    if expected != actual {
+                        writeln!(diff, "  {id}.{field}: expected {expected}, got {actual}").unwrap();
+                    }
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    diff
+}
+
+/// Compares the tessellation output of `data` against the golden file `name`, or regenerates it
+/// if [`UPDATE_ENV`] is set.
+fn assert_snapshot(name: &str, data: &[u8]) {
+    let actual = snapshot(data);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tessellation/snapshots")
+        .join(format!("{name}.json"));
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|error| {
+        panic!(
+            "failed to read snapshot {}: {error}. Run with {UPDATE_ENV}=1 to create it.",
+            path.display()
+        )
+    });
+    let expected: TileSnapshot = serde_json::from_str(&expected).unwrap();
+
+    if expected != actual {
+        panic!(
+            "tessellation of {name} differs from snapshot {}:\n{}\
+            Run with {UPDATE_ENV}=1 to update the snapshot if the change is intended.",
+            path.display(),
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// Encodes a command of an MVT geometry.
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+/// Encodes `points` as an MVT geometry. Polygon rings are closed with a ClosePath command.
+fn geometry(parts: &[&[(i32, i32)]], polygon: bool) -> Vec<u32> {
+    let zigzag = |value: i32| ((value << 1) ^ (value >> 31)) as u32;
+    let mut geometry = Vec::new();
+    let mut cursor = (0, 0);
+    for part in parts {
+        for (i, point) in part.iter().enumerate() {
+            if i == 0 {
+                geometry.push(command(1, 1));
+            } else if i == 1 {
+                geometry.push(command(2, part.len() as u32 - 1));
+            }
+            geometry.push(zigzag(point.0 - cursor.0));
+            geometry.push(zigzag(point.1 - cursor.1));
+            cursor = *point;
+        }
+        if polygon {
+            geometry.push(command(7, 1));
+        }
+    }
+    geometry
+}
+
+fn layer(name: &str, r#type: tile::GeomType, geometries: Vec<Vec<u32>>) -> tile::Layer {
+    tile::Layer {
+        version: 2,
+        name: name.to_string(),
+        features: geometries
+            .into_iter()
+            .enumerate()
+            .map(|(id, geometry)| tile::Feature {
+                id: Some(id as u64),
+                tags: vec![],
+                r#type: Some(r#type as i32),
+                geometry,
+            })
+            .collect(),
+        keys: vec![],
+        values: vec![],
+        extent: Some(4096),
+    }
+}
+
+/// A square, a square with a hole and a triangle.
+fn water_layer() -> tile::Layer {
+    layer(
+        "water",
+        tile::GeomType::Polygon,
+        vec![
+            geometry(&[&[(0, 0), (1024, 0), (1024, 1024), (0, 1024)]], true),
+            geometry(
+                &[
+                    &[(2048, 2048), (4096, 2048), (4096, 4096), (2048, 4096)],
+                    &[(2560, 2560), (2560, 3584), (3584, 3584), (3584, 2560)],
+                ],
+                true,
+            ),
+            geometry(&[&[(0, 2048), (1024, 4096), (0, 4096)]], true),
+        ],
+    )
+}
+
+/// A straight line, a line with a sharp bend and a multi line.
+fn roads_layer() -> tile::Layer {
+    layer(
+        "roads",
+        tile::GeomType::Linestring,
+        vec![
+            geometry(&[&[(0, 0), (4096, 4096)]], false),
+            geometry(&[&[(512, 3584), (2048, 512), (3584, 3584)]], false),
+            geometry(
+                &[&[(0, 2048), (4096, 2048)], &[(2048, 0), (2048, 1024), (3072, 1024)]],
+                false,
+            ),
+        ],
+    )
+}
+
+fn tile(layers: Vec<tile::Layer>) -> Vec<u8> {
+    geozero::mvt::Tile { layers }.encode_to_vec()
+}
+
+#[test]
+fn test_fill_snapshot() {
+    assert_snapshot("fill", &tile(vec![water_layer()]));
+}
+
+#[test]
+fn test_line_snapshot() {
+    assert_snapshot("line", &tile(vec![roads_layer()]));
+}
+
+#[test]
+fn test_mixed_snapshot() {
+    assert_snapshot("mixed", &tile(vec![water_layer(), roads_layer()]));
+}
+
+#[test]
+fn test_diff() {
+    let layer = LayerSnapshot {
+        features: 1,
+        vertices: 4,
+        indices: 6,
+        hash: "0".to_string(),
+    };
+    let expected = TileSnapshot::from([
+        ("water".to_string(), layer.clone()),
+        ("roads".to_string(), layer.clone()),
+    ]);
+    let actual = TileSnapshot::from([(
+        "water".to_string(),
+        LayerSnapshot {
+            vertices: 5,
+            ..layer
+        },
+    )]);
+
+    assert_eq!(
+        diff(&expected, &actual),
+        "  roads: layer is missing\n  water.vertices: expected 4, got 5\n"
+    );
+}
@@ -0,0 +1,145 @@
+//! Recycles the allocations of [`VertexBuffers`] across tessellations, e.g. when tiles are
+//! tessellated again after the style changed.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use lyon::tessellation::VertexBuffers;
+
+/// The number of buffers which are kept per size class by default.
+pub const DEFAULT_BUFFERS_PER_CLASS: usize = 8;
+
+/// The recycled buffers by their size class.
+type SizeClasses<V, I> = BTreeMap<u32, Vec<VertexBuffers<V, I>>>;
+
+/// Keeps the allocations of released [`VertexBuffers`] by size class. The size class of a buffer
+/// is the capacity of its vertices rounded down to a power of two. Clones share the same buffers.
+#[derive(Clone, Debug)]
+pub struct VertexBuffersPool<V, I> {
+    classes: Arc<Mutex<SizeClasses<V, I>>>,
+    buffers_per_class: usize,
+}
+
+impl<V, I> Default for VertexBuffersPool<V, I> {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUFFERS_PER_CLASS)
+    }
+}
+
+impl<V, I> VertexBuffersPool<V, I> {
+    /// Creates a pool which keeps up to `buffers_per_class` buffers of each size class. Further
+    /// buffers are dropped when they are recycled.
+    pub fn new(buffers_per_class: usize) -> Self {
+        Self {
+            classes: Default::default(),
+            buffers_per_class,
+        }
+    }
+
+    /// The size class of buffers which can hold at least `vertices` vertices.
+    fn size_class(vertices: usize) -> u32 {
+        vertices.max(1).next_power_of_two().trailing_zeros()
+    }
+
+    /// Returns an empty buffer with room for at least `vertices` vertices. The allocation of a
+    /// recycled buffer of the smallest fitting size class is reused if there is one.
+    pub fn take(&self, vertices: usize) -> VertexBuffers<V, I> {
+        let mut classes = self.classes.lock().unwrap();
+        let recycled = classes
+            .range_mut(Self::size_class(vertices)..)
+            .find_map(|(_, buffers)| buffers.pop());
+
+        recycled.unwrap_or_else(|| VertexBuffers::with_capacity(vertices, vertices))
+    }
+
+    /// Clears `buffers` and keeps its allocation for later tessellations.
+    pub fn recycle(&self, mut buffers: VertexBuffers<V, I>) {
+        buffers.vertices.clear();
+        buffers.indices.clear();
+
+        let capacity = buffers.vertices.capacity();
+        if capacity == 0 {
+            return;
+        }
+
+        // Round down, so that each buffer of a class fits the requests for that class
+        let class = usize::BITS - 1 - capacity.leading_zeros();
+        let mut classes = self.classes.lock().unwrap();
+        let buffers_of_class = classes.entry(class).or_default();
+        if buffers_of_class.len() < self.buffers_per_class {
+            buffers_of_class.push(buffers);
+        }
+    }
+
+    /// The number of recycled buffers which are kept.
+    pub fn len(&self) -> usize {
+        self.classes.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::tessellation::VertexBuffers;
+
+    use super::VertexBuffersPool;
+
+    fn filled(vertices: usize) -> VertexBuffers<u32, u32> {
+        let mut buffers = VertexBuffers::with_capacity(vertices, vertices);
+        buffers.vertices.extend(0..vertices as u32);
+        buffers.indices.extend(0..vertices as u32);
+        buffers
+    }
+
+    #[test]
+    fn test_recycled_buffer_is_cleared_and_reused() {
+        let pool = VertexBuffersPool::default();
+        let buffers = filled(100);
+        let allocation = buffers.vertices.as_ptr();
+
+        pool.recycle(buffers);
+        assert_eq!(pool.len(), 1);
+
+        // The request fits into the size class of the recycled buffer
+        let reused = pool.take(60);
+        assert!(pool.is_empty());
+        assert_eq!(reused.vertices.as_ptr(), allocation);
+        assert!(reused.vertices.is_empty());
+        assert!(reused.indices.is_empty());
+        assert!(reused.vertices.capacity() >= 60);
+    }
+
+    #[test]
+    fn test_size_classes() {
+        let pool = VertexBuffersPool::default();
+        pool.recycle(filled(100));
+
+        // Too large for the recycled buffer, which is kept
+        let fresh = pool.take(200);
+        assert!(fresh.vertices.capacity() >= 200);
+        assert_eq!(pool.len(), 1);
+
+        // Shared between clones
+        let clone = pool.clone();
+        assert!(clone.take(10).vertices.capacity() >= 100);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_buffers_per_class() {
+        let pool = VertexBuffersPool::new(2);
+        for _ in 0..3 {
+            pool.recycle(filled(100));
+        }
+        assert_eq!(pool.len(), 2);
+
+        // Buffers without an allocation are not kept
+        pool.recycle(VertexBuffers::with_capacity(0, 0));
+        assert_eq!(pool.len(), 2);
+    }
+}
@@ -1,12 +1,17 @@
 //! Tessellator implementation.
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use cgmath::Point2;
 use geozero::{ColumnValue, FeatureProcessor, GeomProcessor, PropertyProcessor};
 use lyon::{
     geom,
     lyon_tessellation::VertexBuffers,
-    path::{path::Builder, Path},
+    path::{
+        path::{Builder, BuilderWithAttributes},
+        Event, Path,
+    },
     tessellation::{
         geometry_builder::MaxIndex, BuffersBuilder, FillOptions, FillRule, FillTessellator,
         StrokeOptions, StrokeTessellator,
@@ -15,9 +20,16 @@ use lyon::{
 
 use crate::{
     render::ShaderVertex,
-    tessellation::{VertexConstructor, DEFAULT_TOLERANCE},
+    tessellation::{IndexDataType, Tessellator, VertexConstructor, DEFAULT_TOLERANCE},
+    util::math::Aabb2,
+    vector::VectorTileRequest,
+};
+use crate::coords::{LatLon, WorldTileCoords, ZoomLevel};
+use crate::style::expression::{
+    ComparisonLiteral, EvaluationOptions, FilterTrace, LegacyFilterExpression,
 };
-use crate::style::expression::{ComparisonLiteral, LegacyFilterExpression};
+use crate::style::layer::{DataDrivenQuantity, LayerPaint, StyleLayer};
+use crate::style::util::interpolate;
 
 type GeoResult<T> = geozero::error::Result<T>;
 
@@ -27,14 +39,83 @@ pub struct ZeroTessellator<I: std::ops::Add + From<lyon::tessellation::VertexId>
     path_open: bool,
     is_point: bool,
 
+    normalize_winding: bool,
+    /// Index of the current ring within the current polygon, if winding is normalized.
+    ring_index: Option<usize>,
+    /// Points of the current ring, if winding is normalized.
+    ring: Vec<geom::Point<f32>>,
+
     pub buffer: VertexBuffers<ShaderVertex, I>,
 
     pub feature_indices: Vec<u32>,
+    /// Holds for each feature the value of the property which the line width depends on, see
+    /// [`DataDrivenQuantity::evaluate_value`]. Empty if the width does not depend on properties.
+    pub feature_line_width_values: Vec<Option<f64>>,
+    /// Holds for each feature the value of the property which the opacity depends on. Empty if
+    /// the opacity does not depend on properties.
+    pub feature_opacity_values: Vec<Option<f64>>,
     current_index: usize,
+    /// Holds for each feature its sort key, if `sort_key` is set.
+    feature_sort_keys: Vec<Option<f32>>,
+    /// Whether all properties of the features are collected and kept in `feature_properties`.
+    retain_properties: bool,
+    /// Holds for each feature its properties, if `retain_properties` is set. Empty otherwise.
+    pub feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
     
     filter: Option<LegacyFilterExpression>,
+    /// Keys of the properties which are read by `filter`, `feature_line_width` or
+    /// `feature_opacity`. Only these are collected per feature.
+    property_keys: HashSet<String>,
     properties: HashMap<String, ComparisonLiteral>,
     filtered: bool,
+    /// Whether the filter rejects all lines or all polygons respectively, regardless of their
+    /// properties, see [`LegacyFilterExpression::matches_geometry_type`].
+    excludes_lines: bool,
+    excludes_polygons: bool,
+    /// Whether the filter rejects the geometry type of the current feature. Its path is then not
+    /// built at all.
+    skip_geometry: bool,
+    /// The tile and its extent, which locate the features if the filter reads their geometry.
+    tile: Option<(WorldTileCoords, f64)>,
+    /// Vertices of the current feature, if `tile` is set.
+    geometry: Vec<LatLon>,
+
+    feature_line_width: Option<DataDrivenQuantity>,
+    line_gap_width: Option<f32>,
+    feature_opacity: Option<DataDrivenQuantity>,
+    /// The width of the faded edges of lines, see [`ZeroTessellator::with_line_blur`].
+    line_blur: f32,
+    /// Vertices of lines which lie within this distance of the segment between their neighbors
+    /// are dropped before stroking, see [`ZeroTessellator::with_collinear_merging`].
+    collinear_tolerance: Option<f32>,
+
+    sort_key: Option<(DataDrivenQuantity, ZoomLevel)>,
+
+    /// Once passed, the remaining features are skipped.
+    deadline: Option<instant::Instant>,
+    /// Whether features were skipped because the `deadline` passed.
+    pub truncated: bool,
+
+    /// Whether the filter is traced for each feature which it rejects.
+    filter_diagnostics: bool,
+    /// Holds for each feature which the filter rejected its index and why, if
+    /// `filter_diagnostics` is set.
+    pub filter_traces: Vec<(u64, FilterTrace)>,
+    /// The index of the current feature.
+    feature_idx: u64,
+
+    /// The zoom level of the tile, which filters can read as `$zoom`.
+    zoom_level: Option<ZoomLevel>,
+
+    /// Whether the layer has a fill paint, which can only be drawn for polygons.
+    fill_paint: bool,
+    /// The number of features which were skipped because their geometry does not match the paint.
+    pub mismatched_features: usize,
+
+    /// Features whose bounding box does not intersect this region are skipped.
+    clip: Option<Aabb2<f64>>,
+    /// The bounding box of the current feature, if `clip` is set.
+    feature_bounds: Option<Aabb2<f64>>,
 }
 
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> Default
@@ -45,60 +126,392 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> Default
             path_builder: RefCell::new(Path::builder()),
             buffer: VertexBuffers::new(),
             feature_indices: Vec::new(),
+            feature_line_width_values: Vec::new(),
+            feature_opacity_values: Vec::new(),
             current_index: 0,
+            feature_sort_keys: Vec::new(),
+            retain_properties: false,
+            feature_properties: Vec::new(),
             path_open: false,
             is_point: false,
+            normalize_winding: false,
+            ring_index: None,
+            ring: Vec::new(),
             filter: None,
+            property_keys: Default::default(),
             properties: Default::default(),
             filtered: false,
+            excludes_lines: false,
+            excludes_polygons: false,
+            skip_geometry: false,
+            tile: None,
+            geometry: Vec::new(),
+            feature_line_width: None,
+            line_gap_width: None,
+            feature_opacity: None,
+            line_blur: 0.0,
+            collinear_tolerance: None,
+            sort_key: None,
+            deadline: None,
+            truncated: false,
+            filter_diagnostics: false,
+            filter_traces: Vec::new(),
+            feature_idx: 0,
+            zoom_level: None,
+            fill_paint: false,
+            mismatched_features: 0,
+            clip: None,
+            feature_bounds: None,
         }
     }
 }
 
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> ZeroTessellator<I> {
     pub fn new(filter: Option<LegacyFilterExpression>) -> Self {
+        let property_keys = filter
+            .as_ref()
+            .map(LegacyFilterExpression::referenced_keys)
+            .unwrap_or_default();
+        let excludes = |geometry_type| {
+            filter
+                .as_ref()
+                .is_some_and(|filter| filter.matches_geometry_type(geometry_type) == Some(false))
+        };
+        let excludes_lines = excludes("LineString");
+        let excludes_polygons = excludes("Polygon");
+
         Self {
             path_builder: RefCell::new(Path::builder()),
             buffer: VertexBuffers::new(),
             feature_indices: Vec::new(),
+            feature_line_width_values: Vec::new(),
+            feature_opacity_values: Vec::new(),
             current_index: 0,
+            feature_sort_keys: Vec::new(),
+            retain_properties: false,
+            feature_properties: Vec::new(),
             path_open: false,
             is_point: false,
+            normalize_winding: false,
+            ring_index: None,
+            ring: Vec::new(),
             filter,
+            property_keys,
             properties: Default::default(),
             filtered: false,
+            excludes_lines,
+            excludes_polygons,
+            skip_geometry: false,
+            tile: None,
+            geometry: Vec::new(),
+            feature_line_width: None,
+            line_gap_width: None,
+            feature_opacity: None,
+            line_blur: 0.0,
+            collinear_tolerance: None,
+            sort_key: None,
+            deadline: None,
+            truncated: false,
+            filter_diagnostics: false,
+            filter_traces: Vec::new(),
+            feature_idx: 0,
+            zoom_level: None,
+            fill_paint: false,
+            mismatched_features: 0,
+            clip: None,
+            feature_bounds: None,
         }
     }
     
+    /// Collects the value of the property which the line width depends on for each feature, see
+    /// [`ZeroTessellator::feature_line_width_values`]. The width itself is evaluated at the zoom
+    /// of the view when the layer is drawn. Widths which only depend on the zoom level are ignored.
+    pub fn with_feature_line_width(mut self, line_width: &DataDrivenQuantity) -> Self {
+        if let Some(property) = line_width.property() {
+            self.property_keys.insert(property.to_string());
+            self.feature_line_width = Some(line_width.clone());
+        }
+        self
+    }
+
+    /// Collects the value of the property which the opacity depends on for each feature, see
+    /// [`ZeroTessellator::feature_opacity_values`]. Opacities which only depend on the zoom level
+    /// are ignored.
+    pub fn with_feature_opacity(mut self, opacity: &DataDrivenQuantity) -> Self {
+        if let Some(property) = opacity.property() {
+            self.property_keys.insert(property.to_string());
+            self.feature_opacity = Some(opacity.clone());
+        }
+        self
+    }
+
+    /// Locates the features in the tile at `coords`, such that filters can be evaluated against
+    /// their geometry. Only has an effect if the filter reads the geometry, like `within`.
+    pub fn with_tile(mut self, coords: WorldTileCoords, extent: u32) -> Self {
+        if self.filter.as_ref().is_some_and(LegacyFilterExpression::uses_geometry) {
+            self.tile = Some((coords, extent as f64));
+        }
+        self
+    }
+
+    /// Sets the `$zoom` of each feature to `zoom_level`, e.g. to evaluate
+    /// [`StyleLayer::filter_with_zoom`].
+    pub fn with_zoom_level(mut self, zoom_level: ZoomLevel) -> Self {
+        self.zoom_level = Some(zoom_level);
+        self
+    }
+
+    /// Normalizes the winding order of polygon rings, such that the first ring of each polygon is
+    /// filled and all others are holes. This is required for sources which, unlike MVT, do not
+    /// guarantee a winding order, e.g. GeoJSON.
+    pub fn with_winding_normalization(mut self) -> Self {
+        self.normalize_winding = true;
+        self
+    }
+
+    /// Reorders the features by the `sort_key` which is computed from their properties at
+    /// `zoom_level` once the dataset ends. The order of features with equal keys is kept.
+    pub fn with_sort_key(mut self, sort_key: &DataDrivenQuantity, zoom_level: ZoomLevel) -> Self {
+        if let Some(property) = sort_key.property() {
+            self.property_keys.insert(property.to_string());
+        }
+        self.sort_key = Some((sort_key.clone(), zoom_level));
+        self
+    }
+
+    /// Limits the wall-clock time which is spent on tessellating, starting now. The budget is
+    /// checked after each feature. Once it is exceeded, the remaining features are skipped and
+    /// [`ZeroTessellator::truncated`] is set.
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.deadline = Some(instant::Instant::now() + budget);
+        self
+    }
+
+    /// Tessellates into `buffer`, e.g. to reuse an allocation from a
+    /// [`VertexBuffersPool`](crate::tessellation::vertex_buffers_pool::VertexBuffersPool). The
+    /// buffer is expected to be empty.
+    pub fn with_buffer(mut self, buffer: VertexBuffers<ShaderVertex, I>) -> Self {
+        debug_assert!(buffer.vertices.is_empty() && buffer.indices.is_empty());
+        self.buffer = buffer;
+        self
+    }
+
+    /// Keeps all properties of each feature in [`ZeroTessellator::feature_properties`], e.g. to
+    /// look up the attributes of rendered features.
+    pub fn with_retained_properties(mut self) -> Self {
+        self.retain_properties = true;
+        self
+    }
+
+    /// Records why the filter rejected features in [`ZeroTessellator::filter_traces`], e.g. to
+    /// debug styles.
+    pub fn with_filter_diagnostics(mut self) -> Self {
+        self.filter_diagnostics = true;
+        self
+    }
+
+    /// Skips line features, because a fill paint can only be drawn for polygons. Skipped
+    /// features are counted in [`ZeroTessellator::mismatched_features`].
+    pub fn with_fill_paint(mut self) -> Self {
+        self.fill_paint = true;
+        self
+    }
+
+    /// If set, lines are stroked as two parallel casings which are separated by a gap. Both are
+    /// stroked along the centerline and moved apart in the shader, see [`ShaderVertex::offset`].
+    pub fn with_line_gap_width(mut self, line_gap_width: Option<f32>) -> Self {
+        self.line_gap_width = line_gap_width;
+        self
+    }
+
+    /// Only tessellates features whose bounding box intersects `clip`, which is given in the
+    /// coordinates of the features. Other features are skipped like features which are rejected
+    /// by the filter.
+    pub fn with_clip(mut self, clip: Aabb2<f64>) -> Self {
+        self.clip = Some(clip);
+        self
+    }
+
+    /// Fades out the edges of lines over `line_blur` pixels. Stroke vertices are tagged with
+    /// the blur and their side of the centerline, see [`ShaderVertex::edge`].
+    pub fn with_line_blur(mut self, line_blur: f32) -> Self {
+        self.line_blur = line_blur;
+        self
+    }
+
+    /// Merges collinear segments of lines before stroking them, by dropping the vertices which
+    /// lie within `tolerance` of the segment between their neighbors. This reduces the stroke
+    /// geometry of lines with redundant vertices, which are common in vector tiles.
+    pub fn with_collinear_merging(mut self, tolerance: f32) -> Self {
+        self.collinear_tolerance = Some(tolerance);
+        self
+    }
+
     fn cur_feature_matches_filter(&self) -> bool {
-        self.filter.as_ref().is_none_or(|filter| filter.evaluate(&self.properties))
+        self.filter.as_ref().is_none_or(|filter| match self.tile {
+            Some(_) => filter.evaluate_with(
+                &self.properties,
+                &EvaluationOptions {
+                    geometry: Some(&self.geometry),
+                    ..Default::default()
+                },
+            ),
+            None => filter.evaluate(&self.properties),
+        })
     }
     
+    /// Whether the current feature lies outside of the `clip` region.
+    fn cur_feature_is_clipped(&self) -> bool {
+        self.clip.is_some_and(|clip| {
+            self.feature_bounds
+                .is_none_or(|bounds| !clip.intersects(&bounds))
+        })
+    }
+
+    /// Records why the filter rejected the current feature, if diagnostics are enabled.
+    fn trace_rejection(&mut self) {
+        let Some(filter) = self.filter.as_ref().filter(|_| self.filter_diagnostics) else {
+            return;
+        };
+        let geometry = self.tile.map(|_| self.geometry.as_slice());
+        let trace = filter.trace(
+            &self.properties,
+            &EvaluationOptions {
+                geometry,
+                ..Default::default()
+            },
+        );
+        self.filter_traces.push((self.feature_idx, trace));
+    }
+
     fn update_feature_indices(&mut self) {
         let next_index = self.buffer.indices.len();
         let indices = (next_index - self.current_index) as u32;
         self.feature_indices.push(indices);
         self.current_index = next_index;
+
+        if let Some(line_width) = &self.feature_line_width {
+            let value = line_width.property_value(&self.properties);
+            self.feature_line_width_values.push(value);
+        }
+        if let Some(opacity) = &self.feature_opacity {
+            let value = opacity.property_value(&self.properties);
+            self.feature_opacity_values.push(value);
+        }
+        if let Some((sort_key, zoom_level)) = &self.sort_key {
+            let sort_key = sort_key.evaluate(*zoom_level, &self.properties);
+            self.feature_sort_keys.push(sort_key);
+        }
+        if self.retain_properties {
+            let mut properties = self.properties.clone();
+            properties.remove("$type");
+            properties.remove("$zoom");
+            self.feature_properties.push(properties);
+        }
+    }
+
+    /// Reorders the indices of the features in the buffer by their sort keys. The sort is stable,
+    /// features with equal keys keep the order in which they appear in the tile.
+    fn sort_features(&mut self) {
+        if self.sort_key.is_none() {
+            return;
+        }
+
+        let mut indices = std::mem::take(&mut self.buffer.indices).into_iter();
+        let mut features: Vec<_> = self
+            .feature_indices
+            .iter()
+            .enumerate()
+            .map(|(feature, count)| {
+                let feature_indices: Vec<I> = indices.by_ref().take(*count as usize).collect();
+                let sort_key = self.feature_sort_keys.get(feature).copied().flatten();
+                (sort_key, feature, feature_indices)
+            })
+            .collect();
+
+        // Features without a key are drawn first
+        features.sort_by(|(a, ..), (b, ..)| match (a, b) {
+            (Some(a), Some(b)) => a.total_cmp(b),
+            _ => a.is_some().cmp(&b.is_some()),
+        });
+
+        let line_width_values = std::mem::take(&mut self.feature_line_width_values);
+        let opacity_values = std::mem::take(&mut self.feature_opacity_values);
+        let mut properties: Vec<_> = std::mem::take(&mut self.feature_properties)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.feature_indices.clear();
+        self.feature_sort_keys.clear();
+        for (sort_key, feature, feature_indices) in features {
+            self.feature_indices.push(feature_indices.len() as u32);
+            self.feature_sort_keys.push(sort_key);
+            if let Some(value) = line_width_values.get(feature) {
+                self.feature_line_width_values.push(*value);
+            }
+            if let Some(value) = opacity_values.get(feature) {
+                self.feature_opacity_values.push(*value);
+            }
+            if let Some(properties) = properties.get_mut(feature).and_then(Option::take) {
+                self.feature_properties.push(properties);
+            }
+            self.buffer.indices.extend(feature_indices);
+        }
+        self.buffer.indices.extend(indices);
     }
 
     fn tessellate_strokes(&mut self) {
         let path_builder = self.path_builder.replace(Path::builder());
 
+        if self.fill_paint {
+            self.filtered = true;
+            self.mismatched_features += 1;
+            return;
+        }
+
         self.properties.insert("$type".to_string(), ComparisonLiteral::String("LineString".to_string()));
+        if self.skip_geometry {
+            self.trace_rejection();
+            self.filtered = true;
+            return
+        }
+        if self.truncated || self.cur_feature_is_clipped() {
+            self.filtered = true;
+            return
+        }
         if !self.cur_feature_matches_filter() {
+            self.trace_rejection();
             self.filtered = true;
             return
         }
         
         log::info!("UNFILTERED LINE FILTER WAS {:?}\nTHIS LINE HAS PROPS {:?}", self.filter, self.properties);
 
-        StrokeTessellator::new()
-            .tessellate_path(
-                &path_builder.build(),
-                &StrokeOptions::tolerance(DEFAULT_TOLERANCE),
-                &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
-            )
-            .unwrap(); // TODO: Remove unwrap
+        let mut path = path_builder.build();
+        if let Some(tolerance) = self.collinear_tolerance {
+            path = merge_collinear(&path, tolerance);
+        }
+
+        let paths = match self.line_gap_width {
+            Some(gap_width) if gap_width > 0.0 => vec![offset_path(&path, 1.0), offset_path(&path, -1.0)],
+            _ => vec![path],
+        };
+
+        let mut tessellator = StrokeTessellator::new();
+        for path in paths {
+            tessellator
+                .tessellate_path(
+                    &path,
+                    &StrokeOptions::tolerance(DEFAULT_TOLERANCE),
+                    &mut BuffersBuilder::new(
+                        &mut self.buffer,
+                        VertexConstructor {
+                            line_blur: self.line_blur,
+                        },
+                    ),
+                )
+                .unwrap(); // TODO: Remove unwrap
+        }
     }
 
     fn end(&mut self, close: bool) {
@@ -108,11 +521,46 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> ZeroTesse
         }
     }
 
+    /// Adds the current ring to the path. Exterior rings are oriented like in MVT, that is with a
+    /// positive signed area, while interior rings are reversed to a negative one.
+    fn end_ring(&mut self) {
+        let Some(ring_index) = self.ring_index else {
+            return;
+        };
+        self.ring_index = Some(ring_index + 1);
+
+        let mut ring = std::mem::take(&mut self.ring);
+        let is_exterior = ring_index == 0;
+        if (signed_area(&ring) > 0.0) != is_exterior {
+            ring.reverse();
+        }
+
+        let [first, rest @ ..] = ring.as_slice() else {
+            return;
+        };
+        let mut path_builder = self.path_builder.borrow_mut();
+        path_builder.begin(*first);
+        for point in rest {
+            path_builder.line_to(*point);
+        }
+        path_builder.end(true);
+    }
+
     fn tessellate_fill(&mut self) {
         let path_builder = self.path_builder.replace(Path::builder());
         
         self.properties.insert("$type".to_string(), ComparisonLiteral::String("Polygon".to_string()));
+        if self.skip_geometry {
+            self.trace_rejection();
+            self.filtered = true;
+            return
+        }
+        if self.truncated || self.cur_feature_is_clipped() {
+            self.filtered = true;
+            return
+        }
         if !self.cur_feature_matches_filter() {
+            self.trace_rejection();
             self.filtered = true;
             return
         }
@@ -122,20 +570,279 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> ZeroTesse
             .tessellate_path(
                 &path_builder.build(),
                 &FillOptions::tolerance(DEFAULT_TOLERANCE).with_fill_rule(FillRule::NonZero),
-                &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
+                &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor::default()),
             )
             .unwrap(); // TODO: Remove unwrap
     }
 }
 
+/// Computes the signed area of `ring` with the shoelace formula. Rings which are clockwise in tile
+/// coordinates, where y points down, have a positive area.
+fn signed_area(ring: &[geom::Point<f32>]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+/// Annotates every sub path of `path` with the direction in which it is moved along its left-hand
+/// normal, scaled by `side`. The direction is stored as two custom attributes of each point, see
+/// [`ShaderVertex::offset`]. Corners are joined with a miter. Curves are not supported, as they
+/// do not occur in vector tiles.
+fn offset_path(path: &Path, side: f32) -> Path {
+    let mut builder = Path::builder_with_attributes(2);
+    let mut polyline: Vec<geom::Point<f32>> = Vec::new();
+
+    for event in path.iter() {
+        match event {
+            Event::Begin { at } => {
+                polyline.clear();
+                polyline.push(at);
+            }
+            Event::Line { to, .. } => {
+                if polyline.last() != Some(&to) {
+                    polyline.push(to);
+                }
+            }
+            Event::End { close, .. } => {
+                offset_polyline(&mut builder, &polyline, side, close);
+            }
+            Event::Quadratic { .. } | Event::Cubic { .. } => {
+                log::warn!("curves are not supported when offsetting lines");
+            }
+        }
+    }
+
+    builder.build()
+}
+
+/// Drops the vertices of every sub path of `path` which lie within `tolerance` of the segment
+/// between their neighbors. Vertices at which the line turns back are kept. Curves are not
+/// supported, as they do not occur in vector tiles.
+fn merge_collinear(path: &Path, tolerance: f32) -> Path {
+    let mut builder = Path::builder();
+    let mut polyline: Vec<geom::Point<f32>> = Vec::new();
+
+    for event in path.iter() {
+        match event {
+            Event::Begin { at } => {
+                polyline.clear();
+                polyline.push(at);
+            }
+            Event::Line { to, .. } => {
+                // The previous vertex is redundant if it lies on the segment to `to`
+                if let [.., from, via] = polyline.as_slice() {
+                    if is_on_segment(*via, *from, to, tolerance) {
+                        polyline.pop();
+                    }
+                }
+                polyline.push(to);
+            }
+            Event::End { close, .. } => {
+                let [first, rest @ ..] = polyline.as_slice() else {
+                    continue;
+                };
+                builder.begin(*first);
+                for point in rest {
+                    builder.line_to(*point);
+                }
+                builder.end(close);
+            }
+            Event::Quadratic { .. } | Event::Cubic { .. } => {
+                log::warn!("curves are not supported when merging collinear segments");
+            }
+        }
+    }
+
+    builder.build()
+}
+
+/// Whether `point` lies within `tolerance` of the segment from `from` to `to`, between its ends.
+fn is_on_segment(
+    point: geom::Point<f32>,
+    from: geom::Point<f32>,
+    to: geom::Point<f32>,
+    tolerance: f32,
+) -> bool {
+    let segment = to - from;
+    let length = segment.square_length();
+    if length == 0.0 {
+        return false;
+    }
+
+    let t = (point - from).dot(segment) / length;
+    (0.0..=1.0).contains(&t) && (from + segment * t - point).length() <= tolerance
+}
+
+fn offset_polyline(
+    builder: &mut BuilderWithAttributes,
+    polyline: &[geom::Point<f32>],
+    side: f32,
+    close: bool,
+) {
+    let polyline = match polyline {
+        [first, rest @ .., last] if close && first == last => &polyline[..rest.len() + 1],
+        _ => polyline,
+    };
+    let len = polyline.len();
+    if len < 2 {
+        return;
+    }
+
+    let normal = |from: geom::Point<f32>, to: geom::Point<f32>| {
+        let direction = (to - from).normalize();
+        geom::vector(-direction.y, direction.x)
+    };
+
+    let offset_direction = |i: usize| {
+        let previous = if i > 0 {
+            Some(normal(polyline[i - 1], polyline[i]))
+        } else if close {
+            Some(normal(polyline[len - 1], polyline[0]))
+        } else {
+            None
+        };
+        let next = if i + 1 < len {
+            Some(normal(polyline[i], polyline[i + 1]))
+        } else if close {
+            Some(normal(polyline[len - 1], polyline[0]))
+        } else {
+            None
+        };
+
+        let miter = match (previous, next) {
+            (Some(previous), Some(next)) if (previous + next).square_length() > f32::EPSILON => {
+                let miter = (previous + next).normalize();
+                // Limit the length of the miter for very sharp corners
+                miter / miter.dot(next).max(0.25)
+            }
+            (_, Some(normal)) | (Some(normal), None) => normal,
+            (None, None) => unreachable!(),
+        };
+
+        (miter * side).to_array()
+    };
+
+    builder.begin(polyline[0], &offset_direction(0));
+    for (i, point) in polyline.iter().enumerate().skip(1) {
+        builder.line_to(*point, &offset_direction(i));
+    }
+    builder.end(close);
+}
+
+impl Tessellator for ZeroTessellator<IndexDataType> {
+    fn for_style_layer(request: &VectorTileRequest, style_layer: &StyleLayer, extent: u32) -> Self {
+        let coords = request.coords;
+        let mut tessellator = Self::new(style_layer.filter_with_zoom())
+            .with_tile(coords, extent)
+            .with_zoom_level(coords.z);
+
+        if let Some(LayerPaint::Line(paint)) = &style_layer.paint {
+            let line_gap_width = paint.line_gap_width
+                .as_ref()
+                .and_then(|gap_width| interpolate(gap_width, coords.z));
+            let line_blur = paint.line_blur
+                .as_ref()
+                .and_then(|blur| interpolate(blur, coords.z))
+                .unwrap_or(0.0);
+
+            tessellator = tessellator
+                .with_line_gap_width(line_gap_width)
+                .with_line_blur(line_blur);
+            if let Some(width) = &paint.line_width {
+                tessellator = tessellator.with_feature_line_width(width);
+            }
+        }
+        if let Some(LayerPaint::Fill(_)) = &style_layer.paint {
+            tessellator = tessellator.with_fill_paint();
+        }
+        if let Some(opacity) = style_layer.paint.as_ref().and_then(LayerPaint::get_feature_opacity) {
+            tessellator = tessellator.with_feature_opacity(opacity);
+        }
+        if request.retain_feature_properties {
+            tessellator = tessellator.with_retained_properties();
+        }
+        if let Some(budget) = request.tessellation_budget {
+            tessellator = tessellator.with_time_budget(budget);
+        }
+        if let Some(clip) = request.clip {
+            tessellator = tessellator.with_clip(clip);
+        }
+        if let Some(sort_key) = style_layer
+            .layout
+            .as_ref()
+            .and_then(|layout| layout.symbol_sort_key.as_ref())
+        {
+            tessellator = tessellator.with_sort_key(sort_key, coords.z);
+        }
+        tessellator
+    }
+
+    fn buffer(&self) -> &VertexBuffers<ShaderVertex, IndexDataType> {
+        &self.buffer
+    }
+
+    fn feature_indices(&self) -> &[u32] {
+        &self.feature_indices
+    }
+
+    fn feature_line_width_values(&self) -> &[Option<f64>] {
+        &self.feature_line_width_values
+    }
+
+    fn feature_opacity_values(&self) -> &[Option<f64>] {
+        &self.feature_opacity_values
+    }
+
+    fn feature_properties(&self) -> &[HashMap<String, ComparisonLiteral>] {
+        &self.feature_properties
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        if self.mismatched_features == 0 {
+            return Vec::new();
+        }
+        vec![format!(
+            "skipped {} line features, because a fill paint can only be drawn for polygons",
+            self.mismatched_features
+        )]
+    }
+
+    fn into_buffer(self) -> VertexBuffers<ShaderVertex, IndexDataType> {
+        self.buffer
+    }
+}
+
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProcessor
     for ZeroTessellator<I>
 {
     fn xy(&mut self, x: f64, y: f64, _idx: usize) -> GeoResult<()> {
         // log::info!("xy");
+        if self.skip_geometry {
+            return Ok(());
+        }
+
+        if let Some((coords, extent)) = &self.tile {
+            self.geometry.push(coords.lat_lon_at(x, y, *extent));
+        }
+        if self.clip.is_some() {
+            let point = Point2::new(x, y);
+            self.feature_bounds = Some(match self.feature_bounds {
+                Some(bounds) => bounds.grow(point),
+                None => Aabb2::new(point, point),
+            });
+        }
 
         if self.is_point {
             // log::info!("point");
+        } else if self.ring_index.is_some() {
+            self.ring.push(geom::point(x as f32, y as f32));
         } else if !self.path_open {
             self.path_builder
                 .borrow_mut()
@@ -171,14 +878,18 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProce
         Ok(())
     }
 
-    fn linestring_begin(&mut self, _tagged: bool, _size: usize, _idx: usize) -> GeoResult<()> {
+    fn linestring_begin(&mut self, tagged: bool, _size: usize, _idx: usize) -> GeoResult<()> {
         // log::info!("linestring_begin");
+        if tagged {
+            self.skip_geometry = self.excludes_lines;
+        }
         Ok(())
     }
 
     fn linestring_end(&mut self, tagged: bool, _idx: usize) -> GeoResult<()> {
         // log::info!("linestring_end");
 
+        self.end_ring();
         self.end(false);
 
         if tagged {
@@ -189,6 +900,7 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProce
 
     fn multilinestring_begin(&mut self, _size: usize, _idx: usize) -> GeoResult<()> {
         // log::info!("multilinestring_begin");
+        self.skip_geometry = self.excludes_lines;
         Ok(())
     }
 
@@ -198,14 +910,21 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProce
         Ok(())
     }
 
-    fn polygon_begin(&mut self, _tagged: bool, _size: usize, _idx: usize) -> GeoResult<()> {
+    fn polygon_begin(&mut self, tagged: bool, _size: usize, _idx: usize) -> GeoResult<()> {
         // log::info!("polygon_begin");
+        if tagged {
+            self.skip_geometry = self.excludes_polygons;
+        }
+        if self.normalize_winding {
+            self.ring_index = Some(0);
+        }
         Ok(())
     }
 
     fn polygon_end(&mut self, tagged: bool, _idx: usize) -> GeoResult<()> {
         // log::info!("polygon_end");
 
+        self.ring_index = None;
         self.end(true);
         if tagged {
             self.tessellate_fill();
@@ -215,6 +934,7 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProce
 
     fn multipolygon_begin(&mut self, _size: usize, _idx: usize) -> GeoResult<()> {
         // log::info!("multipolygon_begin");
+        self.skip_geometry = self.excludes_polygons;
         Ok(())
     }
 
@@ -230,7 +950,9 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> PropertyP
     for ZeroTessellator<I>
 {
     fn property(&mut self, _idx: usize, name: &str, value: &ColumnValue) -> geozero::error::Result<bool> {
-        self.properties.insert(name.to_string(), value.into());
+        if self.retain_properties || self.property_keys.contains(name) {
+            self.properties.insert(name.to_string(), value.into());
+        }
         Ok(true)
     }
 }
@@ -238,16 +960,667 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> PropertyP
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> FeatureProcessor
     for ZeroTessellator<I>
 {
-    fn feature_begin(&mut self, _idx: u64) -> geozero::error::Result<()> {
+    fn dataset_end(&mut self) -> geozero::error::Result<()> {
+        self.sort_features();
+        Ok(())
+    }
+
+    fn feature_begin(&mut self, idx: u64) -> geozero::error::Result<()> {
+        self.feature_idx = idx;
         self.properties.clear();
+        if let Some(zoom_level) = self.zoom_level {
+            let zoom = ComparisonLiteral::Integer(u8::from(zoom_level) as isize);
+            self.properties.insert("$zoom".to_string(), zoom);
+        }
+        self.geometry.clear();
+        self.feature_bounds = None;
         self.filtered = false;
+        self.skip_geometry = false;
         Ok(())
     }
     
     fn feature_end(&mut self, _idx: u64) -> geozero::error::Result<()> {
+        if self.truncated {
+            return Ok(());
+        }
         if !self.filtered {
             self.update_feature_indices();
         }
+        if self
+            .deadline
+            .is_some_and(|deadline| instant::Instant::now() >= deadline)
+        {
+            self.truncated = true;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::Point2;
+    use geozero::{ColumnValue, FeatureProcessor, GeomProcessor, PropertyProcessor};
+
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        style::{
+            expression::{ComparisonLiteral, LegacyFilterExpression},
+            layer::{DataDrivenQuantity, StyleLayer},
+        },
+        tessellation::{
+            vertex_buffers_pool::VertexBuffersPool, zero_tessellator::ZeroTessellator,
+            IndexDataType, Tessellator,
+        },
+        util::math::Aabb2,
+    };
+
+    fn tessellate_line(line_gap_width: Option<f32>) -> ZeroTessellator<IndexDataType> {
+        let mut tessellator =
+            ZeroTessellator::<IndexDataType>::default().with_line_gap_width(line_gap_width);
+
+        tessellator.linestring_begin(true, 2, 0).unwrap();
+        tessellator.xy(0.0, 0.0, 0).unwrap();
+        tessellator.xy(100.0, 0.0, 1).unwrap();
+        tessellator.linestring_end(true, 0).unwrap();
+        tessellator
+    }
+
+    #[test]
+    fn test_line_gap_width() {
+        let tessellator = tessellate_line(Some(10.0));
+
+        // Both casings are stroked along the centerline and carry the direction in which the
+        // shader moves them by (gap width + line width) / 2
+        let mut above = 0;
+        let mut below = 0;
+        for vertex in &tessellator.buffer.vertices {
+            assert!(vertex.position[1].abs() < 1e-4, "{vertex:?}");
+            let y = match vertex.offset {
+                [x, y] if x.abs() < 1e-4 && (y - 1.0).abs() < 1e-4 => {
+                    above += 1;
+                    6.0
+                }
+                [x, y] if x.abs() < 1e-4 && (y + 1.0).abs() < 1e-4 => {
+                    below += 1;
+                    -6.0
+                }
+                _ => panic!("vertex {vertex:?} is not on one of the casings"),
+            };
+
+            // Each band extends by half the line width, which keeps the gap empty
+            let extruded = (y + vertex.normal[1] * 1.0).abs();
+            assert!((5.0 - 1e-4..=7.0 + 1e-4).contains(&extruded));
+        }
+
+        assert!(above > 0 && below > 0);
+        assert_eq!(above, below);
+    }
+
+    #[test]
+    fn test_without_line_gap_width() {
+        let tessellator = tessellate_line(None);
+
+        assert!(!tessellator.buffer.vertices.is_empty());
+        assert!(tessellator
+            .buffer
+            .vertices
+            .iter()
+            .all(|vertex| vertex.position[1].abs() < 1e-4 && vertex.offset == [0.0, 0.0]));
+
+        let with_gap = tessellate_line(Some(10.0));
+        assert_eq!(
+            with_gap.buffer.indices.len(),
+            2 * tessellator.buffer.indices.len()
+        );
+    }
+
+    #[test]
+    fn test_line_blur() {
+        let mut tessellator = ZeroTessellator::<IndexDataType>::default().with_line_blur(1.5);
+        tessellator.linestring_begin(true, 2, 0).unwrap();
+        tessellator.xy(0.0, 0.0, 0).unwrap();
+        tessellator.xy(100.0, 0.0, 1).unwrap();
+        tessellator.linestring_end(true, 0).unwrap();
+
+        // Each stroke vertex carries the blur and lies on one side of the centerline
+        let vertices = &tessellator.buffer.vertices;
+        assert!(vertices.iter().all(|vertex| vertex.blur == 1.5));
+        assert!(vertices.iter().all(|vertex| vertex.edge.abs() == 1.0));
+        for vertex in vertices {
+            assert_eq!(vertex.edge.signum(), vertex.normal[1].signum(), "{vertex:?}");
+        }
+        let left = vertices.iter().filter(|vertex| vertex.edge < 0.0).count();
+        assert_eq!(left * 2, vertices.len());
+
+        // Lines are not blurred by default
+        let tessellator = tessellate_line(None);
+        assert!(tessellator.buffer.vertices.iter().all(|vertex| vertex.blur == 0.0));
+    }
+
+    fn tessellate_polyline(
+        mut tessellator: ZeroTessellator<IndexDataType>,
+        points: &[(f64, f64)],
+    ) -> ZeroTessellator<IndexDataType> {
+        tessellator.linestring_begin(true, points.len(), 0).unwrap();
+        for (idx, (x, y)) in points.iter().enumerate() {
+            tessellator.xy(*x, *y, idx).unwrap();
+        }
+        tessellator.linestring_end(true, 0).unwrap();
+        tessellator
+    }
+
+    #[test]
+    fn test_collinear_merging() {
+        // A straight line with redundant midpoints, one of which is slightly off the line
+        let points = [(0.0, 0.0), (25.0, 0.0), (50.0, 0.001), (75.0, 0.0), (100.0, 0.0)];
+
+        let plain = tessellate_polyline(ZeroTessellator::default(), &points);
+        let merged = tessellate_polyline(
+            ZeroTessellator::default().with_collinear_merging(0.01),
+            &points,
+        );
+        assert!(merged.buffer.vertices.len() < plain.buffer.vertices.len());
+        assert_eq!(
+            merged.buffer.vertices.len(),
+            tessellate_polyline(ZeroTessellator::default(), &[(0.0, 0.0), (100.0, 0.0)])
+                .buffer
+                .vertices
+                .len()
+        );
+
+        // Corners and vertices at which the line turns back are kept
+        for points in [
+            [(0.0, 0.0), (50.0, 0.0), (50.0, 50.0)],
+            [(0.0, 0.0), (100.0, 0.0), (50.0, 0.0)],
+        ] {
+            let plain = tessellate_polyline(ZeroTessellator::default(), &points);
+            let merged = tessellate_polyline(
+                ZeroTessellator::default().with_collinear_merging(0.01),
+                &points,
+            );
+            assert_eq!(merged.buffer.vertices.len(), plain.buffer.vertices.len());
+        }
+    }
+
+    fn tessellate_feature(
+        tessellator: &mut ZeroTessellator<IndexDataType>,
+        properties: &[(&str, ColumnValue)],
+    ) {
+        tessellator.feature_begin(0).unwrap();
+        for (idx, (name, value)) in properties.iter().enumerate() {
+            tessellator.property(idx, name, value).unwrap();
+        }
+        tessellator.linestring_begin(true, 2, 0).unwrap();
+        tessellator.xy(0.0, 0.0, 0).unwrap();
+        tessellator.xy(100.0, 0.0, 1).unwrap();
+        tessellator.linestring_end(true, 0).unwrap();
+        tessellator.feature_end(0).unwrap();
+    }
+
+    #[test]
+    fn test_clip() {
+        // The line from (0, 0) to (100, 0) crosses the region
+        let clip = Aabb2::new(Point2::new(50.0, -10.0), Point2::new(200.0, 10.0));
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None).with_clip(clip);
+        tessellate_feature(&mut tessellator, &[]);
+        assert_eq!(tessellator.feature_indices.len(), 1);
+        assert!(tessellator.feature_indices[0] > 0);
+
+        let clip = Aabb2::new(Point2::new(2048.0, 2048.0), Point2::new(4096.0, 4096.0));
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None).with_clip(clip);
+        tessellate_feature(&mut tessellator, &[]);
+        assert!(tessellator.feature_indices.is_empty());
+        assert!(tessellator.buffer.indices.is_empty());
+    }
+
+    #[test]
+    fn test_only_filtered_properties_are_collected() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["all", ["==", "$type", "LineString"], ["==", "class", "primary"]]"#)
+                .unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(Some(filter));
+
+        tessellate_feature(
+            &mut tessellator,
+            &[
+                ("class", ColumnValue::String("primary")),
+                ("name", ColumnValue::String("Main Street")),
+            ],
+        );
+        assert_eq!(tessellator.properties.len(), 2);
+        assert!(tessellator.properties.contains_key("class"));
+        assert!(tessellator.properties.contains_key("$type"));
+
+        tessellate_feature(
+            &mut tessellator,
+            &[
+                ("class", ColumnValue::String("path")),
+                ("name", ColumnValue::String("Main Street")),
+            ],
+        );
+
+        // Only the first feature matches the filter
+        assert_eq!(tessellator.feature_indices.len(), 1);
+        assert!(tessellator.feature_indices[0] > 0);
+    }
+
+    #[test]
+    fn test_feature_line_width_values() {
+        let line_width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "stops": [[1, 1], [5, 9]]}"#).unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_feature_line_width(&line_width);
+
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(1))]);
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(3))]);
+        tessellate_feature(&mut tessellator, &[("name", ColumnValue::String("path"))]);
+
+        // The widths are evaluated when the layer is drawn, the feature without a rank has no value
+        assert_eq!(tessellator.feature_line_width_values, vec![Some(1.0), Some(3.0), None]);
+        assert_eq!(tessellator.feature_indices.len(), 3);
+    }
+
+    #[test]
+    fn test_feature_opacity_values() {
+        let opacity: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "alpha", "type": "identity", "default": 1}"#)
+                .unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_feature_opacity(&opacity);
+
+        tessellate_feature(&mut tessellator, &[("alpha", ColumnValue::Double(0.25))]);
+        tessellate_feature(&mut tessellator, &[("alpha", ColumnValue::Double(0.75))]);
+        tessellate_feature(&mut tessellator, &[("name", ColumnValue::String("path"))]);
+
+        assert_eq!(tessellator.feature_opacity_values, vec![Some(0.25), Some(0.75), None]);
+
+        // Opacities which only depend on the zoom level are the same for all features
+        let opacity: DataDrivenQuantity = serde_json::from_str("0.5").unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_feature_opacity(&opacity);
+        tessellate_feature(&mut tessellator, &[("alpha", ColumnValue::Double(0.25))]);
+        assert!(tessellator.feature_opacity_values.is_empty());
+    }
+
+    #[test]
+    fn test_zoom_line_width_has_no_feature_line_width_values() {
+        let line_width: DataDrivenQuantity = serde_json::from_str("2").unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_feature_line_width(&line_width);
+
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(1))]);
+
+        assert!(tessellator.feature_line_width_values.is_empty());
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let sort_key: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "type": "identity"}"#).unwrap();
+        let line_width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "stops": [[0, 0], [10, 10]]}"#).unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_feature_line_width(&line_width)
+            .with_sort_key(&sort_key, ZoomLevel::new(10));
+
+        tessellator.dataset_begin(None).unwrap();
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(3))]);
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(1))]);
+        tessellate_feature(&mut tessellator, &[("name", ColumnValue::String("no rank"))]);
+        tessellate_feature(&mut tessellator, &[("rank", ColumnValue::Int(2))]);
+
+        let unsorted_indices = tessellator.buffer.indices.clone();
+        let count = tessellator.feature_indices[0] as usize;
+        tessellator.dataset_end().unwrap();
+
+        // The line width values identify the features
+        assert_eq!(
+            tessellator.feature_line_width_values,
+            vec![None, Some(1.0), Some(2.0), Some(3.0)]
+        );
+        assert_eq!(tessellator.feature_sort_keys, vec![None, Some(1.0), Some(2.0), Some(3.0)]);
+
+        // The indices of the first feature moved to the end
+        let indices = &tessellator.buffer.indices;
+        assert_eq!(indices.len(), unsorted_indices.len());
+        assert_eq!(indices[indices.len() - count..], unsorted_indices[..count]);
+    }
+
+    #[test]
+    fn test_stable_feature_order() {
+        let sort_key: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "type": "identity"}"#).unwrap();
+        let tessellate = || {
+            let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+                .with_sort_key(&sort_key, ZoomLevel::new(10))
+                .with_retained_properties();
+
+            tessellator.dataset_begin(None).unwrap();
+            for (rank, name) in [(1, "a"), (0, "b"), (1, "c"), (0, "d"), (1, "e")] {
+                tessellate_feature(
+                    &mut tessellator,
+                    &[("rank", ColumnValue::Int(rank)), ("name", ColumnValue::String(name))],
+                );
+            }
+            tessellator.dataset_end().unwrap();
+            tessellator
+        };
+        let names = |tessellator: &ZeroTessellator<IndexDataType>| -> Vec<ComparisonLiteral> {
+            tessellator
+                .feature_properties
+                .iter()
+                .map(|properties| properties["name"].clone())
+                .collect()
+        };
+
+        let first = tessellate();
+        // Features with equal sort keys keep the order of the tile
+        assert_eq!(
+            names(&first),
+            ["b", "d", "a", "c", "e"]
+                .map(|name| ComparisonLiteral::String(name.to_string()))
+                .to_vec()
+        );
+
+        let second = tessellate();
+        assert_eq!(names(&first), names(&second));
+        assert_eq!(first.feature_indices, second.feature_indices);
+        assert_eq!(first.buffer.indices, second.buffer.indices);
+    }
+
+    #[test]
+    fn test_retained_properties() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["!=", "class", "hidden"]"#).unwrap();
+        let sort_key: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "type": "identity"}"#).unwrap();
+        let line_width: DataDrivenQuantity =
+            serde_json::from_str(r#"{"property": "rank", "stops": [[0, 0], [10, 10]]}"#).unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(Some(filter))
+            .with_feature_line_width(&line_width)
+            .with_sort_key(&sort_key, ZoomLevel::new(10))
+            .with_retained_properties();
+
+        tessellator.dataset_begin(None).unwrap();
+        for (rank, name, class) in [(3, "c", "road"), (0, "x", "hidden"), (1, "a", "road"), (2, "b", "path")] {
+            tessellate_feature(
+                &mut tessellator,
+                &[
+                    ("rank", ColumnValue::Int(rank)),
+                    ("name", ColumnValue::String(name)),
+                    ("class", ColumnValue::String(class)),
+                ],
+            );
+        }
+        tessellator.dataset_end().unwrap();
+
+        // The filtered feature has neither indices nor properties
+        let properties = &tessellator.feature_properties;
+        assert_eq!(properties.len(), tessellator.feature_indices.len());
+        assert_eq!(properties.len(), 3);
+
+        // The properties were sorted together with the index ranges, which are identified by the
+        // line width values
+        for ((properties, line_width), name) in properties
+            .iter()
+            .zip(&tessellator.feature_line_width_values)
+            .zip(["a", "b", "c"])
+        {
+            assert_eq!(properties.len(), 3);
+            assert_eq!(properties["rank"], ComparisonLiteral::Integer(line_width.unwrap() as isize));
+            assert_eq!(properties["name"], ComparisonLiteral::String(name.to_string()));
+        }
+        assert_eq!(
+            tessellator.feature_indices.iter().sum::<u32>() as usize,
+            tessellator.buffer.indices.len()
+        );
+
+        // Without the option only the properties for the filter are collected and none retained
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None);
+        tessellate_feature(&mut tessellator, &[("name", ColumnValue::String("a"))]);
+        assert!(tessellator.feature_properties.is_empty());
+        assert!(!tessellator.properties.contains_key("name"));
+    }
+
+    #[test]
+    fn test_recycled_buffer() {
+        let pool = VertexBuffersPool::default();
+
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None).with_buffer(pool.take(0));
+        tessellate_feature(&mut tessellator, &[]);
+        let expected = tessellator.buffer.clone();
+        pool.recycle(tessellator.buffer);
+
+        // The recycled allocation holds nothing of the previous tessellation
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None).with_buffer(pool.take(1));
+        tessellate_feature(&mut tessellator, &[]);
+        assert!(pool.is_empty());
+        assert_eq!(tessellator.buffer.vertices.len(), expected.vertices.len());
+        assert_eq!(tessellator.buffer.indices, expected.indices);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut tessellator =
+            ZeroTessellator::<IndexDataType>::new(None).with_time_budget(Duration::ZERO);
+        tessellate_feature(&mut tessellator, &[]);
+        let indices = tessellator.buffer.indices.len();
+        tessellate_feature(&mut tessellator, &[]);
+        tessellate_feature(&mut tessellator, &[]);
+
+        // The budget is checked after a feature, so the first one is complete
+        assert!(tessellator.truncated);
+        assert_eq!(tessellator.feature_indices, vec![indices as u32]);
+        assert_eq!(tessellator.buffer.indices.len(), indices);
+
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None)
+            .with_time_budget(Duration::from_secs(60));
+        tessellate_feature(&mut tessellator, &[]);
+        tessellate_feature(&mut tessellator, &[]);
+        assert!(!tessellator.truncated);
+        assert_eq!(tessellator.feature_indices.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_diagnostics() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["all", ["==", "$type", "LineString"], ["==", "class", "primary"]]"#)
+                .unwrap();
+        let mut tessellator =
+            ZeroTessellator::<IndexDataType>::new(Some(filter.clone())).with_filter_diagnostics();
+        tessellate_feature(&mut tessellator, &[("class", ColumnValue::String("primary"))]);
+        tessellate_feature(&mut tessellator, &[("class", ColumnValue::String("minor"))]);
+
+        // Only the rejected feature is traced
+        assert_eq!(tessellator.filter_traces.len(), 1);
+        let (idx, trace) = &tessellator.filter_traces[0];
+        assert_eq!(*idx, 0);
+        assert_eq!(trace.rejected_by(), vec![&trace.children[1]]);
+
+        // Without the flag nothing is traced
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(Some(filter));
+        tessellate_feature(&mut tessellator, &[("class", ColumnValue::String("minor"))]);
+        assert!(tessellator.filter_traces.is_empty());
+    }
+
+    #[test]
+    fn test_excluded_geometry_type_is_not_built() {
+        let filter: LegacyFilterExpression =
+            serde_json::from_str(r#"["all", ["==", "$type", "Polygon"], ["has", "name"]]"#).unwrap();
+        let mut tessellator =
+            ZeroTessellator::<IndexDataType>::new(Some(filter)).with_filter_diagnostics();
+
+        // The path of the line is never started
+        tessellator.feature_begin(0).unwrap();
+        tessellator.property(0, "name", &ColumnValue::String("River")).unwrap();
+        tessellator.linestring_begin(true, 2, 0).unwrap();
+        tessellator.xy(0.0, 0.0, 0).unwrap();
+        tessellator.xy(100.0, 0.0, 1).unwrap();
+        assert!(!tessellator.path_open);
+        tessellator.linestring_end(true, 0).unwrap();
+        tessellator.feature_end(0).unwrap();
+        assert!(tessellator.feature_indices.is_empty());
+        assert_eq!(tessellator.filter_traces.len(), 1);
+
+        // Polygons are still built and filtered by their properties
+        for (idx, name) in [(1, Some("Lake")), (2, None)] {
+            tessellator.feature_begin(idx).unwrap();
+            if let Some(name) = name {
+                tessellator.property(0, "name", &ColumnValue::String(name)).unwrap();
+            }
+            tessellator.polygon_begin(true, 1, 0).unwrap();
+            tessellator.linestring_begin(false, 4, 0).unwrap();
+            for (i, (x, y)) in [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)].into_iter().enumerate() {
+                tessellator.xy(x, y, i).unwrap();
+            }
+            assert!(tessellator.path_open);
+            tessellator.linestring_end(false, 0).unwrap();
+            tessellator.polygon_end(true, 0).unwrap();
+            tessellator.feature_end(idx).unwrap();
+        }
+        assert_eq!(tessellator.feature_indices.len(), 1);
+        assert!(tessellator.feature_indices[0] > 0);
+        assert_eq!(tessellator.filter_traces.len(), 2);
+    }
+
+    #[test]
+    fn test_zoom_range_filter() {
+        let layer: StyleLayer = serde_json::from_str(
+            r#"{"id": "roads", "type": "line", "minzoom": 10, "maxzoom": 14, "filter": ["==", "class", "primary"]}"#,
+        )
+        .unwrap();
+        let tessellate = |zoom_level: u8| {
+            let mut tessellator = ZeroTessellator::<IndexDataType>::new(layer.filter_with_zoom())
+                .with_zoom_level(ZoomLevel::new(zoom_level))
+                .with_retained_properties();
+            tessellate_feature(&mut tessellator, &[("class", ColumnValue::String("primary"))]);
+            tessellator
+        };
+
+        // The feature passes the attribute filter, but is dropped outside of the zoom range
+        for zoom_level in [9, 14] {
+            let tessellator = tessellate(zoom_level);
+            assert!(tessellator.feature_indices.is_empty());
+            assert!(tessellator.buffer.indices.is_empty());
+        }
+
+        let tessellator = tessellate(12);
+        assert_eq!(tessellator.feature_indices.len(), 1);
+        assert!(!tessellator.buffer.indices.is_empty());
+        assert!(!tessellator.feature_properties[0].contains_key("$zoom"));
+    }
+
+    #[test]
+    fn test_fill_paint_skips_lines() {
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None).with_fill_paint();
+        tessellate_feature(&mut tessellator, &[]);
+        tessellate_feature(&mut tessellator, &[]);
+
+        assert_eq!(tessellator.mismatched_features, 2);
+        assert!(tessellator.buffer.indices.is_empty());
+        assert!(tessellator.feature_indices.is_empty());
+        assert_eq!(
+            tessellator.warnings(),
+            vec!["skipped 2 line features, because a fill paint can only be drawn for polygons"]
+        );
+
+        // Polygons are filled as usual
+        let tessellator = tessellate_square_with_hole(
+            ZeroTessellator::<IndexDataType>::new(None).with_fill_paint(),
+            false,
+        );
+        assert_eq!(tessellator.mismatched_features, 0);
+        assert!(!tessellator.buffer.indices.is_empty());
+        assert!(tessellator.warnings().is_empty());
+
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(None);
+        tessellate_feature(&mut tessellator, &[]);
+        assert!(tessellator.warnings().is_empty());
+    }
+
+    /// Tessellates a polygon with a 100x100 exterior ring and a 50x50 hole, both with the same
+    /// winding. `clockwise` is in tile coordinates, where y points down.
+    fn tessellate_square_with_hole(
+        mut tessellator: ZeroTessellator<IndexDataType>,
+        clockwise: bool,
+    ) -> ZeroTessellator<IndexDataType> {
+        let square = |min: f64, max: f64| {
+            let ring = [(min, min), (max, min), (max, max), (min, max), (min, min)];
+            if clockwise {
+                ring.to_vec()
+            } else {
+                ring.into_iter().rev().collect()
+            }
+        };
+
+        tessellator.polygon_begin(true, 2, 0).unwrap();
+        for ring in [square(0.0, 100.0), square(25.0, 75.0)] {
+            tessellator.linestring_begin(false, ring.len(), 0).unwrap();
+            for (idx, (x, y)) in ring.into_iter().enumerate() {
+                tessellator.xy(x, y, idx).unwrap();
+            }
+            tessellator.linestring_end(false, 0).unwrap();
+        }
+        tessellator.polygon_end(true, 0).unwrap();
+        tessellator
+    }
+
+    fn filled_area(tessellator: &ZeroTessellator<IndexDataType>) -> f32 {
+        let vertices = &tessellator.buffer.vertices;
+        tessellator
+            .buffer
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+                ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_winding_normalization() {
+        for clockwise in [true, false] {
+            let tessellator = tessellate_square_with_hole(
+                ZeroTessellator::<IndexDataType>::default().with_winding_normalization(),
+                clockwise,
+            );
+            assert!((filled_area(&tessellator) - 7500.0).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_without_winding_normalization() {
+        // The hole has the same winding as the exterior ring and is filled
+        let tessellator =
+            tessellate_square_with_hole(ZeroTessellator::<IndexDataType>::default(), true);
+        assert!((filled_area(&tessellator) - 10000.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_within_filter() {
+        let filter: LegacyFilterExpression = serde_json::from_str(
+            r#"["within", {"type": "Polygon", "coordinates": [
+                [[-10, -10], [10, -10], [10, 10], [-10, 10], [-10, -10]]
+            ]}]"#,
+        )
+        .unwrap();
+        let mut tessellator = ZeroTessellator::<IndexDataType>::new(Some(filter))
+            .with_tile(WorldTileCoords::from((0, 0, ZoomLevel::new(0))), 4096);
+
+        // A line through the center of the world tile is inside, one in its corner is outside
+        for (x, y) in [(2000.0, 2048.0), (0.0, 100.0)] {
+            tessellator.feature_begin(0).unwrap();
+            tessellator.linestring_begin(true, 2, 0).unwrap();
+            tessellator.xy(x, y, 0).unwrap();
+            tessellator.xy(x + 96.0, y, 1).unwrap();
+            tessellator.linestring_end(true, 0).unwrap();
+            tessellator.feature_end(0).unwrap();
+        }
+
+        assert_eq!(tessellator.feature_indices.len(), 1);
+        assert_eq!(tessellator.feature_indices[0] as usize, tessellator.buffer.indices.len());
+    }
+}
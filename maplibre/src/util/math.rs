@@ -229,6 +229,7 @@ pub(crate) fn max<S: PartialOrd + Copy>(lhs: S, rhs: S) -> S {
 }
 
 /// A two-dimensional AABB, aka a rectangle.
+#[derive(Clone, Copy, PartialEq)]
 pub struct Aabb2<S> {
     /// Minimum point of the AABB
     pub min: Point2<S>,
@@ -256,6 +257,24 @@ impl<S: BaseNum> Aabb2<S> {
             self.max,
         ]
     }
+
+    /// Extend the AABB such that it contains `point`.
+    #[inline]
+    pub fn grow(&self, point: Point2<S>) -> Aabb2<S> {
+        Aabb2::new(
+            Point2::new(min(self.min.x, point.x), min(self.min.y, point.y)),
+            Point2::new(max(self.max.x, point.x), max(self.max.y, point.y)),
+        )
+    }
+
+    /// Whether the AABBs overlap. AABBs which only touch at their edges intersect.
+    #[inline]
+    pub fn intersects(&self, other: &Aabb2<S>) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
 }
 
 impl<S: BaseNum> fmt::Debug for Aabb2<S> {
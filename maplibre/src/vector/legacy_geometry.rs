@@ -0,0 +1,251 @@
+//! Normalizes the geometry of legacy version 1 MVT layers, such that they are decoded like
+//! version 2 layers.
+
+use geozero::mvt::tile;
+
+/// The first MVT version which specifies the winding order of polygon rings and one command per
+/// path, which is what `geozero` expects when decoding geometries.
+const MVT_VERSION_2: u32 = 2;
+
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+/// A path of absolute positions within the tile, as decoded from the geometry commands.
+#[derive(Debug, Default)]
+struct Path {
+    points: Vec<[i32; 2]>,
+    closed: bool,
+}
+
+/// Whether `layer` was encoded with a version of the MVT specification before version 2.
+pub fn is_legacy_layer(layer: &tile::Layer) -> bool {
+    layer.version < MVT_VERSION_2
+}
+
+/// Rewrites the geometries of a version 1 `layer` to follow the rules of version 2 and marks the
+/// layer as version 2:
+///
+/// * The winding order of polygon rings was unspecified. The first ring of a polygon is its
+///   exterior, rings with the opposite winding are holes. Rings are reversed such that exterior
+///   rings are clockwise.
+/// * A path may be split across multiple `LineTo` commands, and the points of a point geometry
+///   across multiple `MoveTo` commands. These are merged into one command.
+/// * Degenerate paths, like rings with less than three points, and truncated commands are dropped.
+///
+/// Geometries of unknown type are left unchanged.
+pub fn normalize_legacy_layer(layer: &mut tile::Layer) {
+    for feature in &mut layer.features {
+        let paths = decode_paths(&feature.geometry);
+        feature.geometry = match feature.r#type {
+            Some(r#type) if r#type == tile::GeomType::Point as i32 => encode_points(&paths),
+            Some(r#type) if r#type == tile::GeomType::Linestring as i32 => {
+                encode_linestrings(&paths)
+            }
+            Some(r#type) if r#type == tile::GeomType::Polygon as i32 => encode_polygons(paths),
+            _ => continue,
+        };
+    }
+
+    layer.version = MVT_VERSION_2;
+}
+
+/// Decodes the commands of `geometry` into paths. Decoding stops at the first unknown or
+/// truncated command.
+fn decode_paths(geometry: &[u32]) -> Vec<Path> {
+    let mut paths: Vec<Path> = Vec::new();
+    let mut cursor = [0i32; 2];
+    let mut commands = geometry.iter().copied();
+
+    while let Some(command) = commands.next() {
+        let (id, count) = (command & 0x7, command >> 3);
+        match id {
+            MOVE_TO | LINE_TO => {
+                for _ in 0..count {
+                    let (Some(dx), Some(dy)) = (commands.next(), commands.next()) else {
+                        return paths;
+                    };
+                    cursor[0] = cursor[0].wrapping_add(decode_parameter(dx));
+                    cursor[1] = cursor[1].wrapping_add(decode_parameter(dy));
+
+                    match paths.last_mut() {
+                        Some(path) if id == LINE_TO && !path.closed => path.points.push(cursor),
+                        // A LineTo without a preceding MoveTo starts a path as well
+                        _ => paths.push(Path {
+                            points: vec![cursor],
+                            closed: false,
+                        }),
+                    }
+                }
+            }
+            CLOSE_PATH => {
+                if let Some(path) = paths.last_mut() {
+                    path.closed = true;
+                }
+            }
+            _ => break,
+        }
+    }
+
+    paths
+}
+
+fn decode_parameter(parameter: u32) -> i32 {
+    ((parameter >> 1) as i32) ^ -((parameter & 1) as i32)
+}
+
+fn encode_parameter(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn encode_command(id: u32, count: usize) -> u32 {
+    (id & 0x7) | ((count as u32) << 3)
+}
+
+/// Appends the commands for `points` to `geometry`. The first point is moved to, the others are
+/// lined to. `cursor` is the position after the previous command.
+fn encode_path(geometry: &mut Vec<u32>, cursor: &mut [i32; 2], points: &[[i32; 2]]) {
+    for (i, point) in points.iter().enumerate() {
+        match i {
+            0 => geometry.push(encode_command(MOVE_TO, 1)),
+            1 => geometry.push(encode_command(LINE_TO, points.len() - 1)),
+            _ => {}
+        }
+        geometry.push(encode_parameter(point[0].wrapping_sub(cursor[0])));
+        geometry.push(encode_parameter(point[1].wrapping_sub(cursor[1])));
+        *cursor = *point;
+    }
+}
+
+fn encode_points(paths: &[Path]) -> Vec<u32> {
+    let points: Vec<[i32; 2]> = paths.iter().flat_map(|path| path.points.first()).copied().collect();
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let mut geometry = vec![encode_command(MOVE_TO, points.len())];
+    let mut cursor = [0, 0];
+    for point in points {
+        geometry.push(encode_parameter(point[0].wrapping_sub(cursor[0])));
+        geometry.push(encode_parameter(point[1].wrapping_sub(cursor[1])));
+        cursor = point;
+    }
+    geometry
+}
+
+fn encode_linestrings(paths: &[Path]) -> Vec<u32> {
+    let mut geometry = Vec::new();
+    let mut cursor = [0, 0];
+    for path in paths.iter().filter(|path| path.points.len() >= 2) {
+        encode_path(&mut geometry, &mut cursor, &path.points);
+    }
+    geometry
+}
+
+fn encode_polygons(paths: Vec<Path>) -> Vec<u32> {
+    let mut rings: Vec<Vec<[i32; 2]>> = paths
+        .into_iter()
+        .map(|path| {
+            let mut points = path.points;
+            // The closing point is implied by ClosePath
+            if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            points
+        })
+        .filter(|ring| ring.len() >= 3 && signed_area(ring) != 0)
+        .collect();
+
+    // The first ring is an exterior, which has a positive area in version 2
+    if rings.first().is_some_and(|ring| signed_area(ring) < 0) {
+        rings.iter_mut().for_each(|ring| ring.reverse());
+    }
+
+    let mut geometry = Vec::new();
+    let mut cursor = [0, 0];
+    for ring in &rings {
+        encode_path(&mut geometry, &mut cursor, ring);
+        geometry.push(encode_command(CLOSE_PATH, 1));
+    }
+    geometry
+}
+
+/// Twice the area of `ring` by the shoelace formula. Positive for clockwise rings in tile
+/// coordinates, whose y axis points down.
+fn signed_area(ring: &[[i32; 2]]) -> i64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] as i64 * b[1] as i64 - a[1] as i64 * b[0] as i64)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use geozero::mvt::tile;
+
+    use super::{is_legacy_layer, normalize_legacy_layer};
+
+    fn layer(r#type: tile::GeomType, geometry: Vec<u32>) -> tile::Layer {
+        tile::Layer {
+            version: 1,
+            name: "legacy".to_string(),
+            features: vec![tile::Feature {
+                id: Some(1),
+                tags: vec![],
+                r#type: Some(r#type as i32),
+                geometry,
+            }],
+            keys: vec![],
+            values: vec![],
+            extent: Some(4096),
+        }
+    }
+
+    #[test]
+    fn test_counter_clockwise_polygon() {
+        // MoveTo(0, 0), LineTo(0, 10), LineTo(10, 10), (10, 0), ClosePath with a hole
+        // MoveTo(2, 2), LineTo(4, 2), (4, 4), (2, 4), ClosePath
+        let mut layer = layer(
+            tile::GeomType::Polygon,
+            vec![
+                9, 0, 0, 10, 0, 20, 18, 20, 0, 0, 19, 15, 9, 15, 4, 26, 4, 0, 0, 4, 3, 0, 15,
+            ],
+        );
+        assert!(is_legacy_layer(&layer));
+
+        normalize_legacy_layer(&mut layer);
+
+        assert!(!is_legacy_layer(&layer));
+        // Both rings are reversed: (10, 0), (10, 10), (0, 10), (0, 0) and
+        // (2, 4), (4, 4), (4, 2), (2, 2)
+        assert_eq!(
+            layer.features[0].geometry,
+            vec![9, 20, 0, 26, 0, 20, 19, 0, 0, 19, 15, 9, 4, 8, 26, 4, 0, 0, 3, 3, 0, 15]
+        );
+    }
+
+    #[test]
+    fn test_split_commands() {
+        // MoveTo(1, 1), LineTo(2, 2), LineTo(3, 3), a truncated LineTo
+        let mut lines = layer(tile::GeomType::Linestring, vec![9, 2, 2, 10, 2, 2, 10, 2, 2, 10, 2]);
+        normalize_legacy_layer(&mut lines);
+        assert_eq!(lines.features[0].geometry, vec![9, 2, 2, 18, 2, 2, 2, 2]);
+
+        // MoveTo(1, 1), MoveTo(2, 2)
+        let mut points = layer(tile::GeomType::Point, vec![9, 2, 2, 9, 2, 2]);
+        normalize_legacy_layer(&mut points);
+        assert_eq!(points.features[0].geometry, vec![17, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_degenerate_paths_are_dropped() {
+        // A line with a single point and a ring with two points and an explicit closing point
+        let mut lines = layer(tile::GeomType::Linestring, vec![9, 2, 2]);
+        normalize_legacy_layer(&mut lines);
+        assert!(lines.features[0].geometry.is_empty());
+
+        let mut polygons = layer(tile::GeomType::Polygon, vec![9, 0, 0, 18, 20, 0, 19, 0, 15]);
+        normalize_legacy_layer(&mut polygons);
+        assert!(polygons.features[0].geometry.is_empty());
+    }
+}
@@ -1,4 +1,11 @@
-use std::{marker::PhantomData, ops::Deref, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    ops::Deref,
+    rc::Rc,
+};
+
+use lyon::tessellation::VertexBuffers;
 
 use crate::{
     coords::WorldTileCoords,
@@ -12,6 +19,7 @@ use crate::{
         RenderStageLabel, ShaderVertex,
     },
     schedule::Schedule,
+    style::expression::ComparisonLiteral,
     tcs::{system::SystemContainer, tiles::TileComponent, world::World},
     tessellation::{IndexDataType, OverAlignedVertexBuffer},
     vector::{
@@ -21,6 +29,7 @@ use crate::{
     },
 };
 
+mod legacy_geometry;
 mod populate_world_system;
 mod process_vector;
 mod queue_system;
@@ -28,13 +37,15 @@ mod render_commands;
 mod request_system;
 mod resource;
 mod resource_system;
+mod style_change;
 mod transferables;
 mod upload_system;
 
 pub use process_vector::*;
+pub use style_change::retessellate_for_style_change;
 pub use transferables::{
-    DefaultVectorTransferables, LayerIndexed, LayerMissing, LayerTessellated, TileTessellated,
-    VectorTransferables,
+    DefaultVectorTransferables, LayerChunk, LayerIndexed, LayerMissing, LayerTessellated,
+    TileTessellated, VectorTransferables,
 };
 
 use crate::render::graph::RenderGraph;
@@ -57,11 +68,29 @@ pub type VectorBufferPool = BufferPool<
     ShaderFeatureStyle,
 >;
 
-pub struct VectorPlugin<T>(PhantomData<T>);
+/// The number of tiles which are processed at once by default.
+pub const DEFAULT_MAX_WORKERS: usize = 8;
+
+pub struct VectorPlugin<T> {
+    max_workers: usize,
+    phantom_t: PhantomData<T>,
+}
 
 impl<T: VectorTransferables> Default for VectorPlugin<T> {
     fn default() -> Self {
-        Self(Default::default())
+        Self {
+            max_workers: DEFAULT_MAX_WORKERS,
+            phantom_t: Default::default(),
+        }
+    }
+}
+
+impl<T: VectorTransferables> VectorPlugin<T> {
+    /// Limits how many tiles are processed at once, such that panning quickly does not pile up
+    /// work. The tiles nearest to the center of the view are processed first.
+    pub fn with_max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = max_workers;
+        self
     }
 }
 
@@ -100,7 +129,7 @@ impl<E: Environment, T: VectorTransferables> Plugin<E> for VectorPlugin<T> {
 
         schedule.add_system_to_stage(
             RenderStageLabel::Extract,
-            SystemContainer::new(RequestSystem::<E, T>::new(&kernel)),
+            SystemContainer::new(RequestSystem::<E, T>::new(&kernel, self.max_workers)),
         );
         schedule.add_system_to_stage(
             RenderStageLabel::Extract,
@@ -119,9 +148,44 @@ pub struct AvailableVectorLayerData {
     pub buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
     /// Holds for each feature the count of indices.
     pub feature_indices: Vec<u32>,
+    /// Holds for each feature the value of the property which the line width depends on, if it
+    /// depends on feature properties. Empty otherwise.
+    pub feature_line_width_values: Vec<Option<f64>>,
+    /// Holds for each feature the value of the property which the opacity depends on, if it
+    /// depends on feature properties. Empty otherwise.
+    pub feature_opacity_values: Vec<Option<f64>>,
+    /// Holds for each feature its properties, if they are retained. Empty otherwise.
+    pub feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
+    /// The extent of the tessellated tile layer.
+    pub extent: u32,
     pub style_layer_id: String,
 }
 
+impl AvailableVectorLayerData {
+    /// Appends the features of `chunk`, which was tessellated from further features of the same
+    /// layer.
+    pub fn append(&mut self, chunk: AvailableVectorLayerData) {
+        let buffer = &mut self.buffer.buffer;
+        // Drop the padding of the indices, it is added again below
+        buffer.indices.truncate(self.buffer.usable_indices as usize);
+
+        let vertex_offset = buffer.vertices.len() as IndexDataType;
+        let chunk_buffer = chunk.buffer.buffer;
+        buffer.vertices.extend(chunk_buffer.vertices);
+        buffer.indices.extend(
+            chunk_buffer.indices[..chunk.buffer.usable_indices as usize]
+                .iter()
+                .map(|index| index + vertex_offset),
+        );
+
+        self.buffer = std::mem::replace(buffer, VertexBuffers::new()).into();
+        self.feature_indices.extend(chunk.feature_indices);
+        self.feature_line_width_values.extend(chunk.feature_line_width_values);
+        self.feature_opacity_values.extend(chunk.feature_opacity_values);
+        self.feature_properties.extend(chunk.feature_properties);
+    }
+}
+
 pub struct MissingVectorLayerData {
     pub coords: WorldTileCoords,
     pub style_layer_id: String,
@@ -135,7 +199,16 @@ pub enum VectorLayerData {
 #[derive(Default)]
 pub struct VectorLayersDataComponent {
     pub done: bool,
+    /// Whether the tile is done, but none of its layers have geometry, e.g. because none of them
+    /// matched the style. The tile is loaded, but blank.
+    pub empty: bool,
     pub layers: Vec<VectorLayerData>,
+    /// Chunks of layers which are tessellated incrementally. A layer is moved to `layers` once
+    /// all of its chunks arrived.
+    pub pending_chunks: Vec<AvailableVectorLayerData>,
+    /// Style layers which are tessellated again because the style changed, see
+    /// [`retessellate_for_style_change`].
+    pub retessellate: HashSet<String>,
 }
 
 impl TileComponent for VectorLayersDataComponent {}
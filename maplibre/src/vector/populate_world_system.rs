@@ -2,8 +2,12 @@ use std::{borrow::Cow, marker::PhantomData, rc::Rc};
 
 use crate::{
     context::MapContext,
+    coords::WorldTileCoords,
     environment::Environment,
-    io::apc::{AsyncProcedureCall, Message},
+    io::{
+        apc::{AsyncProcedureCall, Message},
+        worker_pool::RunningPermits,
+    },
     kernel::Kernel,
     tcs::system::System,
     vector::{transferables::*, VectorLayerData, VectorLayersDataComponent},
@@ -38,6 +42,12 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
             let message: Message = message;
             if message.has_tag(T::TileTessellated::message_tag()) {
                 let message = message.into_transferable::<T::TileTessellated>();
+                // The request of the tile is finished, see `RequestSystem`
+                if let Some(running) = world.resources.get_mut::<RunningPermits<WorldTileCoords>>()
+                {
+                    running.release(&message.coords());
+                }
+
                 let Some(component) = world
                     .tiles
                     .query_mut::<&mut VectorLayersDataComponent>(message.coords())
@@ -46,6 +56,11 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
                 };
 
                 component.done = true;
+                // Layers which were tessellated before, e.g. prior to a style change, are kept
+                component.empty = message.is_empty()
+                    && !component.layers.iter().any(|layer| {
+                        matches!(layer, VectorLayerData::Available(layer) if layer.buffer.usable_indices > 0)
+                    });
             } else if message.has_tag(T::LayerMissing::message_tag()) {
                 let message = message.into_transferable::<T::LayerMissing>();
                 let Some(component) = world
@@ -55,6 +70,10 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
                     continue;
                 };
 
+                // Chunks which were sent before the layer failed are never completed
+                component
+                    .pending_chunks
+                    .retain(|pending| pending.style_layer_id != message.layer_name());
                 component
                     .layers
                     .push(VectorLayerData::Missing(message.to_layer()));
@@ -72,11 +91,35 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
                     continue;
                 };
                 
+                let chunk = message.chunk();
                 let layer = message.to_layer();
 
-                component
-                    .layers
-                    .push(VectorLayerData::Available(layer));
+                match chunk {
+                    LayerChunk::Complete => component
+                        .layers
+                        .push(VectorLayerData::Available(layer)),
+                    LayerChunk::Partial { .. } => {
+                        if let Some(pending) = component
+                            .pending_chunks
+                            .iter_mut()
+                            .find(|pending| pending.style_layer_id == layer.style_layer_id)
+                        {
+                            pending.append(layer);
+                        } else {
+                            component.pending_chunks.push(layer);
+                        }
+                    }
+                    LayerChunk::End { .. } => {
+                        if let Some(position) = component
+                            .pending_chunks
+                            .iter()
+                            .position(|pending| pending.style_layer_id == layer.style_layer_id)
+                        {
+                            let layer = component.pending_chunks.swap_remove(position);
+                            component.layers.push(VectorLayerData::Available(layer));
+                        }
+                    }
+                }
             } else if message.has_tag(T::LayerIndexed::message_tag()) {
                 let message = message.into_transferable::<T::LayerIndexed>();
                 world
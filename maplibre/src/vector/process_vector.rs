@@ -1,4 +1,4 @@
-use std::{borrow::Cow, collections::HashSet, marker::PhantomData};
+use std::{borrow::Cow, collections::HashSet, marker::PhantomData, time::Duration};
 
 use geozero::{
     mvt::{tile, Message},
@@ -7,16 +7,20 @@ use geozero::{
 use thiserror::Error;
 
 use crate::{
-    coords::WorldTileCoords,
+    coords::{WorldTileCoords, EXTENT_UINT},
     io::{
-        apc::{Context, SendError},
+        apc::{BufferingContext, CancellationToken, Context, SendError},
         // geometry_index::{IndexProcessor, IndexedGeometry, TileIndex},
         geometry_index::{IndexedGeometry, TileIndex},
     },
-    render::ShaderVertex,
-    tessellation::{zero_tessellator::ZeroTessellator, IndexDataType, OverAlignedVertexBuffer},
-    vector::transferables::{
-        LayerIndexed, LayerMissing, LayerTessellated, TileTessellated, VectorTransferables,
+    tessellation::{zero_tessellator::ZeroTessellator, IndexDataType, Tessellator},
+    util::math::Aabb2,
+    vector::{
+        legacy_geometry::{is_legacy_layer, normalize_legacy_layer},
+        transferables::{
+            LayerChunk, LayerIndexed, LayerMissing, LayerTessellated, TileTessellated,
+            VectorTransferables,
+        },
     },
 };
 use crate::style::layer::StyleLayer;
@@ -30,6 +34,9 @@ pub enum ProcessVectorError {
     /// Error when decoding e.g. the protobuf file
     #[error("decoding failed")]
     Decoding(Cow<'static, str>),
+    /// The tile is no longer needed and processing was stopped
+    #[error("processing was cancelled")]
+    Cancelled,
 }
 
 /// A request for a tile at the given coordinates and in the given layers.
@@ -37,62 +44,150 @@ pub struct VectorTileRequest {
     pub coords: WorldTileCoords,
     pub layers: HashSet<String>,
     pub style: Style,
+    /// Only the style layers with these ids are processed, e.g. the layers which changed with
+    /// the style. Nothing is sent for the other layers, such that their existing data is left in
+    /// place. All layers are processed if unset.
+    pub style_layer_ids: Option<HashSet<String>>,
+    /// The wall-clock time which may be spent on tessellating each layer. Features beyond the
+    /// budget are skipped and the layer is sent as partial.
+    pub tessellation_budget: Option<Duration>,
+    /// Sends each layer in chunks of this many features, so that large layers are transferred
+    /// while the rest of the layer is still tessellated. Each layer is followed by a
+    /// [`LayerChunk::End`] marker.
+    pub chunk_size: Option<usize>,
+    /// Keeps the properties of the tessellated features and sends them along with the layers,
+    /// see [`LayerTessellated::feature_properties`]. Off by default to save memory.
+    pub retain_feature_properties: bool,
+    /// Only features whose bounding box intersects this region of the tile are tessellated, e.g.
+    /// to tessellate a focus region first. The region is in tile-local units, from 0 to the
+    /// extent of the layers. All features are tessellated if unset.
+    pub clip: Option<Aabb2<f64>>,
 }
 
 pub fn process_vector_tile<T: VectorTransferables, C: Context>(
     data: &[u8],
     tile_request: VectorTileRequest,
     context: &mut ProcessVectorContext<T, C>,
+) -> Result<(), ProcessVectorError> {
+    process_vector_tile_with::<ZeroTessellator<IndexDataType>, T, C>(data, tile_request, context)
+}
+
+/// Processes the tile like [`process_vector_tile`], but tessellates its layers with `Z` instead
+/// of the [`ZeroTessellator`].
+pub fn process_vector_tile_with<Z: Tessellator, T: VectorTransferables, C: Context>(
+    data: &[u8],
+    tile_request: VectorTileRequest,
+    context: &mut ProcessVectorContext<T, C>,
 ) -> Result<(), ProcessVectorError> {
     // Decode
 
     let mut tile = geozero::mvt::Tile::decode(data)
         .map_err(|e| ProcessVectorError::Decoding(e.to_string().into()))?;
+    // Version 1 geometries are rewritten before geozero decodes them with the rules of version 2
+    for layer in tile.layers.iter_mut().filter(|layer| is_legacy_layer(layer)) {
+        log::debug!("normalizing version {} layer {}", layer.version, layer.name);
+        normalize_legacy_layer(layer);
+    }
+    tile.layers = merge_duplicate_layers(tile.layers);
 
     // Available
 
     let coords = &tile_request.coords;
 
-    for layer in &mut tile.layers {
-        let layer_name: &str = &layer.name;
-        if !tile_request.layers.contains(layer_name) {
-            continue;
-        }
-        
-        let corresponding_style_layers: Vec<&StyleLayer> = tile_request.style.layers
+    let corresponding_style_layers = |layer_name: &str| -> Vec<&StyleLayer> {
+        tile_request.style.layers
             .iter()
             .filter(|style_layer| style_layer.source_layer
                 .as_ref()
                 .is_some_and(|source| source.as_str() == layer_name)
             )
-            .collect();
-        
-        for style_layer in corresponding_style_layers {
+            .filter(|style_layer| tile_request
+                .style_layer_ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&style_layer.id))
+            )
+            .collect()
+    };
+
+    let layers_total: usize = tile
+        .layers
+        .iter()
+        .filter(|layer| tile_request.layers.contains(&layer.name))
+        .map(|layer| corresponding_style_layers(&layer.name).len())
+        .sum();
+    let mut layers_done = 0;
+
+    for layer in &mut tile.layers {
+        let layer_name: &str = &layer.name;
+        if !tile_request.layers.contains(layer_name) {
+            continue;
+        }
+
+        for style_layer in corresponding_style_layers(layer_name) {
+            context.check_cancelled()?;
+
+            if let Some(chunk_size) = tile_request.chunk_size {
+                process_layer_chunks::<Z, T, C>(
+                    layer,
+                    style_layer,
+                    chunk_size,
+                    &tile_request,
+                    context,
+                )?;
+
+                layers_done += 1;
+                context.layer_progress(coords, layers_done, layers_total);
+                continue;
+            }
+
             let mut layer = layer.clone();
             log::info!("Processing layer {} with filter {:?}", style_layer.id, &style_layer.filter);
-            let mut tessellator = ZeroTessellator::<IndexDataType>::new(style_layer.filter.clone());
-            if let Err(e) = layer.process(&mut tessellator) {
+            let mut tessellator = Z::for_style_layer(
+                &tile_request,
+                style_layer,
+                layer.extent.unwrap_or(EXTENT_UINT),
+            );
+            let result = layer.process(&mut tessellator);
+
+            // Tessellating can take a while, the tile might have been despawned in the meantime
+            context.check_cancelled()?;
+
+            if let Err(e) = result {
                 context.layer_missing(coords, style_layer.id.as_str())?;
 
                 log::error!("layer {} at {coords} tesselation failed {e:?}", style_layer.id.as_str());
             } else {
+                if tessellator.is_truncated() {
+                    log::warn!(
+                        "layer {} at {coords} exceeded its tessellation budget",
+                        style_layer.id.as_str()
+                    );
+                }
+                for warning in tessellator.warnings() {
+                    log::warn!("layer {} at {coords}: {warning}", style_layer.id.as_str());
+                }
                 if let Err(e) = context.layer_tesselation_finished(
                     coords,
-                    tessellator.buffer.into(),
-                    tessellator.feature_indices,
+                    tessellator,
                     layer,
-                    style_layer.id.clone()
+                    style_layer.id.clone(),
+                    LayerChunk::Complete,
                 ) {
                     context.layer_missing(coords, style_layer.id.as_str())?;
 
                     log::error!("layer {} at {coords} failed to send tesselation finished {e:?}", style_layer.id.as_str());
                 }
             }
+
+            layers_done += 1;
+            context.layer_progress(coords, layers_done, layers_total);
         }
     }
 
     // Missing
 
+    context.check_cancelled()?;
+
     let coords = &tile_request.coords;
     
     let available_layers: HashSet<_> = tile
@@ -124,8 +219,124 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
     Ok(())
 }
 
+/// Tessellates the features of `layer` in chunks of `chunk_size` and sends each chunk as soon as
+/// it is tessellated. The last chunk is followed by a [`LayerChunk::End`] marker. If a chunk fails,
+/// the layer is reported as missing instead.
+fn process_layer_chunks<Z: Tessellator, T: VectorTransferables, C: Context>(
+    layer: &tile::Layer,
+    style_layer: &StyleLayer,
+    chunk_size: usize,
+    tile_request: &VectorTileRequest,
+    context: &mut ProcessVectorContext<T, C>,
+) -> Result<(), ProcessVectorError> {
+    let coords = &tile_request.coords;
+    let extent = layer.extent.unwrap_or(EXTENT_UINT);
+    let layer_without_features = || tile::Layer {
+        version: layer.version,
+        name: layer.name.clone(),
+        features: vec![],
+        keys: layer.keys.clone(),
+        values: layer.values.clone(),
+        extent: layer.extent,
+    };
+
+    let mut chunks = 0;
+    for (index, features) in layer.features.chunks(chunk_size.max(1)).enumerate() {
+        let mut chunk = layer_without_features();
+        chunk.features = features.to_vec();
+
+        let mut tessellator = Z::for_style_layer(tile_request, style_layer, extent);
+        let result = chunk.process(&mut tessellator);
+
+        // Tessellating can take a while, the tile might have been despawned in the meantime
+        context.check_cancelled()?;
+
+        if let Err(e) = result {
+            context.layer_missing(coords, style_layer.id.as_str())?;
+
+            log::error!("layer {} at {coords} chunk {index} tesselation failed {e:?}", style_layer.id.as_str());
+            return Ok(());
+        }
+
+        for warning in tessellator.warnings() {
+            log::warn!("layer {} at {coords} chunk {index}: {warning}", style_layer.id.as_str());
+        }
+        if let Err(e) = context.layer_tesselation_finished(
+            coords,
+            tessellator,
+            chunk,
+            style_layer.id.clone(),
+            LayerChunk::Partial { index },
+        ) {
+            context.layer_missing(coords, style_layer.id.as_str())?;
+
+            log::error!("layer {} at {coords} failed to send chunk {index} {e:?}", style_layer.id.as_str());
+            return Ok(());
+        }
+
+        chunks += 1;
+    }
+
+    if let Err(e) = context.layer_tesselation_finished(
+        coords,
+        Z::for_style_layer(tile_request, style_layer, extent),
+        layer_without_features(),
+        style_layer.id.clone(),
+        LayerChunk::End { chunks },
+    ) {
+        context.layer_missing(coords, style_layer.id.as_str())?;
+
+        log::error!("layer {} at {coords} failed to send the end of its chunks {e:?}", style_layer.id.as_str());
+    }
+
+    Ok(())
+}
+
+/// Merges the features of layers which share the same name into the first of these layers. MVT
+/// allows repeated layer names, but the rest of the pipeline identifies layers by their name.
+fn merge_duplicate_layers(layers: Vec<tile::Layer>) -> Vec<tile::Layer> {
+    let mut merged: Vec<tile::Layer> = Vec::with_capacity(layers.len());
+
+    for layer in layers {
+        let Some(target) = merged.iter_mut().find(|merged| merged.name == layer.name) else {
+            merged.push(layer);
+            continue;
+        };
+
+        if target.extent != layer.extent {
+            log::warn!(
+                "merging layers named {} with different extents {:?} and {:?}",
+                layer.name,
+                target.extent,
+                layer.extent
+            );
+        }
+
+        // Tags are pairs of indices into the keys and values of the layer
+        let key_offset = target.keys.len() as u32;
+        let value_offset = target.values.len() as u32;
+
+        target.keys.extend(layer.keys);
+        target.values.extend(layer.values);
+        target
+            .features
+            .extend(layer.features.into_iter().map(|mut feature| {
+                for tag in feature.tags.chunks_exact_mut(2) {
+                    tag[0] += key_offset;
+                    tag[1] += value_offset;
+                }
+                feature
+            }));
+    }
+
+    merged
+}
+
 pub struct ProcessVectorContext<T: VectorTransferables, C: Context> {
     context: C,
+    cancellation: CancellationToken,
+    /// Whether a layer of the current tile produced geometry, see [`TileTessellated::is_empty`].
+    has_geometry: bool,
     phantom_t: PhantomData<T>,
 }
 
@@ -133,9 +344,26 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
     pub fn new(context: C) -> Self {
         Self {
             context,
+            cancellation: CancellationToken::default(),
+            has_geometry: false,
             phantom_t: Default::default(),
         }
     }
+
+    /// Stops processing at the next layer boundary once `cancellation` is cancelled. Nothing is
+    /// sent back after that.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+}
+
+impl<T: VectorTransferables> ProcessVectorContext<T, BufferingContext> {
+    /// All messages which were produced while processing, in the order in which they were sent.
+    /// Used to drive [`process_vector_tile`] synchronously without a worker.
+    pub fn into_results(self) -> Vec<crate::io::apc::Message> {
+        self.context.into_messages()
+    }
 }
 
 impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
@@ -143,12 +371,25 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
         self.context
     }
 
+    fn check_cancelled(&self) -> Result<(), ProcessVectorError> {
+        if self.cancellation.is_cancelled() {
+            Err(ProcessVectorError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
     fn tile_finished(&mut self, coords: &WorldTileCoords) -> Result<(), ProcessVectorError> {
+        let empty = !std::mem::take(&mut self.has_geometry);
         self.context
-            .send_back(T::TileTessellated::build_from(*coords))
+            .send_back(T::TileTessellated::build_from(*coords, empty))
             .map_err(|e| ProcessVectorError::SendError(e))
     }
 
+    fn layer_progress(&self, coords: &WorldTileCoords, layers_done: usize, layers_total: usize) {
+        self.context.layer_progress(coords, layers_done, layers_total)
+    }
+
     fn layer_missing(
         &mut self,
         coords: &WorldTileCoords,
@@ -159,22 +400,36 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
             .map_err(|e| ProcessVectorError::SendError(e))
     }
 
-    fn layer_tesselation_finished(
+    fn layer_tesselation_finished<Z: Tessellator>(
         &mut self,
         coords: &WorldTileCoords,
-        buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
-        feature_indices: Vec<u32>,
+        tessellator: Z,
         layer_data: tile::Layer,
-        style_layer_id: String
+        style_layer_id: String,
+        chunk: LayerChunk,
     ) -> Result<(), ProcessVectorError> {
+        let feature_indices = tessellator.feature_indices().to_vec();
+        let feature_line_width_values = tessellator.feature_line_width_values().to_vec();
+        let feature_opacity_values = tessellator.feature_opacity_values().to_vec();
+        let feature_properties = tessellator.feature_properties().to_vec();
+        let partial = tessellator.is_truncated();
+        self.has_geometry |= !tessellator.buffer().indices.is_empty();
+
         self.context
-            .send_back(T::LayerTessellated::build_from(
-                *coords,
-                buffer,
-                feature_indices,
-                layer_data,
-                style_layer_id,
-            ))
+            .send_back(
+                T::LayerTessellated::build_from(
+                    *coords,
+                    tessellator.into_buffer().into(),
+                    feature_indices,
+                    feature_line_width_values,
+                    layer_data,
+                    style_layer_id,
+                    partial,
+                )
+                .with_chunk(chunk)
+                .with_feature_properties(feature_properties)
+                .with_feature_opacity_values(feature_opacity_values),
+            )
             .map_err(|e| ProcessVectorError::SendError(e))
     }
 
@@ -194,16 +449,762 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use geozero::{
+        mvt::{tile, Message},
+        FeatureProcessor, GeomProcessor, GeozeroDatasource, PropertyProcessor,
+    };
+    use cgmath::Point2;
+    use lyon::tessellation::VertexBuffers;
+
     use super::ProcessVectorContext;
     use crate::{
-        coords::ZoomLevel,
-        io::apc::tests::DummyContext,
+        coords::{WorldTileCoords, ZoomLevel},
+        io::apc::{
+            tests::DummyContext, BufferingContext, CancellationToken, Context, IntoMessage,
+            SendError,
+        },
+        render::ShaderVertex,
+        style::{expression::ComparisonLiteral, layer::StyleLayer, Style},
+        tessellation::{zero_tessellator::ZeroTessellator, IndexDataType, Tessellator},
+        util::math::Aabb2,
         vector::{
-            process_vector::{process_vector_tile, VectorTileRequest},
+            process_vector::{
+                merge_duplicate_layers, process_vector_tile, process_vector_tile_with,
+                ProcessVectorError, VectorTileRequest,
+            },
+            transferables::{
+                DefaultLayerMissing, DefaultLayerTesselated, DefaultTileTessellated, LayerChunk,
+                LayerMissing, LayerTessellated, TileTessellated,
+            },
             DefaultVectorTransferables,
         },
     };
 
+    /// Counts the progress callbacks and checks that they are increasing.
+    struct ProgressContext {
+        progress: Arc<AtomicUsize>,
+    }
+
+    impl Context for ProgressContext {
+        fn send_back<T: IntoMessage>(&self, _message: T) -> Result<(), SendError> {
+            Ok(())
+        }
+
+        fn layer_progress(&self, _coords: &WorldTileCoords, layers_done: usize, layers_total: usize) {
+            let previous = self.progress.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(previous + 1, layers_done);
+            assert_eq!(layers_total, 2);
+        }
+    }
+
+    /// Counts the messages which are sent back.
+    struct CountingContext {
+        messages: Arc<AtomicUsize>,
+    }
+
+    impl Context for CountingContext {
+        fn send_back<T: IntoMessage>(&self, _message: T) -> Result<(), SendError> {
+            self.messages.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A layer with a single square which is tagged with `class`.
+    fn square_layer(name: &str, class: &str) -> tile::Layer {
+        tile::Layer {
+            version: 2,
+            name: name.to_string(),
+            features: vec![tile::Feature {
+                id: Some(1),
+                tags: vec![0, 0],
+                r#type: Some(tile::GeomType::Polygon as i32),
+                // MoveTo(0, 0), LineTo(10, 0), (10, 10), (0, 10), ClosePath
+                geometry: vec![9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15],
+            }],
+            keys: vec!["class".to_string()],
+            values: vec![tile::Value {
+                string_value: Some(class.to_string()),
+                ..Default::default()
+            }],
+            extent: Some(4096),
+        }
+    }
+
+    /// Encodes a tile with a single square in each of the `layers`.
+    fn square_tile(layers: &[&str]) -> Vec<u8> {
+        geozero::mvt::Tile {
+            layers: layers.iter().map(|name| square_layer(name, name)).collect(),
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_legacy_layer_is_tessellated() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let mut layer = square_layer("water", "lake");
+        layer.version = 1;
+        // A counter-clockwise square whose path is split across two LineTo commands:
+        // MoveTo(0, 0), LineTo(0, 10), LineTo(10, 10), (10, 0), ClosePath
+        layer.features[0].geometry = vec![9, 0, 0, 10, 0, 20, 18, 20, 0, 0, 19, 15];
+
+        // geozero rejects the polygon, because it does not start with a clockwise ring
+        let style_layer = style_layer("water", "water");
+        let request = VectorTileRequest {
+            coords: (0, 0, ZoomLevel::default()).into(),
+            layers: HashSet::from(["water".to_string()]),
+            style: Style {
+                layers: vec![style_layer.clone()],
+                ..Style::default()
+            },
+            style_layer_ids: None,
+            tessellation_budget: None,
+            chunk_size: None,
+            retain_feature_properties: false,
+            clip: None,
+        };
+        let mut tessellator =
+            ZeroTessellator::<IndexDataType>::for_style_layer(&request, &style_layer, 4096);
+        assert!(layer.clone().process(&mut tessellator).is_err());
+
+        let data = geozero::mvt::Tile {
+            layers: vec![layer],
+        }
+        .encode_to_vec();
+        process_vector_tile(
+            &data,
+            request,
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let messages: Vec<_> = messages.lock().unwrap().drain(..).collect();
+        assert!(!messages
+            .iter()
+            .any(|message| message.has_tag(DefaultLayerMissing::message_tag())));
+        let tessellated = messages
+            .into_iter()
+            .find(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+        assert_eq!(tessellated.feature_indices.len(), 1);
+        assert!(tessellated.to_layer().buffer.usable_indices > 0);
+    }
+
+    #[test]
+    fn test_merge_duplicate_layers() {
+        let merged = merge_duplicate_layers(vec![
+            square_layer("roads", "primary"),
+            square_layer("water", "lake"),
+            square_layer("roads", "secondary"),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        let roads = &merged[0];
+        assert_eq!(roads.name, "roads");
+        assert_eq!(roads.features.len(), 2);
+
+        // The tags of the second feature point to its own key and value
+        let tags = &roads.features[1].tags;
+        assert_eq!(roads.keys[tags[0] as usize], "class");
+        assert_eq!(
+            roads.values[tags[1] as usize].string_value.as_deref(),
+            Some("secondary")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_layers_are_tessellated_once() {
+        let messages = Arc::new(AtomicUsize::new(0));
+        let data = geozero::mvt::Tile {
+            layers: vec![
+                square_layer("roads", "primary"),
+                square_layer("roads", "secondary"),
+            ],
+        }
+        .encode_to_vec();
+
+        process_vector_tile(
+            &data,
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["roads".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("roads", "roads")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CountingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        // One tessellated layer and the finished tile
+        assert_eq!(messages.load(Ordering::SeqCst), 2);
+    }
+
+    /// Collects the messages which are sent back.
+    struct CollectingContext {
+        messages: Arc<Mutex<Vec<crate::io::apc::Message>>>,
+    }
+
+    impl Context for CollectingContext {
+        fn send_back<T: IntoMessage>(&self, message: T) -> Result<(), SendError> {
+            self.messages.lock().unwrap().push(IntoMessage::into(message));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tessellation_budget() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let data = geozero::mvt::Tile {
+            layers: vec![
+                square_layer("roads", "primary"),
+                square_layer("roads", "secondary"),
+            ],
+        }
+        .encode_to_vec();
+
+        process_vector_tile(
+            &data,
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["roads".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("roads", "roads")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: Some(Duration::ZERO),
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let tessellated = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .find(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+
+        // Only the first of both features fits into the budget
+        assert!(tessellated.is_partial());
+        assert_eq!(tessellated.feature_indices.len(), 1);
+    }
+
+    #[test]
+    fn test_chunked_layer() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let mut layer = square_layer("roads", "primary");
+        layer.features = vec![layer.features[0].clone(); 100];
+        let data = geozero::mvt::Tile {
+            layers: vec![layer],
+        }
+        .encode_to_vec();
+
+        process_vector_tile(
+            &data,
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["roads".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("roads", "roads")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: Some(25),
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let tessellated: Vec<_> = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .filter(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .map(|message| message.into_transferable::<DefaultLayerTesselated>())
+            .collect();
+
+        // Four chunks of 25 features and the end marker
+        assert_eq!(tessellated.len(), 5);
+        for (index, chunk) in tessellated[..4].iter().enumerate() {
+            assert_eq!(chunk.chunk(), LayerChunk::Partial { index });
+            assert_eq!(chunk.feature_indices.len(), 25);
+        }
+        assert_eq!(tessellated[4].chunk(), LayerChunk::End { chunks: 4 });
+        assert!(tessellated[4].is_empty());
+
+        // The chunks add up to the whole layer
+        let mut chunks = tessellated.into_iter().take(4).map(|chunk| chunk.to_layer());
+        let mut merged = chunks.next().unwrap();
+        let usable_indices = merged.buffer.usable_indices;
+        chunks.for_each(|chunk| merged.append(chunk));
+        assert_eq!(merged.feature_indices.len(), 100);
+        assert_eq!(merged.buffer.usable_indices, usable_indices * 4);
+        let vertices = merged.buffer.buffer.vertices.len() as IndexDataType;
+        assert!(merged.buffer.buffer.indices[..merged.buffer.usable_indices as usize]
+            .iter()
+            .all(|index| *index < vertices));
+    }
+
+    #[test]
+    fn test_retained_feature_properties() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+
+        process_vector_tile(
+            &square_tile(&["water"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("water", "water")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: true,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let tessellated = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .find(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+
+        let properties = tessellated.feature_properties();
+        assert_eq!(properties.len(), tessellated.feature_indices.len());
+        assert_eq!(
+            properties[0]["class"],
+            ComparisonLiteral::String("water".to_string())
+        );
+        assert_eq!(tessellated.to_layer().feature_properties.len(), 1);
+    }
+
+    /// Emits a single triangle for each feature.
+    struct TriangleTessellator {
+        buffer: VertexBuffers<ShaderVertex, IndexDataType>,
+        feature_indices: Vec<u32>,
+    }
+
+    impl GeomProcessor for TriangleTessellator {}
+    impl PropertyProcessor for TriangleTessellator {}
+    impl FeatureProcessor for TriangleTessellator {
+        fn feature_end(&mut self, _idx: u64) -> geozero::error::Result<()> {
+            let first = self.buffer.vertices.len() as IndexDataType;
+            for position in [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]] {
+                self.buffer
+                    .vertices
+                    .push(ShaderVertex::new(position, [0.0, 0.0]));
+            }
+            self.buffer
+                .indices
+                .extend([first, first + 1, first + 2]);
+            self.feature_indices.push(3);
+            Ok(())
+        }
+    }
+
+    impl Tessellator for TriangleTessellator {
+        fn for_style_layer(
+            _request: &VectorTileRequest,
+            _style_layer: &StyleLayer,
+            _extent: u32,
+        ) -> Self {
+            Self {
+                buffer: VertexBuffers::new(),
+                feature_indices: Vec::new(),
+            }
+        }
+
+        fn buffer(&self) -> &VertexBuffers<ShaderVertex, IndexDataType> {
+            &self.buffer
+        }
+
+        fn feature_indices(&self) -> &[u32] {
+            &self.feature_indices
+        }
+
+        fn into_buffer(self) -> VertexBuffers<ShaderVertex, IndexDataType> {
+            self.buffer
+        }
+    }
+
+    #[test]
+    fn test_custom_tessellator() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+
+        process_vector_tile_with::<TriangleTessellator, _, _>(
+            &square_tile(&["water"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("water", "water")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let tessellated = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .find(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+
+        assert_eq!(tessellated.feature_indices, vec![3]);
+        assert_eq!(tessellated.buffer.usable_indices, 3);
+        assert_eq!(&tessellated.buffer.buffer.indices[..3], &[0, 1, 2]);
+        assert!(!tessellated.is_partial());
+    }
+
+    fn style_layer(id: &str, source_layer: &str) -> StyleLayer {
+        StyleLayer {
+            id: id.to_string(),
+            source_layer: Some(source_layer.to_string()),
+            ..StyleLayer::default()
+        }
+    }
+
+    #[test]
+    fn test_into_results() {
+        let mut context =
+            ProcessVectorContext::<DefaultVectorTransferables, _>::new(BufferingContext::default());
+        process_vector_tile(
+            &square_tile(&["water"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string(), "land".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("water", "water"), style_layer("land", "land")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut context,
+        )
+        .unwrap();
+
+        let mut results = context.into_results();
+        assert_eq!(results.len(), 3);
+
+        let tile = results.pop().unwrap();
+        assert!(tile.has_tag(DefaultTileTessellated::message_tag()));
+        assert!(!tile.into_transferable::<DefaultTileTessellated>().is_empty());
+
+        let (tessellated, missing): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .partition(|message| message.has_tag(DefaultLayerTesselated::message_tag()));
+
+        let missing: Vec<_> = missing
+            .into_iter()
+            .map(|message| {
+                assert!(message.has_tag(DefaultLayerMissing::message_tag()));
+                message
+                    .into_transferable::<DefaultLayerMissing>()
+                    .layer_name()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(missing, vec!["land".to_string()]);
+
+        let [tessellated] = <[_; 1]>::try_from(tessellated).ok().unwrap();
+        let layer = tessellated
+            .into_transferable::<DefaultLayerTesselated>()
+            .to_layer();
+        assert_eq!(layer.style_layer_id, "water");
+        assert_eq!(layer.feature_indices.len(), 1);
+        assert!(layer.buffer.usable_indices > 0);
+    }
+
+    #[test]
+    fn test_layer_progress() {
+        let progress = Arc::new(AtomicUsize::new(0));
+        let style = Style {
+            layers: vec![
+                style_layer("water-fill", "water"),
+                style_layer("water-outline", "water"),
+                style_layer("park", "park"),
+                style_layer("road", "transportation"),
+            ],
+            ..Style::default()
+        };
+
+        process_vector_tile(
+            &square_tile(&["water", "park"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string()]),
+                style,
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(ProgressContext {
+                progress: progress.clone(),
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(progress.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_style_layer_subset() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let style = Style {
+            layers: vec![
+                style_layer("water-fill", "water"),
+                style_layer("water-outline", "water"),
+                style_layer("park", "park"),
+            ],
+            ..Style::default()
+        };
+
+        process_vector_tile(
+            &square_tile(&["water", "park"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string(), "park".to_string()]),
+                style,
+                style_layer_ids: Some(HashSet::from(["water-outline".to_string()])),
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let messages: Vec<_> = messages.lock().unwrap().drain(..).collect();
+        assert!(!messages
+            .iter()
+            .any(|message| message.has_tag(DefaultLayerMissing::message_tag())));
+        let tessellated: Vec<String> = messages
+            .into_iter()
+            .filter(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .map(|message| {
+                message
+                    .into_transferable::<DefaultLayerTesselated>()
+                    .to_layer()
+                    .style_layer_id
+            })
+            .collect();
+        assert_eq!(tessellated, vec!["water-outline".to_string()]);
+    }
+
+    /// Processes a tile with the given source layers against a style for the "roads" layer and
+    /// returns whether the tile was reported as empty.
+    fn process_tile_empty(source_layers: &[&str]) -> bool {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+
+        process_vector_tile(
+            &square_tile(source_layers),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string(), "roads".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("roads", "roads")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let message = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .find(|message| message.has_tag(DefaultTileTessellated::message_tag()))
+            .unwrap();
+        message
+            .into_transferable::<DefaultTileTessellated>()
+            .is_empty()
+    }
+
+    #[test]
+    fn test_empty_tile() {
+        // None of the layers of the tile is matched by the style
+        assert!(process_tile_empty(&["water"]));
+        assert!(!process_tile_empty(&["water", "roads"]));
+    }
+
+    #[test]
+    fn test_clip() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let data = geozero::mvt::Tile {
+            layers: vec![square_layer("water", "lake")],
+        }
+        .encode_to_vec();
+
+        // The square spans from (0, 0) to (10, 10)
+        process_vector_tile(
+            &data,
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string()]),
+                style: Style {
+                    layers: vec![style_layer("water", "water")],
+                    ..Style::default()
+                },
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: Some(Aabb2::new(Point2::new(100.0, 100.0), Point2::new(200.0, 200.0))),
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CollectingContext {
+                messages: messages.clone(),
+            }),
+        )
+        .unwrap();
+
+        let tessellated = messages
+            .lock()
+            .unwrap()
+            .drain(..)
+            .find(|message| message.has_tag(DefaultLayerTesselated::message_tag()))
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+        assert!(tessellated.feature_indices.is_empty());
+        assert_eq!(tessellated.to_layer().buffer.usable_indices, 0);
+    }
+
+    /// Cancels `cancellation` after `cancel_after` processed layers.
+    struct CancellingContext {
+        messages: Arc<AtomicUsize>,
+        cancellation: CancellationToken,
+        cancel_after: usize,
+    }
+
+    impl Context for CancellingContext {
+        fn send_back<T: IntoMessage>(&self, _message: T) -> Result<(), SendError> {
+            assert!(!self.cancellation.is_cancelled());
+            self.messages.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn layer_progress(&self, _coords: &WorldTileCoords, layers_done: usize, _layers_total: usize) {
+            if layers_done == self.cancel_after {
+                self.cancellation.cancel();
+            }
+        }
+    }
+
+    fn process_cancelled(cancellation: CancellationToken, cancel_after: usize) -> usize {
+        let messages = Arc::new(AtomicUsize::new(0));
+        let style = Style {
+            layers: vec![
+                style_layer("water", "water"),
+                style_layer("park", "park"),
+            ],
+            ..Style::default()
+        };
+
+        let result = process_vector_tile(
+            &square_tile(&["water", "park"]),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: HashSet::from(["water".to_string(), "park".to_string()]),
+                style,
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
+            },
+            &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(CancellingContext {
+                messages: messages.clone(),
+                cancellation: cancellation.clone(),
+                cancel_after,
+            })
+            .with_cancellation(cancellation),
+        );
+
+        assert!(matches!(result, Err(ProcessVectorError::Cancelled)));
+        messages.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_cancelled_before_processing() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        assert_eq!(process_cancelled(cancellation, usize::MAX), 0);
+    }
+
+    #[test]
+    fn test_cancelled_while_processing() {
+        // Only the first layer is sent, neither the second layer nor the finished tile
+        assert_eq!(process_cancelled(CancellationToken::new(), 1), 1);
+    }
+
     #[test] // TODO: Add proper tile byte array
     #[ignore]
     fn test() {
@@ -212,7 +1213,12 @@ mod tests {
             VectorTileRequest {
                 coords: (0, 0, ZoomLevel::default()).into(),
                 layers: Default::default(),
-                style: Default::default()
+                style: Default::default(),
+                style_layer_ids: None,
+                tessellation_budget: None,
+                chunk_size: None,
+                retain_feature_properties: false,
+                clip: None,
             },
             &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(DummyContext),
         );
@@ -5,8 +5,8 @@ use crate::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
         resource::TrackedRenderPass,
+        settings::CameraMode,
         tile_view_pattern::WgpuTileViewPattern,
-        INDEX_FORMAT,
     },
     tcs::world::World,
     vector::{VectorBufferPool, VectorPipeline},
@@ -51,16 +51,24 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
 
         let Some(entry) = vector_layers
             .iter()
-            .find(|entry| entry.style_layer.id == item.style_layer)
+            .find(|entry| !entry.is_stale() && entry.style_layer.id == item.style_layer)
         else {
             log::error!("Rendering {} failed because the original entry couldn't be found", item.style_layer);
             return RenderCommandResult::Failure;
         };
 
         let source_shape = &item.source_shape;
+        let tile_view_pattern_buffer = match tile_view_pattern.shape_slice(source_shape) {
+            Ok(slice) => slice,
+            Err(error) => {
+                log::error!("Rendering {} failed: {error}", item.style_layer);
+                return RenderCommandResult::Failure;
+            }
+        };
 
         // Uses stencil value of requested tile and the shape of the requested tile
-        let reference = source_shape.coords().stencil_reference_value_3d() as u32;
+        let camera_mode = world.resources.get::<CameraMode>().copied().unwrap_or_default();
+        let reference = source_shape.coords().stencil_reference_value(camera_mode) as u32;
 
         let index_range = entry.indices_buffer_range();
         let vertex_range = entry.vertices_buffer_range();
@@ -84,18 +92,12 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
 
         pass.set_stencil_reference(reference);
 
-        pass.set_index_buffer(buffer_pool.indices().slice(index_range), INDEX_FORMAT);
+        pass.set_index_buffer(buffer_pool.indices().slice(index_range), entry.index_format());
         pass.set_vertex_buffer(
             0,
             buffer_pool.vertices().slice(entry.vertices_buffer_range()),
         );
-        let tile_view_pattern_buffer = source_shape
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
-        pass.set_vertex_buffer(
-            1,
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
+        pass.set_vertex_buffer(1, tile_view_pattern_buffer);
         pass.set_vertex_buffer(
             2,
             buffer_pool
@@ -117,3 +119,157 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
 }
 
 pub type DrawVectorTiles = (SetVectorTilePipeline, DrawVectorTile);
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use bytemuck::Zeroable;
+    use cgmath::Point2;
+    use lyon::tessellation::VertexBuffers;
+
+    use super::{DrawVectorTile, DrawVectorTiles};
+    use crate::{
+        coords::{ViewRegion, WorldTileCoords, Zoom, ZoomLevel},
+        render::{
+            eventually::Eventually,
+            render_phase::{DrawState, LayerItem, RenderCommand, RenderCommandResult},
+            resource::{BackingBufferDescriptor, TrackedRenderPass},
+            shaders::{ShaderFeatureStyle, ShaderLayerMetadata, ShaderVertex},
+            tile_view_pattern::{HasTile, TileShape, TileViewPattern, WgpuTileViewPattern},
+        },
+        style::layer::StyleLayer,
+        tcs::{tiles::Tile, world::World},
+        util::math::Aabb2,
+        vector::VectorBufferPool,
+    };
+
+    struct AllTiles;
+
+    impl HasTile for AllTiles {
+        fn has_tile(&self, _coords: WorldTileCoords, _world: &World) -> bool {
+            true
+        }
+    }
+
+    async fn device() -> (wgpu::Device, wgpu::Queue) {
+        let backends = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let adapter = wgpu::util::initialize_adapter_from_env_or_default(&instance, None)
+            .await
+            .expect("Unable to initialize adapter");
+        adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .expect("Unable to request device")
+    }
+
+    fn buffer(device: &wgpu::Device, usage: wgpu::BufferUsages) -> BackingBufferDescriptor<wgpu::Buffer> {
+        let size = 1 << 16;
+        BackingBufferDescriptor::new(
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            size,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_missing_view_pattern_buffer_fails() {
+        let (device, queue) = device().await;
+        let coords: WorldTileCoords = (0, 0, ZoomLevel::default()).into();
+
+        let mut buffer_pool = VectorBufferPool::new(
+            buffer(&device, wgpu::BufferUsages::VERTEX),
+            buffer(&device, wgpu::BufferUsages::INDEX),
+            buffer(&device, wgpu::BufferUsages::VERTEX),
+            buffer(&device, wgpu::BufferUsages::VERTEX),
+        );
+        let mut geometry = VertexBuffers::new();
+        geometry.vertices.resize(3, ShaderVertex::zeroed());
+        geometry.indices.extend([0, 1, 2]);
+        buffer_pool.allocate_layer_geometry(
+            &queue,
+            coords,
+            StyleLayer {
+                id: "water".to_string(),
+                ..StyleLayer::default()
+            },
+            &geometry.into(),
+            ShaderLayerMetadata::new(0.0),
+            &[ShaderFeatureStyle::zeroed(); 3],
+        );
+        // The layer is available, such that only the view pattern is missing
+        assert_eq!(buffer_pool.index().get_layers(coords).map(|layers| layers.len()), Some(1));
+
+        // A pattern which is generated but not uploaded yet
+        let pattern: WgpuTileViewPattern =
+            TileViewPattern::new(buffer(&device, wgpu::BufferUsages::VERTEX));
+        let zoom = Zoom::new(0.0);
+        let view_region = ViewRegion::new(
+            Aabb2::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)),
+            0,
+            1,
+            zoom,
+            ZoomLevel::default(),
+        );
+        let mut world = World::default();
+        let mut shapes: Vec<TileShape> = Vec::new();
+        for view_tile in pattern.generate_pattern(&view_region, &AllTiles, zoom, &world) {
+            view_tile.render(|shape| shapes.push(shape.clone()));
+        }
+        assert!(pattern.shape_slice(&shapes[0]).is_err());
+
+        world
+            .resources
+            .insert(Eventually::Initialized(buffer_pool));
+        world.resources.insert(Eventually::Initialized(pattern));
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 16,
+                height: 16,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut pass = TrackedRenderPass::new(encoder.begin_render_pass(
+            &wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        ));
+
+        let item = LayerItem {
+            draw_function: Box::new(DrawState::<LayerItem, DrawVectorTiles>::new()),
+            index: 0,
+            style_layer: "water".to_string(),
+            tile: Tile { coords },
+            source_shape: shapes.remove(0),
+        };
+        assert!(matches!(
+            DrawVectorTile::render(&world, &item, &mut pass),
+            RenderCommandResult::Failure
+        ));
+    }
+}
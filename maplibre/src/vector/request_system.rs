@@ -4,31 +4,52 @@ use std::{borrow::Cow, collections::HashSet, marker::PhantomData, rc::Rc};
 
 use crate::{
     context::MapContext,
+    coords::WorldTileCoords,
     environment::{Environment, OffscreenKernel},
     io::{
-        apc::{AsyncProcedureCall, AsyncProcedureFuture, Context, Input, ProcedureError},
+        apc::{
+            AsyncProcedureCall, AsyncProcedureFuture, CancellationToken, Context, Input,
+            ProcedureError,
+        },
         source_type::{SourceType, TessellateSource},
+        worker_pool::{RunningPermits, WorkerPool},
     },
     kernel::Kernel,
     render::tile_view_pattern::DEFAULT_TILE_SIZE,
-    style::layer::LayerPaint,
+    style::{layer::LayerPaint, Style},
     tcs::system::System,
     vector::{
-        process_vector::{process_vector_tile, ProcessVectorContext, VectorTileRequest},
-        transferables::{LayerMissing, VectorTransferables},
+        process_vector::{
+            process_vector_tile, ProcessVectorContext, ProcessVectorError, VectorTileRequest,
+        },
+        transferables::{LayerMissing, TileTessellated, VectorTransferables},
         VectorLayersDataComponent,
     },
 };
 
+/// A tile which waits for a free worker to be processed.
+struct TileRequest {
+    coords: WorldTileCoords,
+    style: Style,
+    /// The style layers which are processed, all if unset.
+    style_layer_ids: Option<HashSet<String>>,
+    cancellation: CancellationToken,
+}
+
 pub struct RequestSystem<E: Environment, T> {
     kernel: Rc<Kernel<E>>,
+    /// Limits how many tiles are processed at once.
+    workers: WorkerPool<TileRequest>,
     phantom_t: PhantomData<T>,
 }
 
 impl<E: Environment, T> RequestSystem<E, T> {
-    pub fn new(kernel: &Rc<Kernel<E>>) -> Self {
+    /// Processes at most `max_workers` tiles at once. Further tiles are queued and the tiles
+    /// nearest to the center of the view are processed first.
+    pub fn new(kernel: &Rc<Kernel<E>>, max_workers: usize) -> Self {
         Self {
             kernel: kernel.clone(),
+            workers: WorkerPool::new(max_workers),
             phantom_t: Default::default(),
         }
     }
@@ -54,6 +75,21 @@ impl<E: Environment, T: VectorTransferables> System for RequestSystem<E, T> {
 
         if view_state.did_camera_change() || view_state.did_zoom_change() {
             if let Some(view_region) = &view_region {
+                // Tiles which left the view before they were tessellated are not needed anymore
+                for coords in world.tiles.cancel_outside(view_region, |tiles, coords| {
+                    tiles
+                        .query::<&VectorLayersDataComponent>(coords)
+                        .is_some_and(|component| !component.done)
+                }) {
+                    log::info!("tile request cancelled, because it left the view: {coords}");
+                }
+
+                let source_id = style
+                    .layers
+                    .iter()
+                    .filter(|layer| layer.source_layer.is_some())
+                    .find_map(|layer| layer.source.clone());
+
                 // TODO: We also need to request tiles from layers above if we are over the maximum zoom level
 
                 for coords in view_region.iter() {
@@ -70,56 +106,139 @@ impl<E: Environment, T: VectorTransferables> System for RequestSystem<E, T> {
                         continue;
                     }
 
-                    world
-                        .tiles
-                        .spawn_mut(coords)
-                        .unwrap()
-                        .insert(VectorLayersDataComponent::default());
-
-                    tracing::event!(tracing::Level::ERROR, %coords, "tile request started: {coords}");
-                    log::info!("tile request started: {coords}");
-
-                    self.kernel
-                        .apc()
-                        .call(
-                            Input::TileRequest {
-                                coords,
-                                style: style.clone(), // TODO: Avoid cloning whole style
-                            },
-                            fetch_vector_apc::<
-                                E::OffscreenKernelEnvironment,
-                                T,
-                                <E::AsyncProcedureCall as AsyncProcedureCall<
-                                    E::OffscreenKernelEnvironment,
-                                >>::Context,
-                            >,
-                        )
-                        .unwrap(); // TODO: Remove unwrap
+                    let component = VectorLayersDataComponent::default();
+                    let mut tile = world.tiles.spawn_mut(coords).unwrap();
+                    match &source_id {
+                        Some(source_id) => tile.insert_from_source(source_id, component),
+                        None => tile.insert(component),
+                    };
+                    let cancellation = world.tiles.cancellation_token(coords).unwrap();
+
+                    tracing::event!(tracing::Level::ERROR, %coords, "tile request queued: {coords}");
+                    log::info!("tile request queued: {coords}");
+
+                    self.workers.push(
+                        view_region.priority(&coords),
+                        TileRequest {
+                            coords,
+                            style: style.clone(), // TODO: Avoid cloning whole style
+                            style_layer_ids: None,
+                            cancellation,
+                        },
+                    );
                 }
             }
         }
 
+        // Tessellate the layers again which changed with the style, see
+        // `retessellate_for_style_change`
+        if let Some(view_region) = &view_region {
+            for coords in view_region.iter() {
+                let Some(component) = world
+                    .tiles
+                    .query_mut::<&mut VectorLayersDataComponent>(coords)
+                else {
+                    continue;
+                };
+                if component.retessellate.is_empty() {
+                    continue;
+                }
+                let layers = std::mem::take(&mut component.retessellate);
+
+                let Some(cancellation) = world.tiles.cancellation_token(coords) else {
+                    continue;
+                };
+
+                log::info!("tile request queued to retessellate {layers:?}: {coords}");
+
+                self.workers.push(
+                    view_region.priority(&coords),
+                    TileRequest {
+                        coords,
+                        style: style.clone(),
+                        style_layer_ids: Some(layers),
+                        cancellation,
+                    },
+                );
+            }
+        }
+
+        // Tiles which were despawned while they were queued are dropped
+        self.workers
+            .retain(|request| !request.cancellation.is_cancelled());
+        // The permits are released by the `PopulateWorldSystem` once the tiles are tessellated
+        let running = world
+            .resources
+            .get_or_init_mut::<RunningPermits<WorldTileCoords>>();
+        running.release_cancelled();
+        let kernel = &self.kernel;
+        self.workers.dispatch(|request, permit| {
+            log::info!("tile request started: {}", request.coords);
+            running.hold(request.coords, request.cancellation.clone(), permit);
+            Self::request_tile(kernel, request)
+        });
+
         view_state.update_references();
     }
 }
 
+impl<E: Environment, T: VectorTransferables> RequestSystem<E, T> {
+    fn request_tile(kernel: &Kernel<E>, request: TileRequest) {
+        let TileRequest {
+            coords,
+            style,
+            style_layer_ids,
+            cancellation,
+        } = request;
+
+        kernel
+            .apc()
+            .call(
+                Input::TileRequest {
+                    coords,
+                    style,
+                    style_layer_ids,
+                    cancellation,
+                },
+                fetch_vector_apc::<
+                    E::OffscreenKernelEnvironment,
+                    T,
+                    <E::AsyncProcedureCall as AsyncProcedureCall<E::OffscreenKernelEnvironment>>::Context,
+                >,
+            )
+            .unwrap(); // TODO: Remove unwrap
+    }
+}
+
 pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context + Clone + Send>(
     input: Input,
     context: C,
     kernel: K,
 ) -> AsyncProcedureFuture {
     Box::pin(async move {
-        let Input::TileRequest { coords, style } = input else {
+        let Input::TileRequest {
+            coords,
+            style,
+            style_layer_ids,
+            cancellation,
+        } = input
+        else {
             return Err(ProcedureError::IncompatibleInput);
         };
 
+        // Only the source layers of the requested style layers are processed
         let fill_layers: HashSet<String> = style
             .layers
             .iter()
+            .filter(|layer| {
+                style_layer_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&layer.id))
+            })
             .filter_map(|layer| {
-                if matches!(layer.paint, Some(LayerPaint::Fill(_)))
-                    || matches!(layer.paint, Some(LayerPaint::Line(_)))
-                {
+                // TODO: Request fill extrusions once their walls and roofs can be tessellated and
+                //  uploaded
+                if matches!(layer.paint, Some(LayerPaint::Fill(_) | LayerPaint::Line(_))) {
                     layer.source_layer.clone()
                 } else {
                     None
@@ -129,36 +248,60 @@ pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context +
 
         let client = kernel.source_client();
 
-        if !fill_layers.is_empty() {
-            let context = context.clone();
-            let source = SourceType::Tessellate(TessellateSource::default());
-            match client.fetch(&coords, &source).await {
-                Ok(data) => {
-                    let data = data.into_boxed_slice();
-
-                    let mut pipeline_context = ProcessVectorContext::<T, C>::new(context);
-                    process_vector_tile(
-                        &data,
-                        VectorTileRequest {
-                            coords,
-                            layers: fill_layers,
-                            style
-                        },
-                        &mut pipeline_context,
-                    )
-                    .map_err(|e| ProcedureError::Execution(Box::new(e)))?;
-                }
-                Err(e) => {
-                    log::error!("{e:?}");
-                    for to_load in &fill_layers {
-                        context
-                            .send_back(<T as VectorTransferables>::LayerMissing::build_from(
-                                coords,
-                                to_load.to_string(),
-                            ))
-                            .map_err(ProcedureError::Send)?;
+        // Every request which is not cancelled ends with a `TileTessellated` message, which frees
+        // the slot of the worker pool on the requesting side
+        let tile_finished = |context: &C| {
+            context
+                .send_back(T::TileTessellated::build_from(coords, true))
+                .map_err(ProcedureError::Send)
+        };
+
+        if fill_layers.is_empty() {
+            return tile_finished(&context);
+        }
+
+        let source = SourceType::Tessellate(TessellateSource::default());
+        match client.fetch(&coords, &source).await {
+            Ok(data) => {
+                let data = data.into_boxed_slice();
+
+                let mut pipeline_context = ProcessVectorContext::<T, C>::new(context.clone())
+                    .with_cancellation(cancellation);
+                match process_vector_tile(
+                    &data,
+                    VectorTileRequest {
+                        coords,
+                        layers: fill_layers,
+                        style,
+                        style_layer_ids,
+                        tessellation_budget: None,
+                        chunk_size: None,
+                        retain_feature_properties: false,
+                        clip: None,
+                    },
+                    &mut pipeline_context,
+                ) {
+                    Ok(()) => {}
+                    Err(ProcessVectorError::Cancelled) => {
+                        log::info!("tile request cancelled: {coords}");
                     }
+                    Err(e) => {
+                        tile_finished(&context)?;
+                        return Err(ProcedureError::Execution(Box::new(e)));
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{e:?}");
+                for to_load in &fill_layers {
+                    context
+                        .send_back(<T as VectorTransferables>::LayerMissing::build_from(
+                            coords,
+                            to_load.to_string(),
+                        ))
+                        .map_err(ProcedureError::Send)?;
                 }
+                tile_finished(&context)?;
             }
         }
 
@@ -38,6 +38,8 @@ pub struct BufferPool<Q, B, V, I, TM, FM> {
     feature_metadata: BackingBuffer<B>,
 
     index: RingIndex,
+    /// Decides where data is stored and which entries are evicted for it.
+    strategy: Box<dyn AllocationStrategy>,
     phantom_v: PhantomData<V>,
     phantom_i: PhantomData<I>,
     phantom_q: PhantomData<Q>,
@@ -45,7 +47,7 @@ pub struct BufferPool<Q, B, V, I, TM, FM> {
     phantom_fm: PhantomData<FM>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BackingBufferType {
     Vertices,
     Indices,
@@ -72,7 +74,49 @@ impl<B> BackingBuffer<B> {
     }
 }
 
-impl<V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<wgpu::Queue, wgpu::Buffer, V, I, TM, FM> {
+/// Decides where new data is stored within the backing buffers of a [`BufferPool`] and which
+/// entries are evicted if there is not enough space. This allows e.g. memory-constrained devices
+/// to evict more eagerly. [`RingBufferStrategy`] is used by default.
+pub trait AllocationStrategy: Debug {
+    /// Returns the range of `size` bytes within the backing buffer `typ`, which is `inner_size`
+    /// bytes large, at which new data is stored. Returns `None` if an entry has to be evicted
+    /// first, see [`AllocationStrategy::evict`].
+    fn find_space(
+        &mut self,
+        index: &RingIndex,
+        typ: BackingBufferType,
+        size: wgpu::BufferAddress,
+        inner_size: wgpu::BufferAddress,
+    ) -> Option<Range<wgpu::BufferAddress>>;
+
+    /// Removes an entry from `index` to make room for new data and returns it. Returns `None` if
+    /// there is nothing left to evict.
+    fn evict(&mut self, index: &mut RingIndex) -> Option<IndexEntry>;
+}
+
+/// Stores data like a ring buffer. New data is placed in the largest gap after the newest or
+/// before the oldest entry, and the oldest entries are evicted first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RingBufferStrategy;
+
+impl AllocationStrategy for RingBufferStrategy {
+    fn find_space(
+        &mut self,
+        index: &RingIndex,
+        typ: BackingBufferType,
+        size: wgpu::BufferAddress,
+        inner_size: wgpu::BufferAddress,
+    ) -> Option<Range<wgpu::BufferAddress>> {
+        let gap = index.find_largest_gap(typ, inner_size);
+        (size <= gap.end - gap.start).then(|| gap.start..gap.start + size)
+    }
+
+    fn evict(&mut self, index: &mut RingIndex) -> Option<IndexEntry> {
+        index.pop_front()
+    }
+}
+
+impl<V: Pod, I: Pod + Into<u32>, TM: Pod, FM: Pod> BufferPool<wgpu::Queue, wgpu::Buffer, V, I, TM, FM> {
     pub fn from_device(device: &wgpu::Device) -> Self {
         let vertex_buffer_desc = wgpu::BufferDescriptor {
             label: Some("vertex buffer"),
@@ -122,7 +166,7 @@ impl<V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<wgpu::Queue, wgpu::Buffer, V,
         )
     }
 }
-impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM, FM> {
+impl<Q: Queue<B>, B, V: Pod, I: Pod + Into<u32>, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM, FM> {
     pub fn new(
         vertices: BackingBufferDescriptor<B>,
         indices: BackingBufferDescriptor<B>,
@@ -151,6 +195,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
                 BackingBufferType::FeatureMetadata,
             ),
             index: RingIndex::new(),
+            strategy: Box::new(RingBufferStrategy),
             phantom_v: Default::default(),
             phantom_i: Default::default(),
             phantom_q: Default::default(),
@@ -159,26 +204,60 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         }
     }
 
+    /// Allocates space with `strategy` instead of the [`RingBufferStrategy`].
+    pub fn with_strategy(mut self, strategy: impl AllocationStrategy + 'static) -> Self {
+        self.strategy = Box::new(strategy);
+        self
+    }
+
     pub fn clear(&mut self) {
         self.index.clear()
     }
 
+    fn backing_buffer(&self, typ: BackingBufferType) -> &BackingBuffer<B> {
+        match typ {
+            BackingBufferType::Vertices => &self.vertices,
+            BackingBufferType::Indices => &self.indices,
+            BackingBufferType::Metadata => &self.layer_metadata,
+            BackingBufferType::FeatureMetadata => &self.feature_metadata,
+        }
+    }
+
     #[cfg(test)]
     fn available_space(&self, typ: BackingBufferType) -> wgpu::BufferAddress {
-        let gap = self.index.find_largest_gap(
-            typ,
-            match typ {
-                BackingBufferType::Vertices => &self.vertices,
-                BackingBufferType::Indices => &self.indices,
-                BackingBufferType::Metadata => &self.layer_metadata,
-                BackingBufferType::FeatureMetadata => &self.feature_metadata,
-            }
-            .inner_size,
-        );
+        let gap = self
+            .index
+            .find_largest_gap(typ, self.backing_buffer(typ).inner_size);
 
         gap.end - gap.start
     }
 
+    /// Finds room for `new_data` bytes in the backing buffer `typ` with the allocation strategy,
+    /// which evicts entries until enough space is available.
+    fn make_room(
+        &mut self,
+        new_data: wgpu::BufferAddress,
+        typ: BackingBufferType,
+    ) -> Range<wgpu::BufferAddress> {
+        let inner_size = self.backing_buffer(typ).inner_size;
+        if new_data > inner_size {
+            panic!("can not allocate because backing buffer {typ:?} are too small")
+        }
+
+        loop {
+            if let Some(range) = self
+                .strategy
+                .find_space(&self.index, typ, new_data, inner_size)
+            {
+                return range;
+            }
+            // no more space, we need to evict items
+            if self.strategy.evict(&mut self.index).is_none() {
+                panic!("evicted even though index is empty")
+            }
+        }
+    }
+
     pub fn vertices(&self) -> &B {
         &self.vertices.inner
     }
@@ -218,11 +297,18 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         self.index.get_layers(coords).map(|layers| {
             layers
                 .iter()
+                .filter(|entry| !entry.stale)
                 .map(|entry| entry.style_layer.id.clone())
                 .collect()
         })
     }
 
+    /// Marks the uploaded geometry of the style layers `layer_ids` as stale, e.g. because the
+    /// style changed. The layers are uploaded again from the tessellated tiles.
+    pub fn invalidate_layers(&mut self, layer_ids: &HashSet<String>) {
+        self.index.invalidate_layers(layer_ids)
+    }
+
     /// Allocates
     /// * `geometry`
     /// * `layer_metadata` and
@@ -239,7 +325,6 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         feature_metadata: &[FM],
     ) {
         let vertices_stride = size_of::<V>() as wgpu::BufferAddress;
-        let indices_stride = size_of::<I>() as wgpu::BufferAddress;
         let layer_metadata_stride = size_of::<TM>() as wgpu::BufferAddress;
         let feature_metadata_stride = size_of::<FM>() as wgpu::BufferAddress;
 
@@ -248,11 +333,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
             geometry.buffer.vertices.len() as wgpu::BufferAddress,
             geometry.buffer.vertices.len() as wgpu::BufferAddress,
         );
-        let (indices_bytes, aligned_indices_bytes) = Self::align(
-            indices_stride,
-            geometry.buffer.indices.len() as wgpu::BufferAddress,
-            geometry.usable_indices as wgpu::BufferAddress,
-        );
+        let indices = geometry.index_bytes();
         let (layer_metadata_bytes, aligned_layer_metadata_bytes) =
             Self::align(layer_metadata_stride, 1, 1);
 
@@ -272,27 +353,14 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         let maybe_entry = IndexEntry {
             coords,
             style_layer,
-            buffer_vertices: self.index.make_room(
-                vertices_bytes,
-                self.vertices.typ,
-                self.vertices.inner_size,
-            ),
-            buffer_indices: self.index.make_room(
-                indices_bytes,
-                self.indices.typ,
-                self.indices.inner_size,
-            ),
+            buffer_vertices: self.make_room(vertices_bytes, self.vertices.typ),
+            buffer_indices: self.make_room(indices.len() as wgpu::BufferAddress, self.indices.typ),
             usable_indices: geometry.usable_indices,
-            buffer_layer_metadata: self.index.make_room(
-                layer_metadata_bytes,
-                self.layer_metadata.typ,
-                self.layer_metadata.inner_size,
-            ),
-            buffer_feature_metadata: self.index.make_room(
-                feature_metadata_bytes,
-                self.feature_metadata.typ,
-                self.feature_metadata.inner_size,
-            ),
+            index_format: geometry.index_format(),
+            buffer_layer_metadata: self.make_room(layer_metadata_bytes, self.layer_metadata.typ),
+            buffer_feature_metadata: self
+                .make_room(feature_metadata_bytes, self.feature_metadata.typ),
+            stale: false,
         };
 
         // write_buffer() is the preferred method for WASM: https://toji.github.io/webgpu-best-practices/buffer-uploads.html#when-in-doubt-writebuffer
@@ -305,7 +373,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         queue.write_buffer(
             &self.indices.inner,
             maybe_entry.buffer_indices.start,
-            &bytemuck::cast_slice(&geometry.buffer.indices)[0..aligned_indices_bytes as usize],
+            &indices,
         );
 
         queue.write_buffer(
@@ -323,6 +391,68 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         self.index.push_back(maybe_entry);
     }
 
+    /// Allocates the staged geometry and metadata of the layers of a tile like
+    /// [`BufferPool::allocate_layer_geometry`]. The layers are stored next to each other, so that
+    /// each backing buffer is written only once for the whole tile. Each layer keeps its own
+    /// [`IndexEntry`].
+    #[tracing::instrument(skip_all)]
+    pub fn allocate_tile_geometry(&mut self, queue: &Q, tile: TileGeometry<V, I, TM, FM>) {
+        if tile.layers.is_empty() {
+            return;
+        }
+
+        if tile.feature_metadata.len() as wgpu::BufferAddress % wgpu::COPY_BUFFER_ALIGNMENT != 0 {
+            // TODO: align if not aligned?
+            panic!(
+                "feature_metadata is not aligned. This should not happen as long as size_of::<FM>() is a multiple of the alignment."
+            )
+        }
+
+        let vertices_start = self
+            .make_room(tile.vertices.len() as wgpu::BufferAddress, self.vertices.typ)
+            .start;
+        let indices_start = self
+            .make_room(tile.indices.len() as wgpu::BufferAddress, self.indices.typ)
+            .start;
+        let layer_metadata_start = self
+            .make_room(tile.layer_metadata.len() as wgpu::BufferAddress, self.layer_metadata.typ)
+            .start;
+        let feature_metadata_start = self
+            .make_room(tile.feature_metadata.len() as wgpu::BufferAddress, self.feature_metadata.typ)
+            .start;
+
+        queue.write_buffer(&self.vertices.inner, vertices_start, &tile.vertices);
+        queue.write_buffer(&self.indices.inner, indices_start, &tile.indices);
+        queue.write_buffer(
+            &self.layer_metadata.inner,
+            layer_metadata_start,
+            &tile.layer_metadata,
+        );
+        queue.write_buffer(
+            &self.feature_metadata.inner,
+            feature_metadata_start,
+            &tile.feature_metadata,
+        );
+
+        let offset = |start: wgpu::BufferAddress, range: Range<wgpu::BufferAddress>| {
+            start + range.start..start + range.end
+        };
+
+        for layer in tile.layers {
+            self.index.push_back(IndexEntry {
+                coords: tile.coords,
+                style_layer: layer.style_layer,
+                buffer_vertices: offset(vertices_start, layer.vertices),
+                buffer_indices: offset(indices_start, layer.indices),
+                usable_indices: layer.usable_indices,
+                index_format: layer.index_format,
+                buffer_layer_metadata: offset(layer_metadata_start, layer.layer_metadata),
+                buffer_feature_metadata: offset(feature_metadata_start, layer.feature_metadata),
+                stale: false,
+            });
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn update_layer_metadata(&self, queue: &Q, entry: &IndexEntry, layer_metadata: TM) {
         let layer_metadata_stride = size_of::<TM>() as wgpu::BufferAddress; // TODO: deduplicate
@@ -377,6 +507,85 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
     }
 }
 
+/// The geometry and metadata of the layers of a tile, which are staged to be allocated at once
+/// by [`BufferPool::allocate_tile_geometry`].
+pub struct TileGeometry<V, I, TM, FM> {
+    coords: WorldTileCoords,
+    vertices: Vec<u8>,
+    indices: Vec<u8>,
+    layer_metadata: Vec<u8>,
+    feature_metadata: Vec<u8>,
+    layers: Vec<StagedLayer>,
+    phantom_v: PhantomData<V>,
+    phantom_i: PhantomData<I>,
+    phantom_m: PhantomData<TM>,
+    phantom_fm: PhantomData<FM>,
+}
+
+/// A layer within a [`TileGeometry`] and the ranges of its data within the staged bytes.
+struct StagedLayer {
+    style_layer: StyleLayer,
+    vertices: Range<wgpu::BufferAddress>,
+    indices: Range<wgpu::BufferAddress>,
+    layer_metadata: Range<wgpu::BufferAddress>,
+    feature_metadata: Range<wgpu::BufferAddress>,
+    usable_indices: u32,
+    index_format: wgpu::IndexFormat,
+}
+
+impl<V: Pod, I: Pod + Into<u32>, TM: Pod, FM: Pod> TileGeometry<V, I, TM, FM> {
+    pub fn new(coords: WorldTileCoords) -> Self {
+        Self {
+            coords,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            layer_metadata: Vec::new(),
+            feature_metadata: Vec::new(),
+            layers: Vec::new(),
+            phantom_v: Default::default(),
+            phantom_i: Default::default(),
+            phantom_m: Default::default(),
+            phantom_fm: Default::default(),
+        }
+    }
+
+    /// The style layers which were pushed, in order.
+    pub fn style_layers(&self) -> impl Iterator<Item = &StyleLayer> {
+        self.layers.iter().map(|layer| &layer.style_layer)
+    }
+
+    /// Copies the geometry and metadata of a layer to the staged bytes of the tile.
+    pub fn push(
+        &mut self,
+        style_layer: StyleLayer,
+        geometry: &OverAlignedVertexBuffer<V, I>,
+        layer_metadata: TM,
+        feature_metadata: &[FM],
+    ) {
+        fn stage(staging: &mut Vec<u8>, data: &[u8]) -> Range<wgpu::BufferAddress> {
+            let start = staging.len() as wgpu::BufferAddress;
+            staging.extend_from_slice(data);
+            start..staging.len() as wgpu::BufferAddress
+        }
+
+        self.layers.push(StagedLayer {
+            style_layer,
+            vertices: stage(
+                &mut self.vertices,
+                bytemuck::cast_slice(&geometry.buffer.vertices),
+            ),
+            indices: stage(&mut self.indices, &geometry.index_bytes()),
+            layer_metadata: stage(&mut self.layer_metadata, bytemuck::bytes_of(&layer_metadata)),
+            feature_metadata: stage(
+                &mut self.feature_metadata,
+                bytemuck::cast_slice(feature_metadata),
+            ),
+            usable_indices: geometry.usable_indices,
+            index_format: geometry.index_format(),
+        });
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IndexEntry {
     pub coords: WorldTileCoords, // TODO: replace with generic key
@@ -389,12 +598,21 @@ pub struct IndexEntry {
     buffer_layer_metadata: Range<wgpu::BufferAddress>,
     // Range of bytes within the backing buffer for feature metadata
     buffer_feature_metadata: Range<wgpu::BufferAddress>,
-    // Amount of actually usable indices. Each index has the size/format `IndexDataType`.
+    // Amount of actually usable indices. Each index has the size/format `index_format`.
     // Can be lower than size(buffer_indices) / indices_stride because of alignment.
     usable_indices: u32,
+    // Format of the indices, which depends on the number of vertices of the layer
+    index_format: wgpu::IndexFormat,
+    // Whether the style layer changed since the upload. Stale entries are neither drawn nor
+    // reported as loaded and are evicted like any other entry.
+    stale: bool,
 }
 
 impl IndexEntry {
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
     pub fn indices_range(&self) -> Range<u32> {
         0..self.usable_indices
     }
@@ -403,6 +621,11 @@ impl IndexEntry {
         self.buffer_indices.clone()
     }
 
+    /// The format of the indices, see [`OverAlignedVertexBuffer::index_format`].
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
     pub fn vertices_buffer_range(&self) -> Range<wgpu::BufferAddress> {
         self.buffer_vertices.clone()
     }
@@ -469,7 +692,18 @@ impl RingIndex {
             .flat_map(|key| self.tree_index.get(key).map(|entry| entry.layers.iter()))
     }
 
-    fn pop_front(&mut self) -> Option<IndexEntry> {
+    fn invalidate_layers(&mut self, layer_ids: &HashSet<String>) {
+        for entry in self.tree_index.values_mut() {
+            for layer in &mut entry.layers {
+                if layer_ids.contains(&layer.style_layer.id) {
+                    layer.stale = true;
+                }
+            }
+        }
+    }
+
+    /// Removes the oldest entry.
+    pub fn pop_front(&mut self) -> Option<IndexEntry> {
         if let Some(entry) = self
             .linear_index
             .pop_front()
@@ -500,31 +734,10 @@ impl RingIndex {
         }
     }
 
-    fn make_room(
-        &mut self,
-        new_data: wgpu::BufferAddress,
-        typ: BackingBufferType,
-        inner_size: wgpu::BufferAddress,
-    ) -> Range<wgpu::BufferAddress> {
-        if new_data > inner_size {
-            panic!("can not allocate because backing buffer {typ:?} are too small")
-        }
-
-        let mut available_gap = self.find_largest_gap(typ, inner_size);
-
-        while new_data > available_gap.end - available_gap.start {
-            // no more space, we need to evict items
-            if self.pop_front().is_some() {
-                available_gap = self.find_largest_gap(typ, inner_size);
-            } else {
-                panic!("evicted even though index is empty")
-            }
-        }
-
-        available_gap.start..available_gap.start + new_data
-    }
-
-    fn find_largest_gap(
+    /// The largest range of the backing buffer `typ`, which is `inner_size` bytes large, that is
+    /// not occupied by entries. Ranges are only searched after the newest and before the oldest
+    /// entry.
+    pub fn find_largest_gap(
         &self,
         typ: BackingBufferType,
         inner_size: wgpu::BufferAddress,
@@ -570,7 +783,7 @@ impl RingIndex {
     }
 }
 
-impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> HasTile for BufferPool<Q, B, V, I, TM, FM> {
+impl<Q: Queue<B>, B, V: Pod, I: Pod + Into<u32>, TM: Pod, FM: Pod> HasTile for BufferPool<Q, B, V, I, TM, FM> {
     fn has_tile(&self, coords: WorldTileCoords, _world: &World) -> bool {
         self.index().get_layers(coords).is_some()
     }
@@ -584,13 +797,24 @@ impl Default for RingIndex {
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        collections::HashSet,
+        ops::Range,
+        rc::Rc,
+    };
+
     use lyon::tessellation::VertexBuffers;
 
     use crate::{
         coords::ZoomLevel,
         render::resource::{BackingBufferDescriptor, Queue},
         style::layer::StyleLayer,
-        vector::resource::{BackingBufferType, BufferPool},
+        tessellation::OverAlignedVertexBuffer,
+        vector::resource::{
+            AllocationStrategy, BackingBufferType, BufferPool, IndexEntry, RingBufferStrategy,
+            RingIndex, TileGeometry,
+        },
     };
 
     #[derive(Debug)]
@@ -607,6 +831,19 @@ mod tests {
         }
     }
 
+    /// Counts the writes to the buffers.
+    #[derive(Default)]
+    struct CountingQueue {
+        writes: Cell<usize>,
+    }
+
+    impl Queue<TestBuffer> for CountingQueue {
+        fn write_buffer(&self, buffer: &TestBuffer, offset: wgpu::BufferAddress, data: &[u8]) {
+            TestQueue.write_buffer(buffer, offset, data);
+            self.writes.set(self.writes.get() + 1);
+        }
+    }
+
     #[repr(C)]
     #[derive(Default, Copy, Clone, bytemuck_derive::Pod, bytemuck_derive::Zeroable)]
     struct TestVertex {
@@ -718,4 +955,247 @@ mod tests {
         println!("{:?}", pool.index);
         assert_eq!(0, pool.available_space(BackingBufferType::Vertices));
     }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        Allocate(BackingBufferType),
+        Evict(String),
+    }
+
+    /// Allocates like the [`RingBufferStrategy`] and records the calls.
+    #[derive(Debug, Default)]
+    struct RecordingStrategy {
+        calls: Rc<RefCell<Vec<Call>>>,
+    }
+
+    impl AllocationStrategy for RecordingStrategy {
+        fn find_space(
+            &mut self,
+            index: &RingIndex,
+            typ: BackingBufferType,
+            size: wgpu::BufferAddress,
+            inner_size: wgpu::BufferAddress,
+        ) -> Option<Range<wgpu::BufferAddress>> {
+            let range = RingBufferStrategy.find_space(index, typ, size, inner_size);
+            if range.is_some() {
+                self.calls.borrow_mut().push(Call::Allocate(typ));
+            }
+            range
+        }
+
+        fn evict(&mut self, index: &mut RingIndex) -> Option<IndexEntry> {
+            let entry = RingBufferStrategy.evict(index);
+            if let Some(entry) = &entry {
+                self.calls
+                    .borrow_mut()
+                    .push(Call::Evict(entry.style_layer.id.clone()));
+            }
+            entry
+        }
+    }
+
+    #[test]
+    fn test_allocation_strategy() {
+        let strategy = RecordingStrategy::default();
+        let calls = strategy.calls.clone();
+        let mut pool: BufferPool<TestQueue, TestBuffer, TestVertex, u32, u32, u32> =
+            BufferPool::new(
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+            )
+            .with_strategy(strategy);
+
+        let mut buffer = VertexBuffers::new();
+        buffer.vertices.append(&mut create_48byte());
+        buffer.indices.append(&mut vec![1, 2, 3, 4]);
+        let geometry = buffer.into();
+
+        let allocations = [
+            BackingBufferType::Vertices,
+            BackingBufferType::Indices,
+            BackingBufferType::Metadata,
+            BackingBufferType::FeatureMetadata,
+        ]
+        .map(Call::Allocate);
+        for id in ["water", "roads"] {
+            let style_layer = StyleLayer {
+                id: id.to_string(),
+                ..StyleLayer::default()
+            };
+            pool.allocate_layer_geometry(
+                &TestQueue,
+                (0, 0, ZoomLevel::default()).into(),
+                style_layer,
+                &geometry,
+                2,
+                &[],
+            );
+        }
+        let both_layers = [allocations.as_slice(), allocations.as_slice()].concat();
+        assert_eq!(calls.borrow_mut().drain(..).collect::<Vec<_>>(), both_layers);
+
+        // The vertices of a third layer only fit once the oldest layer is evicted
+        pool.allocate_layer_geometry(
+            &TestQueue,
+            (0, 0, ZoomLevel::default()).into(),
+            StyleLayer::default(),
+            &geometry,
+            2,
+            &[],
+        );
+        let mut expected = vec![Call::Evict("water".to_string())];
+        expected.extend(allocations);
+        assert_eq!(*calls.borrow(), expected);
+    }
+
+    #[test]
+    fn test_allocate_tile() {
+        type TestPool = BufferPool<CountingQueue, TestBuffer, TestVertex, u32, u32, u32>;
+        let create_pool = || -> TestPool {
+            BufferPool::new(
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+            )
+        };
+
+        let coords = (0, 0, ZoomLevel::default()).into();
+        let layers: Vec<(StyleLayer, OverAlignedVertexBuffer<TestVertex, u32>)> = [
+            ("water", create_48byte(), vec![0, 1, 0, 1]),
+            ("roads", create_24byte(), vec![0, 0, 0]),
+            ("buildings", create_48byte(), vec![0, 1]),
+        ]
+        .into_iter()
+        .map(|(id, mut vertices, mut indices)| {
+            let mut buffer = VertexBuffers::new();
+            buffer.vertices.append(&mut vertices);
+            buffer.indices.append(&mut indices);
+            let style_layer = StyleLayer {
+                id: id.to_string(),
+                ..StyleLayer::default()
+            };
+            (style_layer, buffer.into())
+        })
+        .collect();
+
+        let queue = CountingQueue::default();
+        let mut per_layer = create_pool();
+        for (style_layer, geometry) in &layers {
+            per_layer.allocate_layer_geometry(&queue, coords, style_layer.clone(), geometry, 2, &[]);
+        }
+        assert_eq!(queue.writes.get(), 4 * layers.len());
+
+        let queue = CountingQueue::default();
+        let mut batched = create_pool();
+        let mut tile = TileGeometry::new(coords);
+        for (style_layer, geometry) in &layers {
+            tile.push(style_layer.clone(), geometry, 2, &[]);
+        }
+        batched.allocate_tile_geometry(&queue, tile);
+        // One write for each backing buffer
+        assert_eq!(queue.writes.get(), 4);
+
+        // The layers keep the same ranges
+        let per_layer = per_layer.index().get_layers(coords).unwrap();
+        let batched = batched.index().get_layers(coords).unwrap();
+        assert_eq!(batched.len(), layers.len());
+        for (expected, entry) in per_layer.iter().zip(batched) {
+            assert_eq!(entry.style_layer.id, expected.style_layer.id);
+            assert_eq!(entry.indices_range(), expected.indices_range());
+            assert_eq!(entry.indices_buffer_range(), expected.indices_buffer_range());
+            assert_eq!(entry.vertices_buffer_range(), expected.vertices_buffer_range());
+            assert_eq!(
+                entry.layer_metadata_buffer_range(),
+                expected.layer_metadata_buffer_range()
+            );
+            assert_eq!(
+                entry.feature_metadata_buffer_range(),
+                expected.feature_metadata_buffer_range()
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_format() {
+        let size = 1 << 21;
+        let mut pool: BufferPool<TestQueue, TestBuffer, TestVertex, u32, u32, u32> =
+            BufferPool::new(
+                BackingBufferDescriptor::new(TestBuffer { size }, size),
+                BackingBufferDescriptor::new(TestBuffer { size }, size),
+                BackingBufferDescriptor::new(TestBuffer { size }, size),
+                BackingBufferDescriptor::new(TestBuffer { size }, size),
+            );
+        let geometry = |vertices: usize| -> OverAlignedVertexBuffer<TestVertex, u32> {
+            let mut buffer = VertexBuffers::new();
+            buffer.vertices.resize(vertices, TestVertex::default());
+            buffer.indices.extend([0, 1, vertices as u32 - 1]);
+            buffer.into()
+        };
+
+        let small = geometry(3);
+        assert_eq!(small.index_format(), wgpu::IndexFormat::Uint16);
+        // Three 16 bit indices, padded to the copy alignment
+        let expected: Vec<u8> = [0u16, 1, 2, 0].into_iter().flat_map(u16::to_ne_bytes).collect();
+        assert_eq!(small.index_bytes(), expected);
+
+        let large = geometry(1 << 16);
+        assert_eq!(large.index_format(), wgpu::IndexFormat::Uint32);
+        let expected: Vec<u8> = [0u32, 1, 65535].into_iter().flat_map(u32::to_ne_bytes).collect();
+        assert_eq!(large.index_bytes(), expected);
+
+        let coords = (0, 0, ZoomLevel::default()).into();
+        for geometry in [&small, &large] {
+            pool.allocate_layer_geometry(&TestQueue, coords, StyleLayer::default(), geometry, 2, &[]);
+        }
+
+        let entries = pool.index().get_layers(coords).unwrap();
+        let formats: Vec<_> = entries.iter().map(IndexEntry::index_format).collect();
+        assert_eq!(
+            formats,
+            vec![wgpu::IndexFormat::Uint16, wgpu::IndexFormat::Uint32]
+        );
+        let sizes: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.indices_buffer_range().end - entry.indices_buffer_range().start)
+            .collect();
+        assert_eq!(sizes, vec![8, 12]);
+        assert!(entries.iter().all(|entry| entry.indices_range() == (0..3)));
+    }
+
+    #[test]
+    fn test_invalidate_layers() {
+        let mut pool: BufferPool<CountingQueue, TestBuffer, TestVertex, u32, u32, u32> =
+            BufferPool::new(
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+                BackingBufferDescriptor::new(TestBuffer { size: 256 }, 256),
+            );
+        let queue = CountingQueue::default();
+        let coords = (0, 0, ZoomLevel::default()).into();
+
+        for id in ["water", "roads"] {
+            let mut buffer = VertexBuffers::new();
+            buffer.vertices.append(&mut create_24byte());
+            buffer.indices.append(&mut vec![0, 1, 0, 1]);
+            let style_layer = StyleLayer {
+                id: id.to_string(),
+                ..StyleLayer::default()
+            };
+            pool.allocate_layer_geometry(&queue, coords, style_layer, &buffer.into(), 2, &[]);
+        }
+
+        pool.invalidate_layers(&HashSet::from(["roads".to_string()]));
+
+        // Stale layers are uploaded again
+        assert_eq!(
+            pool.get_loaded_layers_at(coords),
+            Some(HashSet::from(["water".to_string()]))
+        );
+        let entries = pool.index().get_layers(coords).unwrap();
+        assert_eq!(entries.iter().filter(|entry| entry.is_stale()).count(), 1);
+    }
 }
@@ -0,0 +1,165 @@
+//! Applies a change of the [`Style`] to the tessellated tiles.
+
+use crate::{
+    style::{Style, StyleDiff},
+    tcs::tiles::Tiles,
+    vector::{VectorLayerData, VectorLayersDataComponent},
+};
+
+/// Prepares the `tiles` for switching from the `old` to the `new` style without reloading
+/// everything. The data of layers which have to be tessellated again or which were removed is
+/// dropped and the layers are scheduled for [`VectorLayersDataComponent::retessellate`]. Layers
+/// whose paint changed keep their tessellated data and only need to be uploaded again, the
+/// returned [`StyleDiff`] lists them.
+pub fn retessellate_for_style_change(tiles: &mut Tiles, old: &Style, new: &Style) -> StyleDiff {
+    let diff = old.diff(new);
+    if diff.retessellate.is_empty() && diff.removed.is_empty() {
+        return diff;
+    }
+
+    // Layers without a source layer are backed by the background tile
+    let retessellate: Vec<&String> = new
+        .layers
+        .iter()
+        .filter(|layer| layer.source_layer.is_some() && diff.retessellate.contains(&layer.id))
+        .map(|layer| &layer.id)
+        .collect();
+    let is_outdated =
+        |id: &String| diff.retessellate.contains(id) || diff.removed.contains(id);
+
+    let coords: Vec<_> = tiles.tiles.values().map(|tile| tile.coords).collect();
+    for coords in coords {
+        let Some(component) = tiles.query_mut::<&mut VectorLayersDataComponent>(coords) else {
+            continue;
+        };
+
+        component.layers.retain(|layer| {
+            let style_layer_id = match layer {
+                VectorLayerData::Available(layer) => &layer.style_layer_id,
+                VectorLayerData::Missing(layer) => &layer.style_layer_id,
+            };
+            !is_outdated(style_layer_id)
+        });
+        component
+            .pending_chunks
+            .retain(|pending| !is_outdated(&pending.style_layer_id));
+        component
+            .retessellate
+            .retain(|style_layer_id| !diff.removed.contains(style_layer_id));
+        component
+            .retessellate
+            .extend(retessellate.iter().map(|id| id.to_string()));
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr};
+
+    use super::retessellate_for_style_change;
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        style::{layer::LayerPaint, Style},
+        tcs::tiles::{BackgroundTileBuilder, Tiles},
+        vector::{VectorLayerData, VectorLayersDataComponent},
+    };
+
+    fn style() -> Style {
+        // language=JSON
+        Style::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {"id": "water", "type": "fill", "source-layer": "water", "paint": {"fill-color": "#0000ff"}},
+            {"id": "roads", "type": "line", "source-layer": "transportation", "paint": {"line-color": "#000000"}}
+          ]
+        }
+        "##,
+        )
+        .unwrap()
+    }
+
+    fn tiles(coords: WorldTileCoords) -> Tiles {
+        let mut tiles = Tiles::default();
+        tiles.spawn_mut(coords).unwrap().insert(VectorLayersDataComponent {
+            done: true,
+            empty: false,
+            layers: ["water", "roads"]
+                .into_iter()
+                .map(|id| {
+                    VectorLayerData::Available(
+                        BackgroundTileBuilder::new().with_style_layer_id(id).build(),
+                    )
+                })
+                .collect(),
+            pending_chunks: vec![],
+            retessellate: Default::default(),
+        });
+        tiles
+    }
+
+    fn layer_ids(tiles: &Tiles, coords: WorldTileCoords) -> Vec<String> {
+        tiles
+            .query::<&VectorLayersDataComponent>(coords)
+            .unwrap()
+            .layers
+            .iter()
+            .map(|layer| match layer {
+                VectorLayerData::Available(layer) => layer.style_layer_id.clone(),
+                VectorLayerData::Missing(layer) => layer.style_layer_id.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paint_change_is_only_uploaded() {
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let mut tiles = tiles(coords);
+        let old = style();
+        let mut new = old.clone();
+        if let Some(LayerPaint::Fill(paint)) = &mut new.layers[0].paint {
+            paint.fill_color = Some(serde_json::from_str(r##""#ff0000""##).unwrap());
+        }
+
+        let diff = retessellate_for_style_change(&mut tiles, &old, &new);
+
+        assert_eq!(diff.reupload, HashSet::from(["water".to_string()]));
+        assert!(diff.retessellate.is_empty());
+        assert_eq!(layer_ids(&tiles, coords), vec!["water", "roads"]);
+        let component = tiles.query::<&VectorLayersDataComponent>(coords).unwrap();
+        assert!(component.retessellate.is_empty());
+    }
+
+    #[test]
+    fn test_filter_change_is_retessellated() {
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let mut tiles = tiles(coords);
+        let old = style();
+        let mut new = old.clone();
+        new.layers[1].filter =
+            Some(serde_json::from_str(r#"["==", "class", "primary"]"#).unwrap());
+
+        let diff = retessellate_for_style_change(&mut tiles, &old, &new);
+
+        assert_eq!(diff.retessellate, HashSet::from(["roads".to_string()]));
+        assert!(diff.reupload.is_empty());
+        // The water layer is left in place
+        assert_eq!(layer_ids(&tiles, coords), vec!["water"]);
+        let component = tiles.query::<&VectorLayersDataComponent>(coords).unwrap();
+        assert_eq!(component.retessellate, HashSet::from(["roads".to_string()]));
+
+        // Removed layers are dropped without tessellating them again
+        let mut removed = new.clone();
+        removed.layers.remove(1);
+        let diff = retessellate_for_style_change(&mut tiles, &new, &removed);
+        assert_eq!(diff.removed, HashSet::from(["roads".to_string()]));
+        let component = tiles.query::<&VectorLayersDataComponent>(coords).unwrap();
+        assert!(component.retessellate.is_empty());
+    }
+}
@@ -1,14 +1,18 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+};
 
 use geozero::mvt::tile::Layer;
 
 use crate::{
-    coords::WorldTileCoords,
+    coords::{WorldTileCoords, EXTENT_UINT},
     io::{
         apc::{IntoMessage, Message, MessageTag},
         geometry_index::TileIndex,
     },
     render::ShaderVertex,
+    style::expression::ComparisonLiteral,
     tessellation::{IndexDataType, OverAlignedVertexBuffer},
     vector::{AvailableVectorLayerData, MissingVectorLayerData},
 };
@@ -30,11 +34,15 @@ impl MessageTag for VectorMessageTag {
 pub trait TileTessellated: IntoMessage + Debug + Send {
     fn message_tag() -> &'static dyn MessageTag;
 
-    fn build_from(coords: WorldTileCoords) -> Self
+    fn build_from(coords: WorldTileCoords, empty: bool) -> Self
     where
         Self: Sized;
 
     fn coords(&self) -> WorldTileCoords;
+
+    /// Whether none of the layers of the tile produced geometry, e.g. because none of them
+    /// matched the style. Such tiles are loaded, but blank.
+    fn is_empty(&self) -> bool;
 }
 
 pub trait LayerMissing: IntoMessage + Debug + Send {
@@ -51,6 +59,18 @@ pub trait LayerMissing: IntoMessage + Debug + Send {
     fn to_layer(self) -> MissingVectorLayerData;
 }
 
+/// Where a [`LayerTessellated`] message is placed within the stream of messages of its layer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum LayerChunk {
+    /// The message holds the whole layer.
+    #[default]
+    Complete,
+    /// The message holds the features of the chunk at `index`. Further chunks follow.
+    Partial { index: usize },
+    /// The message holds no features and marks that all `chunks` of the layer were sent.
+    End { chunks: usize },
+}
+
 pub trait LayerTessellated: IntoMessage + Debug + Send {
     fn message_tag() -> &'static dyn MessageTag;
 
@@ -58,8 +78,10 @@ pub trait LayerTessellated: IntoMessage + Debug + Send {
         coords: WorldTileCoords,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
+        feature_line_width_values: Vec<Option<f64>>,
         layer_data: Layer,
-        style_layer_id: String
+        style_layer_id: String,
+        partial: bool,
     ) -> Self
     where
         Self: Sized;
@@ -68,6 +90,35 @@ pub trait LayerTessellated: IntoMessage + Debug + Send {
 
     fn is_empty(&self) -> bool;
 
+    /// Whether only some of the features were tessellated, because the tessellation budget of
+    /// the layer was exceeded.
+    fn is_partial(&self) -> bool;
+
+    /// Marks the message as a chunk of a layer which is tessellated incrementally.
+    fn with_chunk(self, chunk: LayerChunk) -> Self
+    where
+        Self: Sized;
+
+    fn chunk(&self) -> LayerChunk;
+
+    /// Attaches the properties of each feature, which are retained if
+    /// [`VectorTileRequest::retain_feature_properties`] is set.
+    fn with_feature_properties(
+        self,
+        feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
+    ) -> Self
+    where
+        Self: Sized;
+
+    /// Holds for each feature its properties, if they are retained. Empty otherwise.
+    fn feature_properties(&self) -> &[HashMap<String, ComparisonLiteral>];
+
+    /// Attaches the value of the property which the opacity of each feature depends on, if it
+    /// depends on feature properties.
+    fn with_feature_opacity_values(self, feature_opacity_values: Vec<Option<f64>>) -> Self
+    where
+        Self: Sized;
+
     fn to_layer(self) -> AvailableVectorLayerData;
 }
 
@@ -85,11 +136,12 @@ pub trait LayerIndexed: IntoMessage + Debug + Send {
 
 pub struct DefaultTileTessellated {
     coords: WorldTileCoords,
+    empty: bool,
 }
 
 impl Debug for DefaultTileTessellated {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "DefaultTileTessellated({})", self.coords)
+        write!(f, "DefaultTileTessellated({}, empty: {})", self.coords, self.empty)
     }
 }
 
@@ -104,13 +156,17 @@ impl TileTessellated for DefaultTileTessellated {
         &VectorMessageTag::TileTessellated
     }
 
-    fn build_from(coords: WorldTileCoords) -> Self {
-        Self { coords }
+    fn build_from(coords: WorldTileCoords, empty: bool) -> Self {
+        Self { coords, empty }
     }
 
     fn coords(&self) -> WorldTileCoords {
         self.coords
     }
+
+    fn is_empty(&self) -> bool {
+        self.empty
+    }
 }
 
 pub struct DefaultLayerMissing {
@@ -161,8 +217,19 @@ pub struct DefaultLayerTesselated {
     pub buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
     /// Holds for each feature the count of indices.
     pub feature_indices: Vec<u32>,
+    /// Holds for each feature the value of the property which the line width depends on, if it
+    /// depends on feature properties.
+    pub feature_line_width_values: Vec<Option<f64>>,
+    /// Holds for each feature the value of the property which the opacity depends on, if it
+    /// depends on feature properties.
+    pub feature_opacity_values: Vec<Option<f64>>,
     pub layer_data: Layer, // FIXME (perf): Introduce a better structure for this
-    pub style_layer_id: String
+    pub style_layer_id: String,
+    /// Whether features were skipped because the tessellation budget was exceeded.
+    pub partial: bool,
+    pub chunk: LayerChunk,
+    /// Holds for each feature its properties, if they are retained. Empty otherwise.
+    pub feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
 }
 
 impl Debug for DefaultLayerTesselated {
@@ -186,15 +253,22 @@ impl LayerTessellated for DefaultLayerTesselated {
         coords: WorldTileCoords,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
+        feature_line_width_values: Vec<Option<f64>>,
         layer_data: Layer,
-        style_layer_id: String
+        style_layer_id: String,
+        partial: bool,
     ) -> Self {
         Self {
             coords,
             buffer,
             feature_indices,
+            feature_line_width_values,
+            feature_opacity_values: Vec::new(),
             layer_data,
-            style_layer_id
+            style_layer_id,
+            partial,
+            chunk: LayerChunk::Complete,
+            feature_properties: Vec::new(),
         }
     }
 
@@ -206,11 +280,45 @@ impl LayerTessellated for DefaultLayerTesselated {
         self.buffer.usable_indices == 0
     }
 
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    fn with_chunk(mut self, chunk: LayerChunk) -> Self {
+        self.chunk = chunk;
+        self
+    }
+
+    fn chunk(&self) -> LayerChunk {
+        self.chunk
+    }
+
+    fn with_feature_properties(
+        mut self,
+        feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
+    ) -> Self {
+        self.feature_properties = feature_properties;
+        self
+    }
+
+    fn feature_properties(&self) -> &[HashMap<String, ComparisonLiteral>] {
+        &self.feature_properties
+    }
+
+    fn with_feature_opacity_values(mut self, feature_opacity_values: Vec<Option<f64>>) -> Self {
+        self.feature_opacity_values = feature_opacity_values;
+        self
+    }
+
     fn to_layer(self) -> AvailableVectorLayerData {
         AvailableVectorLayerData {
             coords: self.coords,
             buffer: self.buffer,
             feature_indices: self.feature_indices,
+            feature_line_width_values: self.feature_line_width_values,
+            feature_opacity_values: self.feature_opacity_values,
+            feature_properties: self.feature_properties,
+            extent: self.layer_data.extent.unwrap_or(EXTENT_UINT),
             style_layer_id: self.style_layer_id,
         }
     }
@@ -1,30 +1,39 @@
 //! Uploads data to the GPU which is needed for rendering.
 
-use std::iter;
+use std::{collections::HashSet, iter};
 use crate::{
     context::MapContext,
-    coords::ViewRegion,
+    coords::{ViewRegion, WorldTileCoords, EXTENT, TILE_SIZE},
     render::{
         eventually::{Eventually, Eventually::Initialized},
-        shaders::{ShaderFeatureStyle, ShaderLayerMetadata, Vec4f32},
+        shaders::{ShaderFeatureStyle, ShaderLayerMetadata, ShaderVertex, Vec4f32},
         tile_view_pattern::DEFAULT_TILE_SIZE,
+        view_state::ViewState,
         Renderer,
     },
     style::Style,
     tcs::tiles::Tiles,
     vector::{
-        AvailableVectorLayerData, VectorBufferPool,
+        resource::TileGeometry, AvailableVectorLayerData, VectorBufferPool,
     },
+    tessellation::IndexDataType,
+};
+use crate::coords::Zoom;
+use cgmath::Rad;
+
+use crate::style::{
+    layer::{LayerPaint, LinePaint, StyleLayer, TranslateAnchor},
+    util::interpolate,
 };
-use crate::style::layer::{LayerPaint, LinePaint};
-use crate::style::util::interpolate;
 
 pub fn upload_system(
     MapContext {
         world,
         style,
         view_state,
-        renderer: Renderer { device, queue, .. },
+        renderer: Renderer {
+            queue, settings, ..
+        },
         ..
     }: &mut MapContext,
 ) {
@@ -39,153 +48,636 @@ pub fn upload_system(
         view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
     if let Some(view_region) = &view_region {
+        let min_line_width = settings.min_line_width.map(line_width_from_pixels);
+        update_metadata(
+            buffer_pool,
+            queue,
+            &world.tiles,
+            style,
+            view_region,
+            view_state,
+            min_line_width,
+        );
         upload_tesselated_layer(
             buffer_pool,
-            device,
             queue,
             &mut world.tiles,
             style,
             view_region,
+            view_state,
+            min_line_width,
         );
-        // self.update_metadata(state, tile_repository, queue);
     }
 }
 
-/* FIXME tcs fn update_metadata(
-    buffer_pool: &VectorBufferPool,
-    tiles: &Tiles,
-    queue: &wgpu::Queue,
-) {
-    let animated_one = 0.5
-        * (1.0
-            + ((std::time::SystemTime::now()
-                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs_f64())
-            .sin()));
-
-    for entries in buffer_pool.index().iter() {
-        for entry in entries {
-            let world_coords = entry.coords;
-
-            let source_layer = entry.style_layer.source_layer.as_ref().unwrap();
-
-            let Some(stored_layer) =
-                tile_repository
-                    .iter_layers_at(&world_coords)
-                    .and_then(|mut layers| {
-                        layers.find(|layer| source_layer.as_str() == layer.layer_name())
-                    })  else { continue; };
-
-            let color: Option<Vec4f32> = entry
-                .style_layer
-                .paint
-                .as_ref()
-                .and_then(|paint| paint.get_color())
-                .map(|mut color| {
-                    color.color.b = animated_one as f32;
-                    color.into()
-                });
-
-            match stored_layer {
-                StoredLayer::UnavailableLayer { .. } => {}
-                StoredLayer::TessellatedLayer {
-                    feature_indices, ..
-                } => {
-                    /* let feature_metadata = layer_data
-                    .features()
-                    .iter()
-                    .enumerate()
-                    .flat_map(|(i, _feature)| {
-                        iter::repeat(ShaderFeatureStyle {
-                            color: color.unwrap(),
-                        })
-                        .take(feature_indices[i] as usize)
-                    })
-                    .collect::<Vec<_>>();*/
-
-                    let feature_metadata = (0..feature_indices.len())
-                        .flat_map(|i| {
-                            iter::repeat(ShaderFeatureStyle {
-                                color: color.unwrap(),
-                            })
-                            .take(feature_indices[i] as usize)
-                        })
-                        .collect::<Vec<_>>();
-
-                    buffer_pool.update_feature_metadata(queue, entry, &feature_metadata);
-                }
-
-                StoredLayer::RasterLayer { .. } => {}
-            }
-        }
-    }
-}*/
-
 fn upload_tesselated_layer(
     buffer_pool: &mut VectorBufferPool,
-    _device: &wgpu::Device,
     queue: &wgpu::Queue,
     tiles: &mut Tiles,
     style: &Style,
     view_region: &ViewRegion,
+    view_state: &ViewState,
+    min_line_width: Option<f32>,
 ) {
-    // Upload all tessellated layers which are in view
+    // Styles are interpolated at the fractional zoom of the view, not the zoom level of the tiles
+    let zoom = view_state.zoom();
+    let bearing = view_state.camera().get_roll();
+
+    // Upload all tessellated layers which are in view. The layers of a tile are uploaded together.
     for coords in view_region.iter() {
-        for style_layer in &style.layers {
-            let layer_data = tiles.find_layer(coords, &style_layer.source_layer, &style_layer.id, buffer_pool);
-
-            let Some(AvailableVectorLayerData {
-                         buffer,
-                         feature_indices,
-                         ..
-                     }) = layer_data else {
-                continue
-            };
+        let loaded_layers = buffer_pool
+            .get_loaded_layers_at(coords)
+            .unwrap_or_default();
 
-            let color: Option<Vec4f32> = style_layer
-                .paint
-                .as_ref()
-                .and_then(|paint| paint.get_color(coords.z))
-                .map(|color| color.into());
+        let tile = tile_geometry(
+            coords,
+            tiles,
+            style,
+            &loaded_layers,
+            zoom,
+            bearing,
+            min_line_width,
+        );
+        buffer_pool.allocate_tile_geometry(queue, tile);
+    }
+}
 
-            let color = color.expect(&format!("Layer {} with source {:?} had None color", style_layer.id, style_layer.source_layer));
+/// Updates the metadata of the layers in view which are already loaded. Zoom-dependent paint
+/// follows the zoom of the view and translations anchored to the viewport follow its bearing from
+/// frame to frame.
+fn update_metadata(
+    buffer_pool: &VectorBufferPool,
+    queue: &wgpu::Queue,
+    tiles: &Tiles,
+    style: &Style,
+    view_region: &ViewRegion,
+    view_state: &ViewState,
+    min_line_width: Option<f32>,
+) {
+    let zoom = view_state.zoom();
+    let bearing = view_state.camera().get_roll();
 
-            let width = style_layer
-                .paint
-                .as_ref()
-                .and_then(|paint| match paint {
-                    LayerPaint::Line(LinePaint { line_width, .. }) => line_width.as_ref(),
-                    _ => None
-                })
-                .and_then(|width_interpolant| interpolate(width_interpolant, coords.z))
-                .unwrap_or(0.0);
+    for coords in view_region.iter() {
+        let Some(entries) = buffer_pool.index().get_layers(coords) else {
+            continue;
+        };
 
-            let feature_metadata = feature_indices
+        for (style_layer, layer_data) in tiles.layers_in_draw_order(coords, style) {
+            let Some(entry) = entries
                 .iter()
-                .flat_map(|i| {
-                    iter::repeat(ShaderFeatureStyle {
-                        color,
-                        width,
-                    })
-                    .take(*i as usize)
-                })
-                .collect::<Vec<_>>();
+                .find(|entry| !entry.is_stale() && entry.style_layer.id == style_layer.id)
+            else {
+                continue;
+            };
+
+            buffer_pool.update_layer_metadata(queue, entry, layer_metadata(style_layer, bearing));
 
-            log::info!("Allocating geometry at {coords} for layer {} with width {width} color {color:?} z-index {}, has {} features", style_layer.id, style_layer.index, feature_metadata.len());
-            
-            if feature_metadata.is_empty() {
+            let Some(style) = feature_style(style_layer, zoom, min_line_width) else {
                 continue;
+            };
+            let feature_metadata =
+                feature_metadata(style_layer, style, zoom, layer_data, min_line_width);
+            buffer_pool.update_feature_metadata(queue, entry, &feature_metadata);
+        }
+    }
+}
+
+/// Stages the geometry of the layers of `style` at `coords` which are available but not
+/// `loaded_layers` yet.
+fn tile_geometry(
+    coords: WorldTileCoords,
+    tiles: &mut Tiles,
+    style: &Style,
+    loaded_layers: &HashSet<String>,
+    zoom: Zoom,
+    bearing: Rad<f64>,
+    min_line_width: Option<f32>,
+) -> TileGeometry<ShaderVertex, IndexDataType, ShaderLayerMetadata, ShaderFeatureStyle> {
+    tiles.prepare_backgrounds([tiles.extent(coords)]);
+
+    let mut tile = TileGeometry::new(coords);
+    for (style_layer, layer_data) in tiles.layers_in_draw_order(coords, style) {
+        if loaded_layers.contains(&style_layer.id) {
+            continue;
+        }
+
+        // TODO: Extrude fill-extrusion layers into walls and roofs, flat fills would be misleading
+        if matches!(style_layer.paint, Some(LayerPaint::FillExtrusion(_))) {
+            continue;
+        }
+
+        let Some(style) = feature_style(style_layer, zoom, min_line_width) else {
+            log::warn!(
+                "Skipping layer {} at {coords}, because it has no color",
+                style_layer.id
+            );
+            continue;
+        };
+
+        let feature_metadata =
+            feature_metadata(style_layer, style, zoom, layer_data, min_line_width);
+
+        log::info!("Allocating geometry at {coords} for layer {} with style {style:?} z-index {}, has {} features", style_layer.id, style_layer.index, feature_metadata.len());
+
+        if feature_metadata.is_empty() {
+            continue;
+        }
+
+        tile.push(
+            style_layer.clone(),
+            &layer_data.buffer,
+            layer_metadata(style_layer, bearing),
+            &feature_metadata,
+        );
+    }
+    tile
+}
+
+/// Builds the style of all features of a layer. The alpha of the color and the opacity of the
+/// layer are kept apart and only multiplied in the shader. Returns `None` if the layer has no
+/// color, e.g. because it is not a fill or line layer.
+fn feature_style(style_layer: &StyleLayer, zoom: impl Into<Zoom>, min_line_width: Option<f32>) -> Option<ShaderFeatureStyle> {
+    let zoom: Zoom = zoom.into();
+    let paint = style_layer.paint.as_ref();
+
+    let color: Vec4f32 = paint.and_then(|paint| paint.get_color(zoom))?.into();
+
+    Some(ShaderFeatureStyle {
+        color,
+        width: line_width(style_layer, zoom, min_line_width),
+        gap_width: line_gap_width(style_layer, zoom),
+        opacity: paint.map_or(1.0, |paint| paint.get_opacity(zoom)),
+        feature_order: 0.0,
+    })
+}
+
+/// Repeats `style` for the indices of each feature of `layer_data`. If the width or opacity of
+/// `style_layer` depends on feature properties, it is evaluated at `zoom` for each feature and
+/// replaces the width or opacity of `style`. Each feature gets its position in the layer as
+/// [`ShaderFeatureStyle::feature_order`].
+fn feature_metadata(
+    style_layer: &StyleLayer,
+    style: ShaderFeatureStyle,
+    zoom: Zoom,
+    layer_data: &AvailableVectorLayerData,
+    min_line_width: Option<f32>,
+) -> Vec<ShaderFeatureStyle> {
+    let paint = style_layer.paint.as_ref();
+    let line_width = match paint {
+        Some(LayerPaint::Line(LinePaint { line_width, .. })) => line_width.as_ref(),
+        _ => None,
+    };
+    let opacity = paint.and_then(LayerPaint::get_feature_opacity);
+
+    let AvailableVectorLayerData {
+        feature_indices,
+        feature_line_width_values,
+        feature_opacity_values,
+        ..
+    } = layer_data;
+
+    let features = feature_indices.len() as f32;
+    feature_indices
+        .iter()
+        .enumerate()
+        .flat_map(|(feature, indices)| {
+            let mut style = style;
+            style.feature_order = feature as f32 / features;
+            if let (Some(line_width), Some(value)) =
+                (line_width, feature_line_width_values.get(feature))
+            {
+                let width = line_width.evaluate_value(zoom, *value).unwrap_or(0.0);
+                style.width = apply_min_line_width(width, min_line_width);
+            }
+            if let (Some(opacity), Some(value)) = (opacity, feature_opacity_values.get(feature)) {
+                style.opacity = opacity.evaluate_value(zoom, *value).unwrap_or(1.0);
+            }
+            iter::repeat(style).take(*indices as usize)
+        })
+        .collect()
+}
+
+/// Builds the metadata of a layer. Translations anchored to the viewport are rotated by the
+/// `bearing` of the map so that they stay fixed relative to the screen. As the bearing changes
+/// while the map is rotated, loaded layers are updated every frame, see [`update_metadata`].
+fn layer_metadata(style_layer: &StyleLayer, bearing: Rad<f64>) -> ShaderLayerMetadata {
+    let metadata = ShaderLayerMetadata::new(style_layer.index as f32);
+
+    let Some(([x, y], anchor)) = style_layer
+        .paint
+        .as_ref()
+        .and_then(|paint| paint.get_translate())
+    else {
+        return metadata;
+    };
+
+    let translate = match anchor {
+        TranslateAnchor::Map => [x, y],
+        TranslateAnchor::Viewport => {
+            let (sin, cos) = (-bearing.0 as f32).sin_cos();
+            [x * cos - y * sin, x * sin + y * cos]
+        }
+    };
+
+    metadata.with_translate(translate)
+}
+
+/// Converts a line width in `pixels` to the tile units of [`ShaderFeatureStyle::width`]. The
+/// shader multiplies widths with the zoom factor of the tile, which cancels out the scaling of the
+/// tile to the view. A width therefore covers the same pixels at every zoom.
+fn line_width_from_pixels(pixels: f32) -> f32 {
+    pixels * (EXTENT / TILE_SIZE) as f32
+}
+
+/// Interpolates the width of line layers at `zoom` and raises it to `min_line_width`, which is in
+/// tile units like the width, see [`line_width_from_pixels`]. Other layers have a width of zero.
+fn line_width(style_layer: &StyleLayer, zoom: impl Into<Zoom>, min_line_width: Option<f32>) -> f32 {
+    let Some(LayerPaint::Line(LinePaint { line_width, .. })) = &style_layer.paint else {
+        return 0.0;
+    };
+
+    let width = line_width
+        .as_ref()
+        .and_then(|width| width.evaluate(zoom, &Default::default()))
+        .unwrap_or(0.0);
+
+    apply_min_line_width(width, min_line_width)
+}
+
+/// Interpolates the gap between the casings of line layers at `zoom`. Other layers have no gap.
+fn line_gap_width(style_layer: &StyleLayer, zoom: impl Into<Zoom>) -> f32 {
+    let Some(LayerPaint::Line(LinePaint { line_gap_width, .. })) = &style_layer.paint else {
+        return 0.0;
+    };
+
+    line_gap_width
+        .as_ref()
+        .and_then(|gap_width| interpolate(gap_width, zoom))
+        .unwrap_or(0.0)
+}
+
+fn apply_min_line_width(width: f32, min_line_width: Option<f32>) -> f32 {
+    min_line_width.map_or(width, |min_line_width| width.max(min_line_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use cgmath::Rad;
+
+    use csscolorparser::Color;
+
+    use std::collections::HashSet;
+
+    use super::{
+        feature_metadata, feature_style, layer_metadata, line_gap_width, line_width,
+        line_width_from_pixels, tile_geometry,
+    };
+    use crate::{
+        coords::{Zoom, ZoomLevel},
+        style::{
+            layer::{
+                FillPaint, InterpolatedQuantity, LayerPaint, LinePaint, StyleLayer,
+                TranslateAnchor,
+            },
+            Style,
+        },
+        tcs::tiles::{BackgroundTileBuilder, Tiles},
+        vector::{AvailableVectorLayerData, VectorLayerData, VectorLayersDataComponent},
+    };
+
+    fn fill_layer(translate: Option<[f32; 2]>, anchor: Option<TranslateAnchor>) -> StyleLayer {
+        StyleLayer {
+            index: 3,
+            paint: Some(LayerPaint::Fill(FillPaint {
+                fill_color: None,
+                fill_opacity: None,
+                fill_translate: translate,
+                fill_translate_anchor: anchor,
+                unsupported: Default::default(),
+            })),
+            ..StyleLayer::default()
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let metadata = layer_metadata(&fill_layer(None, None), Rad(0.0));
+        assert_eq!(metadata.z_index, 3.0);
+        assert_eq!(metadata.translate, [0.0, 0.0]);
+
+        let metadata = layer_metadata(&fill_layer(Some([2.0, -1.0]), None), Rad(FRAC_PI_2));
+        assert_eq!(metadata.z_index, 3.0);
+        assert_eq!(metadata.translate, [2.0, -1.0]);
+    }
+
+    #[test]
+    fn test_viewport_anchored_translate() {
+        let layer = fill_layer(Some([2.0, 0.0]), Some(TranslateAnchor::Viewport));
+
+        let metadata = layer_metadata(&layer, Rad(0.0));
+        assert_eq!(metadata.translate, [2.0, 0.0]);
+
+        // Counteracts the rotation of the map
+        let [x, y] = layer_metadata(&layer, Rad(FRAC_PI_2)).translate;
+        assert!(x.abs() < 1e-6);
+        assert!((y + 2.0).abs() < 1e-6);
+    }
+
+    fn line_layer() -> StyleLayer {
+        StyleLayer {
+            paint: Some(LayerPaint::Line(LinePaint {
+                line_color: None,
+                line_opacity: None,
+                line_width: Some(
+                    InterpolatedQuantity::Interpolated {
+                        base: 1.0,
+                        stops: vec![(0.0, 0.0), (10.0, 2.0)],
+                    }
+                    .into(),
+                ),
+                line_gap_width: None,
+                line_blur: None,
+                line_translate: None,
+                line_translate_anchor: None,
+                unsupported: Default::default(),
+            })),
+            ..StyleLayer::default()
+        }
+    }
+
+    #[test]
+    fn test_min_line_width() {
+        let layer = line_layer();
+
+        // Interpolates to 0.2 at zoom 1
+        assert!((line_width(&layer, ZoomLevel::new(1), None) - 0.2).abs() < 1e-6);
+        assert_eq!(line_width(&layer, ZoomLevel::new(1), Some(0.5)), 0.5);
+
+        // Wider lines are not affected
+        assert_eq!(line_width(&layer, ZoomLevel::new(10), Some(0.5)), 2.0);
+
+        // The minimum is given in pixels, a tile of 4096 units is 512 pixels wide
+        assert_eq!(line_width_from_pixels(0.5), 4.0);
+        assert_eq!(
+            line_width(&layer, ZoomLevel::new(10), Some(line_width_from_pixels(0.5))),
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_line_width_at_fractional_zoom() {
+        let layer = line_layer();
+
+        // The midpoint between zoom 5 and 6, instead of the width at zoom level 5
+        assert!((line_width(&layer, Zoom::new(5.5), None) - 1.1).abs() < 1e-6);
+        assert!((line_width(&layer, ZoomLevel::new(5), None) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_line_gap_width_at_fractional_zoom() {
+        let mut layer = line_layer();
+        assert_eq!(line_gap_width(&layer, Zoom::new(5.5)), 0.0);
+
+        let Some(LayerPaint::Line(paint)) = &mut layer.paint else {
+            unreachable!()
+        };
+        paint.line_gap_width = Some(InterpolatedQuantity::Interpolated {
+            base: 1.0,
+            stops: vec![(0.0, 0.0), (10.0, 20.0)],
+        });
+        assert!((line_gap_width(&layer, Zoom::new(5.5)) - 11.0).abs() < 1e-6);
+        assert_eq!(line_gap_width(&fill_layer(None, None), Zoom::new(5.5)), 0.0);
+    }
+
+    #[test]
+    fn test_min_line_width_ignores_other_layers() {
+        let layer = StyleLayer {
+            paint: Some(LayerPaint::Fill(FillPaint {
+                fill_color: None,
+                fill_opacity: None,
+                fill_translate: None,
+                fill_translate_anchor: None,
+                unsupported: Default::default(),
+            })),
+            ..StyleLayer::default()
+        };
+
+        assert_eq!(line_width(&layer, ZoomLevel::new(1), Some(0.5)), 0.0);
+    }
+
+    #[test]
+    fn test_color_alpha_and_opacity_are_separate() {
+        let layer = StyleLayer {
+            paint: Some(LayerPaint::Line(LinePaint {
+                line_color: Some(Color::new(1.0, 0.0, 0.0, 0.8).into()),
+                line_opacity: Some(InterpolatedQuantity::Fixed(0.5).into()),
+                line_width: None,
+                line_gap_width: None,
+                line_blur: None,
+                line_translate: None,
+                line_translate_anchor: None,
+                unsupported: Default::default(),
+            })),
+            ..StyleLayer::default()
+        };
+
+        let style = feature_style(&layer, ZoomLevel::new(1), None).unwrap();
+        assert_eq!(style.color, [1.0, 0.0, 0.0, 0.8]);
+        assert_eq!(style.opacity, 0.5);
+    }
+
+    #[test]
+    fn test_layer_without_color_has_no_style() {
+        let mut layer = line_layer_with_color();
+        if let Some(LayerPaint::Line(paint)) = &mut layer.paint {
+            paint.line_color = None;
+        }
+        assert!(feature_style(&layer, ZoomLevel::new(1), None).is_none());
+        assert!(feature_style(&StyleLayer::default(), ZoomLevel::new(1), None).is_none());
+    }
+
+    fn line_layer_with_color() -> StyleLayer {
+        let mut layer = line_layer();
+        if let Some(LayerPaint::Line(paint)) = &mut layer.paint {
+            paint.line_color = Some(Color::new(0.0, 0.0, 1.0, 0.8).into());
+        }
+        layer
+    }
+
+    #[test]
+    fn test_opacity_defaults_to_opaque() {
+        let layer = line_layer_with_color();
+
+        let style = feature_style(&layer, ZoomLevel::new(1), None).unwrap();
+        assert_eq!(style.color[3], 0.8);
+        assert_eq!(style.opacity, 1.0);
+    }
+
+    fn layer_data(
+        feature_indices: &[u32],
+        feature_line_width_values: &[Option<f64>],
+        feature_opacity_values: &[Option<f64>],
+    ) -> AvailableVectorLayerData {
+        AvailableVectorLayerData {
+            feature_indices: feature_indices.to_vec(),
+            feature_line_width_values: feature_line_width_values.to_vec(),
+            feature_opacity_values: feature_opacity_values.to_vec(),
+            ..BackgroundTileBuilder::new().build()
+        }
+    }
+
+    #[test]
+    fn test_feature_line_widths() {
+        let layer: StyleLayer = serde_json::from_str(
+            r##"{"id": "roads", "type": "line", "paint": {
+                "line-color": "#ff0000",
+                "line-width": {"property": "rank", "stops": [
+                    [{"zoom": 10, "value": 1}, 0.2], [{"zoom": 10, "value": 5}, 4],
+                    [{"zoom": 11, "value": 1}, 0.4], [{"zoom": 11, "value": 5}, 8]
+                ]}
+            }}"##,
+        )
+        .unwrap();
+        let zoom = Zoom::new(10.0);
+        let style = feature_style(&layer, zoom, None).unwrap();
+
+        // Two features with different widths and three and two indices
+        let data = layer_data(&[3, 2], &[Some(5.0), Some(1.0)], &[]);
+        let metadata = feature_metadata(&layer, style, zoom, &data, Some(0.5));
+        let widths: Vec<f32> = metadata.iter().map(|style| style.width).collect();
+        assert_eq!(widths, vec![4.0, 4.0, 4.0, 0.5, 0.5]);
+
+        // The widths follow the fractional zoom of the view
+        let metadata = feature_metadata(&layer, style, Zoom::new(10.5), &data, None);
+        let widths: Vec<f32> = metadata.iter().map(|style| style.width).collect();
+        assert_eq!(widths, vec![6.0, 6.0, 6.0, 0.3, 0.3]);
+
+        // Without feature widths, the width of the layer is used
+        let layer = line_layer_with_color();
+        let style = feature_style(&layer, zoom, None).unwrap();
+        let data = layer_data(&[3, 2], &[], &[]);
+        let metadata = feature_metadata(&layer, style, zoom, &data, Some(0.5));
+        assert!(metadata.iter().all(|feature| feature.width == 2.0));
+    }
+
+    #[test]
+    fn test_feature_opacities() {
+        let layer: StyleLayer = serde_json::from_str(
+            r##"{"id": "roads", "type": "line", "paint": {
+                "line-color": "#ff0000",
+                "line-opacity": {"property": "alpha", "type": "identity", "default": 1}
+            }}"##,
+        )
+        .unwrap();
+        let paint = layer.paint.as_ref().unwrap();
+        assert!(paint.get_feature_opacity().is_some());
+
+        let zoom = Zoom::new(10.0);
+        let style = feature_style(&layer, zoom, None).unwrap();
+        assert_eq!(style.opacity, 1.0);
+
+        // Two features with different opacities and two indices each
+        let data = layer_data(&[2, 2], &[], &[Some(0.25), Some(0.75)]);
+        let metadata = feature_metadata(&layer, style, zoom, &data, None);
+        let opacities: Vec<f32> = metadata.iter().map(|style| style.opacity).collect();
+        assert_eq!(opacities, vec![0.25, 0.25, 0.75, 0.75]);
+        assert!(metadata.iter().all(|feature| feature.color == style.color));
+    }
+
+    #[test]
+    fn test_feature_order() {
+        let layer = line_layer_with_color();
+        let zoom = Zoom::new(10.0);
+        let style = feature_style(&layer, zoom, None).unwrap();
+        assert_eq!(style.feature_order, 0.0);
+
+        let data = layer_data(&[2, 1, 3, 2], &[], &[]);
+        let metadata = feature_metadata(&layer, style, zoom, &data, None);
+        let orders: Vec<f32> = metadata.iter().map(|style| style.feature_order).collect();
+        assert_eq!(orders, vec![0.0, 0.0, 0.25, 0.5, 0.5, 0.5, 0.75, 0.75]);
+    }
+
+    #[test]
+    fn test_only_layer() {
+        // language=JSON
+        let style: Style = serde_json::from_str(
+            r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {},
+          "layers": [
+            {
+              "id": "water",
+              "type": "fill",
+              "source-layer": "water",
+              "paint": {"fill-color": "#0000ff"}
+            },
+            {
+              "id": "landuse",
+              "type": "fill",
+              "source-layer": "landuse",
+              "paint": {"fill-color": "#00ff00"}
             }
-            
-            buffer_pool.allocate_layer_geometry(
-                queue,
+          ]
+        }
+        "##,
+        )
+        .unwrap();
+
+        let coords = (0, 0, ZoomLevel::new(0)).into();
+        let mut tiles = Tiles::default();
+        tiles.spawn_mut(coords).unwrap().insert(VectorLayersDataComponent {
+            done: true,
+            empty: false,
+            layers: ["water", "landuse"]
+                .into_iter()
+                .map(|id| {
+                    VectorLayerData::Available(
+                        BackgroundTileBuilder::new().with_style_layer_id(id).build(),
+                    )
+                })
+                .collect(),
+            pending_chunks: vec![],
+            retessellate: Default::default(),
+        });
+
+        let uploaded_layers = |style: &Style, tiles: &mut Tiles| {
+            let tile = tile_geometry(
                 coords,
-                style_layer.clone(),
-                buffer,
-                ShaderLayerMetadata::new(style_layer.index as f32),
-                &feature_metadata,
+                tiles,
+                style,
+                &HashSet::new(),
+                Zoom::new(0.0),
+                Rad(0.0),
+                None,
             );
-        }
+            tile.style_layers()
+                .map(|layer| (layer.id.clone(), layer.index))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            uploaded_layers(&style, &mut tiles),
+            vec![("water".to_string(), 0), ("landuse".to_string(), 1)]
+        );
+
+        // Only the geometry of the water layer is uploaded, at its original z-index
+        let water = style.only_layer("water");
+        assert_eq!(water.layers.len(), 1);
+        assert_eq!(
+            uploaded_layers(&water, &mut tiles),
+            vec![("water".to_string(), 0)]
+        );
+
+        let landuse = style.only_layer("landuse");
+        assert_eq!(
+            uploaded_layers(&landuse, &mut tiles),
+            vec![("landuse".to_string(), 1)]
+        );
+        assert!(style.only_layer("missing").layers.is_empty());
     }
 }
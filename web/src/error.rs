@@ -22,6 +22,9 @@ pub enum WebError {
     TypeError(Cow<'static, str>),
     #[error("fetching data failed")]
     FetchError(Cow<'static, str>),
+    /// The server responded with this unsuccessful HTTP status
+    #[error("the server responded with status {0}")]
+    HttpStatus(u16),
     /// Any other Error
     #[error("Error from JS")]
     GenericError(Cow<'static, str>),
@@ -92,6 +92,8 @@ pub async fn run_maplibre(new_worker: js_sys::Function) -> Result<(), JSError> {
 
     let offscreen_kernel_config = OffscreenKernelConfig {
         cache_directory: None,
+        fetch_policy: Default::default(),
+        tile_cache: Default::default(),
     };
 
     #[cfg(target_feature = "atomics")]
@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
-use js_sys::{ArrayBuffer, Uint8Array};
-use maplibre::io::source_client::{HttpClient, SourceFetchError};
+use js_sys::{ArrayBuffer, Promise, Uint8Array};
+use maplibre::io::source_client::{FetchErrorKind, HttpClient, SourceFetchError};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{Request, RequestInit, Response, WorkerGlobalScope};
@@ -23,16 +25,16 @@ impl WHATWGFetchHttpClient {
             .dyn_into::<WorkerGlobalScope>()
             .map_err(|_e| WebError::TypeError("Unable to cast to WorkerGlobalScope".into()))?;
 
-        // Call fetch on global scope
-        let maybe_response = JsFuture::from(scope.fetch_with_request(&request)).await?;
+        // Call fetch on global scope. It rejects if the request could not be sent.
+        let maybe_response = JsFuture::from(scope.fetch_with_request(&request))
+            .await
+            .map_err(|error| WebError::FetchError(format!("{:?}", WebError::from(error)).into()))?;
         let response: Response = maybe_response
             .dyn_into()
             .map_err(|_e| WebError::TypeError("Unable to cast to Response".into()))?;
 
         if !response.ok() {
-            return Err(WebError::GenericError(
-                format!("failed to fetch {}", response.status()).into(),
-            ));
+            return Err(WebError::HttpStatus(response.status()));
         }
 
         // Get ArrayBuffer
@@ -65,8 +67,32 @@ impl Clone for WHATWGFetchHttpClient {
 #[async_trait(?Send)]
 impl HttpClient for WHATWGFetchHttpClient {
     async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
-        self.fetch_bytes(url)
-            .await
-            .map_err(|e| SourceFetchError(Box::new(e)))
+        self.fetch_bytes(url).await.map_err(|e| {
+            let kind = match &e {
+                WebError::HttpStatus(status) => FetchErrorKind::Status(*status),
+                WebError::FetchError(_) => FetchErrorKind::Connection,
+                _ => FetchErrorKind::Other,
+            };
+            SourceFetchError::new(kind, e)
+        })
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        let promise = Promise::new(&mut |resolve, _reject| {
+            let timeout = js_sys::global()
+                .dyn_into::<WorkerGlobalScope>()
+                .map_err(JsValue::from)
+                .and_then(|scope| {
+                    scope.set_timeout_with_callback_and_timeout_and_arguments_0(
+                        &resolve,
+                        duration.as_millis().min(i32::MAX as u128) as i32,
+                    )
+                });
+            // Retry immediately if no timer is available
+            if timeout.is_err() {
+                let _ = resolve.call0(&JsValue::NULL);
+            }
+        });
+        let _ = JsFuture::from(promise).await;
     }
 }
@@ -1,11 +1,14 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+};
 
 use flatbuffers::FlatBufferBuilder;
 use image::RgbaImage;
 use js_sys::{ArrayBuffer, Uint8Array};
 use maplibre::{
     benchmarking::tessellation::{IndexDataType, OverAlignedVertexBuffer},
-    coords::WorldTileCoords,
+    coords::{WorldTileCoords, EXTENT_UINT},
     io::{
         apc::{IntoMessage, Message, MessageTag},
         geometry_index::TileIndex,
@@ -15,9 +18,10 @@ use maplibre::{
         RasterTransferables,
     },
     render::ShaderVertex,
+    style::expression::ComparisonLiteral,
     tile::Layer,
     vector::{
-        AvailableVectorLayerData, LayerIndexed, LayerMissing, LayerTessellated,
+        AvailableVectorLayerData, LayerChunk, LayerIndexed, LayerMissing, LayerTessellated,
         MissingVectorLayerData, TileTessellated, VectorTransferables,
     },
 };
@@ -95,7 +99,7 @@ impl TileTessellated for FlatBufferTransferable {
         &WebMessageTag::TileTessellated
     }
 
-    fn build_from(coords: WorldTileCoords) -> Self {
+    fn build_from(coords: WorldTileCoords, empty: bool) -> Self {
         let mut inner_builder = FlatBufferBuilder::with_capacity(1024);
         let mut builder = FlatTileTessellatedBuilder::new(&mut inner_builder);
 
@@ -104,6 +108,7 @@ impl TileTessellated for FlatBufferTransferable {
             coords.y,
             coords.z.into(),
         ));
+        builder.add_empty(empty);
         let root = builder.finish();
         inner_builder.finish(root, None);
         let (data, start) = inner_builder.collapse();
@@ -118,6 +123,11 @@ impl TileTessellated for FlatBufferTransferable {
         let data = root_as_flat_tile_tessellated(&self.data[self.start..]).unwrap();
         data.coords().unwrap().into()
     }
+
+    fn is_empty(&self) -> bool {
+        let data = root_as_flat_tile_tessellated(&self.data[self.start..]).unwrap();
+        data.empty()
+    }
 }
 
 impl LayerMissing for FlatBufferTransferable {
@@ -186,9 +196,13 @@ impl LayerTessellated for FlatBufferTransferable {
         coords: WorldTileCoords,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
+        // TODO: need to incorporate this in the web flatbuffer defs
+        _feature_line_width_values: Vec<Option<f64>>,
         layer_data: Layer,
         // TODO(aidangoettsch): need to incorporate this in the web flatbuffer defs
         style_layer_id: String,
+        // TODO: need to incorporate this in the web flatbuffer defs
+        _partial: bool,
     ) -> Self {
         let mut inner_builder = FlatBufferBuilder::with_capacity(1024);
 
@@ -197,6 +211,7 @@ impl LayerTessellated for FlatBufferTransferable {
                 .buffer
                 .vertices
                 .iter()
+                // TODO: need to incorporate the edge and blur in the web flatbuffer defs
                 .map(|vertex| FlatShaderVertex::new(&vertex.position, &vertex.normal))
                 .collect::<Vec<_>>(),
         );
@@ -237,6 +252,36 @@ impl LayerTessellated for FlatBufferTransferable {
         data.usable_indices() == 0
     }
 
+    fn is_partial(&self) -> bool {
+        false
+    }
+
+    fn with_chunk(self, _chunk: LayerChunk) -> Self {
+        // TODO: need to incorporate this in the web flatbuffer defs
+        self
+    }
+
+    fn chunk(&self) -> LayerChunk {
+        LayerChunk::Complete
+    }
+
+    fn with_feature_properties(
+        self,
+        _feature_properties: Vec<HashMap<String, ComparisonLiteral>>,
+    ) -> Self {
+        // TODO: need to incorporate this in the web flatbuffer defs
+        self
+    }
+
+    fn feature_properties(&self) -> &[HashMap<String, ComparisonLiteral>] {
+        &[]
+    }
+
+    fn with_feature_opacity_values(self, _feature_opacity_values: Vec<Option<f64>>) -> Self {
+        // TODO: need to incorporate this in the web flatbuffer defs
+        self
+    }
+
     fn to_layer(self) -> AvailableVectorLayerData {
         let data = root_as_flat_layer_tessellated(&self.data[self.start..]).unwrap();
         let vertices = data
@@ -253,6 +298,12 @@ impl LayerTessellated for FlatBufferTransferable {
             source_layer: data.layer_name().unwrap().to_owned(),
             buffer: OverAlignedVertexBuffer::from_iters(vertices, indices, usable_indices),
             feature_indices,
+            feature_line_width_values: Vec::new(),
+            // TODO: need to incorporate this in the web flatbuffer defs
+            feature_opacity_values: Vec::new(),
+            // TODO: need to incorporate this in the web flatbuffer defs
+            feature_properties: Vec::new(),
+            extent: EXTENT_UINT,
         }
     }
 }